@@ -1,5 +1,6 @@
 #include "ProcessGuardClient.hpp"
 #include <algorithm>
+#include <cctype>
 #include <sstream>
 #include <filesystem>
 #include <condition_variable>
@@ -406,13 +407,23 @@ namespace ProcessGuard
 
         std::function<void(const std::string &)> heartbeatFailedCallback;
         std::function<void(bool)> connectedChangedCallback;
+        std::function<void(const std::string &)> prepareShutdownCallback;
 
         std::atomic<bool> connected{false};
         mutable std::string lastError;
+        mutable std::string lastErrorId;
+        mutable std::string lastErrorParams;
         std::string selfMonitorId;
 
         Impl() : pipeClient(std::make_unique<PipeClient>()),
                  serviceManager(std::make_unique<ServiceManager>()) {}
+
+        void SetResponseError(const nlohmann::json &response)
+        {
+            lastError = response.value("message", "Unknown error");
+            lastErrorId = response.value("message_id", "");
+            lastErrorParams = response.contains("params") ? response["params"].dump() : "{}";
+        }
     };
 
     Client::Client() : impl_(std::make_unique<Impl>()) {}
@@ -446,6 +457,16 @@ namespace ProcessGuard
         return impl_->lastError;
     }
 
+    std::string Client::GetLastErrorId() const
+    {
+        return impl_->lastErrorId;
+    }
+
+    std::string Client::GetLastErrorParams() const
+    {
+        return impl_->lastErrorParams;
+    }
+
     bool Client::IsServiceInstalled()
     {
         return impl_->serviceManager->IsServiceInstalled();
@@ -583,6 +604,7 @@ namespace ProcessGuard
             config["no_window"] = item.noWindow;
             config["enabled"] = item.enabled;
             config["heartbeat_timeout_ms"] = static_cast<int64_t>(item.heartbeatTimeoutMs);
+            config["heartbeat_required"] = item.heartbeatRequired;
             if (!item.args.empty())
             {
                 config["args"] = item.args;
@@ -593,7 +615,7 @@ namespace ProcessGuard
             impl_->connected = impl_->pipeClient->IsConnected();
             if (!response.is_object() || !response.value("success", false))
             {
-                impl_->lastError = response.value("message", "Unknown error");
+                impl_->SetResponseError(response);
                 return false;
             }
             return true;
@@ -612,6 +634,45 @@ namespace ProcessGuard
         }
     }
 
+    bool Client::CloneMonitorItem(const std::string &id, const std::string &newId, const std::string &name,
+                                  const std::string &overridesJson)
+    {
+        if (!impl_->connected && !Connect())
+            return false;
+
+        try
+        {
+            nlohmann::json request;
+            request["type"] = "clone";
+            request["id"] = id;
+            request["new_id"] = newId;
+            request["name"] = name;
+            if (!overridesJson.empty())
+                request["overrides"] = nlohmann::json::parse(overridesJson);
+
+            auto response = impl_->pipeClient->SendRequest(request);
+            impl_->connected = impl_->pipeClient->IsConnected();
+            if (!response.is_object() || !response.value("success", false))
+            {
+                impl_->SetResponseError(response);
+                return false;
+            }
+            return true;
+        }
+        catch (const std::exception &e)
+        {
+            impl_->connected = false;
+            impl_->lastError = std::string("CloneMonitorItem error: ") + e.what();
+            return false;
+        }
+        catch (...)
+        {
+            impl_->connected = false;
+            impl_->lastError = "CloneMonitorItem unknown error";
+            return false;
+        }
+    }
+
     bool Client::UpdateMonitorItem(const MonitorItem &item)
     {
         if (!impl_->connected && !Connect())
@@ -630,6 +691,7 @@ namespace ProcessGuard
             config["no_window"] = item.noWindow;
             config["enabled"] = item.enabled;
             config["heartbeat_timeout_ms"] = item.heartbeatTimeoutMs;
+            config["heartbeat_required"] = item.heartbeatRequired;
             if (!item.args.empty())
             {
                 config["args"] = item.args;
@@ -640,7 +702,7 @@ namespace ProcessGuard
             impl_->connected = impl_->pipeClient->IsConnected();
             if (!response.is_object() || !response.value("success", false))
             {
-                impl_->lastError = response.value("message", "Unknown error");
+                impl_->SetResponseError(response);
                 return false;
             }
             return true;
@@ -674,7 +736,7 @@ namespace ProcessGuard
             impl_->connected = impl_->pipeClient->IsConnected();
             if (!response.is_object() || !response.value("success", false))
             {
-                impl_->lastError = response.value("message", "Unknown error");
+                impl_->SetResponseError(response);
                 return false;
             }
             return true;
@@ -708,7 +770,7 @@ namespace ProcessGuard
             impl_->connected = impl_->pipeClient->IsConnected();
             if (!response.is_object() || !response.value("success", false))
             {
-                impl_->lastError = response.value("message", "Unknown error");
+                impl_->SetResponseError(response);
                 return false;
             }
             return true;
@@ -742,7 +804,7 @@ namespace ProcessGuard
             impl_->connected = impl_->pipeClient->IsConnected();
             if (!response.is_object() || !response.value("success", false))
             {
-                impl_->lastError = response.value("message", "Unknown error");
+                impl_->SetResponseError(response);
                 return false;
             }
             return true;
@@ -776,7 +838,7 @@ namespace ProcessGuard
             impl_->connected = impl_->pipeClient->IsConnected();
             if (!response.is_object() || !response.value("success", false))
             {
-                impl_->lastError = response.value("message", "Unknown error");
+                impl_->SetResponseError(response);
                 return false;
             }
             return true;
@@ -795,6 +857,330 @@ namespace ProcessGuard
         }
     }
 
+    bool Client::KillMonitorItem(const std::string &id)
+    {
+        if (!impl_->connected && !Connect())
+            return false;
+
+        try
+        {
+            nlohmann::json request;
+            request["type"] = "kill";
+            request["id"] = id;
+
+            auto response = impl_->pipeClient->SendRequest(request);
+            impl_->connected = impl_->pipeClient->IsConnected();
+            if (!response.is_object() || !response.value("success", false))
+            {
+                impl_->SetResponseError(response);
+                return false;
+            }
+            return true;
+        }
+        catch (const std::exception &e)
+        {
+            impl_->connected = false;
+            impl_->lastError = std::string("KillMonitorItem error: ") + e.what();
+            return false;
+        }
+        catch (...)
+        {
+            impl_->connected = false;
+            impl_->lastError = "KillMonitorItem unknown error";
+            return false;
+        }
+    }
+
+    bool Client::SuspendProcess(const std::string &id)
+    {
+        if (!impl_->connected && !Connect())
+            return false;
+
+        try
+        {
+            nlohmann::json request;
+            request["type"] = "suspend";
+            request["id"] = id;
+
+            auto response = impl_->pipeClient->SendRequest(request);
+            impl_->connected = impl_->pipeClient->IsConnected();
+            if (!response.is_object() || !response.value("success", false))
+            {
+                impl_->SetResponseError(response);
+                return false;
+            }
+            return true;
+        }
+        catch (const std::exception &e)
+        {
+            impl_->connected = false;
+            impl_->lastError = std::string("SuspendProcess error: ") + e.what();
+            return false;
+        }
+        catch (...)
+        {
+            impl_->connected = false;
+            impl_->lastError = "SuspendProcess unknown error";
+            return false;
+        }
+    }
+
+    bool Client::ResumeProcess(const std::string &id)
+    {
+        if (!impl_->connected && !Connect())
+            return false;
+
+        try
+        {
+            nlohmann::json request;
+            request["type"] = "resume";
+            request["id"] = id;
+
+            auto response = impl_->pipeClient->SendRequest(request);
+            impl_->connected = impl_->pipeClient->IsConnected();
+            if (!response.is_object() || !response.value("success", false))
+            {
+                impl_->SetResponseError(response);
+                return false;
+            }
+            return true;
+        }
+        catch (const std::exception &e)
+        {
+            impl_->connected = false;
+            impl_->lastError = std::string("ResumeProcess error: ") + e.what();
+            return false;
+        }
+        catch (...)
+        {
+            impl_->connected = false;
+            impl_->lastError = "ResumeProcess unknown error";
+            return false;
+        }
+    }
+
+    bool Client::TrimProcessMemory(const std::string &id)
+    {
+        if (!impl_->connected && !Connect())
+            return false;
+
+        try
+        {
+            nlohmann::json request;
+            request["type"] = "trim_memory";
+            request["id"] = id;
+
+            auto response = impl_->pipeClient->SendRequest(request);
+            impl_->connected = impl_->pipeClient->IsConnected();
+            if (!response.is_object() || !response.value("success", false))
+            {
+                impl_->SetResponseError(response);
+                return false;
+            }
+            return true;
+        }
+        catch (const std::exception &e)
+        {
+            impl_->connected = false;
+            impl_->lastError = std::string("TrimProcessMemory error: ") + e.what();
+            return false;
+        }
+        catch (...)
+        {
+            impl_->connected = false;
+            impl_->lastError = "TrimProcessMemory unknown error";
+            return false;
+        }
+    }
+
+    bool Client::DeferMonitorItem(const std::string &id, int minutes)
+    {
+        if (!impl_->connected && !Connect())
+            return false;
+
+        try
+        {
+            nlohmann::json request;
+            request["type"] = "defer";
+            request["id"] = id;
+            request["minutes"] = minutes;
+
+            auto response = impl_->pipeClient->SendRequest(request);
+            impl_->connected = impl_->pipeClient->IsConnected();
+            if (!response.is_object() || !response.value("success", false))
+            {
+                impl_->SetResponseError(response);
+                return false;
+            }
+            return true;
+        }
+        catch (const std::exception &e)
+        {
+            impl_->connected = false;
+            impl_->lastError = std::string("DeferMonitorItem error: ") + e.what();
+            return false;
+        }
+        catch (...)
+        {
+            impl_->connected = false;
+            impl_->lastError = "DeferMonitorItem unknown error";
+            return false;
+        }
+    }
+
+    bool Client::PauseAll(int minutes)
+    {
+        if (!impl_->connected && !Connect())
+            return false;
+
+        try
+        {
+            nlohmann::json request;
+            request["type"] = "pause_all";
+            if (minutes > 0)
+                request["minutes"] = minutes;
+
+            auto response = impl_->pipeClient->SendRequest(request);
+            impl_->connected = impl_->pipeClient->IsConnected();
+            if (!response.is_object() || !response.value("success", false))
+            {
+                impl_->SetResponseError(response);
+                return false;
+            }
+            return true;
+        }
+        catch (const std::exception &e)
+        {
+            impl_->connected = false;
+            impl_->lastError = std::string("PauseAll error: ") + e.what();
+            return false;
+        }
+        catch (...)
+        {
+            impl_->connected = false;
+            impl_->lastError = "PauseAll unknown error";
+            return false;
+        }
+    }
+
+    bool Client::ResumeAll()
+    {
+        if (!impl_->connected && !Connect())
+            return false;
+
+        try
+        {
+            nlohmann::json request;
+            request["type"] = "resume_all";
+
+            auto response = impl_->pipeClient->SendRequest(request);
+            impl_->connected = impl_->pipeClient->IsConnected();
+            if (!response.is_object() || !response.value("success", false))
+            {
+                impl_->SetResponseError(response);
+                return false;
+            }
+            return true;
+        }
+        catch (const std::exception &e)
+        {
+            impl_->connected = false;
+            impl_->lastError = std::string("ResumeAll error: ") + e.what();
+            return false;
+        }
+        catch (...)
+        {
+            impl_->connected = false;
+            impl_->lastError = "ResumeAll unknown error";
+            return false;
+        }
+    }
+
+    int Client::BulkMonitorItems(const std::string &action, const std::string &tag,
+                                 const std::string &group, const std::string &namePattern)
+    {
+        if (!impl_->connected && !Connect())
+            return -1;
+
+        try
+        {
+            nlohmann::json selector = nlohmann::json::object();
+            if (!tag.empty())
+                selector["tag"] = tag;
+            if (!group.empty())
+                selector["group"] = group;
+            if (!namePattern.empty())
+                selector["name"] = namePattern;
+
+            nlohmann::json request;
+            request["type"] = "bulk";
+            request["action"] = action;
+            request["selector"] = selector;
+
+            auto response = impl_->pipeClient->SendRequest(request);
+            impl_->connected = impl_->pipeClient->IsConnected();
+            if (!response.is_object() || !response.value("success", false))
+            {
+                impl_->SetResponseError(response);
+                return -1;
+            }
+            if (response.contains("data") && response["data"].contains("items") &&
+                response["data"]["items"].is_array())
+                return static_cast<int>(response["data"]["items"].size());
+            return 0;
+        }
+        catch (const std::exception &e)
+        {
+            impl_->connected = false;
+            impl_->lastError = std::string("BulkMonitorItems error: ") + e.what();
+            return -1;
+        }
+        catch (...)
+        {
+            impl_->connected = false;
+            impl_->lastError = "BulkMonitorItems unknown error";
+            return -1;
+        }
+    }
+
+    std::string Client::GetOutput(const std::string &id)
+    {
+        if (!impl_->connected && !Connect())
+            return {};
+
+        try
+        {
+            nlohmann::json request;
+            request["type"] = "get_output";
+            request["id"] = id;
+
+            auto response = impl_->pipeClient->SendRequest(request);
+            impl_->connected = impl_->pipeClient->IsConnected();
+            if (!response.is_object() || !response.value("success", false))
+            {
+                impl_->SetResponseError(response);
+                return {};
+            }
+            if (response.contains("data") && response["data"].is_object())
+            {
+                return response["data"].value("output", "");
+            }
+            return {};
+        }
+        catch (const std::exception &e)
+        {
+            impl_->connected = false;
+            impl_->lastError = std::string("GetOutput error: ") + e.what();
+            return {};
+        }
+        catch (...)
+        {
+            impl_->connected = false;
+            impl_->lastError = "GetOutput unknown error";
+            return {};
+        }
+    }
+
     std::vector<MonitorItem> Client::GetAllMonitorItems()
     {
         if (!impl_->connected && !Connect())
@@ -832,6 +1218,24 @@ namespace ProcessGuard
                         mi.noWindow = item.value("no_window", false);
                         mi.enabled = item.value("enabled", false);
                         mi.heartbeatTimeoutMs = item.value("heartbeat_timeout_ms", 1000);
+                        mi.heartbeatRequired = item.value("heartbeat_required", true);
+                        if (item.contains("stats") && item["stats"].is_object())
+                        {
+                            const auto &stats = item["stats"];
+                            if (stats.contains("current_uptime_secs") && !stats["current_uptime_secs"].is_null())
+                                mi.currentUptimeSecs = stats["current_uptime_secs"].get<int64_t>();
+                            if (stats.contains("mean_time_between_restarts_secs") &&
+                                !stats["mean_time_between_restarts_secs"].is_null())
+                                mi.meanTimeBetweenRestartsSecs = stats["mean_time_between_restarts_secs"].get<int64_t>();
+                            if (stats.contains("last_failure") && stats["last_failure"].is_object())
+                            {
+                                const auto &failure = stats["last_failure"];
+                                mi.lastFailureKind = failure.value("kind", "");
+                                mi.lastFailureDetail = failure.value("detail", "");
+                                mi.lastFailureUnixMs = failure.value("ts", static_cast<int64_t>(0));
+                            }
+                            mi.availability24hPercent = stats.value("availability_24h_percent", -1.0);
+                        }
                         items.push_back(mi);
                     }
                     catch (const std::exception &e)
@@ -879,6 +1283,10 @@ namespace ProcessGuard
                     const auto &data = response["data"];
                     status.serviceRunning = data.value("service_running", false);
                     status.totalItems = data.value("total_items", 0);
+                    status.paused = data.value("paused", false);
+                    if (data.contains("paused_remaining_secs") && data["paused_remaining_secs"].is_number())
+                        status.pausedRemainingSecs = data["paused_remaining_secs"].get<int64_t>();
+                    status.safeMode = data.value("safe_mode", false);
 
                     if (data.contains("items") && data["items"].is_array())
                     {
@@ -901,9 +1309,52 @@ namespace ProcessGuard
                                 }
                                 ps.lastHeartbeatMs = item.value("last_heartbeat_ms", 0);
                                 ps.heartbeatTimeoutMs = item.value("heartbeat_timeout_ms", 1000);
+                                ps.heartbeatRequired = item.value("heartbeat_required", true);
                                 ps.restartCount = item.value("restart_count", 0);
                                 ps.isAlive = item.value("is_alive", false);
                                 ps.isHeartbeatOk = item.value("is_heartbeat_ok", false);
+                                ps.missedHeartbeats = item.value("missed_heartbeats", 0);
+                                ps.suspended = item.value("suspended", false);
+                                if (item.contains("deferred_secs") && item["deferred_secs"].is_number())
+                                    ps.deferredSecs = item["deferred_secs"].get<int64_t>();
+                                if (item.contains("cpu_percent") && item["cpu_percent"].is_number())
+                                {
+                                    ps.cpuPercent = item["cpu_percent"].get<double>();
+                                }
+                                if (item.contains("resources") && item["resources"].is_object())
+                                {
+                                    const auto &res = item["resources"];
+                                    ps.workingSetBytes = res.value("working_set_bytes", static_cast<uint64_t>(0));
+                                    ps.privateBytes = res.value("private_bytes", static_cast<uint64_t>(0));
+                                    if (res.contains("thread_count") && res["thread_count"].is_number())
+                                        ps.threadCount = res["thread_count"].get<int>();
+                                    if (res.contains("handle_count") && res["handle_count"].is_number())
+                                        ps.handleCount = res["handle_count"].get<int>();
+                                    if (res.contains("start_time_unix_ms") && res["start_time_unix_ms"].is_number())
+                                        ps.startTimeUnixMs = res["start_time_unix_ms"].get<int64_t>();
+                                    if (res.contains("session_id") && res["session_id"].is_number())
+                                        ps.sessionId = res["session_id"].get<int>();
+                                }
+                                if (item.contains("context") && item["context"].is_object())
+                                {
+                                    const auto &ctx = item["context"];
+                                    if (ctx.contains("user") && ctx["user"].is_string())
+                                        ps.userName = ctx["user"].get<std::string>();
+                                    if (ctx.contains("window_station") && ctx["window_station"].is_string())
+                                        ps.windowStation = ctx["window_station"].get<std::string>();
+                                    if (ctx.contains("command_line") && ctx["command_line"].is_string())
+                                        ps.commandLine = ctx["command_line"].get<std::string>();
+                                    if (ps.sessionId < 0 && ctx.contains("session_id") && ctx["session_id"].is_number())
+                                        ps.sessionId = ctx["session_id"].get<int>();
+                                }
+                                if (item.contains("last_start_error") && item["last_start_error"].is_string())
+                                {
+                                    ps.lastStartError = item["last_start_error"].get<std::string>();
+                                }
+                                if (item.contains("last_restart_reason") && item["last_restart_reason"].is_string())
+                                {
+                                    ps.lastRestartReason = item["last_restart_reason"].get<std::string>();
+                                }
                                 status.items.push_back(ps);
                             }
                             catch (const std::exception &e)
@@ -960,6 +1411,14 @@ namespace ProcessGuard
                 if (impl_->heartbeatFailedCallback)
                     impl_->heartbeatFailedCallback(itemId);
             }
+            else if (response.contains("data") && response["data"].is_object() &&
+                     response["data"].value("prepare_shutdown", false))
+            {
+                // 服务等待确认后才会终止进程，先让程序保存状态
+                if (impl_->prepareShutdownCallback)
+                    impl_->prepareShutdownCallback(itemId);
+                SendShutdownReady(itemId);
+            }
 
             return success;
         }
@@ -977,6 +1436,55 @@ namespace ProcessGuard
         }
     }
 
+    bool Client::SendShutdownReady(const std::string &itemId)
+    {
+        if (!impl_->connected && !Connect())
+            return false;
+
+        try
+        {
+            nlohmann::json request;
+            request["type"] = "shutdown_ready";
+            request["item_id"] = itemId;
+
+            auto response = impl_->pipeClient->SendRequest(request);
+            impl_->connected = impl_->pipeClient->IsConnected();
+            bool success = response.is_object() && response.value("success", false);
+            if (!success)
+                impl_->lastError = "ShutdownReady failed: " + response.value("message", "Unknown error");
+            return success;
+        }
+        catch (const std::exception &e)
+        {
+            impl_->connected = false;
+            impl_->lastError = std::string("SendShutdownReady error: ") + e.what();
+            return false;
+        }
+        catch (...)
+        {
+            impl_->connected = false;
+            impl_->lastError = "SendShutdownReady unknown error";
+            return false;
+        }
+    }
+
+    bool Client::WaitForStopSignal(const std::string &itemId, int timeoutMs)
+    {
+        std::string name = "Global\\PG_Stop_" + itemId;
+        std::replace(name.begin() + 7, name.end(), '\\', '_');
+
+        HANDLE event = OpenEventA(SYNCHRONIZE, FALSE, name.c_str());
+        if (!event)
+        {
+            impl_->lastError = "Stop event not found: " + name;
+            return false;
+        }
+
+        DWORD result = WaitForSingleObject(event, timeoutMs < 0 ? INFINITE : static_cast<DWORD>(timeoutMs));
+        CloseHandle(event);
+        return result == WAIT_OBJECT_0;
+    }
+
     void Client::StartHeartbeatThread(const std::string &itemId, int intervalMs)
     {
         std::lock_guard<std::mutex> lock(impl_->heartbeatMutex);
@@ -1064,6 +1572,11 @@ namespace ProcessGuard
         impl_->connectedChangedCallback = std::move(callback);
     }
 
+    void Client::SetPrepareShutdownCallback(std::function<void(const std::string &)> callback)
+    {
+        impl_->prepareShutdownCallback = std::move(callback);
+    }
+
     bool Client::AddSelfMonitor(const std::string &id, int heartbeatTimeoutMs)
     {
         // 确保已连接到服务
@@ -1190,4 +1703,93 @@ namespace ProcessGuard
         }
     }
 
+    static std::string ToLowerAscii(std::string value)
+    {
+        std::transform(value.begin(), value.end(), value.begin(), [](unsigned char c)
+                       { return static_cast<char>(std::tolower(c)); });
+        return value;
+    }
+
+    std::string Client::RegisterAndHeartbeat(const std::string &name, const std::string &exePath,
+                                             int heartbeatTimeoutMs, int intervalMs)
+    {
+        if (!impl_->connected && !Connect())
+        {
+            impl_->lastError = "Failed to connect to service";
+            return "";
+        }
+
+        std::string path = exePath.empty() ? GetCurrentExePath() : exePath;
+        if (path.empty())
+        {
+            impl_->lastError = "Failed to get current executable path";
+            return "";
+        }
+
+        std::string itemName = name;
+        if (itemName.empty())
+        {
+            try
+            {
+                itemName = std::filesystem::path(path).stem().string();
+            }
+            catch (...)
+            {
+            }
+            if (itemName.empty())
+                itemName = "SelfMonitoredProcess";
+        }
+
+        // 已有同路径的监控项（如配置文件中手工添加的）直接复用，Windows 路径不区分大小写
+        const std::string lowerPath = ToLowerAscii(path);
+        auto items = GetAllMonitorItems();
+        if (!impl_->connected)
+            return "";
+
+        std::string itemId;
+        for (const auto &existing : items)
+        {
+            if (ToLowerAscii(existing.exePath) == lowerPath)
+            {
+                itemId = existing.id;
+                break;
+            }
+        }
+
+        if (itemId.empty())
+        {
+            // 由程序名生成可读的 ID，与已有 ID 重复时追加序号
+            std::string base;
+            for (unsigned char c : itemName)
+                base += std::isalnum(c) && c < 0x80 ? static_cast<char>(std::tolower(c)) : '-';
+            base.erase(0, base.find_first_not_of('-'));
+            base.erase(base.find_last_not_of('-') + 1);
+            if (base.empty())
+                base = "self";
+
+            auto taken = [&items](const std::string &id)
+            {
+                return std::any_of(items.begin(), items.end(), [&id](const MonitorItem &item)
+                                   { return item.id == id; });
+            };
+            itemId = base;
+            for (int n = 2; taken(itemId); ++n)
+                itemId = base + "-" + std::to_string(n);
+
+            MonitorItem item;
+            item.id = itemId;
+            item.exePath = path;
+            item.name = itemName;
+            item.enabled = true;
+            item.heartbeatTimeoutMs = heartbeatTimeoutMs;
+
+            if (!AddMonitorItem(item))
+                return "";
+        }
+
+        impl_->selfMonitorId = itemId;
+        StartHeartbeatThread(itemId, intervalMs);
+        return itemId;
+    }
+
 }
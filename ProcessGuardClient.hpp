@@ -27,6 +27,15 @@ namespace ProcessGuard
         bool noWindow = false;
         bool enabled = true;
         int heartbeatTimeoutMs = 1000;
+        bool heartbeatRequired = true; // false 时只检查进程是否存活，无需发送心跳
+
+        // 以下为 GetAllMonitorItems 返回的统计，来自事件库；事件库未启用时保持默认值
+        int64_t currentUptimeSecs = -1;           // 当前运行时长，未运行时为 -1
+        int64_t meanTimeBetweenRestartsSecs = -1; // 平均重启间隔，少于两次重启时为 -1
+        std::string lastFailureKind;              // 最近一次故障，如 process_exited、heartbeat_timeout
+        std::string lastFailureDetail;
+        int64_t lastFailureUnixMs = 0;
+        double availability24hPercent = -1.0;     // 最近 24 小时可用率
 
         MonitorItem() = default;
 
@@ -63,14 +72,33 @@ namespace ProcessGuard
         int processId = 0;
         int64_t lastHeartbeatMs = 0;
         int heartbeatTimeoutMs = 1000;
+        bool heartbeatRequired = true;
         int restartCount = 0;
         bool isAlive = false;
         bool isHeartbeatOk = false;
+        int missedHeartbeats = 0; // 自最后一次心跳起错过的心跳周期数
+        bool suspended = false;
+        int64_t deferredSecs = 0; // defer 剩余秒数，0 表示未暂缓
+        uint64_t workingSetBytes = 0;
+        uint64_t privateBytes = 0;
+        double cpuPercent = 0.0;
+        int threadCount = 0;
+        int handleCount = 0;
+        int64_t startTimeUnixMs = 0;
+        int sessionId = -1;
+        std::string userName;
+        std::string windowStation;
+        std::string commandLine;
+        std::string lastStartError;
+        std::string lastRestartReason; // 最近一次重启原因，如 process_exited、heartbeat_timeout
     };
 
     struct ServiceStatus
     {
         bool serviceRunning = false;
+        bool paused = false;           // 是否处于 pause_all 暂停中
+        int64_t pausedRemainingSecs = -1; // 距自动恢复的秒数，-1 表示不自动恢复或未暂停
+        bool safeMode = false;         // 是否以安全模式启动且尚未 resume_all
         int totalItems = 0;
         std::vector<ProcessStatus> items;
     };
@@ -88,6 +116,9 @@ namespace ProcessGuard
         static std::string GetCurrentExeDir();
 
         std::string GetLastError() const;
+        // 最近一次服务端错误在消息目录中的 ID（如 item_not_found）与参数 JSON，用于本地化显示
+        std::string GetLastErrorId() const;
+        std::string GetLastErrorParams() const;
 
         bool IsServiceInstalled();
         bool IsServiceRunning();
@@ -103,11 +134,25 @@ namespace ProcessGuard
         bool IsConnected() const;
 
         bool AddMonitorItem(const MonitorItem &item);
+        bool CloneMonitorItem(const std::string &id, const std::string &newId, const std::string &name,
+                              const std::string &overridesJson = "");
         bool UpdateMonitorItem(const MonitorItem &item);
         bool RemoveMonitorItem(const std::string &id);
         bool StopMonitorItem(const std::string &id);
         bool StartMonitorItem(const std::string &id);
         bool PauseMonitorItem(const std::string &id);
+        bool KillMonitorItem(const std::string &id);
+        bool SuspendProcess(const std::string &id);
+        bool ResumeProcess(const std::string &id);
+        bool TrimProcessMemory(const std::string &id);
+        bool DeferMonitorItem(const std::string &id, int minutes);
+        bool PauseAll(int minutes = 0);
+        bool ResumeAll();
+        // 对匹配 tag、group 与名称通配符（空字符串表示不限）的监控项批量执行 enable、disable 或 restart，
+        // 返回实际处理的监控项数量，失败时返回 -1
+        int BulkMonitorItems(const std::string &action, const std::string &tag,
+                             const std::string &group = "", const std::string &namePattern = "");
+        std::string GetOutput(const std::string &id);
         bool ResumeMonitorItem(const std::string &id) { return StartMonitorItem(id); }
 
         std::vector<MonitorItem> GetAllMonitorItems();
@@ -117,12 +162,18 @@ namespace ProcessGuard
         void StartHeartbeatThread(const std::string &itemId, int intervalMs = 500);
         void StopHeartbeatThread(const std::string &itemId);
         void StopAllHeartbeatThreads();
+        bool SendShutdownReady(const std::string &itemId);
+        // 监控项配置了 stop_event 时，等待服务置位 Global\PG_Stop_<id>；timeoutMs 为 -1 时一直等待
+        // 返回 true 表示服务要求程序退出
+        bool WaitForStopSignal(const std::string &itemId, int timeoutMs = -1);
 
         bool EnsureServiceInstalled(const std::string &servicePath);
         bool EnsureServiceRunning();
 
         void SetHeartbeatFailedCallback(std::function<void(const std::string &)> callback);
         void SetConnectedChangedCallback(std::function<void(bool)> callback);
+        // 服务即将重启该监控项（prepare_shutdown）时在心跳线程中调用，返回后自动发送 shutdown_ready
+        void SetPrepareShutdownCallback(std::function<void(const std::string &)> callback);
 
         bool AddSelfMonitor(const std::string &id = "", int heartbeatTimeoutMs = 86400000);
         bool RemoveSelfMonitor();
@@ -132,6 +183,9 @@ namespace ProcessGuard
         std::string GetSelfMonitorId() const;
         bool StartSelfHeartbeat(int intervalMs = 500);
         void StopSelfHeartbeat();
+        // 按程序路径查找已有监控项，找不到则自动添加，然后启动心跳线程；返回监控项 ID，失败返回空串
+        std::string RegisterAndHeartbeat(const std::string &name = "", const std::string &exePath = "",
+                                         int heartbeatTimeoutMs = 30000, int intervalMs = 500);
 
     private:
         struct HeartbeatThreadEntry;
@@ -0,0 +1,384 @@
+use crate::acl::SecurityDescriptor;
+use crate::models::{PipeAccessConfig, RemoteHost};
+use crate::session0::to_wide_string;
+use log::warn;
+use windows::core::{PCWSTR, PWSTR};
+use windows::Win32::Foundation::{
+    CloseHandle, GetLastError, LocalFree, BOOL, ERROR_PIPE_LOCAL, HANDLE, HLOCAL, WIN32_ERROR,
+};
+use windows::Win32::Security::Authorization::{ConvertSidToStringSidW, ConvertStringSidToSidW};
+use windows::Win32::Security::{
+    CheckTokenMembership, LookupAccountNameW, RevertToSelf, PSID, SID_NAME_USE, TOKEN_QUERY,
+};
+use windows::Win32::System::Pipes::{GetNamedPipeClientComputerNameW, ImpersonateNamedPipeClient};
+use windows::Win32::System::Threading::{GetCurrentThread, OpenThreadToken};
+
+/// 管道调用方角色，按权限从低到高排列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    Observer,
+    Operator,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Observer => "observer",
+            Role::Operator => "operator",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Role> {
+        match name.to_ascii_lowercase().as_str() {
+            "observer" => Some(Role::Observer),
+            "operator" => Some(Role::Operator),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+}
+
+/// 各请求类型所需的最低角色；未列出的类型按 admin 处理
+pub fn required_role(request_type: &str) -> Role {
+    match request_type {
+        "list" | "status" | "heartbeat" | "shutdown_ready" | "uptime_report"
+        | "metrics_history" | "launch_history" | "peer_status" | "diagnostics" => Role::Observer,
+        "start" | "stop" | "pause" | "kill" | "suspend" | "resume" | "trim_memory" | "defer"
+        | "pause_all" | "resume_all" | "get_output" | "stream_logs" | "bulk" => Role::Operator,
+        _ => Role::Admin,
+    }
+}
+
+/// 安全模式下允许的请求：只读查询、输出订阅，以及退出安全模式的 resume_all
+pub fn allowed_in_safe_mode(request_type: &str) -> bool {
+    required_role(request_type) == Role::Observer
+        || matches!(request_type, "get_output" | "stream_logs" | "resume_all")
+}
+
+/// 根据组成员关系确定调用方可获得的最高角色
+pub fn resolve_role(access: &PipeAccessConfig, is_member: impl Fn(&str) -> bool) -> Option<Role> {
+    let tiers = [
+        (Role::Admin, &access.admin_groups),
+        (Role::Operator, &access.operator_groups),
+        (Role::Observer, &access.observer_groups),
+    ];
+    tiers
+        .into_iter()
+        .find(|(_, groups)| groups.iter().any(|group| is_member(group)))
+        .map(|(role, _)| role)
+}
+
+/// 远程主机可获得的最高角色；精确匹配优先于 "*"，未列出的主机返回 None
+pub fn remote_role_cap(hosts: &[RemoteHost], client: &str) -> Option<Role> {
+    let entry = hosts
+        .iter()
+        .find(|h| h.host.eq_ignore_ascii_case(client))
+        .or_else(|| hosts.iter().find(|h| h.host == "*"))?;
+    match Role::parse(&entry.max_role) {
+        Some(role) => Some(role),
+        None => {
+            warn!(
+                "Invalid max_role {:?} for remote host {}, using observer",
+                entry.max_role, entry.host
+            );
+            Some(Role::Observer)
+        }
+    }
+}
+
+/// 管道另一端的调用方位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PipeClient {
+    Local,
+    Remote(String),
+    /// 查询计算机名失败且不是 ERROR_PIPE_LOCAL，无法确认是本机调用方
+    Unknown(u32),
+}
+
+/// 查询计算机名失败时只有 ERROR_PIPE_LOCAL 表示本机调用方
+fn client_from_error(error: WIN32_ERROR) -> PipeClient {
+    if error == ERROR_PIPE_LOCAL {
+        PipeClient::Local
+    } else {
+        PipeClient::Unknown(error.0)
+    }
+}
+
+pub fn pipe_client(pipe: HANDLE) -> PipeClient {
+    let mut buffer = [0u16; 256];
+    let ok = unsafe {
+        GetNamedPipeClientComputerNameW(pipe, PWSTR(buffer.as_mut_ptr()), (buffer.len() * 2) as u32)
+    };
+    if !ok.as_bool() {
+        return client_from_error(unsafe { GetLastError() });
+    }
+    let len = buffer.iter().position(|c| *c == 0).unwrap_or(buffer.len());
+    PipeClient::Remote(String::from_utf16_lossy(&buffer[..len]))
+}
+
+/// 调用方可获得的最高角色，None 表示拒绝连接。无法确认位置时：未配置 remote_hosts 的管道
+/// 由系统拒绝远程连接，仍按本机处理；否则最多 observer
+pub fn client_role_cap(client: &PipeClient, hosts: &[RemoteHost]) -> Option<Role> {
+    match client {
+        PipeClient::Local => Some(Role::Admin),
+        PipeClient::Remote(host) => remote_role_cap(hosts, host),
+        PipeClient::Unknown(_) if hosts.is_empty() => Some(Role::Admin),
+        PipeClient::Unknown(_) => Some(Role::Observer),
+    }
+}
+
+/// 检查令牌是否属于指定组；组可写为 "S-1-5-32-544" 或 "BUILTIN\Administrators"
+fn token_is_member(token: HANDLE, group: &str) -> bool {
+    let group_wide = to_wide_string(group);
+
+    unsafe {
+        if group.starts_with("S-1-") {
+            let mut sid = PSID::default();
+            if ConvertStringSidToSidW(PCWSTR(group_wide.as_ptr()), &mut sid).is_err() {
+                warn!("Invalid SID in pipe_access: {}", group);
+                return false;
+            }
+            let mut member = BOOL(0);
+            let result = CheckTokenMembership(token, sid, &mut member);
+            let _ = LocalFree(HLOCAL(sid.0));
+            return result.is_ok() && member.as_bool();
+        }
+
+        let mut sid_buffer = [0u8; 256];
+        if !lookup_group_sid(group, &mut sid_buffer) {
+            warn!("Unknown group in pipe_access: {}", group);
+            return false;
+        }
+
+        let mut member = BOOL(0);
+        CheckTokenMembership(token, PSID(sid_buffer.as_mut_ptr().cast()), &mut member).is_ok()
+            && member.as_bool()
+    }
+}
+
+/// 按账户名查找组的 SID，写入 sid_buffer
+fn lookup_group_sid(group: &str, sid_buffer: &mut [u8; 256]) -> bool {
+    let group_wide = to_wide_string(group);
+    let mut sid_size = sid_buffer.len() as u32;
+    let mut domain = [0u16; 256];
+    let mut domain_size = domain.len() as u32;
+    let mut sid_use = SID_NAME_USE::default();
+    unsafe {
+        LookupAccountNameW(
+            PCWSTR::null(),
+            PCWSTR(group_wide.as_ptr()),
+            PSID(sid_buffer.as_mut_ptr().cast()),
+            &mut sid_size,
+            PWSTR(domain.as_mut_ptr()),
+            &mut domain_size,
+            &mut sid_use,
+        )
+        .is_ok()
+    }
+}
+
+/// pipe_access 中组对应的 SID 字符串；写成 SID 时校验格式，写成名称时查找账户
+fn group_sid_string(group: &str) -> Option<String> {
+    unsafe {
+        if group.starts_with("S-1-") {
+            let group_wide = to_wide_string(group);
+            let mut sid = PSID::default();
+            ConvertStringSidToSidW(PCWSTR(group_wide.as_ptr()), &mut sid).ok()?;
+            let _ = LocalFree(HLOCAL(sid.0));
+            return Some(group.to_string());
+        }
+
+        let mut sid_buffer = [0u8; 256];
+        if !lookup_group_sid(group, &mut sid_buffer) {
+            return None;
+        }
+        let mut text = PWSTR::null();
+        ConvertSidToStringSidW(PSID(sid_buffer.as_mut_ptr().cast()), &mut text).ok()?;
+        let sid = text.to_string().ok();
+        let _ = LocalFree(HLOCAL(text.0.cast()));
+        sid
+    }
+}
+
+/// 主管道的 DACL：SYSTEM 与 Administrators 完全控制，group_sids 中的组可读写；
+/// authenticated_users 时已验证用户（AU）可读写，network 时网络登录（NU）可读写
+fn build_pipe_sddl(group_sids: &[String], authenticated_users: bool, network: bool) -> String {
+    let mut sddl = "D:P(A;;GA;;;SY)(A;;GA;;;BA)".to_string();
+    for sid in group_sids {
+        sddl.push_str(&format!("(A;;GRGW;;;{})", sid));
+    }
+    if authenticated_users {
+        sddl.push_str("(A;;GRGW;;;AU)");
+    }
+    if network {
+        sddl.push_str("(A;;GRGW;;;NU)");
+    }
+    sddl
+}
+
+/// 主管道的安全描述符；pipe_access 与 remote_hosts 都未配置时返回 None，沿用默认安全描述符。
+/// 配置 remote_hosts 时允许网络登录的调用方连接，其角色再由 remote_role_cap 限制。
+/// 无法解析为 SID 的组退化为允许已验证用户连接，连接后仍由角色检查拒绝不属于任何组的调用方
+pub fn pipe_security(
+    access: Option<&PipeAccessConfig>,
+    remote: bool,
+) -> Option<SecurityDescriptor> {
+    if access.is_none() && !remote {
+        return None;
+    }
+    let mut group_sids = Vec::new();
+    let mut unresolved = false;
+    let groups = access.into_iter().flat_map(|access| {
+        access
+            .observer_groups
+            .iter()
+            .chain(&access.operator_groups)
+            .chain(&access.admin_groups)
+    });
+    for group in groups {
+        match group_sid_string(group) {
+            Some(sid) if !group_sids.contains(&sid) => group_sids.push(sid),
+            Some(_) => {}
+            None => {
+                warn!(
+                    "Unknown group in pipe_access: {}, allowing authenticated users to connect",
+                    group
+                );
+                unresolved = true;
+            }
+        }
+    }
+    let sddl = build_pipe_sddl(&group_sids, unresolved, remote);
+    match SecurityDescriptor::from_sddl(&sddl) {
+        Ok(security) => Some(security),
+        Err(e) => {
+            warn!("Using the default pipe security descriptor: {}", e);
+            None
+        }
+    }
+}
+
+/// 模拟管道客户端以读取其令牌，并据此解析调用方角色
+pub fn caller_role(pipe: HANDLE, access: &PipeAccessConfig) -> Result<Option<Role>, String> {
+    unsafe {
+        ImpersonateNamedPipeClient(pipe)
+            .map_err(|e| format!("ImpersonateNamedPipeClient failed: {:?}", e))?;
+
+        let mut token = HANDLE::default();
+        let opened = OpenThreadToken(GetCurrentThread(), TOKEN_QUERY, true, &mut token);
+
+        // 读取令牌后立即恢复服务自身身份
+        if let Err(e) = RevertToSelf() {
+            if opened.is_ok() {
+                let _ = CloseHandle(token);
+            }
+            return Err(format!("RevertToSelf failed: {:?}", e));
+        }
+        opened.map_err(|e| format!("OpenThreadToken failed: {:?}", e))?;
+
+        let role = resolve_role(access, |group| token_is_member(token, group));
+        let _ = CloseHandle(token);
+        Ok(role)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        allowed_in_safe_mode, build_pipe_sddl, client_from_error, client_role_cap, remote_role_cap,
+        required_role, resolve_role, PipeClient, Role,
+    };
+    use crate::models::{PipeAccessConfig, RemoteHost};
+    use windows::Win32::Foundation::{ERROR_ACCESS_DENIED, ERROR_PIPE_LOCAL};
+
+    fn access() -> PipeAccessConfig {
+        PipeAccessConfig {
+            observer_groups: vec!["Helpdesk".to_string()],
+            operator_groups: vec!["Operators".to_string()],
+            admin_groups: vec!["S-1-5-32-544".to_string()],
+        }
+    }
+
+    #[test]
+    fn mutating_requests_require_higher_roles() {
+        assert_eq!(required_role("status"), Role::Observer);
+        assert_eq!(required_role("shutdown_ready"), Role::Observer);
+        assert_eq!(required_role("update"), Role::Admin);
+        assert_eq!(required_role("stop"), Role::Operator);
+        assert_eq!(required_role("kill"), Role::Operator);
+        assert_eq!(required_role("remove"), Role::Admin);
+        assert!(Role::Observer < Role::Operator && Role::Operator < Role::Admin);
+
+        assert!(allowed_in_safe_mode("status") && allowed_in_safe_mode("resume_all"));
+        assert!(!allowed_in_safe_mode("start") && !allowed_in_safe_mode("update"));
+    }
+
+    #[test]
+    fn highest_matching_role_wins() {
+        let access = access();
+        assert_eq!(
+            resolve_role(&access, |g| g == "Helpdesk" || g == "Operators"),
+            Some(Role::Operator)
+        );
+        assert_eq!(
+            resolve_role(&access, |g| g == "S-1-5-32-544"),
+            Some(Role::Admin)
+        );
+        assert_eq!(resolve_role(&access, |_| false), None);
+    }
+
+    #[test]
+    fn pipe_dacl_grants_configured_groups_and_remote_callers() {
+        let sids = vec!["S-1-5-32-545".to_string()];
+        assert_eq!(
+            build_pipe_sddl(&sids, false, false),
+            "D:P(A;;GA;;;SY)(A;;GA;;;BA)(A;;GRGW;;;S-1-5-32-545)"
+        );
+        assert_eq!(
+            build_pipe_sddl(&[], true, false),
+            "D:P(A;;GA;;;SY)(A;;GA;;;BA)(A;;GRGW;;;AU)"
+        );
+        assert_eq!(
+            build_pipe_sddl(&[], false, true),
+            "D:P(A;;GA;;;SY)(A;;GA;;;BA)(A;;GRGW;;;NU)"
+        );
+    }
+
+    #[test]
+    fn remote_hosts_cap_roles_and_exact_names_win() {
+        let host = |host: &str, max_role: &str| RemoteHost {
+            host: host.to_string(),
+            max_role: max_role.to_string(),
+        };
+        let hosts = vec![host("*", "observer"), host("ADMIN-WS01", "admin")];
+        assert_eq!(remote_role_cap(&hosts, "admin-ws01"), Some(Role::Admin));
+        assert_eq!(remote_role_cap(&hosts, "LAPTOP7"), Some(Role::Observer));
+        assert_eq!(remote_role_cap(&hosts[1..], "LAPTOP7"), None);
+        assert_eq!(
+            remote_role_cap(&[host("ops", "bogus")], "OPS"),
+            Some(Role::Observer)
+        );
+    }
+
+    #[test]
+    fn only_pipe_local_error_means_a_local_caller() {
+        let hosts = vec![RemoteHost {
+            host: "ADMIN-WS01".to_string(),
+            max_role: "admin".to_string(),
+        }];
+        let local = client_from_error(ERROR_PIPE_LOCAL);
+        assert_eq!(local, PipeClient::Local);
+        assert_eq!(client_role_cap(&local, &hosts), Some(Role::Admin));
+
+        let unknown = client_from_error(ERROR_ACCESS_DENIED);
+        assert_eq!(unknown, PipeClient::Unknown(ERROR_ACCESS_DENIED.0));
+        assert_eq!(client_role_cap(&unknown, &hosts), Some(Role::Observer));
+        // 未配置 remote_hosts 时系统拒绝远程连接
+        assert_eq!(client_role_cap(&unknown, &[]), Some(Role::Admin));
+
+        let remote = PipeClient::Remote("LAPTOP7".to_string());
+        assert_eq!(client_role_cap(&remote, &hosts), None);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::session0::to_wide_string;
+use log::{debug, warn};
+use std::path::Path;
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{LocalFree, BOOL, ERROR_SUCCESS, HLOCAL};
+use windows::Win32::Security::Authorization::{
+    ConvertStringSecurityDescriptorToSecurityDescriptorW, SetNamedSecurityInfoW, SDDL_REVISION_1,
+    SE_FILE_OBJECT,
+};
+use windows::Win32::Security::{
+    GetSecurityDescriptorDacl, ACL, DACL_SECURITY_INFORMATION, PROTECTED_DACL_SECURITY_INFORMATION,
+    PSECURITY_DESCRIPTOR, PSID, SECURITY_ATTRIBUTES,
+};
+
+/// 普通用户对受保护路径的访问级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsersAccess {
+    None,
+    Read,
+}
+
+/// 受保护（不继承父目录）的 DACL：SYSTEM 与 Administrators 完全控制，Users 按需只读
+fn build_sddl(users: UsersAccess, inheritable: bool) -> String {
+    let flags = if inheritable { "OICI" } else { "" };
+    let mut sddl = format!("D:P(A;{0};FA;;;SY)(A;{0};FA;;;BA)", flags);
+    if users == UsersAccess::Read {
+        sddl.push_str(&format!("(A;{};FR;;;BU)", flags));
+    }
+    sddl
+}
+
+fn apply_sddl(path: &Path, sddl: &str) -> Result<(), String> {
+    let path_wide = to_wide_string(&path.to_string_lossy());
+    let sddl_wide = to_wide_string(sddl);
+
+    unsafe {
+        let mut descriptor = PSECURITY_DESCRIPTOR::default();
+        ConvertStringSecurityDescriptorToSecurityDescriptorW(
+            PCWSTR(sddl_wide.as_ptr()),
+            SDDL_REVISION_1,
+            &mut descriptor,
+            None,
+        )
+        .map_err(|e| format!("Invalid security descriptor {}: {:?}", sddl, e))?;
+
+        let mut present = BOOL(0);
+        let mut defaulted = BOOL(0);
+        let mut dacl: *mut ACL = std::ptr::null_mut();
+        let result = GetSecurityDescriptorDacl(descriptor, &mut present, &mut dacl, &mut defaulted)
+            .map_err(|e| format!("GetSecurityDescriptorDacl failed: {:?}", e))
+            .and_then(|()| {
+                let status = SetNamedSecurityInfoW(
+                    PCWSTR(path_wide.as_ptr()),
+                    SE_FILE_OBJECT,
+                    DACL_SECURITY_INFORMATION | PROTECTED_DACL_SECURITY_INFORMATION,
+                    PSID::default(),
+                    PSID::default(),
+                    Some(dacl),
+                    None,
+                );
+                if status == ERROR_SUCCESS {
+                    Ok(())
+                } else {
+                    Err(format!("SetNamedSecurityInfoW failed: {:?}", status))
+                }
+            });
+
+        let _ = LocalFree(HLOCAL(descriptor.0));
+        result
+    }
+}
+
+/// 由 SDDL 构造的安全描述符，用于创建命名管道等内核对象
+pub struct SecurityDescriptor(PSECURITY_DESCRIPTOR);
+
+impl SecurityDescriptor {
+    pub fn from_sddl(sddl: &str) -> Result<Self, String> {
+        let sddl_wide = to_wide_string(sddl);
+        let mut descriptor = PSECURITY_DESCRIPTOR::default();
+        unsafe {
+            ConvertStringSecurityDescriptorToSecurityDescriptorW(
+                PCWSTR(sddl_wide.as_ptr()),
+                SDDL_REVISION_1,
+                &mut descriptor,
+                None,
+            )
+        }
+        .map_err(|e| format!("Invalid security descriptor {}: {:?}", sddl, e))?;
+        Ok(Self(descriptor))
+    }
+
+    /// 引用本描述符的 SECURITY_ATTRIBUTES，使用期间本对象必须存活
+    pub fn attributes(&self) -> SECURITY_ATTRIBUTES {
+        SECURITY_ATTRIBUTES {
+            nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+            lpSecurityDescriptor: self.0 .0,
+            bInheritHandle: BOOL(0),
+        }
+    }
+}
+
+impl Drop for SecurityDescriptor {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = LocalFree(HLOCAL(self.0 .0));
+        }
+    }
+}
+
+/// 为文件设置受保护的 DACL；文件不存在时忽略
+pub fn harden_file(path: &Path, users: UsersAccess) {
+    if !path.exists() {
+        return;
+    }
+    match apply_sddl(path, &build_sddl(users, false)) {
+        Ok(()) => debug!("Hardened ACL on {:?}", path),
+        Err(e) => warn!("Failed to harden ACL on {:?}: {}", path, e),
+    }
+}
+
+/// 为目录设置可继承的受保护 DACL，目录下的已有与新建文件一并生效
+pub fn harden_dir(path: &Path, users: UsersAccess) -> Result<(), String> {
+    apply_sddl(path, &build_sddl(users, true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_sddl, UsersAccess};
+
+    #[test]
+    fn file_sddl_denies_users_by_default() {
+        assert_eq!(
+            build_sddl(UsersAccess::None, false),
+            "D:P(A;;FA;;;SY)(A;;FA;;;BA)"
+        );
+    }
+
+    #[test]
+    fn directory_sddl_is_inheritable_and_grants_read() {
+        assert_eq!(
+            build_sddl(UsersAccess::Read, true),
+            "D:P(A;OICI;FA;;;SY)(A;OICI;FA;;;BA)(A;OICI;FR;;;BU)"
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use crate::models::AppContainerConfig;
+use crate::session0::to_wide_string;
+use log::{info, warn};
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{LocalFree, HLOCAL};
+use windows::Win32::Security::Authorization::ConvertStringSidToSidW;
+use windows::Win32::Security::Isolation::{
+    CreateAppContainerProfile, DeriveAppContainerSidFromAppContainerName,
+};
+use windows::Win32::Security::{
+    DeriveCapabilitySidsFromName, FreeSid, PSID, SECURITY_CAPABILITIES, SID_AND_ATTRIBUTES,
+};
+
+const SE_GROUP_ENABLED: u32 = 0x0000_0004;
+const ERROR_ALREADY_EXISTS_HRESULT: i32 = 0x8007_00B7u32 as i32;
+const MAX_CONTAINER_NAME_LEN: usize = 64;
+
+/// AppContainer 名称最长 64 个字符，仅允许字母、数字以及 . - _
+fn validate_container_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > MAX_CONTAINER_NAME_LEN {
+        return Err(format!(
+            "AppContainer name must be 1-{} characters: {:?}",
+            MAX_CONTAINER_NAME_LEN, name
+        ));
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+    {
+        return Err(format!(
+            "AppContainer name contains invalid characters: {:?}",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// 启动 AppContainer 进程所需的 SID 与 SECURITY_CAPABILITIES，析构时统一释放
+pub struct AppContainerLaunch {
+    container_sid: PSID,
+    /// 通过 LocalAlloc 分配的能力 SID
+    owned_sids: Vec<PSID>,
+    capabilities: Vec<SID_AND_ATTRIBUTES>,
+    security_capabilities: Box<SECURITY_CAPABILITIES>,
+}
+
+impl AppContainerLaunch {
+    /// 创建（或复用已存在的）AppContainer 配置文件，并准备能力列表
+    pub fn prepare(config: &AppContainerConfig) -> Result<Self, String> {
+        validate_container_name(&config.name)?;
+
+        let mut launch = Self {
+            container_sid: PSID::default(),
+            owned_sids: Vec::new(),
+            capabilities: Vec::new(),
+            security_capabilities: Box::new(SECURITY_CAPABILITIES::default()),
+        };
+
+        for capability in &config.capabilities {
+            launch.add_capability(capability)?;
+        }
+
+        let name = to_wide_string(&config.name);
+        let display = to_wide_string(&format!("Process Guard sandbox ({})", config.name));
+        unsafe {
+            launch.container_sid = match CreateAppContainerProfile(
+                PCWSTR(name.as_ptr()),
+                PCWSTR(display.as_ptr()),
+                PCWSTR(display.as_ptr()),
+                if launch.capabilities.is_empty() {
+                    None
+                } else {
+                    Some(&launch.capabilities)
+                },
+            ) {
+                Ok(sid) => {
+                    info!("Created AppContainer profile {}", config.name);
+                    sid
+                }
+                Err(e) if e.code().0 == ERROR_ALREADY_EXISTS_HRESULT => {
+                    DeriveAppContainerSidFromAppContainerName(PCWSTR(name.as_ptr())).map_err(
+                        |e| {
+                            format!(
+                                "Failed to derive AppContainer SID for {}: {:?}",
+                                config.name, e
+                            )
+                        },
+                    )?
+                }
+                Err(e) => {
+                    return Err(format!(
+                        "Failed to create AppContainer profile {}: {:?}",
+                        config.name, e
+                    ))
+                }
+            };
+        }
+
+        launch.fill_security_capabilities();
+        Ok(launch)
+    }
+
+    /// 能力可写为名称（如 internetClient）或 SID 字符串（如 S-1-15-3-1）
+    fn add_capability(&mut self, capability: &str) -> Result<(), String> {
+        let capability_wide = to_wide_string(capability);
+
+        unsafe {
+            if capability.starts_with("S-1-") {
+                let mut sid = PSID::default();
+                ConvertStringSidToSidW(PCWSTR(capability_wide.as_ptr()), &mut sid)
+                    .map_err(|e| format!("Invalid capability SID {}: {:?}", capability, e))?;
+                self.push_capability(sid);
+                return Ok(());
+            }
+
+            let mut group_sids: *mut PSID = std::ptr::null_mut();
+            let mut group_count: u32 = 0;
+            let mut sids: *mut PSID = std::ptr::null_mut();
+            let mut count: u32 = 0;
+            DeriveCapabilitySidsFromName(
+                PCWSTR(capability_wide.as_ptr()),
+                &mut group_sids,
+                &mut group_count,
+                &mut sids,
+                &mut count,
+            )
+            .map_err(|e| format!("Unknown capability {}: {:?}", capability, e))?;
+
+            for i in 0..group_count as usize {
+                let _ = LocalFree(HLOCAL((*group_sids.add(i)).0));
+            }
+            let _ = LocalFree(HLOCAL(group_sids.cast()));
+
+            for i in 0..count as usize {
+                self.push_capability(*sids.add(i));
+            }
+            let _ = LocalFree(HLOCAL(sids.cast()));
+
+            if count == 0 {
+                warn!("Capability {} did not resolve to any SID", capability);
+            }
+        }
+        Ok(())
+    }
+
+    fn push_capability(&mut self, sid: PSID) {
+        self.owned_sids.push(sid);
+        self.capabilities.push(SID_AND_ATTRIBUTES {
+            Sid: sid,
+            Attributes: SE_GROUP_ENABLED,
+        });
+    }
+
+    fn fill_security_capabilities(&mut self) {
+        *self.security_capabilities = SECURITY_CAPABILITIES {
+            AppContainerSid: self.container_sid,
+            Capabilities: if self.capabilities.is_empty() {
+                std::ptr::null_mut()
+            } else {
+                self.capabilities.as_mut_ptr()
+            },
+            CapabilityCount: self.capabilities.len() as u32,
+            Reserved: 0,
+        };
+    }
+
+    /// PROC_THREAD_ATTRIBUTE_SECURITY_CAPABILITIES 的属性值，地址在 self 存活期间不变
+    pub fn security_capabilities(&self) -> &SECURITY_CAPABILITIES {
+        &self.security_capabilities
+    }
+}
+
+impl Drop for AppContainerLaunch {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.container_sid.0.is_null() {
+                let _ = FreeSid(self.container_sid);
+            }
+            for sid in &self.owned_sids {
+                let _ = LocalFree(HLOCAL(sid.0));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::validate_container_name;
+
+    #[test]
+    fn accepts_simple_container_names() {
+        assert!(validate_container_name("ProcessGuard.Vendor-Helper_1").is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_container_names() {
+        assert!(validate_container_name("").is_err());
+        assert!(validate_container_name("has space").is_err());
+        assert!(validate_container_name(&"a".repeat(65)).is_err());
+    }
+}
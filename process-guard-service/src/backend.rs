@@ -0,0 +1,290 @@
+use crate::appcontainer::AppContainerLaunch;
+use crate::models::{IoPriority, PlacementConfig, PriorityClass};
+use crate::output::{capture_into, OutputBuffer};
+use crate::proc_attributes::LaunchAttributes;
+use crate::session0::{
+    check_process_alive, find_process_by_path, find_process_instance, kill_process_checked,
+    process_creation_time, set_process_priority, snapshot_processes, start_process_in_session0,
+    wait_for_process_exit, DesktopOptions, ProcessEntry,
+};
+use log::warn;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// 启动一个进程所需的参数
+pub struct SpawnRequest<'a> {
+    pub exe_path: &'a str,
+    pub working_dir: Option<&'a str>,
+    pub args: Option<&'a str>,
+    pub minimize: bool,
+    pub no_window: bool,
+    pub app_container: Option<&'a AppContainerLaunch>,
+    /// 设置 PROC_THREAD_ATTRIBUTE_CHILD_PROCESS_POLICY，进程无法再创建子进程
+    pub block_child_processes: bool,
+    /// 启动到的桌面与是否设置 UIAccess
+    pub desktop: DesktopOptions,
+    /// NUMA 节点 / 处理器组放置
+    pub placement: Option<&'a PlacementConfig>,
+    pub priority_class: Option<PriorityClass>,
+    pub io_priority: Option<IoPriority>,
+    /// 设置后 stdout/stderr 写入该缓冲
+    pub output: Option<&'a Arc<OutputBuffer>>,
+}
+
+/// 守护引擎使用的进程操作；服务中由 Win32Backend 调用系统接口，测试中可替换为 MockBackend
+pub trait ProcessBackend: Send + Sync {
+    /// 启动进程，返回 PID
+    fn spawn(&self, request: &SpawnRequest) -> Result<u32, String>;
+
+    fn is_alive(&self, pid: u32) -> bool;
+
+    /// 终止进程；created 为记录的创建时间，PID 已被复用时不终止
+    fn kill(&self, pid: u32, created: Option<u64>) -> bool;
+
+    /// 等待进程退出，超时返回 false；created 为记录的创建时间，PID 已被复用时视为已退出
+    fn wait_for_exit(&self, pid: u32, created: Option<u64>, timeout: Duration) -> bool;
+
+    /// 查找已在运行的实例；args 为 Some 时启动参数也必须一致
+    fn find_by_path(&self, exe_path: &str, args: Option<&str>) -> Option<u32>;
+
+    /// 进程创建时间，与 PID 一起识别被复用的 PID
+    fn creation_time(&self, pid: u32) -> Option<u64>;
+
+    /// 一次遍历获取所有进程的父进程、线程数与映像名
+    fn snapshot(&self) -> HashMap<u32, ProcessEntry>;
+
+    /// PID 是否仍指向记录中的那个进程；未记录创建时间时不做校验
+    fn is_same_process(&self, pid: u32, created: Option<u64>) -> bool {
+        created.is_none() || self.creation_time(pid) == created
+    }
+}
+
+/// 通过 Win32 接口在活动用户会话中启动和管理进程
+pub struct Win32Backend;
+
+impl ProcessBackend for Win32Backend {
+    fn spawn(&self, request: &SpawnRequest) -> Result<u32, String> {
+        if !std::path::Path::new(request.exe_path).exists() {
+            return Err(format!("Executable not found: {}", request.exe_path));
+        }
+        let attributes = LaunchAttributes::build(
+            request.app_container,
+            request.block_child_processes,
+            request.placement,
+        )?;
+        let std_output = request
+            .output
+            .map(|buffer| capture_into(buffer.clone(), request.exe_path))
+            .transpose()?;
+        let result = start_process_in_session0(
+            request.exe_path,
+            request.working_dir,
+            request.args,
+            request.minimize,
+            request.no_window,
+            attributes.as_ref(),
+            &request.desktop,
+            std_output,
+        )
+        .map(|info| {
+            if let Some(attributes) = &attributes {
+                if let Err(e) = attributes.restrict_process(info.process_handle) {
+                    warn!("{}: {}", request.exe_path, e);
+                }
+            }
+            if let Err(e) = set_process_priority(
+                info.process_handle,
+                request.priority_class,
+                request.io_priority,
+            ) {
+                warn!("{}: {}", request.exe_path, e);
+            }
+            info.process_id
+        });
+        if let Some(handle) = std_output {
+            unsafe {
+                let _ = windows::Win32::Foundation::CloseHandle(handle);
+            }
+        }
+        result
+    }
+
+    fn is_alive(&self, pid: u32) -> bool {
+        check_process_alive(pid)
+    }
+
+    fn kill(&self, pid: u32, created: Option<u64>) -> bool {
+        kill_process_checked(pid, created)
+    }
+
+    fn wait_for_exit(&self, pid: u32, created: Option<u64>, timeout: Duration) -> bool {
+        wait_for_process_exit(pid, created, timeout)
+    }
+
+    fn find_by_path(&self, exe_path: &str, args: Option<&str>) -> Option<u32> {
+        match args {
+            Some(args) => find_process_instance(exe_path, Some(args)),
+            None => find_process_by_path(exe_path),
+        }
+    }
+
+    fn creation_time(&self, pid: u32) -> Option<u64> {
+        process_creation_time(pid)
+    }
+
+    fn snapshot(&self) -> HashMap<u32, ProcessEntry> {
+        snapshot_processes()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MockProcess {
+    exe_path: String,
+    args: String,
+    created: u64,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    next_pid: u32,
+    clock: u64,
+    running: HashMap<u32, MockProcess>,
+    spawned: Vec<u32>,
+    killed: Vec<u32>,
+    spawn_error: Option<String>,
+}
+
+/// 内存中的进程表：不启动真实进程即可测试重启、退避与心跳逻辑
+#[derive(Debug, Default)]
+pub struct MockBackend {
+    state: Mutex<MockState>,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 模拟进程自行退出
+    pub fn exit(&self, pid: u32) {
+        self.state.lock().unwrap().running.remove(&pid);
+    }
+
+    /// 模拟服务启动前已在运行的进程，返回其 PID
+    pub fn add_running(&self, exe_path: &str, args: Option<&str>) -> u32 {
+        self.state.lock().unwrap().insert(exe_path, args)
+    }
+
+    /// 之后的 spawn 都返回该错误；None 恢复正常
+    pub fn fail_spawns(&self, error: Option<&str>) {
+        self.state.lock().unwrap().spawn_error = error.map(str::to_string);
+    }
+
+    /// 按启动顺序返回 spawn 得到的 PID
+    pub fn spawned(&self) -> Vec<u32> {
+        self.state.lock().unwrap().spawned.clone()
+    }
+
+    /// 按调用顺序返回被终止的 PID
+    pub fn killed(&self) -> Vec<u32> {
+        self.state.lock().unwrap().killed.clone()
+    }
+}
+
+impl MockState {
+    fn insert(&mut self, exe_path: &str, args: Option<&str>) -> u32 {
+        self.next_pid += 4;
+        self.clock += 1;
+        let pid = 10_000 + self.next_pid;
+        self.running.insert(
+            pid,
+            MockProcess {
+                exe_path: exe_path.to_lowercase(),
+                args: args.unwrap_or_default().trim().to_string(),
+                created: self.clock,
+            },
+        );
+        pid
+    }
+}
+
+impl ProcessBackend for MockBackend {
+    fn spawn(&self, request: &SpawnRequest) -> Result<u32, String> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(error) = &state.spawn_error {
+            return Err(error.clone());
+        }
+        let pid = state.insert(request.exe_path, request.args);
+        state.spawned.push(pid);
+        Ok(pid)
+    }
+
+    fn is_alive(&self, pid: u32) -> bool {
+        self.state.lock().unwrap().running.contains_key(&pid)
+    }
+
+    fn kill(&self, pid: u32, created: Option<u64>) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let reused = matches!(
+            (state.running.get(&pid), created),
+            (Some(process), Some(created)) if process.created != created
+        );
+        if !reused && state.running.remove(&pid).is_some() {
+            state.killed.push(pid);
+        }
+        true
+    }
+
+    fn wait_for_exit(&self, pid: u32, created: Option<u64>, _timeout: Duration) -> bool {
+        !self.is_alive(pid) || !self.is_same_process(pid, created)
+    }
+
+    fn find_by_path(&self, exe_path: &str, args: Option<&str>) -> Option<u32> {
+        let exe_path = exe_path.to_lowercase();
+        let state = self.state.lock().unwrap();
+        let mut matches: Vec<u32> = state
+            .running
+            .iter()
+            .filter(|(_, process)| {
+                process.exe_path == exe_path
+                    && match args {
+                        Some(args) => process.args == args.trim(),
+                        None => true,
+                    }
+            })
+            .map(|(pid, _)| *pid)
+            .collect();
+        matches.sort_unstable();
+        matches.first().copied()
+    }
+
+    fn creation_time(&self, pid: u32) -> Option<u64> {
+        self.state
+            .lock()
+            .unwrap()
+            .running
+            .get(&pid)
+            .map(|process| process.created)
+    }
+
+    fn snapshot(&self) -> HashMap<u32, ProcessEntry> {
+        self.state
+            .lock()
+            .unwrap()
+            .running
+            .iter()
+            .map(|(pid, process)| {
+                let image_name = process.exe_path.rsplit('\\').next().unwrap_or_default();
+                (
+                    *pid,
+                    ProcessEntry {
+                        parent_pid: 0,
+                        thread_count: 1,
+                        image_name: image_name.to_string(),
+                    },
+                )
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,44 @@
+//! 集成测试中被守护的一次性进程。
+//!
+//! 用法：
+//!   pg-test-helper sleep
+//!   pg-test-helper crash-after <秒>
+//!   pg-test-helper heartbeat <管道名> <监控项ID> <间隔毫秒>
+
+use process_guard_core::models::PipeRequest;
+use process_guard_core::pipe_client;
+use std::time::Duration;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let arg = |index: usize| args.get(index).map(String::as_str).unwrap_or_default();
+
+    match arg(0) {
+        "sleep" => loop {
+            std::thread::sleep(Duration::from_secs(60));
+        },
+        "crash-after" => {
+            let secs = arg(1).parse().unwrap_or(1);
+            std::thread::sleep(Duration::from_secs(secs));
+            eprintln!("crash-after: exiting with code 3");
+            std::process::exit(3);
+        }
+        "heartbeat" => {
+            let interval = Duration::from_millis(arg(3).parse().unwrap_or(1000));
+            let request = PipeRequest::Heartbeat {
+                item_id: arg(2).to_string(),
+                timestamp: None,
+            };
+            loop {
+                if let Err(e) = pipe_client::request(arg(1), &request) {
+                    eprintln!("heartbeat failed: {}", e);
+                }
+                std::thread::sleep(interval);
+            }
+        }
+        other => {
+            eprintln!("unknown mode: {}", other);
+            std::process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use crate::config::{get_config_file_path, load_config, save_config};
+use crate::models::{DuplicatePathPolicy, MonitorItem, PipeRequest};
+use crate::{pipe_client, service};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+/// 扫描目录时向下查找的层数（目录本身为第 1 层）
+const SCAN_DEPTH: usize = 2;
+/// 安装、卸载、更新与崩溃上报程序通常不需要守护，扫描时跳过
+const SKIPPED_PREFIXES: [&str; 9] = [
+    "unins",
+    "uninstall",
+    "setup",
+    "install",
+    "update",
+    "crashpad",
+    "crashreport",
+    "vc_redist",
+    "dotnet",
+];
+
+/// --init 的选项
+pub struct InitOptions {
+    /// 要扫描的目录或直接指定的 exe 路径
+    pub sources: Vec<PathBuf>,
+    /// 不逐个询问，接受找到的全部程序
+    pub assume_yes: bool,
+    /// 要求被守护程序通过管道发送心跳；默认只检查进程是否存活
+    pub require_heartbeat: bool,
+    /// 写入配置后安装并启动服务
+    pub install: bool,
+}
+
+fn is_candidate(file_name: &str) -> bool {
+    let lower = file_name.to_lowercase();
+    lower.ends_with(".exe") && !SKIPPED_PREFIXES.iter().any(|p| lower.starts_with(p))
+}
+
+fn scan_dir(dir: &Path, depth: usize, found: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            if depth > 1 {
+                scan_dir(&path, depth - 1, found);
+            }
+        } else if path
+            .file_name()
+            .is_some_and(|name| is_candidate(&name.to_string_lossy()))
+        {
+            found.push(path);
+        }
+    }
+}
+
+/// 目录按 SCAN_DEPTH 查找 exe，文件原样返回；服务程序自身不会被列入
+fn find_executables(source: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut found = Vec::new();
+    if source.is_dir() {
+        scan_dir(source, SCAN_DEPTH, &mut found);
+    } else if source.is_file() {
+        found.push(source.to_path_buf());
+    } else {
+        return Err(format!("{} does not exist", source.display()));
+    }
+    let found = found
+        .into_iter()
+        .map(|path| fs::canonicalize(&path).map(strip_verbatim).unwrap_or(path))
+        .collect::<Vec<_>>();
+    let own = std::env::current_exe()
+        .and_then(fs::canonicalize)
+        .map(strip_verbatim)
+        .ok();
+    Ok(found
+        .into_iter()
+        .filter(|path| own.as_ref() != Some(path))
+        .collect())
+}
+
+/// canonicalize 返回 \\?\ 前缀的路径，写入配置前去掉
+fn strip_verbatim(path: PathBuf) -> PathBuf {
+    match path.to_str().and_then(|p| p.strip_prefix(r"\\?\")) {
+        Some(stripped) if !stripped.starts_with("UNC\\") => PathBuf::from(stripped),
+        _ => path,
+    }
+}
+
+/// 由程序名生成可读的监控项 ID（小写字母数字与 '-'），与已有 ID 重复时追加序号
+fn item_id(stem: &str, taken: &HashSet<String>) -> String {
+    let mut base: String = stem
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    base = base.trim_matches('-').to_string();
+    if base.is_empty() {
+        base = "item".to_string();
+    }
+    if !taken.contains(&base) {
+        return base;
+    }
+    (2..)
+        .map(|n| format!("{}-{}", base, n))
+        .find(|id| !taken.contains(id))
+        .unwrap_or(base)
+}
+
+fn confirm(prompt: &str, default: bool) -> bool {
+    print!("{} [{}] ", prompt, if default { "Y/n" } else { "y/N" });
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer).is_err() {
+        return default;
+    }
+    match answer.trim().to_lowercase().as_str() {
+        "" => default,
+        "y" | "yes" => true,
+        _ => false,
+    }
+}
+
+/// 生成或补充 config.json：已有配置中的监控项保留，已监控的路径跳过
+pub fn run(options: &InitOptions) -> Result<(), String> {
+    let interactive = !options.assume_yes && io::stdin().is_terminal();
+    let mut config = load_config();
+    let allow_duplicates = config.settings.allow_duplicate_exe_paths;
+    // 扫描得到的是默认配置，merge 策略下也不覆盖已有监控项
+    let keep_all = config.settings.duplicate_exe_paths == DuplicatePathPolicy::KeepAll;
+    let mut taken: HashSet<String> = config.items.iter().map(|item| item.id.clone()).collect();
+    let mut added = 0;
+
+    for source in &options.sources {
+        for exe in find_executables(source)? {
+            let exe_path = exe.to_string_lossy().to_string();
+            let name = exe
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| exe_path.clone());
+            let mut item = MonitorItem::new(exe_path, name);
+            let key = item.instance_key(allow_duplicates);
+            if !keep_all
+                && config
+                    .items
+                    .iter()
+                    .any(|existing| existing.instance_key(allow_duplicates) == key)
+            {
+                println!("  skipped {} (already monitored)", item.exe_path);
+                continue;
+            }
+            if interactive && !confirm(&format!("Guard {}?", item.exe_path), true) {
+                continue;
+            }
+            item.id = item_id(&item.name, &taken);
+            item.heartbeat_required = options.require_heartbeat;
+            println!("  added {} ({})", item.name, item.exe_path);
+            taken.insert(item.id.clone());
+            config.items.push(item);
+            added += 1;
+        }
+    }
+
+    if added == 0 {
+        println!("No new programs to add");
+    } else {
+        save_config(&config).map_err(|e| format!("failed to save config: {}", e))?;
+        println!(
+            "Wrote {} new items to {}",
+            added,
+            get_config_file_path().display()
+        );
+    }
+
+    let install =
+        options.install || (interactive && confirm("Install and start the service now?", false));
+    if install {
+        install_and_start()
+    } else {
+        if added > 0 && service::is_service_running() {
+            println!("Run --reload to apply them to the running service");
+        }
+        Ok(())
+    }
+}
+
+/// 安装（如未安装）并启动服务；服务已在运行时重新加载配置
+fn install_and_start() -> Result<(), String> {
+    if !service::is_service_installed() {
+        let exe_path = std::env::current_exe()
+            .map_err(|e| format!("failed to locate the service executable: {}", e))?;
+        service::install_service(&exe_path.to_string_lossy())?;
+        println!("Service installed");
+    }
+    if service::is_service_running() {
+        let response = pipe_client::send_to(None, &PipeRequest::Reload)?;
+        println!("{}", response.message.unwrap_or_default());
+    } else {
+        service::start_service(false)?;
+        println!("Service started");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_candidate, item_id};
+    use std::collections::HashSet;
+
+    #[test]
+    fn skips_installers_and_non_executables() {
+        assert!(is_candidate("Worker.exe"));
+        assert!(!is_candidate("unins000.exe"));
+        assert!(!is_candidate("Setup.EXE"));
+        assert!(!is_candidate("worker.dll"));
+    }
+
+    #[test]
+    fn generates_unique_readable_ids() {
+        let mut taken = HashSet::new();
+        assert_eq!(item_id("Order Worker", &taken), "order-worker");
+        taken.insert("order-worker".to_string());
+        assert_eq!(item_id("Order Worker", &taken), "order-worker-2");
+        assert_eq!(item_id("测试", &taken), "item");
+    }
+}
@@ -0,0 +1,183 @@
+use crate::exe_glob;
+use crate::models::{CleanupAction, CleanupRule};
+use log::{info, warn};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use time::OffsetDateTime;
+
+/// 未设置 archive_dir 时的归档目录名
+const DEFAULT_ARCHIVE_DIR: &str = "cleanup-archive";
+
+fn timestamp() -> String {
+    let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+    now.format(time::macros::format_description!(
+        "[year][month][day]-[hour][minute][second]"
+    ))
+    .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// 服务以 SYSTEM 身份运行，规则中的路径只能是程序目录下的相对路径，不能借此删除或移动任意文件
+fn check_relative(path: &str) -> Result<(), String> {
+    let path = path.trim();
+    let inside = !path.is_empty()
+        && Path::new(path)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if inside {
+        Ok(())
+    } else {
+        Err(format!(
+            "cleanup path {} must be relative to the program directory and must not contain ..",
+            path
+        ))
+    }
+}
+
+/// 校验 pattern 与 archive_dir，供 add / update 拒绝越出程序目录的规则
+pub fn validate(rules: &[CleanupRule]) -> Result<(), String> {
+    for rule in rules {
+        check_relative(&rule.pattern)?;
+        if let Some(dir) = &rule.archive_dir {
+            check_relative(dir)?;
+        }
+    }
+    Ok(())
+}
+
+/// 按 base_dir 解析规则中的路径；越出程序目录时返回 Err
+fn resolve(path: &str, base_dir: &Path) -> Result<PathBuf, String> {
+    check_relative(path)?;
+    Ok(base_dir.join(path.trim()))
+}
+
+/// 通过链接（junction、符号链接）解析到程序目录之外的文件不处理
+fn is_inside(file: &Path, base_dir: &Path) -> bool {
+    match (file.canonicalize(), base_dir.canonicalize()) {
+        (Ok(file), Ok(base_dir)) => file.starts_with(base_dir),
+        _ => false,
+    }
+}
+
+/// 移动文件；跨卷时改为复制后删除
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)?;
+    fs::remove_file(from)
+}
+
+fn apply(rule: &CleanupRule, file: &Path, archive_dir: &Path) -> Result<(), String> {
+    let result = match rule.action {
+        CleanupAction::Delete => fs::remove_file(file),
+        CleanupAction::Archive => fs::create_dir_all(archive_dir).and_then(|_| {
+            move_file(
+                file,
+                &archive_dir.join(file.file_name().unwrap_or_default()),
+            )
+        }),
+    };
+    result.map_err(|e| format!("{}: {}", file.display(), e))
+}
+
+/// 按顺序执行清理规则，base_dir 为程序所在目录；单个文件失败只记录警告，返回处理的文件数
+pub fn run(item_name: &str, rules: &[CleanupRule], base_dir: &Path) -> usize {
+    let stamp = timestamp();
+    let mut cleaned = 0;
+    for rule in rules {
+        // 手动编辑 config.json 时不经过管道校验
+        let resolved = resolve(&rule.pattern, base_dir).and_then(|pattern| {
+            let archive_dir = resolve(
+                rule.archive_dir.as_deref().unwrap_or(DEFAULT_ARCHIVE_DIR),
+                base_dir,
+            )?;
+            Ok((pattern, archive_dir.join(&stamp)))
+        });
+        let (pattern, archive_dir) = match resolved {
+            Ok(paths) => paths,
+            Err(e) => {
+                warn!("Skipping cleanup rule for {}: {}", item_name, e);
+                continue;
+            }
+        };
+        for file in exe_glob::matches(&pattern.to_string_lossy()) {
+            if !is_inside(&file, base_dir) {
+                warn!(
+                    "Skipping cleanup of {} for {}: it resolves outside the program directory",
+                    file.display(),
+                    item_name
+                );
+                continue;
+            }
+            match apply(rule, &file, &archive_dir) {
+                Ok(()) => cleaned += 1,
+                Err(e) => warn!("Cleanup for {} failed: {}", item_name, e),
+            }
+        }
+    }
+    if cleaned > 0 {
+        info!(
+            "Cleaned up {} file(s) for {} before relaunch",
+            cleaned, item_name
+        );
+    }
+    cleaned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run, validate};
+    use crate::models::{CleanupAction, CleanupRule};
+    use std::fs;
+
+    #[test]
+    fn deletes_and_archives_matching_files() {
+        let dir = std::env::temp_dir().join(format!("pg-cleanup-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("data")).unwrap();
+        for name in ["app.lock", "data\\session.tmp", "data\\keep.db"] {
+            fs::write(dir.join(name), "x").unwrap();
+        }
+        let rules = [
+            CleanupRule {
+                pattern: "*.lock".to_string(),
+                action: CleanupAction::Delete,
+                archive_dir: None,
+            },
+            CleanupRule {
+                pattern: "data\\*.tmp".to_string(),
+                action: CleanupAction::Archive,
+                archive_dir: Some("crashed".to_string()),
+            },
+        ];
+        assert_eq!(run("app", &rules, &dir), 2);
+        assert!(!dir.join("app.lock").exists());
+        assert!(!dir.join("data\\session.tmp").exists());
+        assert!(dir.join("data\\keep.db").exists());
+        assert_eq!(fs::read_dir(dir.join("crashed")).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_paths_outside_the_program_directory() {
+        let rule = |pattern: &str, archive_dir: Option<&str>| CleanupRule {
+            pattern: pattern.to_string(),
+            action: CleanupAction::Archive,
+            archive_dir: archive_dir.map(str::to_string),
+        };
+        assert!(validate(&[rule("data\\*.tmp", Some("crashed"))]).is_ok());
+        assert!(validate(&[rule("C:\\Windows\\System32\\*.dll", None)]).is_err());
+        assert!(validate(&[rule("\\Windows\\*.log", None)]).is_err());
+        assert!(validate(&[rule("..\\other\\*.db", None)]).is_err());
+        assert!(validate(&[rule("*.lock", Some("C:\\Users\\Public"))]).is_err());
+
+        let dir = std::env::temp_dir().join(format!("pg-cleanup-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let outside = dir.with_extension("victim");
+        fs::write(&outside, "x").unwrap();
+        let pattern = format!("..\\{}", outside.file_name().unwrap().to_string_lossy());
+        assert_eq!(run("app", &[rule(&pattern, None)], &dir), 0);
+        assert!(outside.exists());
+        fs::remove_file(&outside).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,88 @@
+use std::fmt;
+use std::time::{Duration, Instant, SystemTime};
+
+/// 单调时钟经过的时间比预期多出该值以上，视为系统曾睡眠/休眠（或服务被长时间挂起）
+const STALL_TOLERANCE: Duration = Duration::from_secs(15);
+/// 墙上时间与单调时钟的差超过该值，视为系统时间被调整
+const WALL_CLOCK_TOLERANCE_MS: u64 = 10_000;
+
+/// 两次观测之间检测到的时钟异常
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockEvent {
+    /// 检查循环停顿了这么久，通常是系统从睡眠或休眠恢复
+    Resumed(Duration),
+    /// 墙上时间相对单调时钟跳变的毫秒数（正数为向前）。单调时钟不计睡眠时间时，
+    /// 从睡眠恢复也表现为向前跳变
+    WallClockJump(i64),
+}
+
+impl fmt::Display for ClockEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClockEvent::Resumed(gap) => {
+                write!(
+                    f,
+                    "check loop stalled for {}s (system resumed?)",
+                    gap.as_secs()
+                )
+            }
+            ClockEvent::WallClockJump(ms) => write!(f, "system time jumped by {}s", ms / 1000),
+        }
+    }
+}
+
+fn classify(expected: Duration, monotonic: Duration, wall_ms: i64) -> Option<ClockEvent> {
+    if monotonic > expected + STALL_TOLERANCE {
+        return Some(ClockEvent::Resumed(monotonic));
+    }
+    let drift = wall_ms - monotonic.as_millis() as i64;
+    (drift.unsigned_abs() > WALL_CLOCK_TOLERANCE_MS).then_some(ClockEvent::WallClockJump(drift))
+}
+
+/// 同时记录单调时钟与墙上时间，之后比较两者经过的时间
+pub struct ClockWatch {
+    instant: Instant,
+    wall: SystemTime,
+}
+
+impl ClockWatch {
+    pub fn new() -> Self {
+        Self {
+            instant: Instant::now(),
+            wall: SystemTime::now(),
+        }
+    }
+
+    /// 与创建时比较，expected 为期间预期经过的时间
+    pub fn observe(&self, expected: Duration) -> Option<ClockEvent> {
+        let monotonic = self.instant.elapsed();
+        let wall_ms = match SystemTime::now().duration_since(self.wall) {
+            Ok(elapsed) => elapsed.as_millis() as i64,
+            Err(e) => -(e.duration().as_millis() as i64),
+        };
+        classify(expected, monotonic, wall_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{classify, ClockEvent};
+    use std::time::Duration;
+
+    #[test]
+    fn detects_stalls_and_wall_clock_jumps() {
+        let interval = Duration::from_secs(3);
+        assert_eq!(
+            classify(interval, Duration::from_millis(3_050), 3_040),
+            None
+        );
+        assert_eq!(
+            classify(interval, Duration::from_secs(600), 600_000),
+            Some(ClockEvent::Resumed(Duration::from_secs(600)))
+        );
+        assert_eq!(
+            classify(interval, Duration::from_secs(3), -3_597_000),
+            Some(ClockEvent::WallClockJump(-3_600_000))
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use crate::exe_glob::wildcard_match;
+use crate::models::{Config, MachineCondition};
+use std::collections::HashMap;
+
+/// 去掉可选的 HKLM\ 或 HKEY_LOCAL_MACHINE\ 前缀
+fn registry_subkey(key: &str) -> &str {
+    for prefix in ["HKLM\\", "HKEY_LOCAL_MACHINE\\"] {
+        if key.len() >= prefix.len() && key[..prefix.len()].eq_ignore_ascii_case(prefix) {
+            return &key[prefix.len()..];
+        }
+    }
+    key
+}
+
+fn read_registry_value(key: &str, value: &str) -> Option<String> {
+    let subkey = registry_subkey(key);
+    crate::import::read_registry_string(subkey, value)
+        .or_else(|| crate::import::read_registry_dword(subkey, value).map(|v| v.to_string()))
+}
+
+/// 按给定的本机信息求值；不满足时返回第一个不满足的条件说明
+fn check(
+    condition: &MachineCondition,
+    hostname: &str,
+    env: impl Fn(&str) -> Option<String>,
+    registry: impl Fn(&str, &str) -> Option<String>,
+) -> Result<(), String> {
+    if let Some(pattern) = &condition.hostname {
+        if !wildcard_match(pattern, hostname) {
+            return Err(format!("hostname {} does not match {}", hostname, pattern));
+        }
+    }
+    if let Some(cond) = &condition.env {
+        match (env(&cond.name), &cond.equals) {
+            (None, _) => return Err(format!("environment variable {} is not set", cond.name)),
+            (Some(actual), Some(expected)) if !actual.eq_ignore_ascii_case(expected) => {
+                return Err(format!(
+                    "environment variable {} is {}, expected {}",
+                    cond.name, actual, expected
+                ))
+            }
+            _ => {}
+        }
+    }
+    if let Some(cond) = &condition.registry {
+        let location = format!("{}\\{}", cond.key, cond.value);
+        match (registry(&cond.key, &cond.value), &cond.equals) {
+            (None, _) => return Err(format!("registry value {} does not exist", location)),
+            (Some(actual), Some(expected)) if !actual.eq_ignore_ascii_case(expected) => {
+                return Err(format!(
+                    "registry value {} is {}, expected {}",
+                    location, actual, expected
+                ))
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// 在本机求值条件
+pub fn evaluate(condition: &MachineCondition) -> Result<(), String> {
+    check(
+        condition,
+        &crate::peer::node_name(),
+        |name| std::env::var(name).ok(),
+        read_registry_value,
+    )
+}
+
+/// 条件在本机不满足的监控项：id -> 原因
+pub fn inactive_items(config: &Config) -> HashMap<String, String> {
+    config
+        .items
+        .iter()
+        .filter_map(|item| {
+            let condition = item.condition.as_ref()?;
+            evaluate(condition)
+                .err()
+                .map(|reason| (item.id.clone(), reason))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, registry_subkey};
+    use crate::models::{EnvCondition, MachineCondition, RegistryCondition};
+
+    #[test]
+    fn all_configured_conditions_must_hold() {
+        let condition = MachineCondition {
+            hostname: Some("POS-*".to_string()),
+            env: Some(EnvCondition {
+                name: "SITE".to_string(),
+                equals: Some("north".to_string()),
+            }),
+            registry: Some(RegistryCondition {
+                key: r"HKLM\SOFTWARE\Contoso".to_string(),
+                value: "Role".to_string(),
+                equals: None,
+            }),
+        };
+        let env = |name: &str| (name == "SITE").then(|| "NORTH".to_string());
+        let registry = |_: &str, _: &str| Some("till".to_string());
+
+        assert!(check(&condition, "pos-017", env, registry).is_ok());
+        assert!(check(&condition, "office-01", env, registry).is_err());
+        assert!(check(&condition, "pos-017", |_: &str| None, registry).is_err());
+        assert!(check(&condition, "pos-017", env, |_: &str, _: &str| None).is_err());
+        assert!(check(&MachineCondition::default(), "any", env, registry).is_ok());
+        assert_eq!(
+            registry_subkey(r"hklm\SOFTWARE\Contoso"),
+            r"SOFTWARE\Contoso"
+        );
+    }
+}
@@ -1,6 +1,12 @@
-use crate::models::{Config, MonitorItem, CONFIG_BACKUP_FILE_NAME, CONFIG_FILE_NAME};
+use crate::acl::{harden_file, UsersAccess};
+use crate::models::{
+    Config, DuplicatePathPolicy, LogSink, MonitorItem, CONFIG_BACKUP_FILE_NAME, CONFIG_FILE_NAME,
+    EVENT_DB_FILE_NAME,
+};
 use log::{debug, error, info, warn};
-use std::collections::HashMap;
+use serde_json::{Map, Value};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::fs;
 use std::io;
@@ -20,6 +26,10 @@ pub fn get_config_backup_file_path() -> PathBuf {
     get_config_dir().join(CONFIG_BACKUP_FILE_NAME)
 }
 
+pub fn get_event_db_path() -> PathBuf {
+    get_config_dir().join(EVENT_DB_FILE_NAME)
+}
+
 #[cfg(test)]
 fn get_config_backup_file_path_for_tests(base_dir: &Path) -> PathBuf {
     base_dir.join(CONFIG_BACKUP_FILE_NAME)
@@ -43,7 +53,15 @@ pub fn load_config() -> Config {
     info!("Loading config from: {:?}", config_path);
     info!("Backup config path: {:?}", backup_path);
 
-    load_config_from_paths(&config_path, &backup_path)
+    let config = load_config_from_paths(&config_path, &backup_path);
+    harden_config_files();
+    config
+}
+
+/// 配置文件中可能含有 webhook 凭据等敏感信息，仅允许 SYSTEM 与管理员访问
+fn harden_config_files() {
+    harden_file(&get_config_file_path(), UsersAccess::None);
+    harden_file(&get_config_backup_file_path(), UsersAccess::None);
 }
 
 #[derive(Debug)]
@@ -52,13 +70,14 @@ enum ConfigLoadError {
     Empty,
     Read(io::Error),
     Parse(serde_json::Error),
+    Template(String),
 }
 
 fn load_config_from_paths(config_path: &Path, backup_path: &Path) -> Config {
     match read_config_file(config_path) {
         Ok(config) => {
             info!("Loaded primary config from: {:?}", config_path);
-            return normalize_loaded_config(config, Some(config_path));
+            return normalize_loaded_config(config);
         }
         Err(err) => {
             warn!(
@@ -72,7 +91,7 @@ fn load_config_from_paths(config_path: &Path, backup_path: &Path) -> Config {
     match read_config_file(backup_path) {
         Ok(config) => {
             info!("Recovered config from backup: {:?}", backup_path);
-            let config = normalize_loaded_config(config, None);
+            let config = normalize_loaded_config(config);
 
             if let Err(e) = save_config_to_path(config_path, &config) {
                 error!(
@@ -80,7 +99,10 @@ fn load_config_from_paths(config_path: &Path, backup_path: &Path) -> Config {
                     config_path, e
                 );
             } else {
-                info!("Recovered config synced to primary config: {:?}", config_path);
+                info!(
+                    "Recovered config synced to primary config: {:?}",
+                    config_path
+                );
             }
 
             config
@@ -96,10 +118,18 @@ fn load_config_from_paths(config_path: &Path, backup_path: &Path) -> Config {
     }
 }
 
+/// 日志初始化之前读取日志输出配置，不写日志也不修改配置文件；读取失败时返回空（使用默认输出）
+pub fn read_log_sinks() -> Vec<LogSink> {
+    read_config_file(&get_config_file_path())
+        .or_else(|_| read_config_file(&get_config_backup_file_path()))
+        .map(|config| config.settings.log_sinks)
+        .unwrap_or_default()
+}
+
 fn read_config_file(path: &Path) -> Result<Config, ConfigLoadError> {
     match fs::read_to_string(path) {
         Ok(content) if content.trim().is_empty() => Err(ConfigLoadError::Empty),
-        Ok(content) => serde_json::from_str::<Config>(&content).map_err(ConfigLoadError::Parse),
+        Ok(content) => parse_config(&content),
         Err(e) if e.kind() == io::ErrorKind::NotFound => Err(ConfigLoadError::Missing),
         Err(e) => Err(ConfigLoadError::Read(e)),
     }
@@ -111,12 +141,135 @@ fn describe_load_error(err: &ConfigLoadError) -> String {
         ConfigLoadError::Empty => "file is empty".to_string(),
         ConfigLoadError::Read(e) => format!("read failed: {}", e),
         ConfigLoadError::Parse(e) => format!("invalid json: {}", e),
+        ConfigLoadError::Template(e) => format!("invalid template: {}", e),
     }
 }
 
-fn normalize_loaded_config(config: Config, save_path: Option<&Path>) -> Config {
+fn parse_config(content: &str) -> Result<Config, ConfigLoadError> {
+    let mut value: Value = serde_json::from_str(content).map_err(ConfigLoadError::Parse)?;
+    apply_templates(&mut value).map_err(ConfigLoadError::Template)?;
+    serde_json::from_value(value).map_err(ConfigLoadError::Parse)
+}
+
+/// 模板中不允许设置的字段
+const TEMPLATE_EXCLUDED_FIELDS: [&str; 3] = ["id", "name", "template"];
+
+/// 把模板字段合并到引用它的监控项中，监控项自身写出的字段优先（整体替换，不做深层合并）
+fn apply_templates(config: &mut Value) -> Result<(), String> {
+    let templates = config
+        .get("templates")
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+    let Some(items) = config.get_mut("items").and_then(Value::as_array_mut) else {
+        return Ok(());
+    };
+    for item in items.iter_mut().filter_map(Value::as_object_mut) {
+        let Some(name) = item.get("template").and_then(Value::as_str) else {
+            continue;
+        };
+        let template = templates
+            .get(name)
+            .and_then(Value::as_object)
+            .ok_or_else(|| {
+                let id = item.get("id").and_then(Value::as_str).unwrap_or_default();
+                format!("item {} references unknown template {}", id, name)
+            })?;
+        for (key, value) in template {
+            if !TEMPLATE_EXCLUDED_FIELDS.contains(&key.as_str()) && !item.contains_key(key) {
+                item.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 序列化时省略的字段可能取的显式默认值（None、空列表与枚举默认值）
+fn omitted_field_candidates() -> [Value; 4] {
+    [
+        Value::Null,
+        Value::Array(Vec::new()),
+        Value::String("keep".to_string()),
+        Value::String("adopt".to_string()),
+    ]
+}
+
+/// apply_templates 的逆过程：删除与模板相同的字段，使保存的配置保持精简。
+/// 序列化时被省略的默认值若被模板覆盖，需要显式写出，否则重新读取时会取到模板值
+fn strip_template_fields(config: &mut Value) {
+    let templates = config
+        .get("templates")
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+    let Some(items) = config.get_mut("items").and_then(Value::as_array_mut) else {
+        return;
+    };
+    for item in items.iter_mut().filter_map(Value::as_object_mut) {
+        let Some(template) = item
+            .get("template")
+            .and_then(Value::as_str)
+            .and_then(|name| templates.get(name))
+            .and_then(Value::as_object)
+        else {
+            continue;
+        };
+        let full = item.clone();
+        for (key, value) in template {
+            if TEMPLATE_EXCLUDED_FIELDS.contains(&key.as_str()) {
+                continue;
+            }
+            match full.get(key) {
+                Some(current) if current == value => {
+                    item.remove(key);
+                }
+                Some(_) => {}
+                // 模板值与省略等价（或不是监控项字段）时无需写出
+                None if equivalent_to_omitted(&full, key, value) => {}
+                None => {
+                    if let Some(explicit) = omitted_field_candidates()
+                        .into_iter()
+                        .find(|candidate| equivalent_to_omitted(&full, key, candidate))
+                    {
+                        item.insert(key.clone(), explicit);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 给省略了 key 的监控项写上 value 后，重新序列化的结果是否不变
+fn equivalent_to_omitted(item: &Map<String, Value>, key: &str, value: &Value) -> bool {
+    let mut probe = item.clone();
+    probe.insert(key.to_string(), value.clone());
+    serde_json::from_value::<MonitorItem>(Value::Object(probe))
+        .ok()
+        .and_then(|parsed| serde_json::to_value(parsed).ok())
+        .is_some_and(|round_trip| round_trip.as_object() == Some(item))
+}
+
+/// 以现有监控项为蓝本生成新监控项：overrides 中的字段覆盖原值，ID 与名称使用新值
+pub fn clone_item(
+    source: &MonitorItem,
+    new_id: &str,
+    name: &str,
+    overrides: Option<&Map<String, Value>>,
+) -> Result<MonitorItem, String> {
+    let mut value = serde_json::to_value(source).map_err(|e| e.to_string())?;
+    if let Some(item) = value.as_object_mut() {
+        for (key, override_value) in overrides.into_iter().flatten() {
+            item.insert(key.clone(), override_value.clone());
+        }
+        item.insert("id".to_string(), Value::String(new_id.to_string()));
+        item.insert("name".to_string(), Value::String(name.to_string()));
+    }
+    serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+fn normalize_loaded_config(config: Config) -> Config {
     log_loaded_config(&config);
-    deduplicate_exe_paths_with_target(config, save_path)
+    check_duplicate_exe_paths(config)
 }
 
 fn log_loaded_config(config: &Config) {
@@ -128,61 +281,105 @@ fn log_loaded_config(config: &Config) {
             item.name,
             item.exe_path
         );
-    }
-}
-
-fn deduplicate_exe_paths_with_target(mut config: Config, save_path: Option<&Path>) -> Config {
-    let original_len = config.items.len();
-
-    let mut seen_paths: HashMap<String, usize> = HashMap::new();
-    let mut duplicates_found = false;
-
-    for (index, item) in config.items.iter().enumerate() {
-        let path_lower = item.exe_path.to_lowercase();
-        if let Some(&prev_index) = seen_paths.get(&path_lower) {
-            info!(
-                "Duplicate exe_path found: {} (indices {} and {}), keeping the last one",
-                item.exe_path, prev_index, index
+        for e in crate::schedule::invalid_windows(&item.schedule) {
+            warn!(
+                "Item {} has an invalid schedule window ({}); it is treated as open",
+                item.name, e
             );
-            duplicates_found = true;
         }
-        seen_paths.insert(path_lower, index);
+        if let Some(expr) = &item.health_expression {
+            let unknown = crate::health_expr::unknown_checks(expr);
+            if !unknown.is_empty() {
+                warn!(
+                    "Item {} health_expression references unknown checks {:?}; they are treated as passing",
+                    item.name, unknown
+                );
+            }
+        }
     }
+}
 
-    if duplicates_found {
-        let mut path_to_last_item: HashMap<String, MonitorItem> = HashMap::new();
+/// 实例标识相同的一对监控项：kept 为先出现的一项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExePathConflict {
+    pub exe_path: String,
+    pub kept: String,
+    pub duplicate: String,
+}
 
-        for item in config.items.into_iter() {
-            let path_lower = item.exe_path.to_lowercase();
-            path_to_last_item.insert(path_lower, item);
+/// 查找实例标识重复的监控项；keep_all 时不视为冲突
+pub fn exe_path_conflicts(config: &Config) -> Vec<ExePathConflict> {
+    if config.settings.duplicate_exe_paths == DuplicatePathPolicy::KeepAll {
+        return Vec::new();
+    }
+    let allow_duplicates = config.settings.allow_duplicate_exe_paths;
+    let mut first: HashMap<(String, String), &str> = HashMap::new();
+    let mut conflicts = Vec::new();
+    for item in &config.items {
+        match first.entry(item.instance_key(allow_duplicates)) {
+            Entry::Vacant(entry) => {
+                entry.insert(&item.id);
+            }
+            Entry::Occupied(entry) => conflicts.push(ExePathConflict {
+                exe_path: item.exe_path.clone(),
+                kept: entry.get().to_string(),
+                duplicate: item.id.clone(),
+            }),
         }
+    }
+    conflicts
+}
 
-        let mut sorted_items: Vec<(String, MonitorItem)> = path_to_last_item.into_iter().collect();
-        sorted_items.sort_by(|a, b| {
-            let a_lower = a.0.to_lowercase();
-            let b_lower = b.0.to_lowercase();
-            a_lower.cmp(&b_lower)
-        });
-
-        config.items = sorted_items.into_iter().map(|(_, item)| item).collect();
+/// merge 策略：每个实例标识只保留第一项（与 exe_path_conflicts 相同），其余项保持原有顺序
+fn merge_duplicate_items(config: &mut Config) -> usize {
+    let allow_duplicates = config.settings.allow_duplicate_exe_paths;
+    let original_len = config.items.len();
+    let mut seen = HashSet::new();
+    config
+        .items
+        .retain(|item| seen.insert(item.instance_key(allow_duplicates)));
+    original_len - config.items.len()
+}
 
+/// 加载时不改写 config.json：reject 策略下重复项保留但不启动，merge 策略下只在内存中合并
+fn check_duplicate_exe_paths(mut config: Config) -> Config {
+    let conflicts = exe_path_conflicts(&config);
+    if conflicts.is_empty() {
+        return config;
+    }
+    for conflict in &conflicts {
         warn!(
-            "Removed {} duplicate exe_path entries from config",
-            original_len - config.items.len()
+            "Item {} has the same exe_path as item {}: {}",
+            conflict.duplicate, conflict.kept, conflict.exe_path
+        );
+    }
+    if config.settings.duplicate_exe_paths == DuplicatePathPolicy::Merge {
+        let merged = merge_duplicate_items(&mut config);
+        let message = format!(
+            "Merged {} duplicate exe_path item(s) in memory, keeping the first of each; \
+             config.json is left unchanged until the next save",
+            merged
+        );
+        warn!("{}", message);
+        crate::logging::report_event(log::Level::Warn, &message);
+    } else {
+        warn!(
+            "{} duplicate exe_path item(s) will not be started; remove them from config.json \
+             or set duplicate_exe_paths",
+            conflicts.len()
         );
-
-        if let Some(path) = save_path {
-            if let Err(e) = save_config_to_path(path, &config) {
-                error!("Failed to save deduplicated config to {:?}: {}", path, e);
-            } else {
-                info!("Config file updated with deduplicated items: {:?}", path);
-            }
-        }
     }
-
     config
 }
 
+/// reload 使用：只读取主配置文件，解析失败时返回错误而不回退到备份
+pub fn read_config_for_reload() -> Result<Config, String> {
+    let config_path = get_config_file_path();
+    let config = read_config_file(&config_path)
+        .map_err(|err| format!("{:?}: {}", config_path, describe_load_error(&err)))?;
+    Ok(normalize_loaded_config(config))
+}
+
 pub fn save_config(config: &Config) -> io::Result<()> {
     ensure_config_dir()?;
 
@@ -191,6 +388,7 @@ pub fn save_config(config: &Config) -> io::Result<()> {
     info!("Saving config to: {:?}", config_path);
 
     save_config_to_path(&config_path, config)?;
+    harden_config_files();
 
     info!("Config saved successfully ({} items)", config.items.len());
 
@@ -198,12 +396,38 @@ pub fn save_config(config: &Config) -> io::Result<()> {
     Ok(())
 }
 
+/// 配置的保存方式：服务写入 config.json；嵌入与测试只修改内存中的配置
+pub trait ConfigStore: Send + Sync {
+    fn save(&self, config: &Config) -> io::Result<()>;
+}
+
+/// 保存到服务目录下的 config.json
+pub struct FileConfigStore;
+
+impl ConfigStore for FileConfigStore {
+    fn save(&self, config: &Config) -> io::Result<()> {
+        save_config(config)
+    }
+}
+
+/// 不保存
+pub struct MemoryConfigStore;
+
+impl ConfigStore for MemoryConfigStore {
+    fn save(&self, _config: &Config) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 fn save_config_to_path(path: &Path, config: &Config) -> io::Result<()> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)?;
     }
 
-    let content = serde_json::to_string_pretty(config)
+    let mut value =
+        serde_json::to_value(config).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    strip_template_fields(&mut value);
+    let content = serde_json::to_string_pretty(&value)
         .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
 
     fs::write(path, content)
@@ -424,23 +648,112 @@ mod tests {
     }
 
     #[test]
-    fn duplicate_backup_entries_are_deduplicated_in_main_only() {
+    fn duplicate_entries_are_kept_on_disk_and_reported() {
         let harness = ConfigTestHarness::new();
-        let backup = r#"{
+        let main = r#"{
   "items": [
     {"id":"1","exe_path":"C:\\App.exe","args":null,"name":"App A","minimize":false,"no_window":false,"enabled":true,"heartbeat_timeout_ms":10000},
-    {"id":"2","exe_path":"C:\\App.exe","args":null,"name":"App B","minimize":false,"no_window":false,"enabled":true,"heartbeat_timeout_ms":10000}
+    {"id":"2","exe_path":"C:\\app.exe","args":null,"name":"App B","minimize":false,"no_window":false,"enabled":true,"heartbeat_timeout_ms":10000}
   ]
 }"#;
-        harness.write_backup(backup);
+        harness.write_main(main);
 
         let config = load_config_from_paths(harness.main_path(), harness.backup_path());
 
-        assert_eq!(config.items.len(), 1);
-        let main_after = fs::read_to_string(harness.main_path()).unwrap();
-        let backup_after = fs::read_to_string(harness.backup_path()).unwrap();
-        assert!(main_after.contains("App B"));
-        assert_eq!(backup_after, backup);
+        assert_eq!(config.items.len(), 2);
+        assert_eq!(fs::read_to_string(harness.main_path()).unwrap(), main);
+        assert_eq!(
+            exe_path_conflicts(&config),
+            vec![ExePathConflict {
+                exe_path: "C:\\app.exe".to_string(),
+                kept: "1".to_string(),
+                duplicate: "2".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn duplicate_policy_controls_merge_and_conflicts() {
+        let mut config: Config = serde_json::from_str(
+            r#"{
+  "items": [
+    {"id":"1","exe_path":"C:\\Worker.exe","args":"--queue orders","name":"Orders","minimize":false,"no_window":false,"enabled":true},
+    {"id":"2","exe_path":"C:\\worker.exe","args":"--queue mail","name":"Mail","minimize":false,"no_window":false,"enabled":true},
+    {"id":"3","exe_path":"C:\\Worker.exe","args":" --queue orders ","name":"Orders copy","minimize":false,"no_window":false,"enabled":true}
+  ],
+  "settings": {"allow_duplicate_exe_paths": true, "duplicate_exe_paths": "keep_all"}
+}"#,
+        )
+        .unwrap();
+        assert!(exe_path_conflicts(&config).is_empty());
+
+        config.settings.duplicate_exe_paths = DuplicatePathPolicy::Merge;
+        let mut merged = config.clone();
+        assert_eq!(merge_duplicate_items(&mut merged), 1);
+        let names: Vec<&str> = merged.items.iter().map(|item| item.name.as_str()).collect();
+        assert_eq!(names, ["Orders", "Mail"]);
+
+        config.settings.allow_duplicate_exe_paths = false;
+        assert_eq!(merge_duplicate_items(&mut config), 2);
+        assert_eq!(config.items[0].name, "Orders");
+    }
+
+    #[test]
+    fn templates_fill_missing_fields_and_are_stripped_on_save() {
+        let content = r#"{
+  "templates": {"worker": {"exe_path": "C:\\Worker.exe", "minimize": false, "no_window": true,
+                           "enabled": true, "heartbeat_timeout_ms": 30000, "mode": "forbid"}},
+  "items": [
+    {"id": "w1", "name": "Worker 1", "template": "worker", "args": "--queue 1"},
+    {"id": "w2", "name": "Worker 2", "template": "worker", "heartbeat_timeout_ms": 5000}
+  ]
+}"#;
+        let mut config = parse_config(content).unwrap();
+        assert_eq!(config.items[0].exe_path, r"C:\Worker.exe");
+        assert_eq!(config.items[0].heartbeat_timeout_ms, 30000);
+        assert_eq!(config.items[1].heartbeat_timeout_ms, 5000);
+
+        config.items[1].mode = crate::models::ItemMode::Keep;
+        let mut value = serde_json::to_value(&config).unwrap();
+        strip_template_fields(&mut value);
+        let items = value["items"].as_array().unwrap();
+        assert!(items[0].get("exe_path").is_none());
+        assert_eq!(items[1]["heartbeat_timeout_ms"], 5000);
+        assert_eq!(items[1]["mode"], "keep");
+
+        let reloaded = parse_config(&value.to_string()).unwrap();
+        assert_eq!(reloaded.items[0].args.as_deref(), Some("--queue 1"));
+        assert!(reloaded.items[1].mode.is_keep());
+
+        let unknown = r#"{"items": [{"id": "x", "name": "X", "template": "missing"}]}"#;
+        assert!(matches!(
+            parse_config(unknown),
+            Err(ConfigLoadError::Template(_))
+        ));
+    }
+
+    #[test]
+    fn legacy_aliases_are_accepted_and_unknown_fields_survive_save() {
+        let content = r#"{
+  "x_inventory": {"owner": "ops"},
+  "items": [{"id": "1", "exePath": "C:\\App.exe", "args": null, "name": "App", "minimize": false,
+             "noWindow": true, "enabled": true, "heartbeatTimeoutMs": 5000,
+             "x_cmdb_id": "CI-042"}]
+}"#;
+        let config = parse_config(content).unwrap();
+        let item = &config.items[0];
+        assert_eq!(item.exe_path, r"C:\App.exe");
+        assert!(item.no_window);
+        assert_eq!(item.heartbeat_timeout_ms, 5000);
+
+        let harness = ConfigTestHarness::new();
+        save_config_to_path(harness.main_path(), &config).unwrap();
+        let saved: Value =
+            serde_json::from_str(&fs::read_to_string(harness.main_path()).unwrap()).unwrap();
+        assert_eq!(saved["x_inventory"]["owner"], "ops");
+        assert_eq!(saved["items"][0]["x_cmdb_id"], "CI-042");
+        assert_eq!(saved["items"][0]["exe_path"], r"C:\App.exe");
+        assert!(saved["items"][0].get("exePath").is_none());
     }
 
     fn valid_single_item_json() -> &'static str {
@@ -0,0 +1,201 @@
+use crate::config::get_config_dir;
+use crate::exe_glob;
+use crate::models::{Config, PIPE_NAME};
+use crate::store::now_unix_ms;
+use log::{error, info};
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::ErrorKind;
+use std::path::Path;
+
+/// 一项自检结果；message 在失败时说明原因与处理办法
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DiagnosticCheck {
+    pub name: String,
+    pub ok: bool,
+    pub message: String,
+}
+
+/// 服务启动时的自检结果，通过 diagnostics 请求查询
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DiagnosticsReport {
+    pub ran_at: i64,
+    pub passed: bool,
+    pub checks: Vec<DiagnosticCheck>,
+}
+
+fn check(name: impl Into<String>, result: Result<String, String>) -> DiagnosticCheck {
+    let (ok, message) = match result {
+        Ok(message) => (true, message),
+        Err(message) => (false, message),
+    };
+    DiagnosticCheck {
+        name: name.into(),
+        ok,
+        message,
+    }
+}
+
+fn check_user_token() -> Result<String, String> {
+    match crate::session0::probe_user_token() {
+        Ok(Some(session_id)) => Ok(format!("user token of session {} is available", session_id)),
+        Ok(None) => Ok("no user is logged on; interactive items start after logon".to_string()),
+        Err(e) => Err(format!(
+            "{}; interactive items cannot start in the user session. Run the service as LocalSystem",
+            e
+        )),
+    }
+}
+
+/// 必须在本服务创建管道之前执行
+fn check_pipe_name() -> Result<String, String> {
+    let path = format!(r"\\.\pipe\{}", PIPE_NAME);
+    match OpenOptions::new().read(true).write(true).open(&path) {
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(format!("{} is free", path)),
+        _ => Err(format!(
+            "{} is already in use; stop the other ProcessGuard instance or process holding it",
+            path
+        )),
+    }
+}
+
+fn check_writable(dir: &Path) -> Result<String, String> {
+    let probe = dir.join(".diagnostics-write-test");
+    fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&probe, b"ok"))
+        .and_then(|_| fs::remove_file(&probe))
+        .map(|_| format!("{} is writable", dir.display()))
+        .map_err(|e| {
+            format!(
+                "{} is not writable ({}); config changes, the event store and logs will be lost. \
+                 Check the folder permissions for the service account",
+                dir.display(),
+                e
+            )
+        })
+}
+
+fn check_exe(exe_path: &str) -> Result<String, String> {
+    let resolved = if exe_glob::is_pattern(exe_path) {
+        exe_glob::resolve(exe_path)?
+    } else {
+        exe_path.to_string()
+    };
+    if Path::new(resolved.trim()).is_file() {
+        Ok(format!("{} found", resolved))
+    } else {
+        Err(format!(
+            "{} not found; fix exe_path or install the program",
+            resolved
+        ))
+    }
+}
+
+/// reject 策略下 config.json 中的重复项不会启动
+fn check_duplicate_exe_paths(config: &Config) -> Result<String, String> {
+    let conflicts = crate::config::exe_path_conflicts(config);
+    if conflicts.is_empty() {
+        return Ok("no monitor items share an exe_path".to_string());
+    }
+    let pairs: Vec<String> = conflicts
+        .iter()
+        .map(|conflict| {
+            format!(
+                "{} duplicates {} ({})",
+                conflict.duplicate, conflict.kept, conflict.exe_path
+            )
+        })
+        .collect();
+    Err(format!(
+        "{}; the duplicates are not started. Remove them from config.json or set \
+         duplicate_exe_paths to merge or keep_all",
+        pairs.join(", ")
+    ))
+}
+
+/// 执行启动自检；未启用、禁止运行以及等待存储门控的监控项不检查程序路径
+pub fn run(config: &Config) -> DiagnosticsReport {
+    let mut checks = vec![
+        check("user_token", check_user_token()),
+        check("pipe_name", check_pipe_name()),
+        check("config_dir", check_writable(&get_config_dir())),
+    ];
+    if let Some(log_dir) = crate::logging::current_log_file_path().parent() {
+        checks.push(check("log_dir", check_writable(log_dir)));
+    }
+    checks.push(check(
+        "duplicate_exe_paths",
+        check_duplicate_exe_paths(config),
+    ));
+    for item in &config.items {
+        if item.enabled && item.mode.is_keep() && item.requires_storage.is_none() {
+            checks.push(check(format!("exe:{}", item.id), check_exe(&item.exe_path)));
+        }
+    }
+    DiagnosticsReport {
+        ran_at: now_unix_ms(),
+        passed: checks.iter().all(|check| check.ok),
+        checks,
+    }
+}
+
+/// 事件日志中的摘要：列出未通过的检查
+fn summary(report: &DiagnosticsReport) -> String {
+    let failed: Vec<String> = report
+        .checks
+        .iter()
+        .filter(|check| !check.ok)
+        .map(|check| format!("{}: {}", check.name, check.message))
+        .collect();
+    if failed.is_empty() {
+        format!(
+            "Startup diagnostics passed ({} checks)",
+            report.checks.len()
+        )
+    } else {
+        format!(
+            "Startup diagnostics found {} problem(s):\n{}",
+            failed.len(),
+            failed.join("\n")
+        )
+    }
+}
+
+/// 写入服务日志与应用程序事件日志
+pub fn report(report: &DiagnosticsReport) {
+    for check in report.checks.iter().filter(|check| !check.ok) {
+        error!("Diagnostics {} failed: {}", check.name, check.message);
+    }
+    let level = if report.passed {
+        log::Level::Info
+    } else {
+        log::Level::Error
+    };
+    let summary = summary(report);
+    info!("{}", summary.lines().next().unwrap_or_default());
+    crate::logging::report_event(level, &summary);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, summary, DiagnosticsReport};
+
+    #[test]
+    fn summary_lists_only_failed_checks() {
+        let mut report = DiagnosticsReport {
+            ran_at: 0,
+            passed: true,
+            checks: vec![
+                check("pipe_name", Ok("free".to_string())),
+                check("exe:api", Ok("found".to_string())),
+            ],
+        };
+        assert_eq!(summary(&report), "Startup diagnostics passed (2 checks)");
+
+        report.checks[1] = check("exe:api", Err(r"C:\api.exe not found".to_string()));
+        assert_eq!(
+            summary(&report),
+            "Startup diagnostics found 1 problem(s):\nexe:api: C:\\api.exe not found"
+        );
+    }
+}
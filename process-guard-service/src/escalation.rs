@@ -0,0 +1,153 @@
+use crate::models::{EscalationAction, MonitorItem};
+use crate::notify::{Notification, Notifier};
+use crate::store::{EventKind, EventStore};
+use log::{error, info, warn};
+use std::os::windows::process::CommandExt;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+use windows::Win32::System::SystemInformation::GetTickCount64;
+
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+/// 开机不足 min_uptime 时不再重启，避免开机即崩溃导致无限重启
+fn reboot_allowed(uptime: Duration, min_uptime_secs: u64) -> bool {
+    uptime >= Duration::from_secs(min_uptime_secs)
+}
+
+fn system_uptime() -> Duration {
+    Duration::from_millis(unsafe { GetTickCount64() })
+}
+
+fn schedule_reboot(item: &MonitorItem, delay_secs: u32) -> Result<(), String> {
+    let comment = format!("Process Guard: {} is crash-looping", item.name);
+    let status = Command::new("shutdown.exe")
+        .args(["/r", "/t", &delay_secs.to_string(), "/c", &comment])
+        .creation_flags(CREATE_NO_WINDOW)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| format!("failed to run shutdown.exe: {}", e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("shutdown.exe exited with {}", status))
+    }
+}
+
+fn report(
+    item: &MonitorItem,
+    action: &str,
+    outcome: Result<String, String>,
+    store: Option<&EventStore>,
+    notifier: &Notifier,
+) {
+    let (ok, detail) = match outcome {
+        Ok(detail) => {
+            info!("Escalation {} for {}: {}", action, item.name, detail);
+            (true, detail)
+        }
+        Err(detail) => {
+            error!("Escalation {} for {} failed: {}", action, item.name, detail);
+            (false, detail)
+        }
+    };
+    if let Some(store) = store {
+        store.record_event(
+            &item.id,
+            EventKind::Escalated,
+            &format!("{}: {}", action, detail),
+        );
+    }
+    notifier.notify(Notification::escalation(item, action, ok, &detail));
+}
+
+/// 执行不改变运行时状态的升级动作（恢复命令、重启计算机）；恢复命令在后台线程运行
+pub fn run_external(item: &MonitorItem, store: Option<Arc<EventStore>>, notifier: Arc<Notifier>) {
+    match item.escalation.clone() {
+        Some(EscalationAction::Command {
+            exe_path,
+            args,
+            timeout_ms,
+        }) => {
+            let item = item.clone();
+            std::thread::spawn(move || {
+                info!("Running recovery command for {}: {}", item.name, exe_path);
+                let outcome = crate::probe::run_command(
+                    "recovery command",
+                    &exe_path,
+                    args.as_deref(),
+                    timeout_ms,
+                )
+                .map(|()| "recovery command succeeded".to_string());
+                report(&item, "command", outcome, store.as_deref(), &notifier);
+            });
+        }
+        Some(EscalationAction::Reboot {
+            delay_secs,
+            min_uptime_secs,
+        }) => {
+            let uptime = system_uptime();
+            let outcome = if reboot_allowed(uptime, min_uptime_secs) {
+                warn!(
+                    "Rebooting the machine in {}s because {} is crash-looping",
+                    delay_secs, item.name
+                );
+                schedule_reboot(item, delay_secs)
+                    .map(|()| format!("reboot scheduled in {}s", delay_secs))
+            } else {
+                Err(format!(
+                    "reboot skipped: system uptime {}s is below min_uptime_secs {}",
+                    uptime.as_secs(),
+                    min_uptime_secs
+                ))
+            };
+            report(item, "reboot", outcome, store.as_deref(), &notifier);
+        }
+        Some(EscalationAction::Fallback { .. }) | None => {}
+    }
+}
+
+/// 记录切换到备用程序的结果
+pub fn report_fallback(
+    item: &MonitorItem,
+    outcome: Result<String, String>,
+    store: Option<&EventStore>,
+    notifier: &Notifier,
+) {
+    report(item, "fallback", outcome, store, notifier);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::reboot_allowed;
+    use crate::models::EscalationAction;
+    use std::time::Duration;
+
+    #[test]
+    fn reboot_waits_for_min_uptime() {
+        assert!(!reboot_allowed(Duration::from_secs(600), 1800));
+        assert!(reboot_allowed(Duration::from_secs(1800), 1800));
+    }
+
+    #[test]
+    fn parses_tagged_actions() {
+        let action: EscalationAction =
+            serde_json::from_str(r#"{"action": "fallback", "exe_path": "C:\\old\\app.exe"}"#)
+                .unwrap();
+        assert!(matches!(
+            action,
+            EscalationAction::Fallback { args: None, .. }
+        ));
+
+        let action: EscalationAction = serde_json::from_str(r#"{"action": "reboot"}"#).unwrap();
+        assert_eq!(
+            action,
+            EscalationAction::Reboot {
+                delay_secs: 60,
+                min_uptime_secs: 1800
+            }
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use crate::models::EventLogCheckConfig;
+use crate::session0::to_wide_string;
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{ERROR_HANDLE_EOF, ERROR_INSUFFICIENT_BUFFER, HANDLE};
+use windows::Win32::System::EventLog::{
+    CloseEventLog, GetNumberOfEventLogRecords, GetOldestEventLogRecord, OpenEventLogW,
+    ReadEventLogW, EVENTLOGRECORD, EVENTLOG_ERROR_TYPE, EVENTLOG_SEEK_READ,
+    EVENTLOG_SEQUENTIAL_READ, READ_EVENT_LOG_READ_FLAGS,
+};
+
+/// EVENTLOG_FORWARDS_READ（SystemServices）
+const EVENTLOG_FORWARDS_READ: u32 = 4;
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+struct EventLog(HANDLE);
+
+impl EventLog {
+    fn open(name: &str) -> Result<Self, String> {
+        let wide = to_wide_string(name);
+        unsafe { OpenEventLogW(PCWSTR::null(), PCWSTR(wide.as_ptr())) }
+            .map(Self)
+            .map_err(|e| format!("cannot open {} event log: {}", name, e))
+    }
+
+    /// 最早与最新记录的编号；日志为空时返回 None
+    fn record_range(&self) -> Option<(u32, u32)> {
+        let mut oldest = 0u32;
+        let mut count = 0u32;
+        unsafe {
+            GetOldestEventLogRecord(self.0, &mut oldest).ok()?;
+            GetNumberOfEventLogRecords(self.0, &mut count).ok()?;
+        }
+        (count > 0).then(|| (oldest, oldest + count - 1))
+    }
+}
+
+impl Drop for EventLog {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseEventLog(self.0);
+        }
+    }
+}
+
+/// 读到的一条事件
+struct EventRecord {
+    record: u32,
+    event_id: u32,
+    error: bool,
+    source: String,
+}
+
+/// 日志中最新记录的编号，作为进程本次启动后检查的起点；无法读取时为 0
+pub fn latest_record(log: &str) -> u32 {
+    EventLog::open(log)
+        .ok()
+        .and_then(|log| log.record_range())
+        .map(|(_, newest)| newest)
+        .unwrap_or(0)
+}
+
+/// 起点之后的第一条记录；日志被清空或覆盖时从最早的记录开始
+fn scan_start(origin: u32, oldest: u32) -> u32 {
+    origin.saturating_add(1).max(oldest)
+}
+
+fn matches(config: &EventLogCheckConfig, event: &EventRecord) -> bool {
+    if let Some(source) = &config.source {
+        if !source.eq_ignore_ascii_case(&event.source) {
+            return false;
+        }
+    }
+    if config.event_ids.is_empty() {
+        // 未指定事件 ID 时只匹配错误级别的事件
+        event.error
+    } else {
+        config.event_ids.contains(&event.event_id)
+    }
+}
+
+/// 解析 ReadEventLogW 返回的缓冲区：每条记录以 EVENTLOGRECORD 开头，紧跟以 0 结尾的来源名称
+fn parse_records(buffer: &[u8], events: &mut Vec<EventRecord>) {
+    let header = std::mem::size_of::<EVENTLOGRECORD>();
+    let mut offset = 0;
+    while offset + header <= buffer.len() {
+        let record =
+            unsafe { std::ptr::read_unaligned(buffer[offset..].as_ptr() as *const EVENTLOGRECORD) };
+        let length = record.Length as usize;
+        if length < header || offset + length > buffer.len() {
+            break;
+        }
+        let source: Vec<u16> = buffer[offset + header..offset + length]
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .take_while(|c| *c != 0)
+            .collect();
+        events.push(EventRecord {
+            record: record.RecordNumber,
+            event_id: record.EventID & 0xFFFF,
+            error: record.EventType == EVENTLOG_ERROR_TYPE,
+            source: String::from_utf16_lossy(&source),
+        });
+        offset += length;
+    }
+}
+
+/// 读取编号不小于 start 的所有记录
+fn read_from(log: &EventLog, start: u32) -> Result<Vec<EventRecord>, String> {
+    let mut events = Vec::new();
+    let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+    let mut flags = EVENTLOG_SEEK_READ.0 | EVENTLOG_FORWARDS_READ;
+    loop {
+        let mut read = 0u32;
+        let mut needed = 0u32;
+        let result = unsafe {
+            ReadEventLogW(
+                log.0,
+                READ_EVENT_LOG_READ_FLAGS(flags),
+                start,
+                buffer.as_mut_ptr() as *mut _,
+                buffer.len() as u32,
+                &mut read,
+                &mut needed,
+            )
+        };
+        match result {
+            Ok(()) => {
+                parse_records(&buffer[..read as usize], &mut events);
+                flags = EVENTLOG_SEQUENTIAL_READ.0 | EVENTLOG_FORWARDS_READ;
+            }
+            Err(e) if e.code() == ERROR_INSUFFICIENT_BUFFER.to_hresult() => {
+                buffer.resize(needed as usize, 0);
+            }
+            Err(e) if e.code() == ERROR_HANDLE_EOF.to_hresult() => return Ok(events),
+            Err(e) => return Err(format!("cannot read event log: {}", e)),
+        }
+    }
+}
+
+/// 进程本次启动后（记录编号大于 origin）写入了匹配的事件即视为不健康
+pub fn run(config: &EventLogCheckConfig, origin: u32) -> Result<(), String> {
+    let log = EventLog::open(&config.log)?;
+    let Some((oldest, newest)) = log.record_range() else {
+        return Ok(());
+    };
+    let start = scan_start(origin, oldest);
+    if start > newest {
+        return Ok(());
+    }
+    let events = read_from(&log, start)?;
+    let matched: Vec<&EventRecord> = events
+        .iter()
+        .filter(|event| matches(config, event))
+        .collect();
+    match matched.last() {
+        Some(last) => Err(format!(
+            "{} matching event(s) in the {} log since start, latest: ID {} from {} (record {})",
+            matched.len(),
+            config.log,
+            last.event_id,
+            last.source,
+            last.record
+        )),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{matches, scan_start, EventRecord};
+    use crate::models::EventLogCheckConfig;
+
+    #[test]
+    fn matches_source_and_ids_or_errors_only() {
+        let event = |source: &str, event_id, error| EventRecord {
+            record: 1,
+            event_id,
+            error,
+            source: source.to_string(),
+        };
+        let mut config: EventLogCheckConfig =
+            serde_json::from_str(r#"{"source": "OrderService", "event_ids": [1000, 1001]}"#)
+                .unwrap();
+        assert_eq!(config.log, "Application");
+        assert!(matches(&config, &event("orderservice", 1001, false)));
+        assert!(!matches(&config, &event("OrderService", 7, true)));
+        assert!(!matches(&config, &event("Other", 1000, true)));
+
+        config.event_ids.clear();
+        assert!(matches(&config, &event("OrderService", 7, true)));
+        assert!(!matches(&config, &event("OrderService", 7, false)));
+
+        assert_eq!(scan_start(500, 100), 501);
+        // 起点之后的记录已被覆盖
+        assert_eq!(scan_start(500, 900), 900);
+    }
+}
@@ -0,0 +1,175 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// 路径是否包含通配符 * 或 ?
+pub fn is_pattern(path: &str) -> bool {
+    path.contains(['*', '?'])
+}
+
+/// 不区分大小写的通配符匹配，* 匹配任意长度，? 匹配单个字符
+pub(crate) fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+fn split_components(pattern: &str) -> Vec<&str> {
+    pattern.split(['\\', '/']).collect()
+}
+
+/// 第一个含通配符的路径段之前的目录，用于在解析前检查存储是否可访问
+pub fn fixed_prefix(pattern: &str) -> String {
+    let components = split_components(pattern);
+    let fixed = components
+        .iter()
+        .take_while(|component| !is_pattern(component))
+        .count();
+    components[..fixed].join("\\")
+}
+
+fn expand(base: PathBuf, rest: &[&str], matches: &mut Vec<PathBuf>) {
+    let Some((component, rest)) = rest.split_first() else {
+        if base.is_file() {
+            matches.push(base);
+        }
+        return;
+    };
+    if !is_pattern(component) {
+        expand(base.join(component), rest, matches);
+        return;
+    }
+    let Ok(entries) = fs::read_dir(&base) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if wildcard_match(component, &entry.file_name().to_string_lossy()) {
+            expand(entry.path(), rest, matches);
+        }
+    }
+}
+
+fn modified(path: &Path) -> SystemTime {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+/// 匹配通配符的所有文件；不含通配符时即该文件本身（存在时）
+pub fn matches(pattern: &str) -> Vec<PathBuf> {
+    if !is_pattern(pattern) {
+        let path = PathBuf::from(pattern);
+        return if path.is_file() {
+            vec![path]
+        } else {
+            Vec::new()
+        };
+    }
+    let components = split_components(pattern);
+    let fixed = components
+        .iter()
+        .take_while(|component| !is_pattern(component))
+        .count();
+    // "C:" 需要补上分隔符才表示根目录
+    let base = PathBuf::from(format!("{}\\", components[..fixed].join("\\")));
+
+    let mut matches = Vec::new();
+    expand(base, &components[fixed..], &mut matches);
+    matches
+}
+
+/// 解析为修改时间最新的匹配文件；时间相同按路径自然排序取较大者
+pub fn resolve(pattern: &str) -> Result<String, String> {
+    matches(pattern)
+        .into_iter()
+        .max_by(|a, b| {
+            modified(a)
+                .cmp(&modified(b))
+                .then_with(|| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()))
+        })
+        .map(|path| path.to_string_lossy().to_string())
+        .ok_or_else(|| format!("no file matches {}", pattern))
+}
+
+/// 数字段按数值比较，使 "1.10" 排在 "1.9" 之后
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let take_number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(c) = chars.peek().copied().filter(char::is_ascii_digit) {
+                        digits.push(c);
+                        chars.next();
+                    }
+                    digits.trim_start_matches('0').to_string()
+                };
+                let (x, y) = (take_number(&mut a), take_number(&mut b));
+                let ordering = x.len().cmp(&y.len()).then_with(|| x.cmp(&y));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_ascii_lowercase().cmp(&y.to_ascii_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fixed_prefix, natural_cmp, wildcard_match};
+    use std::cmp::Ordering;
+
+    #[test]
+    fn matches_wildcards_case_insensitively() {
+        assert!(wildcard_match("Foo-*", "foo-2.3.1"));
+        assert!(wildcard_match("app?.exe", "App7.EXE"));
+        assert!(wildcard_match("*.exe", "a.b.exe"));
+        assert!(!wildcard_match("Foo-*", "Bar-2.3"));
+        assert!(!wildcard_match("app?.exe", "app.exe"));
+    }
+
+    #[test]
+    fn orders_version_numbers_naturally() {
+        assert_eq!(
+            natural_cmp(r"C:\Apps\1.10\a.exe", r"C:\Apps\1.9\a.exe"),
+            Ordering::Greater
+        );
+        assert_eq!(natural_cmp("v2", "V02"), Ordering::Equal);
+    }
+
+    #[test]
+    fn prefix_stops_at_first_wildcard() {
+        assert_eq!(fixed_prefix(r"C:\Apps\Foo\*\foo.exe"), r"C:\Apps\Foo");
+        assert_eq!(fixed_prefix(r"\\nas\share\v*\app.exe"), r"\\nas\share");
+    }
+}
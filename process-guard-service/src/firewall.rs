@@ -0,0 +1,188 @@
+use crate::models::{FirewallProtocol, FirewallRule, MonitorItem};
+use log::info;
+use windows::core::BSTR;
+use windows::Win32::Foundation::VARIANT_TRUE;
+use windows::Win32::NetworkManagement::WindowsFirewall::{
+    INetFwPolicy2, INetFwRule, NetFwPolicy2, NetFwRule, NET_FW_ACTION_ALLOW,
+    NET_FW_IP_PROTOCOL_ANY, NET_FW_IP_PROTOCOL_TCP, NET_FW_IP_PROTOCOL_UDP, NET_FW_PROFILE2_ALL,
+    NET_FW_RULE_DIR_IN,
+};
+use windows::Win32::System::Com::{
+    CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_ALL, COINIT_MULTITHREADED,
+};
+
+/// 规则分组，便于在“高级安全 Windows Defender 防火墙”中筛选
+const RULE_GROUP: &str = "ProcessGuard";
+
+fn rule_name(id: &str) -> String {
+    format!("ProcessGuard - {}", id)
+}
+
+/// 端口列表：逗号分隔的端口或端口范围，范围 1-65535
+fn check_ports(ports: &str) -> Result<(), String> {
+    let port = |text: &str| {
+        text.trim()
+            .parse::<u16>()
+            .ok()
+            .filter(|port| *port > 0)
+            .ok_or_else(|| format!("invalid firewall port: {}", text.trim()))
+    };
+    for part in ports.split(',') {
+        match part.split_once('-') {
+            Some((low, high)) if port(low)? > port(high)? => {
+                return Err(format!("invalid firewall port range: {}", part.trim()))
+            }
+            Some(_) => {}
+            None => {
+                port(part)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 添加或更新监控项时校验防火墙配置
+pub fn validate(item: &MonitorItem) -> Result<(), String> {
+    let Some(rule) = &item.firewall else {
+        return Ok(());
+    };
+    match &rule.local_ports {
+        Some(_) if rule.protocol == FirewallProtocol::Any => {
+            Err("firewall.local_ports requires protocol tcp or udp".to_string())
+        }
+        Some(ports) => check_ports(ports),
+        None => Ok(()),
+    }
+}
+
+fn protocol_number(protocol: FirewallProtocol) -> i32 {
+    match protocol {
+        FirewallProtocol::Any => NET_FW_IP_PROTOCOL_ANY.0,
+        FirewallProtocol::Tcp => NET_FW_IP_PROTOCOL_TCP.0,
+        FirewallProtocol::Udp => NET_FW_IP_PROTOCOL_UDP.0,
+    }
+}
+
+fn with_policy<T>(
+    f: impl FnOnce(&INetFwPolicy2) -> windows::core::Result<T>,
+) -> windows::core::Result<T> {
+    unsafe {
+        let initialized = CoInitializeEx(None, COINIT_MULTITHREADED).is_ok();
+        let result = CoCreateInstance::<_, INetFwPolicy2>(&NetFwPolicy2, None, CLSCTX_ALL)
+            .and_then(|policy| f(&policy));
+        if initialized {
+            CoUninitialize();
+        }
+        result
+    }
+}
+
+/// 防火墙保存列表时去掉逗号后的空格（"8080, 9000" 读回为 "8080,9000"），比较前按同样方式规范化
+fn normalize_list(list: &str) -> String {
+    list.split(',')
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join(",")
+        .to_ascii_lowercase()
+}
+
+/// 已有规则与配置一致时无需重建；未设置的端口与地址在防火墙中显示为 "*"
+fn rule_matches(
+    existing: &INetFwRule,
+    exe_path: &str,
+    rule: &FirewallRule,
+) -> windows::core::Result<bool> {
+    let same = |actual: BSTR, expected: Option<&str>| {
+        normalize_list(&actual.to_string()) == normalize_list(expected.unwrap_or("*"))
+    };
+    unsafe {
+        Ok(same(existing.ApplicationName()?, Some(exe_path))
+            && existing.Protocol()? == protocol_number(rule.protocol)
+            && same(existing.LocalPorts()?, rule.local_ports.as_deref())
+            && same(
+                existing.RemoteAddresses()?,
+                rule.remote_addresses.as_deref(),
+            ))
+    }
+}
+
+/// 为监控项创建放行 exe_path 入站连接的规则；同名规则与配置不一致（例如通配符路径解析到新版本）时重建
+pub fn ensure_rule(id: &str, exe_path: &str, rule: &FirewallRule) -> Result<(), String> {
+    let name = BSTR::from(rule_name(id));
+    let created = with_policy(|policy| unsafe {
+        let rules = policy.Rules()?;
+        if let Ok(existing) = rules.Item(&name) {
+            if rule_matches(&existing, exe_path, rule)? {
+                return Ok(false);
+            }
+            rules.Remove(&name)?;
+        }
+        let new_rule: INetFwRule = CoCreateInstance(&NetFwRule, None, CLSCTX_ALL)?;
+        new_rule.SetName(&name)?;
+        new_rule.SetDescription(&BSTR::from(format!(
+            "Created by ProcessGuard for monitor item {}",
+            id
+        )))?;
+        new_rule.SetGrouping(&BSTR::from(RULE_GROUP))?;
+        new_rule.SetApplicationName(&BSTR::from(exe_path))?;
+        new_rule.SetDirection(NET_FW_RULE_DIR_IN)?;
+        new_rule.SetAction(NET_FW_ACTION_ALLOW)?;
+        new_rule.SetProfiles(NET_FW_PROFILE2_ALL.0)?;
+        // 必须先设置协议才能设置端口
+        new_rule.SetProtocol(protocol_number(rule.protocol))?;
+        if let Some(ports) = &rule.local_ports {
+            new_rule.SetLocalPorts(&BSTR::from(ports.as_str()))?;
+        }
+        if let Some(addresses) = &rule.remote_addresses {
+            new_rule.SetRemoteAddresses(&BSTR::from(addresses.as_str()))?;
+        }
+        new_rule.SetEnabled(VARIANT_TRUE)?;
+        rules.Add(&new_rule)?;
+        Ok(true)
+    })
+    .map_err(|e| format!("Failed to create firewall rule for {}: {}", id, e))?;
+    if created {
+        info!(
+            "Created firewall rule \"{}\" for {}",
+            rule_name(id),
+            exe_path
+        );
+    }
+    Ok(())
+}
+
+/// 移除监控项的规则；规则不存在时不报错
+pub fn remove_rule(id: &str) -> Result<(), String> {
+    let name = BSTR::from(rule_name(id));
+    with_policy(|policy| unsafe { policy.Rules()?.Remove(&name) })
+        .map_err(|e| format!("Failed to remove firewall rule for {}: {}", id, e))?;
+    info!("Removed firewall rule \"{}\"", rule_name(id));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_list, validate};
+    use crate::models::{FirewallProtocol, FirewallRule, MonitorItem};
+
+    #[test]
+    fn ports_need_a_protocol_and_valid_ranges() {
+        let item = |protocol, ports: &str| MonitorItem {
+            firewall: Some(FirewallRule {
+                protocol,
+                local_ports: Some(ports.to_string()),
+                remote_addresses: None,
+            }),
+            ..MonitorItem::new(r"C:\srv\api.exe".to_string(), "api".to_string())
+        };
+        assert!(validate(&item(FirewallProtocol::Tcp, "8080, 9000-9010")).is_ok());
+        assert!(validate(&item(FirewallProtocol::Any, "8080")).is_err());
+        assert!(validate(&item(FirewallProtocol::Udp, "9010-9000")).is_err());
+        assert!(validate(&item(FirewallProtocol::Tcp, "0,70000")).is_err());
+
+        assert_eq!(
+            normalize_list("8080, 9000-9010"),
+            normalize_list("8080,9000-9010")
+        );
+    }
+}
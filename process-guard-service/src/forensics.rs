@@ -0,0 +1,335 @@
+use crate::config::get_config_dir;
+use crate::import::read_registry_string;
+use crate::integrity::sha256_hex;
+use crate::models::{ForensicsConfig, MonitorItem};
+use crate::output::OutputBuffer;
+use crate::store::EventStore;
+use log::{debug, info, warn};
+use serde_json::json;
+use std::fs;
+use std::os::windows::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use time::OffsetDateTime;
+
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+/// 取证包中保存的事件条数
+const HISTORY_EVENTS: u32 = 100;
+/// wevtutil 读取的最近 Application 日志条数，再按程序名过滤
+const EVENT_LOG_RECORDS: u32 = 200;
+/// 超过该大小的转储只记录路径，不复制
+const MAX_DUMP_BYTES: u64 = 512 * 1024 * 1024;
+/// 只收集最近这段时间内生成的转储，避免把很久以前的崩溃放进来
+const DUMP_MAX_AGE: Duration = Duration::from_secs(3600);
+const LOCAL_DUMPS_KEY: &str = r"SOFTWARE\Microsoft\Windows\Windows Error Reporting\LocalDumps";
+
+/// 进入崩溃循环时的现场信息
+pub struct CrashLoopInput<'a> {
+    pub item: &'a MonitorItem,
+    pub restarts_in_window: usize,
+    pub window: Duration,
+    pub restart_count: u32,
+    pub last_start_error: Option<&'a str>,
+    pub output: Option<&'a Arc<OutputBuffer>>,
+}
+
+fn bundle_root(config: &ForensicsConfig) -> PathBuf {
+    config
+        .dir
+        .as_deref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| get_config_dir().join("forensics"))
+}
+
+/// 取证包目录名中的监控项 ID 只保留可用于文件名的字符
+fn sanitize_file_name(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// 取证包目录名前缀：清理后的 ID 加原始 ID 的短哈希，避免 a.b 与 a_b 的取证包互相轮转删除
+fn bundle_prefix(id: &str) -> String {
+    format!(
+        "{}-{}-",
+        sanitize_file_name(id),
+        &sha256_hex(id.as_bytes())[..8]
+    )
+}
+
+fn timestamp() -> String {
+    let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+    now.format(time::macros::format_description!(
+        "[year][month][day]-[hour][minute][second]"
+    ))
+    .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// 收集取证包并返回目录。item.json、history.json、output.log 同步写入，
+/// 事件日志与崩溃转储较慢，由后台线程补充。
+pub fn collect(
+    config: &ForensicsConfig,
+    input: &CrashLoopInput,
+    store: Option<&EventStore>,
+) -> Result<PathBuf, String> {
+    let root = bundle_root(config);
+    let prefix = bundle_prefix(&input.item.id);
+    let dir = root.join(format!("{}{}", prefix, timestamp()));
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create forensic bundle {:?}: {}", dir, e))?;
+
+    let item = serde_json::to_string_pretty(&input.item.to_public_value()).unwrap_or_default();
+    write_file(&dir.join("item.json"), item.as_bytes());
+
+    let events = store
+        .map(|store| store.item_events(&input.item.id, HISTORY_EVENTS))
+        .transpose()
+        .unwrap_or_else(|e| {
+            warn!(
+                "Failed to read event history for {}: {}",
+                input.item.name, e
+            );
+            None
+        });
+    let history = json!({
+        "restarts_in_window": input.restarts_in_window,
+        "window_secs": input.window.as_secs(),
+        "restart_count": input.restart_count,
+        "last_start_error": input.last_start_error,
+        "events": events,
+    });
+    write_file(
+        &dir.join("history.json"),
+        serde_json::to_string_pretty(&history)
+            .unwrap_or_default()
+            .as_bytes(),
+    );
+
+    match input.output {
+        Some(buffer) => {
+            let (output, _) = buffer.snapshot();
+            write_file(&dir.join("output.log"), output.as_bytes());
+        }
+        None => write_file(
+            &dir.join("output.log"),
+            b"Output capture is disabled for this item (capture_output = false)\n",
+        ),
+    }
+
+    let exe_name = exe_file_name(&input.item.exe_path);
+    let bundle = dir.clone();
+    let keep = config.keep;
+    std::thread::spawn(move || {
+        if let Some(exe_name) = exe_name {
+            write_file(
+                &bundle.join("eventlog.txt"),
+                event_log_excerpt(&exe_name).as_bytes(),
+            );
+            collect_dump(&bundle, &exe_name);
+        }
+        rotate(&root, &prefix, keep);
+        debug!("Forensic bundle {:?} completed", bundle);
+    });
+
+    info!(
+        "Forensic bundle for {} written to {:?}",
+        input.item.name, dir
+    );
+    Ok(dir)
+}
+
+fn write_file(path: &Path, data: &[u8]) {
+    if let Err(e) = fs::write(path, data) {
+        warn!("Failed to write {:?}: {}", path, e);
+    }
+}
+
+fn exe_file_name(exe_path: &str) -> Option<String> {
+    Path::new(exe_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+}
+
+fn event_log_excerpt(exe_name: &str) -> String {
+    let output = Command::new("wevtutil.exe")
+        .args([
+            "qe",
+            "Application",
+            "/q:*[System[(Level=1 or Level=2 or Level=3)]]",
+            &format!("/c:{}", EVENT_LOG_RECORDS),
+            "/rd:true",
+            "/f:text",
+        ])
+        .creation_flags(CREATE_NO_WINDOW)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output();
+    match output {
+        Ok(output) => {
+            let text = String::from_utf8_lossy(&output.stdout);
+            let events = events_mentioning(&text, exe_name);
+            if events.is_empty() {
+                format!("No recent Application log errors mention {}\n", exe_name)
+            } else {
+                events.join("\n")
+            }
+        }
+        Err(e) => format!("Failed to run wevtutil.exe: {}\n", e),
+    }
+}
+
+/// 从 wevtutil /f:text 的输出中挑出提到程序名的事件（不区分大小写）。
+/// 每条事件以 "Event[n]:" 开头。
+fn events_mentioning(text: &str, exe_name: &str) -> Vec<String> {
+    let needle = exe_name.to_lowercase();
+    let mut events = Vec::new();
+    let mut current = String::new();
+    for line in text.lines() {
+        if line.starts_with("Event[") && !current.is_empty() {
+            events.push(std::mem::take(&mut current));
+        }
+        current.push_str(line);
+        current.push('\n');
+    }
+    if !current.is_empty() {
+        events.push(current);
+    }
+    events.retain(|event| event.to_lowercase().contains(&needle));
+    events
+}
+
+/// WER LocalDumps 配置的转储目录（程序专属配置优先）以及默认的 CrashDumps 目录
+fn dump_folders(exe_name: &str) -> Vec<PathBuf> {
+    let mut folders = Vec::new();
+    for key in [
+        format!(r"{}\{}", LOCAL_DUMPS_KEY, exe_name),
+        LOCAL_DUMPS_KEY.to_string(),
+    ] {
+        // DumpFolder 通常是 REG_EXPAND_SZ，RegGetValueW 会自动展开
+        if let Some(folder) = read_registry_string(&key, "DumpFolder") {
+            folders.push(PathBuf::from(folder));
+        }
+    }
+    let windir = std::env::var("SystemRoot").unwrap_or_else(|_| r"C:\Windows".to_string());
+    folders
+        .push(Path::new(&windir).join(r"System32\config\systemprofile\AppData\Local\CrashDumps"));
+    if let Ok(users) = fs::read_dir(r"C:\Users") {
+        for user in users.flatten() {
+            folders.push(user.path().join(r"AppData\Local\CrashDumps"));
+        }
+    }
+    folders
+}
+
+fn collect_dump(bundle: &Path, exe_name: &str) {
+    let prefix = format!("{}.", exe_name.to_lowercase());
+    let newest = dump_folders(exe_name)
+        .iter()
+        .filter_map(|folder| fs::read_dir(folder).ok())
+        .flat_map(|entries| entries.flatten())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_lowercase();
+            name.starts_with(&prefix) && name.ends_with(".dmp")
+        })
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some((metadata.modified().ok()?, metadata.len(), entry.path()))
+        })
+        .filter(|(modified, _, _)| {
+            SystemTime::now()
+                .duration_since(*modified)
+                .map(|age| age <= DUMP_MAX_AGE)
+                .unwrap_or(true)
+        })
+        .max_by_key(|(modified, _, _)| *modified);
+
+    let Some((_, size, path)) = newest else {
+        write_file(
+            &bundle.join("dump.txt"),
+            b"No recent crash dump found; configure WER LocalDumps to collect one\n",
+        );
+        return;
+    };
+    if size > MAX_DUMP_BYTES {
+        write_file(
+            &bundle.join("dump.txt"),
+            format!("Newest dump {:?} is {} bytes, not copied\n", path, size).as_bytes(),
+        );
+        return;
+    }
+    let target = bundle.join(path.file_name().unwrap_or_default());
+    if let Err(e) = fs::copy(&path, &target) {
+        warn!("Failed to copy crash dump {:?}: {}", path, e);
+    }
+}
+
+/// 按名称排序（名称以时间戳结尾）后需要删除的旧取证包
+fn bundles_to_remove(mut names: Vec<String>, keep: usize) -> Vec<String> {
+    names.sort();
+    let excess = names.len().saturating_sub(keep);
+    names.truncate(excess);
+    names
+}
+
+fn rotate(root: &Path, prefix: &str, keep: usize) {
+    let Ok(entries) = fs::read_dir(root) else {
+        return;
+    };
+    let names: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| {
+            // 前缀后必须紧接时间戳，避免 ID 为 app 时误删 app-2 的取证包
+            name.strip_prefix(prefix)
+                .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+        })
+        .collect();
+    for name in bundles_to_remove(names, keep) {
+        let path = root.join(&name);
+        match fs::remove_dir_all(&path) {
+            Ok(()) => debug!("Removed old forensic bundle {:?}", path),
+            Err(e) => warn!("Failed to remove old forensic bundle {:?}: {}", path, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bundle_prefix, bundles_to_remove, events_mentioning};
+
+    #[test]
+    fn keeps_only_events_that_mention_the_program() {
+        let text = "Event[0]:\n  Source: Application Error\n  Description: Faulting application name: App.exe\n\
+                    Event[1]:\n  Source: Other\n  Description: unrelated\n";
+        let events = events_mentioning(text, "app.exe");
+        assert_eq!(events.len(), 1);
+        assert!(events[0].contains("Faulting application name: App.exe"));
+    }
+
+    #[test]
+    fn ids_that_sanitize_alike_get_different_prefixes() {
+        assert_ne!(bundle_prefix("a.b"), bundle_prefix("a_b"));
+        assert!(bundle_prefix("a.b").starts_with("a_b-"));
+        assert_eq!(bundle_prefix("app"), bundle_prefix("app"));
+    }
+
+    #[test]
+    fn removes_the_oldest_bundles_beyond_keep() {
+        let names = vec![
+            "app-20240102-000000".to_string(),
+            "app-20240101-000000".to_string(),
+            "app-20240103-000000".to_string(),
+        ];
+        assert_eq!(bundles_to_remove(names, 2), vec!["app-20240101-000000"]);
+        assert!(bundles_to_remove(vec!["app-20240101-000000".to_string()], 2).is_empty());
+    }
+}
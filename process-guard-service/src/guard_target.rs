@@ -0,0 +1,84 @@
+use crate::exe_glob;
+use crate::models::MonitorItem;
+use std::path::{Path, PathBuf};
+
+/// 终止后会导致蓝屏、注销或系统服务全部中断的 Windows 进程，不能作为监控项
+const CRITICAL_PROCESSES: [&str; 11] = [
+    "csrss.exe",
+    "smss.exe",
+    "wininit.exe",
+    "winlogon.exe",
+    "lsass.exe",
+    "lsaiso.exe",
+    "services.exe",
+    "svchost.exe",
+    "dwm.exe",
+    "logonui.exe",
+    "ntoskrnl.exe",
+];
+
+fn normalize(path: &Path) -> String {
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    path.to_string_lossy()
+        .trim_start_matches(r"\\?\")
+        .replace('/', "\\")
+        .to_lowercase()
+}
+
+/// exe_path 不允许作为监控项时返回原因；路径均已规范化为小写
+fn rejection_reason(exe_path: &str, own_exe: Option<&str>, system_root: &str) -> Option<String> {
+    if own_exe == Some(exe_path) {
+        return Some("it is the guardian service itself".to_string());
+    }
+    let path = Path::new(exe_path);
+    let file_name = path.file_name()?.to_string_lossy();
+    if !CRITICAL_PROCESSES.contains(&file_name.as_ref()) {
+        return None;
+    }
+    // 不带目录时按搜索路径解析，同样会找到系统目录中的程序
+    let in_system_root = match path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        Some(dir) => dir.starts_with(system_root),
+        None => true,
+    };
+    in_system_root.then(|| format!("{} is a critical Windows process", file_name))
+}
+
+/// 拒绝监控服务自身（命令行客户端是同一个程序）以及 Windows 关键系统进程，
+/// 否则服务会终止并重启自己，或在重启时终止系统进程
+pub fn check(item: &MonitorItem) -> Result<(), String> {
+    let exe_path = if exe_glob::is_pattern(&item.exe_path) {
+        match exe_glob::resolve(&item.exe_path) {
+            Ok(resolved) => resolved,
+            // 暂时无法解析的通配符路径在启动时再检查
+            Err(_) => return Ok(()),
+        }
+    } else {
+        item.exe_path.clone()
+    };
+    let own_exe = std::env::current_exe().ok().map(|exe| normalize(&exe));
+    let system_root = std::env::var("SystemRoot").unwrap_or_else(|_| r"C:\Windows".to_string());
+    match rejection_reason(
+        &normalize(&PathBuf::from(exe_path.trim())),
+        own_exe.as_deref(),
+        &normalize(Path::new(&system_root)),
+    ) {
+        Some(reason) => Err(format!("cannot monitor {}: {}", item.exe_path, reason)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rejection_reason;
+
+    #[test]
+    fn rejects_own_binary_and_system_processes_only() {
+        let own = Some(r"c:\tools\process-guard-service.exe");
+        let root = r"c:\windows";
+        assert!(rejection_reason(r"c:\tools\process-guard-service.exe", own, root).is_some());
+        assert!(rejection_reason(r"c:\windows\system32\lsass.exe", own, root).is_some());
+        assert!(rejection_reason("svchost.exe", own, root).is_some());
+        assert!(rejection_reason(r"d:\apps\legacy\services.exe", own, root).is_none());
+        assert!(rejection_reason(r"c:\apps\worker.exe", own, root).is_none());
+    }
+}
@@ -1,11 +1,44 @@
-use crate::config::load_config;
-use crate::models::{ChangeType, Config, ConfigChange, MonitoredProcess, CHECK_INTERVAL_MS};
+use crate::acl::{harden_file, UsersAccess};
+use crate::appcontainer::AppContainerLaunch;
+use crate::backend::{ProcessBackend, SpawnRequest, Win32Backend};
+use crate::clock::{ClockEvent, ClockWatch};
+use crate::config::{load_config, ConfigStore, FileConfigStore, MemoryConfigStore};
+use crate::diagnostics::DiagnosticsReport;
+use crate::exe_glob;
+use crate::forensics::{self, CrashLoopInput};
+use crate::health_expr;
+use crate::integrity::check_file_hash;
+use crate::item_log::ItemLogs;
+use crate::models::{
+    AlertThresholds, ChangeType, Config, ConfigChange, CpuSample, EscalationAction,
+    ExistingInstance, ForensicsConfig, HeartbeatAction, ItemMode, MetricsHistoryConfig,
+    MonitorItem, MonitoredProcess, OrphanPolicy, ProbeState, RestartReason, Settings,
+    StartupStagger, StorageGate, TaskAction, CHECK_INTERVAL_MS,
+};
+use crate::network::{self, GateState};
+use crate::notify::{Notification, Notifier};
+use crate::output::OutputBuffer;
+use crate::pipe_stats::PipeStats;
+use crate::probe::{
+    file_heartbeat_age, log_check_origin, poll_event_log_check, poll_health_command,
+    poll_http_probe, poll_log_check, poll_port_check, poll_window_check,
+};
+use crate::process_table::ProcessTable;
+use crate::service_deps;
 use crate::session0::{
-    check_process_alive, find_process_by_path, kill_process, start_process_in_session0,
+    find_processes_by_image, query_process_context, query_process_resources, set_process_suspended,
+    trim_working_set, DesktopOptions, LaunchDesktop, ProcessEntry,
 };
+use crate::shutdown_notice::{ShutdownEvent, StopEvent};
+use crate::signature::check_signature;
+use crate::stop_plan::{self, StopActions};
+use crate::store::{now_unix_ms, EventKind, EventStore, ResourceSample};
 use log::{debug, error, info, warn};
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
 use std::time::{Duration, Instant};
 
 fn should_kill_process_for_change(change_type: ChangeType) -> bool {
@@ -16,6 +49,96 @@ fn normalize_startup_config(config: Config) -> (Config, bool) {
     (config, false)
 }
 
+/// reload 时单个监控项需要执行的动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReloadAction {
+    /// 新增或修改后启用：启动新进程
+    Start,
+    /// 修改后仍启用：按新配置重启
+    Restart,
+    /// 修改后禁用：停止进程
+    Stop,
+    Remove,
+    /// 禁用项的配置变化，只更新运行时记录
+    Update,
+}
+
+impl ReloadAction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ReloadAction::Start => "start",
+            ReloadAction::Restart => "restart",
+            ReloadAction::Stop => "stop",
+            ReloadAction::Remove => "remove",
+            ReloadAction::Update => "update",
+        }
+    }
+}
+
+/// 附加字段（extra）只是元数据，只改动它们时不需要重启进程
+fn same_item_config(a: &MonitorItem, b: &MonitorItem) -> bool {
+    let without_extra = |item: &MonitorItem| MonitorItem {
+        extra: serde_json::Map::new(),
+        ..item.clone()
+    };
+    serde_json::to_value(without_extra(a)).ok() == serde_json::to_value(without_extra(b)).ok()
+}
+
+/// 比较当前与磁盘上的监控项，未变化的项不出现在结果中
+fn plan_reload(current: &[MonitorItem], new: &[MonitorItem]) -> Vec<(String, ReloadAction)> {
+    let mut plan = Vec::new();
+    for item in new {
+        let action = match current.iter().find(|old| old.id == item.id) {
+            None if item.enabled => ReloadAction::Start,
+            None => ReloadAction::Update,
+            Some(old) if same_item_config(old, item) => continue,
+            Some(old) => match (old.enabled, item.enabled) {
+                (true, true) => ReloadAction::Restart,
+                (false, true) => ReloadAction::Start,
+                (true, false) => ReloadAction::Stop,
+                (false, false) => ReloadAction::Update,
+            },
+        };
+        plan.push((item.id.clone(), action));
+    }
+    for old in current {
+        if !new.iter().any(|item| item.id == old.id) {
+            plan.push((old.id.clone(), ReloadAction::Remove));
+        }
+    }
+    plan
+}
+
+/// 只在服务启动时生效的全局设置
+fn settings_requiring_restart(current: &Settings, new: &Settings) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    if current.metrics_listen != new.metrics_listen {
+        fields.push("metrics_listen");
+    }
+    if current.health_listen != new.health_listen {
+        fields.push("health_listen");
+    }
+    if current.event_store != new.event_store {
+        fields.push("event_store");
+    }
+    if serde_json::to_value(&current.webhooks).ok() != serde_json::to_value(&new.webhooks).ok() {
+        fields.push("webhooks");
+    }
+    if current.peer != new.peer {
+        fields.push("peer");
+    }
+    if current.tray != new.tray {
+        fields.push("tray");
+    }
+    if current.log_sinks != new.log_sinks {
+        fields.push("log_sinks");
+    }
+    if current.check_engine != new.check_engine {
+        fields.push("check_engine");
+    }
+    fields
+}
+
 fn apply_pause_state(
     processes: &mut HashMap<String, MonitoredProcess>,
     config: &mut Config,
@@ -41,21 +164,460 @@ fn apply_pause_state(
     found
 }
 
+fn compute_cpu_percent(
+    previous_cpu_100ns: u64,
+    current_cpu_100ns: u64,
+    wall_elapsed: Duration,
+    cpu_count: usize,
+) -> f64 {
+    let wall_100ns = wall_elapsed.as_nanos() as f64 / 100.0;
+    if wall_100ns <= 0.0 || cpu_count == 0 {
+        return 0.0;
+    }
+
+    let cpu_delta = current_cpu_100ns.saturating_sub(previous_cpu_100ns) as f64;
+    (cpu_delta / (wall_100ns * cpu_count as f64) * 100.0).min(100.0)
+}
+
+fn sample_resource_usage(
+    process: &mut MonitoredProcess,
+    pid: u32,
+    snapshot: &HashMap<u32, ProcessEntry>,
+) {
+    let Some(mut resources) = query_process_resources(pid) else {
+        process.resources = None;
+        process.cpu_percent = None;
+        process.cpu_sample = None;
+        return;
+    };
+    resources.thread_count = snapshot.get(&pid).map(|entry| entry.thread_count);
+
+    let now = Instant::now();
+    process.resources = Some(resources);
+    process.cpu_percent = match process.cpu_sample {
+        Some(sample) if sample.process_id == pid => {
+            let cpu_count = std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1);
+            Some(compute_cpu_percent(
+                sample.cpu_time_100ns,
+                resources.cpu_time_100ns,
+                now.duration_since(sample.taken_at),
+                cpu_count,
+            ))
+        }
+        _ => None,
+    };
+    process.cpu_sample = Some(CpuSample {
+        process_id: pid,
+        taken_at: now,
+        cpu_time_100ns: resources.cpu_time_100ns,
+    });
+}
+
+/// 检查周期间隔：WMI 退出事件正常工作时按 wmi_poll_interval_ms（不短于默认周期），否则为默认周期
+fn cycle_interval(exit_events_active: bool, wmi_poll_interval_ms: u64) -> Duration {
+    if exit_events_active {
+        Duration::from_millis(wmi_poll_interval_ms.max(CHECK_INTERVAL_MS))
+    } else {
+        Duration::from_millis(CHECK_INTERVAL_MS)
+    }
+}
+
+/// 超出 max_instances 的实例：被守护的实例总是保留，其余按创建时间从早到晚保留，多出的是最新启动的
+fn surplus_instances(mut others: Vec<(u32, Option<u64>)>, max_instances: u32) -> Vec<u32> {
+    others.sort_by_key(|(pid, created)| (created.unwrap_or(u64::MAX), *pid));
+    let keep = max_instances.max(1) as usize - 1;
+    others.into_iter().skip(keep).map(|(pid, _)| pid).collect()
+}
+
+/// 按父进程 ID 查找 root 的所有子孙进程
+fn find_descendants(root: u32, snapshot: &HashMap<u32, ProcessEntry>) -> Vec<u32> {
+    let mut descendants = Vec::new();
+    let mut queue = VecDeque::from([root]);
+    while let Some(parent) = queue.pop_front() {
+        for (&pid, entry) in snapshot {
+            // PID 复用可能形成环，已访问的进程不再加入
+            if entry.parent_pid == parent && pid != root && !descendants.contains(&pid) {
+                descendants.push(pid);
+                queue.push_back(pid);
+            }
+        }
+    }
+    descendants.sort_unstable();
+    descendants
+}
+
+/// 启动器的子孙进程中映像名匹配 worker.image 的进程，按 PID 升序
+fn find_workers(launcher: u32, image: &str, snapshot: &HashMap<u32, ProcessEntry>) -> Vec<u32> {
+    find_descendants(launcher, snapshot)
+        .into_iter()
+        .filter(|pid| {
+            snapshot
+                .get(pid)
+                .map(|entry| exe_glob::wildcard_match(image, &entry.image_name))
+                .unwrap_or(false)
+        })
+        .collect()
+}
+
+/// 停止序列针对某个监控项进程的操作
+struct ProcessStopper<'a> {
+    guardian: &'a Guardian,
+    item: &'a MonitorItem,
+    pid: u32,
+    created: Option<u64>,
+}
+
+impl StopActions for ProcessStopper<'_> {
+    fn wait_for_exit(&self, timeout: Duration) -> bool {
+        self.guardian
+            .backend
+            .wait_for_exit(self.pid, self.created, timeout)
+    }
+
+    fn http(&self, method: &str, url: &str, timeout: Duration) -> Result<u16, String> {
+        crate::http::request(method, url, &[], None, timeout).map(|response| response.status)
+    }
+
+    fn close_window(&self) -> Result<(), String> {
+        crate::window::request_close_in_user_session(self.pid)
+    }
+
+    fn signal_stop_event(&self) -> bool {
+        self.guardian.set_stop_event(self.item)
+    }
+
+    /// 终止进程树时先取快照，避免根进程退出后丢失父子关系
+    fn kill(&self, tree: bool) {
+        let backend = &self.guardian.backend;
+        let descendants = if tree {
+            find_descendants(self.pid, &backend.snapshot())
+        } else {
+            Vec::new()
+        };
+        backend.kill(self.pid, self.created);
+        for pid in descendants {
+            backend.kill(pid, None);
+        }
+    }
+}
+
+/// 返回子孙进程中缺失的必需映像名
+fn missing_children(
+    required: &[String],
+    children: &[u32],
+    snapshot: &HashMap<u32, ProcessEntry>,
+) -> Vec<String> {
+    required
+        .iter()
+        .filter(|name| {
+            !children.iter().any(|pid| {
+                snapshot
+                    .get(pid)
+                    .is_some_and(|entry| entry.image_name.eq_ignore_ascii_case(name))
+            })
+        })
+        .cloned()
+        .collect()
+}
+
+/// 按监控项的告警阈值判断本次重启是否需要通知；发送后重新计数
+fn restart_alert_due(
+    history: &mut VecDeque<Instant>,
+    now: Instant,
+    alerts: Option<&AlertThresholds>,
+) -> bool {
+    let Some(alerts) = alerts else {
+        return true;
+    };
+    if alerts.restarts == 0 {
+        return false;
+    }
+    let window = Duration::from_secs(alerts.window_minutes * 60);
+    if record_restart_in_window(history, now, window) >= alerts.restarts as usize {
+        history.clear();
+        true
+    } else {
+        false
+    }
+}
+
+fn record_restart_in_window(
+    restarts: &mut VecDeque<Instant>,
+    now: Instant,
+    window: Duration,
+) -> usize {
+    restarts.push_back(now);
+    while let Some(oldest) = restarts.front() {
+        if now.duration_since(*oldest) > window {
+            restarts.pop_front();
+        } else {
+            break;
+        }
+    }
+    restarts.len()
+}
+
+/// 服务启动时的进程启动顺序：按 start_priority 升序，相同优先级保持配置顺序
+fn startup_order(items: &[MonitorItem]) -> Vec<&MonitorItem> {
+    let mut ordered: Vec<&MonitorItem> = items.iter().collect();
+    ordered.sort_by_key(|item| item.start_priority);
+    ordered
+}
+
+/// 服务停止时的进程停止顺序：启动顺序的逆序，只包含启用的 keep 项
+fn shutdown_order(items: &[MonitorItem]) -> Vec<String> {
+    startup_order(items)
+        .into_iter()
+        .rev()
+        .filter(|item| item.enabled && item.mode.is_keep())
+        .map(|item| item.id.clone())
+        .collect()
+}
+
+/// reject 策略下不启动的重复项：重复项 id -> 保留项 id
+fn duplicate_item_map(config: &Config) -> HashMap<String, String> {
+    crate::config::exe_path_conflicts(config)
+        .into_iter()
+        .map(|conflict| (conflict.duplicate, conflict.kept))
+        .collect()
+}
+
+/// origin 重启后需要随之重启的监控项：依赖 origin 且开启 restart_on_dependency_restart 的项，
+/// 再依次传递到依赖这些项的项；循环依赖时每项只出现一次，且不包括 origin
+fn dependents_to_restart(items: &[&MonitorItem], origin: &str) -> Vec<String> {
+    let mut restarted = vec![origin.to_string()];
+    let mut next = 0;
+    while next < restarted.len() {
+        let dependency = restarted[next].clone();
+        next += 1;
+        for item in items {
+            if item.enabled
+                && item.mode.is_keep()
+                && item.restart_on_dependency_restart
+                && item.depends_on.contains(&dependency)
+                && !restarted.contains(&item.id)
+            {
+                restarted.push(item.id.clone());
+            }
+        }
+    }
+    restarted.split_off(1)
+}
+
+/// 存储门控需要检查的路径，未配置时检查 exe_path（含通配符时检查通配符之前的目录）
+fn storage_paths(gate: &StorageGate, exe_path: &str) -> Vec<String> {
+    if gate.paths.is_empty() && exe_glob::is_pattern(exe_path) {
+        vec![exe_glob::fixed_prefix(exe_path)]
+    } else if gate.paths.is_empty() {
+        vec![exe_path.to_string()]
+    } else {
+        gate.paths.clone()
+    }
+}
+
+/// 启动第 index 个进程前的等待时间；每批第一个进程之前等待 interval 加抖动
+fn stagger_delay(index: usize, stagger: &StartupStagger, random: u64) -> Duration {
+    let position_in_batch = index % stagger.max_concurrent.max(1) as usize;
+    if index == 0 || position_in_batch > 0 {
+        return Duration::ZERO;
+    }
+    let jitter = if stagger.jitter_ms > 0 {
+        random % (stagger.jitter_ms + 1)
+    } else {
+        0
+    };
+    Duration::from_millis(stagger.interval_ms + jitter)
+}
+
+/// 挂起或暂缓结束后重新计算心跳与探测，避免期间的超时立即触发重启
+fn reset_health_state(process: &mut MonitoredProcess) {
+    process.update_heartbeat();
+    process.heartbeat_miss_reported = false;
+    process.heartbeat_lost = false;
+    process.http_probe_state.lock().unwrap().reset();
+    process.health_command_state.lock().unwrap().reset();
+    process.log_check_state.lock().unwrap().reset();
+    process.window_check_state.lock().unwrap().reset();
+    process.port_check_state.lock().unwrap().reset();
+    process.event_log_state.lock().unwrap().reset();
+}
+
+/// defer 与 pause_all 允许的最长暂缓时间（分钟）
+const MAX_DEFER_MINUTES: u32 = 24 * 60;
+
+/// 等待 prepare_shutdown 确认时的轮询间隔
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 连续多少次用户会话令牌失败后进入“无用户会话”退避
+const SESSION_FAILURE_THRESHOLD: u32 = 5;
+
+/// 退避期间的重试间隔：从 30 秒起每次翻倍，最长 10 分钟
+fn session_retry_delay(blocked_attempts: u32) -> Duration {
+    Duration::from_secs(30u64 << blocked_attempts.min(5)).min(Duration::from_secs(600))
+}
+
+/// 重启后复查新进程是否存活的延迟
+const RESTART_RECHECK_DELAY: Duration = Duration::from_secs(1);
+
+/// forbid 项两次记录拦截事件的最小间隔
+const BLOCK_REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+fn block_report_due(last_report: Option<Instant>, now: Instant) -> bool {
+    match last_report {
+        Some(last) => now.duration_since(last) >= BLOCK_REPORT_INTERVAL,
+        None => true,
+    }
+}
+
+/// 检查的当前连续失败次数与触发重启所需的次数
+fn probe_status(state: &Mutex<ProbeState>, threshold: u32) -> serde_json::Value {
+    let probe = state.lock().unwrap();
+    serde_json::json!({
+        "consecutive_failures": probe.consecutive_failures,
+        "unhealthy_threshold": threshold.max(1),
+        "last_error": probe.last_error,
+    })
+}
+
+/// 状态中各项健康检查的连续失败计数，未配置的检查为 null
+fn health_check_status(p: &MonitoredProcess) -> serde_json::Map<String, serde_json::Value> {
+    let item = &p.item;
+    let heartbeat = p.heartbeat_enabled().then(|| {
+        let threshold = item
+            .on_heartbeat_missed
+            .as_ref()
+            .map_or(1, |policy| policy.consecutive_misses);
+        serde_json::json!({
+            "consecutive_failures": p.missed_heartbeats(),
+            "unhealthy_threshold": threshold.max(1),
+        })
+    });
+    let children = item.children.as_ref().map(|children| {
+        serde_json::json!({
+            "consecutive_failures": p.missing_children_cycles,
+            "unhealthy_threshold": children.failure_threshold.max(1),
+        })
+    });
+    let checks = [
+        ("heartbeat", heartbeat),
+        (
+            "http_probe",
+            item.http_probe
+                .as_ref()
+                .map(|probe| probe_status(&p.http_probe_state, probe.failure_threshold)),
+        ),
+        (
+            "health_command",
+            item.health_command
+                .as_ref()
+                .map(|command| probe_status(&p.health_command_state, command.failure_threshold)),
+        ),
+        (
+            "log_check",
+            item.log_check
+                .as_ref()
+                .map(|check| probe_status(&p.log_check_state, check.failure_threshold)),
+        ),
+        (
+            "window_check",
+            item.window_check
+                .as_ref()
+                .map(|check| probe_status(&p.window_check_state, check.failure_threshold)),
+        ),
+        (
+            "port_check",
+            item.port_check
+                .as_ref()
+                .map(|check| probe_status(&p.port_check_state, check.failure_threshold)),
+        ),
+        (
+            "event_log",
+            item.event_log_check
+                .as_ref()
+                .map(|check| probe_status(&p.event_log_state, check.failure_threshold)),
+        ),
+        ("children", children),
+    ];
+    checks
+        .into_iter()
+        .map(|(name, status)| (name.to_string(), status.unwrap_or(serde_json::Value::Null)))
+        .collect()
+}
+
 pub struct Guardian {
     processes: Arc<Mutex<HashMap<String, MonitoredProcess>>>,
     config: Arc<Mutex<Config>>,
     pending_changes: Arc<Mutex<Vec<ConfigChange>>>,
     running: Arc<Mutex<bool>>,
-    startup_gate: Option<Arc<crate::service::StartupGate>>,
+    startup_gate: Option<Arc<crate::startup_gate::StartupGate>>,
+    check_cycles: AtomicU64,
+    started_at: Instant,
+    event_store: Option<Arc<EventStore>>,
+    notifier: Arc<Notifier>,
+    last_cycle_at: Mutex<Instant>,
+    /// 最近一次执行定时任务的分钟（Unix 分钟）
+    last_task_minute: Mutex<Option<i64>>,
+    /// 最近一次写入资源使用历史的时间
+    last_history_sample: Mutex<Option<Instant>>,
+    /// 睡眠恢复或系统时间跳变后，在此之前不判定心跳超时
+    settle_until: Mutex<Option<Instant>>,
+    /// 服务启动时的自检结果
+    diagnostics: Mutex<Option<DiagnosticsReport>>,
+    /// settings.allow_duplicate_exe_paths 的副本；启动进程时已持有 processes 锁，不能再锁 config
+    allow_duplicate_exe_paths: AtomicBool,
+    /// 因路径重复而不启动的监控项（重复项 id -> 保留项 id），原因同上
+    duplicate_items: Mutex<HashMap<String, String>>,
+    /// condition 在本机不满足的监控项（id -> 原因），加载配置时求值，原因同上
+    inactive_items: Mutex<HashMap<String, String>>,
+    /// settings.observe_only 的副本，原因同上
+    observe_only: AtomicBool,
+    /// 最近一次检查周期的进程快照，供状态查询复用
+    process_table: Mutex<Option<Arc<ProcessTable>>>,
+    /// WMI 退出事件订阅是否正常工作；正常时完整检查周期按 wmi_poll_interval_ms 进行
+    exit_events_active: AtomicBool,
+    /// 受守护进程退出时置位，唤醒等待中的检查循环
+    exit_wake: Mutex<bool>,
+    exit_wake_signal: Condvar,
+    /// 管道服务的连接、心跳与请求统计
+    pipe_stats: PipeStats,
+    backend: Arc<dyn ProcessBackend>,
+    config_store: Arc<dyn ConfigStore>,
+    /// 主备模式下本节点是否为主节点；未配置 peer 时始终为 true
+    ha_active: AtomicBool,
+    /// 已发出 prepare_shutdown、正在等待确认的监控项及是否已确认。
+    /// 等待期间守护循环持有 processes 锁，因此单独加锁
+    shutdown_notices: Mutex<HashMap<String, bool>>,
+    /// stop_event 监控项的停止事件，服务运行期间保持打开，程序随时可以打开等待
+    stop_events: Mutex<HashMap<String, StopEvent>>,
+    /// pause_all 暂停全部守护动作的截止时间；内层为 None 表示不自动恢复
+    paused: Mutex<Option<Option<Instant>>>,
+    /// 以安全模式启动：不启动任何监控项，管道只响应查询，resume_all 后退出
+    safe_mode: AtomicBool,
+    item_logs: ItemLogs,
 }
 
 #[cfg(test)]
 mod tests {
     use super::{
-        apply_pause_state, normalize_startup_config, should_kill_process_for_change,
+        apply_pause_state, block_report_due, compute_cpu_percent, cycle_interval,
+        dependents_to_restart, find_descendants, find_workers, missing_children,
+        normalize_startup_config, plan_reload, record_restart_in_window, restart_alert_due,
+        session_retry_delay, should_kill_process_for_change, shutdown_order, stagger_delay,
+        startup_order, storage_paths, surplus_instances, Guardian, ReloadAction,
+        BLOCK_REPORT_INTERVAL,
+    };
+    use crate::backend::MockBackend;
+    use crate::models::{
+        AlertThresholds, ChangeType, Config, ConfigChange, EscalationAction, ExistingInstance,
+        HeartbeatAction, HeartbeatPolicy, ItemMode, MonitorItem, MonitoredProcess, OrphanPolicy,
+        StartupStagger, StorageGate,
     };
-    use crate::models::{ChangeType, Config, MonitorItem, MonitoredProcess};
-    use std::collections::HashMap;
+    use crate::session0::ProcessEntry;
+    use std::collections::{HashMap, VecDeque};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
 
     #[test]
     fn pause_change_does_not_require_terminating_process() {
@@ -71,13 +633,12 @@ mod tests {
     fn pause_state_disables_monitor_without_clearing_process_id() {
         let item = MonitorItem {
             id: "EnergyMonitor".to_string(),
-            exe_path: r"C:\EnergyMonitor.exe".to_string(),
-            args: None,
-            name: "EnergyMonitor".to_string(),
-            minimize: false,
-            no_window: false,
             enabled: true,
             heartbeat_timeout_ms: 15_000,
+            ..MonitorItem::new(
+                r"C:\EnergyMonitor.exe".to_string(),
+                "EnergyMonitor".to_string(),
+            )
         };
         let mut processes = HashMap::new();
         let mut process = MonitoredProcess::from_item(item.clone());
@@ -86,6 +647,7 @@ mod tests {
 
         let mut config = Config {
             items: vec![item.clone()],
+            ..Config::default()
         };
 
         assert!(apply_pause_state(&mut processes, &mut config, &item.id));
@@ -95,430 +657,3677 @@ mod tests {
     }
 
     #[test]
-    fn startup_keeps_disabled_monitor_items_disabled() {
-        let config = Config {
-            items: vec![MonitorItem {
-                id: "EnergyMonitor".to_string(),
-                exe_path: r"C:\EnergyMonitor.exe".to_string(),
-                args: None,
-                name: "EnergyMonitor".to_string(),
-                minimize: false,
-                no_window: false,
-                enabled: false,
-                heartbeat_timeout_ms: 15_000,
-            }],
+    fn shutdown_walks_enabled_items_in_reverse() {
+        let item = |id: &str, enabled: bool| MonitorItem {
+            id: id.to_string(),
+            enabled,
+            ..MonitorItem::new(format!(r"C:\{}.exe", id), id.to_string())
         };
+        let blocked = MonitorItem {
+            mode: ItemMode::Forbid,
+            ..item("regedit", true)
+        };
+        let items = vec![
+            item("db", true),
+            item("api", true),
+            item("old", false),
+            blocked,
+            item("ui", true),
+        ];
+        assert_eq!(shutdown_order(&items), vec!["ui", "api", "db"]);
+    }
 
-        let (normalized, modified) = normalize_startup_config(config);
-        assert!(!modified);
-        assert!(!normalized.items[0].enabled);
+    #[test]
+    fn start_priority_orders_startup_and_reverses_shutdown() {
+        let item = |id: &str, start_priority: i32| MonitorItem {
+            id: id.to_string(),
+            start_priority,
+            ..MonitorItem::new(format!(r"C:\{}.exe", id), id.to_string())
+        };
+        let items = vec![
+            item("ui", 10),
+            item("db", -5),
+            item("cache", 0),
+            item("api", 0),
+        ];
+        let started: Vec<&str> = startup_order(&items)
+            .iter()
+            .map(|item| item.id.as_str())
+            .collect();
+        assert_eq!(started, vec!["db", "cache", "api", "ui"]);
+        assert_eq!(shutdown_order(&items), vec!["ui", "api", "cache", "db"]);
     }
-}
 
-impl Guardian {
-    pub fn new(
-        running: Arc<Mutex<bool>>,
-        startup_gate: Option<Arc<crate::service::StartupGate>>,
-    ) -> Self {
-        info!("Initializing guardian");
+    #[test]
+    fn dependency_restarts_propagate_once_through_opted_in_items() {
+        let item = |id: &str, depends_on: &[&str], propagate: bool| MonitorItem {
+            id: id.to_string(),
+            depends_on: depends_on.iter().map(|id| id.to_string()).collect(),
+            restart_on_dependency_restart: propagate,
+            ..MonitorItem::new(format!(r"C:\{}.exe", id), id.to_string())
+        };
+        let items = [
+            item("db", &["api"], true),
+            item("api", &["db"], true),
+            item("web", &["api"], true),
+            item("report", &["db"], false),
+            item("batch", &["report"], true),
+        ];
+        let refs: Vec<&MonitorItem> = items.iter().collect();
+        assert_eq!(dependents_to_restart(&refs, "db"), vec!["api", "web"]);
+        assert_eq!(dependents_to_restart(&refs, "web"), Vec::<String>::new());
+    }
 
-        let loaded_config = load_config();
-        let (config, config_modified) = normalize_startup_config(loaded_config);
-        let mut processes = HashMap::new();
+    #[test]
+    fn orphan_policy_defaults_to_adopt() {
+        let json = r#"{"id":"1","exe_path":"C:\\App.exe","args":null,"name":"App","minimize":false,"no_window":false,"enabled":true}"#;
+        let item: MonitorItem = serde_json::from_str(json).unwrap();
+        assert_eq!(item.orphan_policy, OrphanPolicy::Adopt);
 
-        info!("Loaded {} monitor items from config", config.items.len());
+        let json = json.replace(
+            "\"enabled\":true",
+            "\"enabled\":true,\"orphan_policy\":\"alert\"",
+        );
+        let item: MonitorItem = serde_json::from_str(&json).unwrap();
+        assert_eq!(item.orphan_policy, OrphanPolicy::Alert);
+        assert!(serde_json::to_string(&item)
+            .unwrap()
+            .contains("\"orphan_policy\":\"alert\""));
+    }
 
-        if config_modified {
-            if let Err(e) = crate::config::save_config(&config) {
-                error!("Failed to persist normalized startup config: {}", e);
-            } else {
-                info!("Saved normalized startup monitor configuration");
-            }
+    fn entry(parent_pid: u32, image_name: &str) -> ProcessEntry {
+        ProcessEntry {
+            parent_pid,
+            thread_count: 1,
+            image_name: image_name.to_string(),
         }
+    }
 
-        for item in &config.items {
-            let monitored = MonitoredProcess::from_item(item.clone());
-            processes.insert(item.id.clone(), monitored);
-            info!("Registered monitor item: {} ({})", item.name, item.exe_path);
-        }
+    #[test]
+    fn surplus_instances_keep_the_oldest_extras() {
+        let others = vec![
+            (30, Some(300)),
+            (10, Some(100)),
+            (20, None),
+            (40, Some(200)),
+        ];
+        assert_eq!(surplus_instances(others.clone(), 1), vec![10, 40, 30, 20]);
+        assert_eq!(surplus_instances(others.clone(), 3), vec![30, 20]);
+        assert!(surplus_instances(others, 5).is_empty());
+    }
 
-        Self {
-            processes: Arc::new(Mutex::new(processes)),
-            config: Arc::new(Mutex::new(config)),
-            pending_changes: Arc::new(Mutex::new(Vec::new())),
-            running,
-            startup_gate,
-        }
+    #[test]
+    fn descendants_follow_parent_chain() {
+        let snapshot = HashMap::from([
+            (10, entry(1, "app.exe")),
+            (11, entry(10, "worker.exe")),
+            (12, entry(11, "helper.exe")),
+            (20, entry(1, "other.exe")),
+            // PID 复用导致的环不会造成死循环
+            (1, entry(12, "reused.exe")),
+        ]);
+        assert_eq!(find_descendants(10, &snapshot), vec![1, 11, 12]);
+        assert_eq!(
+            missing_children(
+                &["WORKER.exe".to_string(), "indexer.exe".to_string()],
+                &[11, 12],
+                &snapshot
+            ),
+            vec!["indexer.exe".to_string()]
+        );
+        assert_eq!(find_workers(10, "work*.EXE", &snapshot), vec![11]);
+        assert!(find_workers(20, "worker.exe", &snapshot).is_empty());
     }
 
-    pub fn get_processes(&self) -> Arc<Mutex<HashMap<String, MonitoredProcess>>> {
-        self.processes.clone()
+    #[test]
+    fn restart_alerts_fire_once_per_threshold() {
+        let alerts = AlertThresholds {
+            restarts: 3,
+            window_minutes: 10,
+            on_failed: false,
+            heartbeat_missing_secs: 0,
+        };
+        let start = Instant::now();
+        let mut history = VecDeque::new();
+        let fired: Vec<bool> = (0..6)
+            .map(|i| {
+                restart_alert_due(
+                    &mut history,
+                    start + Duration::from_secs(i * 60),
+                    Some(&alerts),
+                )
+            })
+            .collect();
+        assert_eq!(fired, vec![false, false, true, false, false, true]);
+        assert!(restart_alert_due(&mut VecDeque::new(), start, None));
     }
 
-    pub fn get_config(&self) -> Arc<Mutex<Config>> {
-        self.config.clone()
+    #[test]
+    fn reload_only_touches_changed_items() {
+        let item = |name: &str, enabled: bool| MonitorItem {
+            id: name.to_string(),
+            enabled,
+            ..MonitorItem::new(format!(r"C:\{}.exe", name), name.to_string())
+        };
+        let current = vec![
+            item("same", true),
+            item("edited", true),
+            item("gone", true),
+            item("off", true),
+        ];
+        let mut edited = item("edited", true);
+        edited.args = Some("--port 8080".to_string());
+        let mut tagged = item("same", true);
+        tagged.extra.insert("x_owner".to_string(), "ops".into());
+        let new = vec![tagged, edited, item("off", false), item("added", true)];
+
+        assert_eq!(
+            plan_reload(&current, &new),
+            vec![
+                ("edited".to_string(), ReloadAction::Restart),
+                ("off".to_string(), ReloadAction::Stop),
+                ("added".to_string(), ReloadAction::Start),
+                ("gone".to_string(), ReloadAction::Remove),
+            ]
+        );
     }
 
-    pub fn get_pending_changes(&self) -> Arc<Mutex<Vec<ConfigChange>>> {
-        self.pending_changes.clone()
+    #[test]
+    fn storage_gate_defaults_to_exe_path() {
+        let mut gate = StorageGate {
+            paths: Vec::new(),
+            timeout_ms: 0,
+        };
+        assert_eq!(
+            storage_paths(&gate, r"\\nas\apps\app.exe"),
+            vec![r"\\nas\apps\app.exe"]
+        );
+        gate.paths = vec![r"Z:\data".to_string()];
+        assert_eq!(storage_paths(&gate, r"C:\app.exe"), vec![r"Z:\data"]);
+        gate.paths.clear();
+        assert_eq!(storage_paths(&gate, r"C:\Apps\*\app.exe"), vec![r"C:\Apps"]);
     }
 
-    pub fn add_change(&self, change: ConfigChange) {
-        let item_id = change.item.id.clone();
-        let mut pending = self.pending_changes.lock().unwrap();
-        pending.push(change);
-        debug!("Queued config change for {}", item_id);
+    #[test]
+    fn stagger_waits_between_batches() {
+        let stagger = StartupStagger {
+            interval_ms: 2000,
+            jitter_ms: 500,
+            max_concurrent: 2,
+        };
+        let delays: Vec<u128> = (0..5)
+            .map(|index| stagger_delay(index, &stagger, 1234).as_millis())
+            .collect();
+        // 1234 % 501 = 232
+        assert_eq!(delays, vec![0, 0, 2232, 0, 2232]);
     }
 
-    pub fn update_heartbeat(&self, item_id: &str) -> bool {
-        let mut processes = self.processes.lock().unwrap();
-        if let Some(process) = processes.get_mut(item_id) {
-            process.update_heartbeat();
-            debug!("Heartbeat updated for {} ({})", process.item.name, item_id);
-            true
-        } else {
-            warn!("Heartbeat update failed, item not found: {}", item_id);
-            false
-        }
+    #[test]
+    fn block_reports_are_rate_limited() {
+        let now = Instant::now();
+        assert!(block_report_due(None, now));
+        assert!(!block_report_due(Some(now), now + Duration::from_secs(5)));
+        assert!(block_report_due(Some(now), now + BLOCK_REPORT_INTERVAL));
     }
 
-    pub fn run(&self) {
-        info!("Guardian started");
-        info!("Check interval: {} ms", CHECK_INTERVAL_MS);
+    #[test]
+    fn heartbeat_policy_waits_for_consecutive_misses() {
+        let item = MonitorItem {
+            heartbeat_timeout_ms: 10_000,
+            on_heartbeat_missed: Some(HeartbeatPolicy {
+                action: HeartbeatAction::Notify,
+                consecutive_misses: 3,
+            }),
+            ..MonitorItem::new(r"C:\Worker.exe".to_string(), "Worker".to_string())
+        };
+        let mut process = MonitoredProcess::from_item(item);
+        process.last_heartbeat = Instant::now() - Duration::from_secs(25);
+        assert!(process.is_heartbeat_timeout());
+        assert_eq!(process.due_heartbeat_action(), None);
 
-        if let Some(startup_gate) = &self.startup_gate {
-            info!("Waiting for pipe server readiness before starting monitored processes");
-            startup_gate.wait_until_ready();
-        }
+        process.last_heartbeat = Instant::now() - Duration::from_secs(31);
+        assert_eq!(
+            process.due_heartbeat_action(),
+            Some(HeartbeatAction::Notify)
+        );
 
-        self.start_all_processes();
+        process.item.on_heartbeat_missed = None;
+        assert_eq!(
+            process.due_heartbeat_action(),
+            Some(HeartbeatAction::Restart)
+        );
+    }
 
-        let mut check_count: u64 = 0;
+    #[test]
+    fn heartbeat_can_be_disabled_per_item() {
+        let item = MonitorItem::new(r"C:\Plain.exe".to_string(), "Plain".to_string());
+        let mut process = MonitoredProcess::from_item(item.clone());
+        process.last_heartbeat = Instant::now() - Duration::from_secs(60);
+        assert!(process.is_heartbeat_timeout());
 
-        loop {
-            let running = *self.running.lock().unwrap();
-            if !running {
-                info!("Guardian stopping");
-                break;
-            }
+        process.item.heartbeat_required = false;
+        assert!(!process.is_heartbeat_timeout());
 
-            std::thread::sleep(Duration::from_millis(CHECK_INTERVAL_MS));
-            check_count += 1;
+        process.item = MonitorItem {
+            heartbeat_timeout_ms: 0,
+            ..item
+        };
+        assert!(!process.is_heartbeat_timeout());
+    }
 
-            info!("--- Check cycle #{} ---", check_count);
-            self.process_pending_changes();
-            self.check_processes();
-        }
+    #[test]
+    fn startup_keeps_disabled_monitor_items_disabled() {
+        let config = Config {
+            items: vec![MonitorItem {
+                id: "EnergyMonitor".to_string(),
+                enabled: false,
+                heartbeat_timeout_ms: 15_000,
+                ..MonitorItem::new(
+                    r"C:\EnergyMonitor.exe".to_string(),
+                    "EnergyMonitor".to_string(),
+                )
+            }],
+            ..Config::default()
+        };
 
-        info!("Guardian stopped after {} checks", check_count);
+        let (normalized, modified) = normalize_startup_config(config);
+        assert!(!modified);
+        assert!(!normalized.items[0].enabled);
     }
 
-    fn start_all_processes(&self) {
-        info!("Starting all monitored processes");
-
-        let processes = self.processes.lock().unwrap().clone();
+    #[test]
+    fn cpu_percent_is_normalized_by_cpu_count() {
+        // 1s 的 CPU 时间在 1s 墙钟时间内、4 核机器上 = 25%
+        let percent = compute_cpu_percent(0, 10_000_000, Duration::from_secs(1), 4);
+        assert!((percent - 25.0).abs() < 0.01);
+    }
 
-        for (id, mut process) in processes {
-            if process.item.enabled {
+    #[test]
+    fn cpu_percent_handles_counter_reset_and_zero_elapsed() {
+        assert_eq!(
+            compute_cpu_percent(500, 100, Duration::from_secs(1), 2),
+            0.0
+        );
+        assert_eq!(compute_cpu_percent(0, 100, Duration::ZERO, 2), 0.0);
+    }
+
+    #[test]
+    fn cycle_interval_lengthens_only_with_exit_events() {
+        assert_eq!(cycle_interval(false, 15_000), Duration::from_millis(3_000));
+        assert_eq!(cycle_interval(true, 15_000), Duration::from_millis(15_000));
+        assert_eq!(cycle_interval(true, 500), Duration::from_millis(3_000));
+    }
+
+    #[test]
+    fn session_retry_delay_doubles_up_to_ten_minutes() {
+        assert_eq!(session_retry_delay(0), Duration::from_secs(30));
+        assert_eq!(session_retry_delay(2), Duration::from_secs(120));
+        assert_eq!(session_retry_delay(5), Duration::from_secs(600));
+        assert_eq!(session_retry_delay(40), Duration::from_secs(600));
+    }
+
+    #[test]
+    fn restart_window_drops_restarts_older_than_window() {
+        let start = Instant::now();
+        let window = Duration::from_secs(60);
+        let mut restarts = VecDeque::new();
+
+        assert_eq!(record_restart_in_window(&mut restarts, start, window), 1);
+        assert_eq!(
+            record_restart_in_window(&mut restarts, start + Duration::from_secs(30), window),
+            2
+        );
+        assert_eq!(
+            record_restart_in_window(&mut restarts, start + Duration::from_secs(91), window),
+            2
+        );
+    }
+
+    fn mock_guardian(item: MonitorItem) -> (Guardian, Arc<MockBackend>) {
+        let backend = Arc::new(MockBackend::new());
+        let config = Config {
+            items: vec![item],
+            ..Config::default()
+        };
+        let guardian = Guardian::with_backend(config, Arc::new(Mutex::new(true)), backend.clone());
+        (guardian, backend)
+    }
+
+    fn age_process(guardian: &Guardian, id: &str, startup: Duration, heartbeat: Duration) {
+        let processes = guardian.get_processes();
+        let mut processes = processes.lock().unwrap();
+        let process = processes.get_mut(id).unwrap();
+        process.startup_time = Instant::now().checked_sub(startup).unwrap();
+        process.last_heartbeat = Instant::now().checked_sub(heartbeat).unwrap();
+    }
+
+    #[test]
+    fn exited_process_is_relaunched_through_backend() {
+        let item = MonitorItem {
+            id: "worker".to_string(),
+            heartbeat_required: false,
+            post_kill_delay_ms: 0,
+            ..MonitorItem::new(r"C:\worker.exe".to_string(), "worker".to_string())
+        };
+        let (guardian, backend) = mock_guardian(item);
+
+        guardian.start_all_processes();
+        let first = backend.spawned();
+        assert_eq!(first.len(), 1);
+
+        backend.exit(first[0]);
+        age_process(&guardian, "worker", Duration::from_secs(10), Duration::ZERO);
+        guardian.check_processes();
+
+        let spawned = backend.spawned();
+        assert_eq!(spawned.len(), 2);
+        assert!(backend.killed().is_empty());
+        let processes = guardian.get_processes();
+        let process = &processes.lock().unwrap()["worker"];
+        assert_eq!(process.process_id, Some(spawned[1]));
+        assert_eq!(process.restart_count, 1);
+    }
+
+    #[test]
+    fn restart_delay_postpones_relaunch_after_exit() {
+        let item = MonitorItem {
+            id: "worker".to_string(),
+            heartbeat_required: false,
+            post_kill_delay_ms: 0,
+            restart_delay_ms: 60_000,
+            ..MonitorItem::new(r"C:\worker.exe".to_string(), "worker".to_string())
+        };
+        let (guardian, backend) = mock_guardian(item);
+
+        guardian.start_all_processes();
+        let first = backend.spawned();
+        backend.exit(first[0]);
+        age_process(&guardian, "worker", Duration::from_secs(10), Duration::ZERO);
+        guardian.check_processes();
+        assert_eq!(backend.spawned().len(), 1);
+
+        {
+            let processes = guardian.get_processes();
+            let mut processes = processes.lock().unwrap();
+            let process = processes.get_mut("worker").unwrap();
+            process.restart_due = Some(Instant::now().checked_sub(Duration::from_secs(1)).unwrap());
+        }
+        guardian.check_processes();
+        assert_eq!(backend.spawned().len(), 2);
+    }
+
+    #[test]
+    fn existing_instance_fail_neither_adopts_nor_kills() {
+        let item = MonitorItem {
+            id: "app".to_string(),
+            heartbeat_required: false,
+            orphan_policy: OrphanPolicy::Restart,
+            existing_instance: ExistingInstance::Fail,
+            ..MonitorItem::new(r"C:\app.exe".to_string(), "app".to_string())
+        };
+        let (guardian, backend) = mock_guardian(item);
+        let debug_copy = backend.add_running(r"C:\app.exe", None);
+
+        guardian.start_all_processes();
+        assert!(backend.spawned().is_empty());
+        assert!(backend.killed().is_empty());
+        {
+            let processes = guardian.get_processes();
+            let process = &processes.lock().unwrap()["app"];
+            assert_eq!(process.process_id, None);
+            assert_eq!(process.orphan_pid, Some(debug_copy));
+            assert!(process
+                .last_start_error
+                .as_deref()
+                .unwrap()
+                .contains("existing_instance"));
+        }
+
+        backend.exit(debug_copy);
+        age_process(&guardian, "app", Duration::from_secs(10), Duration::ZERO);
+        guardian.check_processes();
+        assert_eq!(backend.spawned().len(), 1);
+    }
+
+    #[test]
+    fn safe_mode_refuses_queued_starts_and_restarts() {
+        let item = MonitorItem {
+            id: "app".to_string(),
+            heartbeat_required: false,
+            ..MonitorItem::new(r"C:\app.exe".to_string(), "app".to_string())
+        };
+        let (guardian, backend) = mock_guardian(item.clone());
+        guardian.enter_safe_mode();
+
+        for change_type in [ChangeType::Start, ChangeType::Kill] {
+            guardian.add_change(ConfigChange {
+                item: item.clone(),
+                change_type,
+            });
+        }
+        guardian.process_pending_changes();
+        assert!(backend.spawned().is_empty());
+        assert!(guardian.get_pending_changes().lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn missed_heartbeat_kills_and_relaunches() {
+        let item = MonitorItem {
+            id: "ui".to_string(),
+            heartbeat_timeout_ms: 1000,
+            post_kill_delay_ms: 0,
+            ..MonitorItem::new(r"C:\ui.exe".to_string(), "ui".to_string())
+        };
+        let (guardian, backend) = mock_guardian(item);
+
+        guardian.start_all_processes();
+        let first = backend.spawned()[0];
+        age_process(
+            &guardian,
+            "ui",
+            Duration::from_secs(10),
+            Duration::from_secs(5),
+        );
+        guardian.check_processes();
+
+        assert_eq!(backend.killed(), vec![first]);
+        assert_eq!(backend.spawned().len(), 2);
+    }
+
+    #[test]
+    fn observe_mode_reports_instead_of_restarting() {
+        let item = MonitorItem {
+            id: "api".to_string(),
+            heartbeat_required: false,
+            observe: true,
+            ..MonitorItem::new(r"C:\api.exe".to_string(), "api".to_string())
+        };
+        let (guardian, backend) = mock_guardian(item);
+        let existing = backend.add_running(r"C:\api.exe", None);
+
+        guardian.start_all_processes();
+        backend.exit(existing);
+        age_process(&guardian, "api", Duration::from_secs(10), Duration::ZERO);
+        guardian.check_processes();
+
+        assert!(backend.spawned().is_empty());
+        let processes = guardian.get_processes();
+        let process = &processes.lock().unwrap()["api"];
+        assert_eq!(
+            process.observed_action.as_deref(),
+            Some("restart: process not alive")
+        );
+        assert_eq!(process.restart_count, 0);
+    }
+
+    #[test]
+    fn observe_mode_tracks_crash_loops_without_escalating() {
+        let item = MonitorItem {
+            id: "api".to_string(),
+            heartbeat_required: false,
+            observe: true,
+            escalation: Some(EscalationAction::Fallback {
+                exe_path: r"C:\api-safe.exe".to_string(),
+                args: None,
+            }),
+            ..MonitorItem::new(r"C:\api.exe".to_string(), "api".to_string())
+        };
+        let (guardian, backend) = mock_guardian(item);
+        {
+            let config = guardian.get_config();
+            let mut config = config.lock().unwrap();
+            config.settings.crash_loop_restarts = 1;
+        }
+        let existing = backend.add_running(r"C:\api.exe", None);
+
+        guardian.start_all_processes();
+        backend.exit(existing);
+        age_process(&guardian, "api", Duration::from_secs(10), Duration::ZERO);
+        guardian.check_processes();
+        guardian.check_processes();
+
+        assert!(backend.spawned().is_empty());
+        let processes = guardian.get_processes();
+        let process = &processes.lock().unwrap()["api"];
+        assert!(process.in_crash_loop);
+        assert_eq!(process.recent_restarts.len(), 1);
+        assert!(!process.escalated);
+        assert_eq!(process.item.exe_path, r"C:\api.exe");
+    }
+}
+
+impl Guardian {
+    pub fn new(
+        running: Arc<Mutex<bool>>,
+        startup_gate: Option<Arc<crate::startup_gate::StartupGate>>,
+    ) -> Self {
+        info!("Initializing guardian");
+
+        let loaded_config = load_config();
+        let (config, config_modified) = normalize_startup_config(loaded_config);
+
+        info!("Loaded {} monitor items from config", config.items.len());
+
+        if config_modified {
+            if let Err(e) = crate::config::save_config(&config) {
+                error!("Failed to persist normalized startup config: {}", e);
+            } else {
+                info!("Saved normalized startup monitor configuration");
+            }
+        }
+
+        let event_store = if config.settings.event_store {
+            let db_path = crate::config::get_event_db_path();
+            match EventStore::open(&db_path) {
+                Ok(store) => {
+                    harden_file(&db_path, UsersAccess::None);
+                    info!("Event store opened: {:?}", db_path);
+                    Some(Arc::new(store))
+                }
+                Err(e) => {
+                    error!("Failed to open event store {:?}: {}", db_path, e);
+                    None
+                }
+            }
+        } else {
+            info!("Event store disabled by settings");
+            None
+        };
+
+        Self::assemble(
+            config,
+            running,
+            startup_gate,
+            event_store,
+            Arc::new(Win32Backend),
+            Arc::new(FileConfigStore),
+        )
+    }
+
+    /// 使用给定配置与进程后端构造：不读写配置文件、不打开事件库，用于嵌入与测试
+    pub fn with_backend(
+        config: Config,
+        running: Arc<Mutex<bool>>,
+        backend: Arc<dyn ProcessBackend>,
+    ) -> Self {
+        Self::assemble(
+            config,
+            running,
+            None,
+            None,
+            backend,
+            Arc::new(MemoryConfigStore),
+        )
+    }
+
+    fn assemble(
+        config: Config,
+        running: Arc<Mutex<bool>>,
+        startup_gate: Option<Arc<crate::startup_gate::StartupGate>>,
+        event_store: Option<Arc<EventStore>>,
+        backend: Arc<dyn ProcessBackend>,
+        config_store: Arc<dyn ConfigStore>,
+    ) -> Self {
+        let mut processes = HashMap::new();
+        for item in &config.items {
+            let monitored = MonitoredProcess::from_item(item.clone());
+            processes.insert(item.id.clone(), monitored);
+            info!("Registered monitor item: {} ({})", item.name, item.exe_path);
+        }
+
+        let notifier = Arc::new(Notifier::new(config.settings.webhooks.clone()));
+        let allow_duplicate_exe_paths = AtomicBool::new(config.settings.allow_duplicate_exe_paths);
+        let duplicate_items = Mutex::new(duplicate_item_map(&config));
+        let inactive_items = crate::condition::inactive_items(&config);
+        for (id, reason) in &inactive_items {
+            info!("Item {} is not active on this machine: {}", id, reason);
+        }
+        let inactive_items = Mutex::new(inactive_items);
+        let observe_only = AtomicBool::new(config.settings.observe_only);
+        if config.settings.observe_only {
+            warn!("Observe-only mode: processes will be checked but never started, restarted or killed");
+        }
+        // 配置了对端时以备用节点启动，由对端监控线程决定何时接管
+        let ha_active = AtomicBool::new(config.settings.peer.is_none());
+        let item_logs = ItemLogs::new(config.settings.item_logs.clone());
+
+        Self {
+            processes: Arc::new(Mutex::new(processes)),
+            config: Arc::new(Mutex::new(config)),
+            pending_changes: Arc::new(Mutex::new(Vec::new())),
+            running,
+            startup_gate,
+            check_cycles: AtomicU64::new(0),
+            started_at: Instant::now(),
+            event_store,
+            notifier,
+            last_cycle_at: Mutex::new(Instant::now()),
+            last_task_minute: Mutex::new(None),
+            last_history_sample: Mutex::new(None),
+            settle_until: Mutex::new(None),
+            diagnostics: Mutex::new(None),
+            allow_duplicate_exe_paths,
+            duplicate_items,
+            inactive_items,
+            observe_only,
+            process_table: Mutex::new(None),
+            exit_events_active: AtomicBool::new(false),
+            exit_wake: Mutex::new(false),
+            exit_wake_signal: Condvar::new(),
+            pipe_stats: PipeStats::default(),
+            backend,
+            config_store,
+            ha_active,
+            shutdown_notices: Mutex::new(HashMap::new()),
+            stop_events: Mutex::new(HashMap::new()),
+            paused: Mutex::new(None),
+            safe_mode: AtomicBool::new(false),
+            item_logs,
+        }
+    }
+
+    pub fn get_processes(&self) -> Arc<Mutex<HashMap<String, MonitoredProcess>>> {
+        self.processes.clone()
+    }
+
+    pub fn get_config(&self) -> Arc<Mutex<Config>> {
+        self.config.clone()
+    }
+
+    /// 按构造时选择的方式保存配置（服务写入 config.json，with_backend 构造的实例不保存）
+    pub fn save_config(&self, config: &Config) -> std::io::Result<()> {
+        self.config_store.save(config)
+    }
+
+    pub fn check_cycles(&self) -> u64 {
+        self.check_cycles.load(Ordering::Relaxed)
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    /// 距上一次完成检查周期的时间，用于判断守护线程是否卡死
+    pub fn last_cycle_age(&self) -> Duration {
+        self.last_cycle_at.lock().unwrap().elapsed()
+    }
+
+    /// 读取缓存的进程表；超过两个检查周期未刷新（守护循环未运行或卡住）时重新快照
+    pub fn process_table(&self) -> Arc<ProcessTable> {
+        let cached = self.process_table.lock().unwrap().clone();
+        match cached {
+            Some(table) if table.age() < Duration::from_millis(CHECK_INTERVAL_MS * 2) => table,
+            _ => self.refresh_process_table(),
+        }
+    }
+
+    fn refresh_process_table(&self) -> Arc<ProcessTable> {
+        let tracked: Vec<u32> = self
+            .processes
+            .lock()
+            .unwrap()
+            .values()
+            .filter_map(|process| process.process_id)
+            .collect();
+        let table = Arc::new(ProcessTable::capture(self.backend.clone(), &tracked));
+        *self.process_table.lock().unwrap() = Some(table.clone());
+        table
+    }
+
+    /// 本节点是否为主节点
+    pub fn is_ha_active(&self) -> bool {
+        self.ha_active.load(Ordering::SeqCst)
+    }
+
+    /// 切换主备角色；变为备用节点后 ha 监控项在下一检查周期停止
+    pub fn set_ha_active(&self, active: bool, reason: &str) {
+        if self.ha_active.swap(active, Ordering::SeqCst) != active {
+            let role = if active { "active" } else { "standby" };
+            warn!("HA role changed to {}: {}", role, reason);
+            self.notify(Notification::ha_role_changed(active, reason));
+        }
+    }
+
+    /// 本节点的主备状态，供对端通过 peer_status 查询
+    pub fn ha_status(&self) -> serde_json::Value {
+        let priority = self
+            .config
+            .lock()
+            .unwrap()
+            .settings
+            .peer
+            .as_ref()
+            .map(|peer| peer.priority);
+        serde_json::json!({
+            "node": crate::peer::node_name(),
+            "priority": priority,
+            "active": self.is_ha_active(),
+        })
+    }
+
+    /// 监控项此时不应运行的原因：本机条件不满足、运行时间窗口外，或本节点是备用节点
+    fn run_window_closed(&self, item: &MonitorItem) -> Option<&'static str> {
+        if self.inactive_items.lock().unwrap().contains_key(&item.id) {
+            Some("machine condition not met")
+        } else if !crate::schedule::is_open_now(&item.schedule) {
+            Some("schedule window closed")
+        } else if item.ha && !self.is_ha_active() {
+            Some("standby node")
+        } else {
+            None
+        }
+    }
+
+    /// 执行启动自检并保存结果；需在管道服务启动前调用
+    pub fn run_diagnostics(&self) {
+        let report = crate::diagnostics::run(&self.config.lock().unwrap());
+        crate::diagnostics::report(&report);
+        *self.diagnostics.lock().unwrap() = Some(report);
+    }
+
+    fn refresh_item_maps(&self, config: &Config) {
+        *self.duplicate_items.lock().unwrap() = duplicate_item_map(config);
+        let inactive = crate::condition::inactive_items(config);
+        let mut current = self.inactive_items.lock().unwrap();
+        for (id, reason) in &inactive {
+            if current.get(id) != Some(reason) {
+                info!("Item {} is not active on this machine: {}", id, reason);
+            }
+        }
+        *current = inactive;
+    }
+
+    pub fn diagnostics(&self) -> Option<DiagnosticsReport> {
+        self.diagnostics.lock().unwrap().clone()
+    }
+
+    pub fn pipe_stats(&self) -> &PipeStats {
+        &self.pipe_stats
+    }
+
+    pub fn get_event_store(&self) -> Option<Arc<EventStore>> {
+        self.event_store.clone()
+    }
+
+    pub fn notify(&self, notification: Notification) {
+        self.notifier.notify(notification);
+    }
+
+    pub fn shutdown_notifier(&self) {
+        self.notifier.shutdown();
+    }
+
+    fn record_event(&self, item_id: &str, kind: EventKind, detail: &str) {
+        self.item_logs.write(item_id, kind.as_str(), detail);
+        if let Some(store) = &self.event_store {
+            store.record_event(item_id, kind, detail);
+        }
+    }
+
+    /// 重新读取 config.json，只对变化的监控项排队启停变更
+    pub fn reload_config(&self) -> Result<serde_json::Value, String> {
+        let new_config = crate::config::read_config_for_reload()?;
+
+        let (plan, old_items, restart_settings) = {
+            let mut config = self.config.lock().unwrap();
+            let plan = plan_reload(&config.items, &new_config.items);
+            let restart_settings =
+                settings_requiring_restart(&config.settings, &new_config.settings);
+            let old_items = std::mem::replace(&mut config.items, new_config.items.clone());
+            config.settings = new_config.settings.clone();
+            config.extra = new_config.extra.clone();
+            self.allow_duplicate_exe_paths
+                .store(config.settings.allow_duplicate_exe_paths, Ordering::Relaxed);
+            self.refresh_item_maps(&config);
+            self.observe_only
+                .store(config.settings.observe_only, Ordering::Relaxed);
+            self.item_logs.configure(config.settings.item_logs.clone());
+            (plan, old_items, restart_settings)
+        };
+
+        if !restart_settings.is_empty() {
+            warn!(
+                "Settings {:?} changed on disk; they take effect after a service restart",
+                restart_settings
+            );
+        }
+
+        let mut changes = Vec::new();
+        let mut processes = self.processes.lock().unwrap();
+        for (id, action) in &plan {
+            let item = new_config
+                .items
+                .iter()
+                .chain(old_items.iter())
+                .find(|item| &item.id == id)
+                .cloned()
+                .expect("planned item exists in old or new config");
+            info!("Reload: {} {} ({})", action.as_str(), item.name, id);
+
+            let change_type = match action {
+                ReloadAction::Start => Some(ChangeType::Start),
+                ReloadAction::Restart => Some(ChangeType::Stop | ChangeType::Start),
+                ReloadAction::Stop => Some(ChangeType::Stop),
+                ReloadAction::Remove => Some(ChangeType::Stop | ChangeType::Remove),
+                ReloadAction::Update => None,
+            };
+            if matches!(action, ReloadAction::Stop | ReloadAction::Update) {
+                processes
+                    .entry(id.clone())
+                    .and_modify(|process| process.item = item.clone())
+                    .or_insert_with(|| MonitoredProcess::from_item(item.clone()));
+            }
+            changes.push(serde_json::json!({
+                "id": id,
+                "name": item.name,
+                "action": action.as_str(),
+            }));
+            if let Some(change_type) = change_type {
+                self.add_change(ConfigChange { item, change_type });
+            }
+        }
+        drop(processes);
+
+        info!("Config reloaded: {} item changes queued", changes.len());
+        Ok(serde_json::json!({
+            "changes": changes,
+            "settings_requiring_restart": restart_settings,
+        }))
+    }
+
+    pub fn get_pending_changes(&self) -> Arc<Mutex<Vec<ConfigChange>>> {
+        self.pending_changes.clone()
+    }
+
+    pub fn add_change(&self, change: ConfigChange) {
+        let item_id = change.item.id.clone();
+        let mut pending = self.pending_changes.lock().unwrap();
+        pending.push(change);
+        debug!("Queued config change for {}", item_id);
+    }
+
+    /// 一次加入多项变更，守护循环在同一批中应用并只保存一次配置
+    pub fn add_changes(&self, changes: Vec<ConfigChange>) {
+        let count = changes.len();
+        self.pending_changes.lock().unwrap().extend(changes);
+        debug!("Queued {} config changes", count);
+    }
+
+    pub fn update_heartbeat(&self, item_id: &str) -> bool {
+        let mut processes = self.processes.lock().unwrap();
+        if let Some(process) = processes.get_mut(item_id) {
+            process.update_heartbeat();
+            debug!("Heartbeat updated for {} ({})", process.item.name, item_id);
+            true
+        } else {
+            warn!("Heartbeat update failed, item not found: {}", item_id);
+            false
+        }
+    }
+
+    /// UDP 心跳：令牌与监控项的 heartbeat_token 一致时更新心跳。
+    /// 等待 prepare_shutdown 确认期间无法回复通知，直接忽略
+    pub fn update_heartbeat_with_token(&self, item_id: &str, token: &str) -> bool {
+        if self.shutdown_requested(item_id) {
+            return false;
+        }
+        let mut processes = self.processes.lock().unwrap();
+        match processes.get_mut(item_id) {
+            Some(process) if process.item.heartbeat_token.as_deref() == Some(token) => {
+                process.update_heartbeat();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 监控项是否正在等待 prepare_shutdown 确认；不获取 processes 锁
+    pub fn shutdown_requested(&self, item_id: &str) -> bool {
+        self.shutdown_notices.lock().unwrap().contains_key(item_id)
+    }
+
+    /// 记录程序的 shutdown_ready 确认，没有待确认的通知时返回 false
+    pub fn acknowledge_shutdown(&self, item_id: &str) -> bool {
+        match self.shutdown_notices.lock().unwrap().get_mut(item_id) {
+            Some(acknowledged) => {
+                *acknowledged = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 按 prepare_shutdown 通知程序即将重启，等待确认、进程自行退出或超时
+    fn prepare_shutdown(&self, item: &MonitorItem, pid: u32) {
+        let Some(config) = &item.prepare_shutdown else {
+            return;
+        };
+        info!(
+            "Asking {} (PID {}) to prepare for shutdown, waiting up to {}s",
+            item.name, pid, config.timeout_secs
+        );
+        self.shutdown_notices
+            .lock()
+            .unwrap()
+            .insert(item.id.clone(), false);
+        let event = config.event.as_deref().and_then(|name| {
+            ShutdownEvent::signal(name)
+                .map_err(|e| warn!("Failed to signal shutdown event for {}: {}", item.name, e))
+                .ok()
+        });
+
+        let deadline = Instant::now() + Duration::from_secs(config.timeout_secs);
+        let outcome = loop {
+            if self.shutdown_notices.lock().unwrap().get(&item.id) == Some(&true)
+                || event.as_ref().is_some_and(|event| event.acknowledged())
+            {
+                break "acknowledged";
+            }
+            if !self.backend.is_alive(pid) {
+                break "exited";
+            }
+            if Instant::now() >= deadline {
+                break "timed out";
+            }
+            std::thread::sleep(SHUTDOWN_POLL_INTERVAL);
+        };
+        self.shutdown_notices.lock().unwrap().remove(&item.id);
+        info!("Shutdown notice for {} {}", item.name, outcome);
+    }
+
+    /// 启动前为 stop_event 监控项创建或复位停止事件；关闭该选项后释放事件
+    fn arm_stop_event(&self, item: &MonitorItem) {
+        let mut events = self.stop_events.lock().unwrap();
+        if !item.stop_event {
+            events.remove(&item.id);
+            return;
+        }
+        let result = if let Some(event) = events.get(&item.id) {
+            event.reset()
+        } else {
+            StopEvent::create(&item.id).map(|event| {
+                events.insert(item.id.clone(), event);
+            })
+        };
+        if let Err(e) = result {
+            warn!("Failed to prepare stop event for {}: {}", item.name, e);
+        }
+    }
+
+    /// 置位监控项的停止事件；`wait` 为 true 时等待进程在 stop_event_timeout_ms 内退出，返回进程是否已退出
+    fn signal_stop_event(
+        &self,
+        item: &MonitorItem,
+        pid: u32,
+        created: Option<u64>,
+        wait: bool,
+    ) -> bool {
+        if !self.set_stop_event(item) {
+            return false;
+        }
+        info!("Signaled stop event of {} (PID {})", item.name, pid);
+        if !wait {
+            return false;
+        }
+        let exited = self.backend.wait_for_exit(
+            pid,
+            created,
+            Duration::from_millis(item.stop_event_timeout_ms),
+        );
+        if !exited {
+            warn!(
+                "{} (PID {}) did not exit within {}ms after the stop event, terminating",
+                item.name, pid, item.stop_event_timeout_ms
+            );
+        }
+        exited
+    }
+
+    /// 置位停止事件，返回是否已置位
+    fn set_stop_event(&self, item: &MonitorItem) -> bool {
+        if !item.stop_event {
+            return false;
+        }
+        match self.stop_events.lock().unwrap().get(&item.id) {
+            Some(event) => event
+                .signal()
+                .map_err(|e| warn!("Failed to signal stop event for {}: {}", item.name, e))
+                .is_ok(),
+            None => false,
+        }
+    }
+
+    /// 停止进程：配置了 stop_sequence 时按序列执行，否则置位停止事件并等待，未退出再强制终止。
+    /// 返回进程是否自行退出
+    fn stop_process(&self, item: &MonitorItem, pid: u32, created: Option<u64>) -> bool {
+        if !item.stop_sequence.is_empty() {
+            info!(
+                "Running stop sequence of {} (PID {}): {} step(s)",
+                item.name,
+                pid,
+                item.stop_sequence.len()
+            );
+            let stopper = ProcessStopper {
+                guardian: self,
+                item,
+                pid,
+                created,
+            };
+            return stop_plan::run(&item.name, &item.stop_sequence, &stopper).graceful;
+        }
+        if self.signal_stop_event(item, pid, created, true) {
+            return true;
+        }
+        self.backend.kill(pid, created);
+        false
+    }
+
+    /// 挂起或恢复监控项的进程；挂起期间守护循环不检查心跳与健康状态
+    pub fn set_suspended(&self, item_id: &str, suspend: bool) -> Result<u32, String> {
+        let mut processes = self.processes.lock().unwrap();
+        let process = processes
+            .get_mut(item_id)
+            .ok_or_else(|| format!("item not found: {}", item_id))?;
+        let pid = self
+            .live_pid(process)
+            .ok_or_else(|| format!("{} is not running", process.item.name))?;
+        if process.suspended == suspend {
+            return Ok(pid);
+        }
+
+        set_process_suspended(pid, suspend)?;
+        process.suspended = suspend;
+        if !suspend {
+            reset_health_state(process);
+        }
+
+        info!(
+            "{} process {} (PID {})",
+            if suspend { "Suspended" } else { "Resumed" },
+            process.item.name,
+            pid
+        );
+        let kind = if suspend {
+            EventKind::Suspended
+        } else {
+            EventKind::Resumed
+        };
+        self.record_event(item_id, kind, &format!("pid={}", pid));
+        Ok(pid)
+    }
+
+    /// 清空监控项进程的工作集，返回 (PID, 收缩前字节数, 收缩后字节数)
+    pub fn trim_memory(&self, item_id: &str) -> Result<(u32, u64, u64), String> {
+        let processes = self.processes.lock().unwrap();
+        let process = processes
+            .get(item_id)
+            .ok_or_else(|| format!("item not found: {}", item_id))?;
+        let pid = self
+            .live_pid(process)
+            .ok_or_else(|| format!("{} is not running", process.item.name))?;
+        let (before, after) = trim_working_set(pid)?;
+        info!(
+            "Trimmed working set of {} (PID {}): {} KB -> {} KB",
+            process.item.name,
+            pid,
+            before / 1024,
+            after / 1024
+        );
+        self.record_event(
+            item_id,
+            EventKind::MemoryTrimmed,
+            &format!(
+                "pid={} before_kb={} after_kb={}",
+                pid,
+                before / 1024,
+                after / 1024
+            ),
+        );
+        Ok((pid, before, after))
+    }
+
+    /// 在 minutes 分钟内暂缓守护监控项（不检查、不重启），到期自动恢复；0 表示立即恢复
+    pub fn set_deferred(&self, item_id: &str, minutes: u32) -> Result<(), String> {
+        if minutes > MAX_DEFER_MINUTES {
+            return Err(format!("minutes must not exceed {}", MAX_DEFER_MINUTES));
+        }
+        let mut processes = self.processes.lock().unwrap();
+        let process = processes
+            .get_mut(item_id)
+            .ok_or_else(|| format!("item not found: {}", item_id))?;
+        if minutes == 0 {
+            if process.deferred_until.take().is_some() {
+                reset_health_state(process);
+                info!(
+                    "Defer for {} cleared, resuming enforcement",
+                    process.item.name
+                );
+            }
+            self.record_event(item_id, EventKind::Deferred, "cleared");
+        } else {
+            process.deferred_until =
+                Some(Instant::now() + Duration::from_secs(u64::from(minutes) * 60));
+            info!(
+                "Not restarting {} for the next {} minutes",
+                process.item.name, minutes
+            );
+            self.record_event(
+                item_id,
+                EventKind::Deferred,
+                &format!("minutes={}", minutes),
+            );
+        }
+        Ok(())
+    }
+
+    /// 暂停全部守护动作（检查、重启、定时任务），状态查询照常；minutes 为自动恢复时间
+    pub fn pause_all(&self, minutes: Option<u32>) -> Result<(), String> {
+        if minutes.is_some_and(|minutes| minutes == 0 || minutes > MAX_DEFER_MINUTES) {
+            return Err(format!(
+                "minutes must be between 1 and {}",
+                MAX_DEFER_MINUTES
+            ));
+        }
+        let until =
+            minutes.map(|minutes| Instant::now() + Duration::from_secs(u64::from(minutes) * 60));
+        *self.paused.lock().unwrap() = Some(until);
+        match minutes {
+            Some(minutes) => warn!(
+                "Guardian paused, resuming automatically in {} minutes",
+                minutes
+            ),
+            None => warn!("Guardian paused until resume_all"),
+        }
+        Ok(())
+    }
+
+    /// 进入安全模式，需在 run 之前调用：加载配置但不启动任何监控项，守护动作暂停直到 resume_all
+    pub fn enter_safe_mode(&self) {
+        warn!("Starting in safe mode: no items will be started until resume_all");
+        self.safe_mode.store(true, Ordering::Relaxed);
+        *self.paused.lock().unwrap() = Some(None);
+    }
+
+    pub fn safe_mode(&self) -> bool {
+        self.safe_mode.load(Ordering::Relaxed)
+    }
+
+    /// 恢复守护；未暂停时返回 false
+    pub fn resume_all(&self) -> bool {
+        if self.paused.lock().unwrap().take().is_none() {
+            return false;
+        }
+        if self.safe_mode.swap(false, Ordering::Relaxed) {
+            info!("Leaving safe mode");
+        }
+        // 暂停期间的心跳与探测不计入，避免恢复后集中重启
+        for process in self.processes.lock().unwrap().values_mut() {
+            reset_health_state(process);
+        }
+        info!("Guardian resumed");
+        true
+    }
+
+    /// 是否处于 pause_all 暂停中，以及距自动恢复的剩余时间
+    pub fn pause_state(&self) -> (bool, Option<Duration>) {
+        match *self.paused.lock().unwrap() {
+            Some(until) => (
+                true,
+                until.map(|until| until.saturating_duration_since(Instant::now())),
+            ),
+            None => (false, None),
+        }
+    }
+
+    /// 暂停已到期时自动恢复，返回本周期是否仍处于暂停
+    fn pause_in_effect(&self) -> bool {
+        let expired = match *self.paused.lock().unwrap() {
+            None => return false,
+            Some(until) => until.is_some_and(|until| Instant::now() >= until),
+        };
+        if expired {
+            info!("Pause expired");
+            self.resume_all();
+            return false;
+        }
+        true
+    }
+
+    pub fn run(&self) {
+        info!("Guardian started");
+        info!("Check interval: {} ms", CHECK_INTERVAL_MS);
+
+        if let Some(startup_gate) = &self.startup_gate {
+            info!("Waiting for pipe server readiness before starting monitored processes");
+            startup_gate.wait_until_ready();
+        }
+
+        // 安全模式下推迟到 resume_all 之后再启动
+        let mut items_started = !self.safe_mode();
+        if items_started {
+            self.start_all_processes();
+        }
+        if let Some(startup_gate) = &self.startup_gate {
+            startup_gate.mark_started();
+        }
+
+        loop {
+            let running = *self.running.lock().unwrap();
+            if !running {
+                info!("Guardian stopping");
+                break;
+            }
+
+            let clock = ClockWatch::new();
+            let interval = cycle_interval(
+                self.exit_events_active.load(Ordering::Relaxed),
+                self.config.lock().unwrap().settings.wmi_poll_interval_ms,
+            );
+            let started = Instant::now();
+            self.wait_for_next_cycle(interval);
+            // 被退出事件提前唤醒时，按实际等待时间判断是否发生睡眠或时间跳变
+            if let Some(event) = clock.observe(interval.min(started.elapsed())) {
+                self.on_clock_event(event);
+            }
+            let check_count = self.check_cycles.fetch_add(1, Ordering::Relaxed) + 1;
+
+            info!("--- Check cycle #{} ---", check_count);
+            if self.pause_in_effect() {
+                // 只刷新进程表，使状态查询中的存活信息保持准确
+                debug!("Guardian is paused, skipping monitoring actions");
+                self.refresh_process_table();
+                self.process_pending_changes();
+                *self.last_cycle_at.lock().unwrap() = Instant::now();
+                continue;
+            }
+            if !items_started {
+                self.start_all_processes();
+                items_started = true;
+            }
+            self.run_scheduled_tasks();
+            self.process_pending_changes();
+            self.check_processes();
+            *self.last_cycle_at.lock().unwrap() = Instant::now();
+        }
+
+        if let Some(store) = &self.event_store {
+            store.end_all_uptime();
+        }
+
+        info!(
+            "Guardian stopped after {} checks",
+            self.check_cycles.load(Ordering::Relaxed)
+        );
+    }
+
+    /// 等待一个检查周期；期间有重启后的复查到期时提前醒来，发现新进程已退出则立即开始下个周期。
+    /// 收到受守护进程的退出事件时也立即开始下个周期
+    fn wait_for_next_cycle(&self, interval: Duration) {
+        let deadline = Instant::now() + interval;
+        loop {
+            let recheck = self
+                .processes
+                .lock()
+                .unwrap()
+                .values()
+                .filter_map(|process| process.recheck_at)
+                .min();
+            let now = Instant::now();
+            match recheck {
+                Some(at) if at < deadline => {
+                    if self.sleep_until_exit_event(at.saturating_duration_since(now))
+                        || self.recheck_restarted()
+                    {
+                        return;
+                    }
+                }
+                _ => {
+                    self.sleep_until_exit_event(deadline.saturating_duration_since(now));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// 最多等待 timeout，期间收到退出事件时提前返回 true
+    fn sleep_until_exit_event(&self, timeout: Duration) -> bool {
+        let woken = self.exit_wake.lock().unwrap();
+        let (mut woken, _) = self
+            .exit_wake_signal
+            .wait_timeout_while(woken, timeout, |woken| !*woken)
+            .unwrap();
+        std::mem::take(&mut *woken)
+    }
+
+    /// WMI 退出事件订阅建立或失效；失效后恢复默认检查周期
+    pub fn set_exit_events_active(&self, active: bool) {
+        if self.exit_events_active.swap(active, Ordering::Relaxed) != active {
+            if active {
+                info!("Process exit events active, full checks run every wmi_poll_interval_ms");
+            } else {
+                warn!(
+                    "Process exit events unavailable, polling every {} ms",
+                    CHECK_INTERVAL_MS
+                );
+            }
+        }
+    }
+
+    /// 收到进程退出事件；PID 属于某个监控项时立即开始下个检查周期
+    pub fn on_process_exit(&self, pid: u32) {
+        let tracked = self.processes.lock().unwrap().values().find_map(|process| {
+            (process.process_id == Some(pid) || process.orphan_pid == Some(pid))
+                .then(|| process.item.name.clone())
+        });
+        if let Some(name) = tracked {
+            debug!("Exit event for {} (PID {}), waking check loop", name, pid);
+            self.wake_check_loop();
+        }
+    }
+
+    /// 立即结束当前的周期等待；服务停止时调用，避免等满一个 wmi_poll_interval_ms
+    pub fn wake_check_loop(&self) {
+        *self.exit_wake.lock().unwrap() = true;
+        self.exit_wake_signal.notify_all();
+    }
+
+    /// 复查到期的重启项，返回是否有新进程已退出
+    fn recheck_restarted(&self) -> bool {
+        let now = Instant::now();
+        let mut died = false;
+        for process in self.processes.lock().unwrap().values_mut() {
+            match process.recheck_at {
+                Some(at) if at <= now => {}
+                _ => continue,
+            }
+            process.recheck_at = None;
+            if self.live_pid(process).is_some() {
+                debug!("Process {} survived restart recheck", process.item.name);
+                continue;
+            }
+            warn!(
+                "Process {} exited within {}ms of restart, checking immediately",
+                process.item.name,
+                process.startup_time.elapsed().as_millis()
+            );
+            process.died_after_restart = true;
+            died = true;
+        }
+        died
+    }
+
+    /// 服务停止时逆序优雅停止所有受守护进程，每处理一项调用一次 `progress`
+    ///
+    /// 先请求进程关闭窗口，在 shutdown_timeout_ms 内未退出再强制终止。
+    pub fn stop_all_processes(&self, mut progress: impl FnMut(usize, usize)) {
+        let (order, timeout) = {
+            let config = self.config.lock().unwrap();
+            (
+                shutdown_order(&config.items),
+                Duration::from_millis(config.settings.shutdown_timeout_ms),
+            )
+        };
+        info!(
+            "Stopping {} monitored processes for service shutdown",
+            order.len()
+        );
+
+        // 在锁内收集要停止的进程，等待其退出时不持有 processes 锁
+        let targets: Vec<Option<(MonitorItem, u32, Option<u64>)>> = {
+            let mut processes = self.processes.lock().unwrap();
+            order
+                .iter()
+                .map(|id| {
+                    let process = processes.get_mut(id)?;
+                    let pid = self.live_pid(process)?;
+                    if self.observing(&process.item) {
+                        self.report_observed(process, "stop", "service shutdown");
+                        return None;
+                    }
+                    Some((process.item.clone(), pid, process.process_created))
+                })
+                .collect()
+        };
+        for (index, target) in targets.iter().enumerate() {
+            progress(index + 1, targets.len());
+
+            let Some((item, pid, created)) = target else {
+                continue;
+            };
+            let (pid, created) = (*pid, *created);
+
+            let graceful = if item.stop_sequence.is_empty() {
+                info!(
+                    "Requesting graceful exit of {} (PID {}) for service shutdown",
+                    item.name, pid
+                );
+                self.signal_stop_event(item, pid, created, false);
+                if let Err(e) = crate::window::request_close_in_user_session(pid) {
+                    debug!("Graceful close of {} not delivered: {}", item.name, e);
+                }
+                let graceful = self.backend.wait_for_exit(pid, created, timeout);
+                if !graceful {
+                    warn!(
+                        "Process {} did not exit within {}ms, terminating",
+                        item.name,
+                        timeout.as_millis()
+                    );
+                    self.backend.kill(pid, created);
+                }
+                graceful
+            } else {
+                self.stop_process(item, pid, created)
+            };
+
+            if let Some(process) = self.processes.lock().unwrap().get_mut(&item.id) {
+                if process.process_id == Some(pid) {
+                    process.process_id = None;
+                }
+            }
+            self.record_event(
+                &item.id,
+                EventKind::Stopped,
+                if graceful {
+                    "service shutdown"
+                } else {
+                    "service shutdown (terminated)"
+                },
+            );
+        }
+        info!("Finished stopping monitored processes");
+    }
+
+    fn start_all_processes(&self) {
+        info!("Starting all monitored processes");
+
+        // 按 start_priority 与配置顺序启动，与停止服务时的逆序停止对应
+        let (order, stagger) = {
+            let config = self.config.lock().unwrap();
+            let order: Vec<String> = startup_order(&config.items)
+                .into_iter()
+                .map(|item| item.id.clone())
+                .collect();
+            (order, config.settings.startup_stagger.clone())
+        };
+        let snapshot = self.processes.lock().unwrap().clone();
+        let processes: Vec<(String, MonitoredProcess)> = order
+            .into_iter()
+            .filter_map(|id| snapshot.get(&id).cloned().map(|process| (id, process)))
+            .filter(|(_, process)| process.item.enabled)
+            .collect();
+        let total = processes.len();
+        let report_progress = |done: usize| {
+            if let Some(startup_gate) = &self.startup_gate {
+                startup_gate.report_progress(done, total);
+            }
+        };
+        report_progress(0);
+
+        let mut launched = 0;
+        for (done, (id, mut process)) in processes.into_iter().enumerate() {
+            // 开始处理下一项前报告已处理（启动、接管或跳过）的数量
+            report_progress(done);
+            if let Some(stagger) = &stagger {
+                let random = RandomState::new().build_hasher().finish();
+                let delay = stagger_delay(launched, stagger, random);
+                if !delay.is_zero() {
+                    info!(
+                        "Staggering startup: waiting {}ms before {}",
+                        delay.as_millis(),
+                        process.item.name
+                    );
+                    if !self.sleep_while_running(delay) {
+                        info!("Service stopping, aborting staggered startup");
+                        return;
+                    }
+                }
+            }
+            if let Some(reason) = self.run_window_closed(&process.item) {
+                info!("Process {} not started: {}", process.item.name, reason);
+                process.outside_schedule = true;
+                self.processes.lock().unwrap().insert(id, process);
+                continue;
+            }
+            if self.reconcile_orphan(&mut process) {
+                self.processes.lock().unwrap().insert(id, process);
+                continue;
+            }
+            info!(
+                "Starting monitored process {} ({})",
+                process.item.name, process.item.exe_path
+            );
+            launched += 1;
+            if let Err(e) = self.start_process_internal(&mut process) {
+                error!(
+                    "Failed to start monitored process {}: {}",
+                    process.item.name, e
+                );
+            } else {
+                let mut procs = self.processes.lock().unwrap();
+                procs.insert(id, process);
+            }
+        }
+        report_progress(total);
+
+        info!("Finished starting monitored processes");
+    }
+
+    /// 分段睡眠，服务停止时提前返回 false
+    fn sleep_while_running(&self, duration: Duration) -> bool {
+        let deadline = Instant::now() + duration;
+        while Instant::now() < deadline {
+            if !*self.running.lock().unwrap() {
+                return false;
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            std::thread::sleep(remaining.min(Duration::from_millis(100)));
+        }
+        true
+    }
+
+    /// 查找该监控项已在运行的实例；允许重复路径时还需启动参数一致。
+    /// 配置了 worker 时按工作进程映像名查找，启动器早已退出
+    fn find_running_instance(&self, process: &MonitoredProcess) -> Option<u32> {
+        if let Some(worker) = &process.item.worker {
+            return self
+                .backend
+                .snapshot()
+                .into_iter()
+                .filter(|(_, entry)| exe_glob::wildcard_match(&worker.image, &entry.image_name))
+                .map(|(pid, _)| pid)
+                .min();
+        }
+        let args = self
+            .allow_duplicate_exe_paths
+            .load(Ordering::Relaxed)
+            .then(|| process.item.args.as_deref().unwrap_or_default());
+        self.backend.find_by_path(process.exe_path(), args)
+    }
+
+    /// 全局或该监控项处于观察模式
+    fn observing(&self, item: &MonitorItem) -> bool {
+        item.observe || self.observe_only.load(Ordering::Relaxed)
+    }
+
+    /// 观察模式：记录并通知将要执行的动作而不执行；同一动作与原因只报告一次，返回是否为新的报告
+    fn report_observed(&self, process: &mut MonitoredProcess, action: &str, reason: &str) -> bool {
+        let summary = format!("{}: {}", action, reason);
+        if process.observed_action.as_deref() == Some(summary.as_str()) {
+            return false;
+        }
+        self.announce_observed(&process.item, action, reason);
+        process.observed_action = Some(summary);
+        true
+    }
+
+    /// 记录并通知观察模式下将要执行的动作，不参与 report_observed 的去重
+    fn announce_observed(&self, item: &MonitorItem, action: &str, reason: &str) {
+        warn!("[observe] Would {} {}: {}", action, item.name, reason);
+        self.record_event(
+            &item.id,
+            EventKind::Observed,
+            &format!("{}: {}", action, reason),
+        );
+        self.notify(Notification::observed(item, action, reason));
+    }
+
+    /// on_heartbeat_missed 为 notify/log 时报告心跳超时而不重启；同一次超时只报告一次
+    fn report_missed_heartbeat(&self, process: &mut MonitoredProcess, action: HeartbeatAction) {
+        if process.heartbeat_miss_reported {
+            return;
+        }
+        process.heartbeat_miss_reported = true;
+        let missing = process.last_heartbeat.elapsed();
+        let missed = process.missed_heartbeats();
+        warn!(
+            "{} missed {} heartbeats ({}s), not restarting (on_heartbeat_missed.action={})",
+            process.item.name,
+            missed,
+            missing.as_secs(),
+            action.as_str()
+        );
+        self.record_event(
+            &process.item.id,
+            EventKind::HeartbeatTimeout,
+            &format!(
+                "pid={:?} missed {} heartbeats, action={}",
+                process.process_id,
+                missed,
+                action.as_str()
+            ),
+        );
+        if action == HeartbeatAction::Notify {
+            self.notify(Notification::heartbeat_missing(&process.item, missing));
+        }
+    }
+
+    /// 在启动器的子孙进程中查找工作进程并改为跟踪它；仍在等待时返回 false，跳过本周期的检查
+    fn bind_worker(
+        &self,
+        process: &mut MonitoredProcess,
+        snapshot: &HashMap<u32, ProcessEntry>,
+    ) -> bool {
+        let (Some(worker), Some(since), Some(launcher)) = (
+            process.item.worker.clone(),
+            process.worker_wait_since,
+            process.process_id,
+        ) else {
+            process.worker_wait_since = None;
+            return true;
+        };
+        // 启动器已退出后其 PID 可能被复用，只接受在启动器之后创建的进程
+        let found = find_workers(launcher, &worker.image, snapshot)
+            .into_iter()
+            .find(
+                |pid| match (process.process_created, self.backend.creation_time(*pid)) {
+                    (Some(launched), Some(created)) => created >= launched,
+                    _ => true,
+                },
+            );
+        if let Some(pid) = found {
+            info!(
+                "Launcher of {} (PID {}) started worker PID {}, tracking the worker",
+                process.item.name, launcher, pid
+            );
+            self.item_logs.write(
+                &process.item.id,
+                "worker_bound",
+                &format!("launcher={} worker={}", launcher, pid),
+            );
+            process.process_id = Some(pid);
+            process.process_created = self.backend.creation_time(pid);
+            process.last_heartbeat = Instant::now();
+            process.startup_time = Instant::now();
+            process.worker_wait_since = None;
+            return true;
+        }
+        if since.elapsed() >= Duration::from_millis(worker.timeout_ms) {
+            let e = format!(
+                "No worker matching {} appeared under launcher PID {} within {}ms",
+                worker.image, launcher, worker.timeout_ms
+            );
+            warn!("{}: {}", process.item.name, e);
+            self.record_event(&process.item.id, EventKind::StartFailed, &e);
+            process.last_start_error = Some(e);
+            process.worker_wait_since = None;
+            return true;
+        }
+        debug!(
+            "Waiting for worker {} of {}",
+            worker.image, process.item.name
+        );
+        false
+    }
+
+    /// 监控项记录的进程仍在运行时返回其 PID；PID 已被复用给其他进程时视为已退出
+    fn live_pid(&self, process: &MonitoredProcess) -> Option<u32> {
+        process.process_id.filter(|pid| {
+            self.backend.is_alive(*pid)
+                && self.backend.is_same_process(*pid, process.process_created)
+        })
+    }
+
+    /// 重启前终止旧进程：等待其退出（最长 kill_wait_ms），再等待 post_kill_delay_ms 让文件锁等资源释放
+    fn terminate_before_relaunch(&self, item: &MonitorItem, pid: u32, created: Option<u64>) {
+        self.prepare_shutdown(item, pid);
+        if !self.stop_process(item, pid, created)
+            && !self
+                .backend
+                .wait_for_exit(pid, created, Duration::from_millis(item.kill_wait_ms))
+        {
+            warn!(
+                "{} (PID {}) did not exit within {}ms after termination",
+                item.name, pid, item.kill_wait_ms
+            );
+        }
+        if item.post_kill_delay_ms > 0 {
+            std::thread::sleep(Duration::from_millis(item.post_kill_delay_ms));
+        }
+    }
+
+    /// 执行 cleanup 规则，相对路径按程序所在目录解析
+    fn clean_up_before_relaunch(&self, item: &MonitorItem, exe_path: &str) {
+        if item.cleanup.is_empty() {
+            return;
+        }
+        let base_dir = std::path::Path::new(exe_path)
+            .parent()
+            .unwrap_or(std::path::Path::new(""));
+        let cleaned = crate::cleanup::run(&item.name, &item.cleanup, base_dir);
+        if cleaned > 0 {
+            self.item_logs
+                .write(&item.id, "cleanup", &format!("{} file(s)", cleaned));
+        }
+    }
+
+    /// 按 orphan_policy 处理服务启动前已在运行的进程，返回 true 表示暂不启动
+    fn reconcile_orphan(&self, process: &mut MonitoredProcess) -> bool {
+        // 观察模式直接接管已有进程
+        if !process.item.mode.is_keep() || self.observing(&process.item) {
+            return false;
+        }
+        // existing_instance = fail 时由启动流程拒绝，不终止也不接管已有进程
+        if process.item.existing_instance == ExistingInstance::Fail {
+            return false;
+        }
+        if let Err(e) = Self::resolve_exe_path(process) {
+            debug!("Cannot check for pre-existing {}: {}", process.item.name, e);
+            return false;
+        }
+        let Some(pid) = self.find_running_instance(process) else {
+            return false;
+        };
+
+        let policy = process.item.orphan_policy;
+        match policy {
+            // launch_process 会接管已有进程
+            OrphanPolicy::Adopt => return false,
+            OrphanPolicy::Restart => {
+                info!(
+                    "Terminating pre-existing {} (PID {}) before restart (orphan_policy=restart)",
+                    process.item.name, pid
+                );
+                self.terminate_before_relaunch(&process.item, pid, self.backend.creation_time(pid));
+            }
+            OrphanPolicy::Alert => {
+                warn!(
+                    "Leaving pre-existing {} (PID {}) alone until it exits (orphan_policy=alert)",
+                    process.item.name, pid
+                );
+                process.orphan_pid = Some(pid);
+            }
+        }
+
+        self.record_event(
+            &process.item.id,
+            EventKind::OrphanDetected,
+            &format!("pid={} policy={}", pid, policy.as_str()),
+        );
+        self.notify(Notification::orphan_detected(
+            &process.item,
+            pid,
+            policy.as_str(),
+        ));
+        process.orphan_pid.is_some()
+    }
+
+    /// 运行窗口关闭（时间窗口外或转为备用节点）：先请求进程关闭窗口，超过 shutdown_timeout 仍未退出再强制终止
+    fn stop_for_schedule(&self, process: &mut MonitoredProcess, timeout: Duration, reason: &str) {
+        let alive_pid = self.live_pid(process);
+        if self.observing(&process.item) {
+            if alive_pid.is_some() {
+                self.report_observed(process, "stop", reason);
+            }
+            return;
+        }
+
+        if !process.outside_schedule {
+            process.outside_schedule = true;
+            info!("Stopping {}: {}", process.item.name, reason);
+            if let Some(pid) = alive_pid {
+                self.signal_stop_event(&process.item, pid, process.process_created, false);
+                if let Err(e) = crate::window::request_close_in_user_session(pid) {
+                    debug!(
+                        "Graceful close of {} not delivered: {}",
+                        process.item.name, e
+                    );
+                }
+                process.schedule_stop_deadline = Some(Instant::now() + timeout);
+            }
+            if let Some(store) = &self.event_store {
+                store.end_uptime(&process.item.id);
+            }
+            self.record_event(&process.item.id, EventKind::Stopped, reason);
+        }
+
+        match alive_pid {
+            Some(pid) => {
+                let deadline_passed = match process.schedule_stop_deadline {
+                    Some(deadline) => Instant::now() >= deadline,
+                    None => true,
+                };
+                if deadline_passed {
+                    warn!(
+                        "Process {} did not exit after {}, terminating",
+                        process.item.name, reason
+                    );
+                    self.backend.kill(pid, process.process_created);
+                    process.process_id = None;
+                    process.schedule_stop_deadline = None;
+                }
+            }
+            None => {
+                process.process_id = None;
+                process.schedule_stop_deadline = None;
+            }
+        }
+    }
+
+    /// 本周期是否写入资源使用历史；到期时同时清理超出保留天数的采样
+    fn history_sample_due(&self, history: &MetricsHistoryConfig) -> bool {
+        let Some(store) = &self.event_store else {
+            return false;
+        };
+        if !history.enabled {
+            return false;
+        }
+        let mut last = self.last_history_sample.lock().unwrap();
+        if last.is_some_and(|at| at.elapsed() < Duration::from_secs(history.interval_secs)) {
+            return false;
+        }
+        *last = Some(Instant::now());
+        drop(last);
+        store.prune_samples(history.retention_days);
+        true
+    }
+
+    /// 睡眠恢复后进程来不及发送心跳，系统时间调整也会打乱基于时间戳的判断；
+    /// 重置健康状态并在 clock_settle_secs 内不判定心跳超时
+    fn on_clock_event(&self, event: ClockEvent) {
+        let settle_secs = self.config.lock().unwrap().settings.clock_settle_secs;
+        if settle_secs == 0 {
+            warn!("{}", event);
+            return;
+        }
+        warn!(
+            "{}; suppressing heartbeat timeouts for {}s",
+            event, settle_secs
+        );
+        *self.settle_until.lock().unwrap() =
+            Some(Instant::now() + Duration::from_secs(settle_secs));
+        for process in self.processes.lock().unwrap().values_mut() {
+            reset_health_state(process);
+        }
+    }
+
+    /// 时钟事件后的稳定期剩余时间
+    fn settle_remaining(&self) -> Option<Duration> {
+        let mut settle_until = self.settle_until.lock().unwrap();
+        let remaining = settle_until.and_then(|until| until.checked_duration_since(Instant::now()));
+        if remaining.is_none() {
+            *settle_until = None;
+        }
+        remaining
+    }
+
+    fn check_processes(&self) {
+        let (crash_loop_restarts, crash_loop_window, shutdown_timeout, forensics, history) = {
+            let config = self.config.lock().unwrap();
+            (
+                config.settings.crash_loop_restarts as usize,
+                Duration::from_secs(config.settings.crash_loop_window_secs),
+                Duration::from_millis(config.settings.shutdown_timeout_ms),
+                config.settings.forensics.clone(),
+                config.settings.metrics_history.clone(),
+            )
+        };
+        let sample_history = self.history_sample_due(&history);
+        let settling = self.settle_remaining().is_some();
+        let table = self.refresh_process_table();
+        let snapshot = table.entries();
+        let mut processes = self.processes.lock().unwrap();
+        let tracked_pids: HashSet<u32> = processes
+            .values()
+            .filter_map(|process| process.process_id)
+            .collect();
+        let mut restarted = Vec::new();
+        let mut relaunches = Vec::new();
+
+        for process in processes.values_mut() {
+            if !process.item.enabled {
+                debug!("Process {} is disabled, skipping check", process.item.name);
+                process.network_wait_since = None;
+                process.service_wait_since = None;
+                process.storage_wait_since = None;
+                process.unlock_wait_since = None;
+                continue;
+            }
+
+            if process.item.mode == ItemMode::Forbid {
+                self.enforce_forbidden(process);
+                continue;
+            }
+
+            if let Some(reason) = self.run_window_closed(&process.item) {
+                self.stop_for_schedule(process, shutdown_timeout, reason);
+                continue;
+            }
+            if process.outside_schedule {
+                info!("Run window opened for {}, starting", process.item.name);
+                process.outside_schedule = false;
+                process.schedule_stop_deadline = None;
+                if let Err(e) = self.start_process_internal(process) {
+                    error!("Failed to start process {}: {}", process.item.name, e);
+                }
+                continue;
+            }
+
+            if process.suspended {
+                if table.is_tracked_alive(process) {
+                    debug!("Process {} is suspended, skipping check", process.item.name);
+                    continue;
+                }
+                process.suspended = false;
+            }
+
+            if let Some(until) = process.deferred_until {
+                if Instant::now() < until {
+                    debug!("Process {} is deferred, skipping check", process.item.name);
+                    continue;
+                }
+                info!(
+                    "Defer for {} expired, resuming enforcement",
+                    process.item.name
+                );
+                process.deferred_until = None;
+                reset_health_state(process);
+            }
+
+            if let Some(orphan_pid) = process.orphan_pid {
+                if table.is_alive(orphan_pid, process.startup_time) {
+                    debug!(
+                        "Process {} is left alone until PID {} exits",
+                        process.item.name, orphan_pid
+                    );
+                    continue;
+                }
+                info!(
+                    "Pre-existing {} (PID {}) exited, resuming monitoring",
+                    process.item.name, orphan_pid
+                );
+                process.orphan_pid = None;
+                if let Err(e) = self.start_process_internal(process) {
+                    error!("Failed to start process {}: {}", process.item.name, e);
+                }
+                continue;
+            }
+
+            if process.network_wait_since.is_some()
+                || process.service_wait_since.is_some()
+                || process.storage_wait_since.is_some()
+                || process.unlock_wait_since.is_some()
+                || process.session_blocked_until.is_some()
+            {
+                if let Err(e) = self.start_process_internal(process) {
+                    error!("Failed to start process {}: {}", process.item.name, e);
+                }
+                continue;
+            }
+
+            if process.worker_wait_since.is_some() && !self.bind_worker(process, snapshot) {
+                continue;
+            }
+
+            let startup_elapsed = process.startup_time.elapsed();
+            let in_grace_period = startup_elapsed < Duration::from_secs(5)
+                && !std::mem::take(&mut process.died_after_restart);
+
+            if in_grace_period {
+                debug!(
+                    "Process {} is in startup grace period ({:.1}s), skipping checks",
+                    process.item.name,
+                    startup_elapsed.as_secs_f64()
+                );
+                continue;
+            }
+
+            let process_alive = table.is_tracked_alive(process);
+            let max_lifetime = process.item.max_lifetime_ms.filter(|ms| *ms > 0);
+            if let Some(max_lifetime) = max_lifetime {
+                if process_alive
+                    && startup_elapsed >= Duration::from_millis(max_lifetime)
+                    && !self.observing(&process.item)
+                {
+                    info!(
+                        "Process {} has been running for {}s (max_lifetime_ms={}), recycling",
+                        process.item.name,
+                        startup_elapsed.as_secs(),
+                        max_lifetime
+                    );
+                    self.request_restart(process, RestartReason::MaxLifetime);
+                    process.last_check = Instant::now();
+                    continue;
+                }
+            }
+            if settling {
+                process.update_heartbeat();
+            }
+            // 文件心跳按修改时间回推，不早于已记录的心跳（包括本次启动时间）
+            if let Some(age) = process
+                .item
+                .heartbeat_file
+                .as_deref()
+                .and_then(file_heartbeat_age)
+            {
+                if let Some(modified_at) = Instant::now().checked_sub(age) {
+                    process.last_heartbeat = process.last_heartbeat.max(modified_at);
+                }
+            }
+            let heartbeat_timed_out = process.is_heartbeat_timeout();
+            if !heartbeat_timed_out {
+                process.heartbeat_miss_reported = false;
+            }
+            if process_alive && heartbeat_timed_out != process.heartbeat_lost {
+                process.heartbeat_lost = heartbeat_timed_out;
+                if heartbeat_timed_out {
+                    let detail = format!(
+                        "last heartbeat {}s ago",
+                        process.last_heartbeat.elapsed().as_secs()
+                    );
+                    self.item_logs
+                        .write(&process.item.id, "heartbeat_lost", &detail);
+                } else {
+                    self.item_logs
+                        .write(&process.item.id, "heartbeat_resumed", "");
+                }
+            }
+            // 只有 restart 动作会让心跳判定为失败，其余动作报告后视为正常
+            let heartbeat_ok = match process.due_heartbeat_action() {
+                Some(HeartbeatAction::Restart) => false,
+                Some(action) => {
+                    if process_alive {
+                        self.report_missed_heartbeat(process, action);
+                    }
+                    true
+                }
+                None => true,
+            };
+            let probe_failure = if process_alive {
+                let http = process.item.http_probe.as_ref().and_then(|probe| {
+                    poll_http_probe(&process.item.name, probe, &process.http_probe_state)
+                });
+                let command = process.item.health_command.as_ref().and_then(|command| {
+                    poll_health_command(&process.item.name, command, &process.health_command_state)
+                });
+                // 运行中新增的日志检查从当前位置开始
+                if process.log_check_origin.is_none() {
+                    process.log_check_origin =
+                        process.item.log_check.as_ref().map(log_check_origin);
+                }
+                let log = match (&process.item.log_check, process.log_check_origin) {
+                    (Some(log_check), Some(origin)) => poll_log_check(
+                        &process.item.name,
+                        log_check,
+                        origin,
+                        &process.log_check_state,
+                    ),
+                    _ => None,
+                };
+                let window = match (&process.item.window_check, process.process_id) {
+                    (Some(window_check), Some(pid)) => poll_window_check(
+                        &process.item.name,
+                        pid,
+                        window_check,
+                        &process.window_check_state,
+                    ),
+                    _ => None,
+                };
+                let port = process.item.port_check.as_ref().and_then(|check| {
+                    poll_port_check(&process.item.name, check, &process.port_check_state)
+                });
+                if process.event_log_origin.is_none() {
+                    process.event_log_origin = process
+                        .item
+                        .event_log_check
+                        .as_ref()
+                        .map(|check| crate::event_log_check::latest_record(&check.log));
+                }
+                let event_log = match (&process.item.event_log_check, process.event_log_origin) {
+                    (Some(check), Some(origin)) => poll_event_log_check(
+                        &process.item.name,
+                        check,
+                        origin,
+                        &process.event_log_state,
+                    ),
+                    _ => None,
+                };
+                let children = match (&process.item.children, process.process_id) {
+                    (Some(children), Some(pid)) => {
+                        process.child_pids = find_descendants(pid, snapshot);
+                        let missing =
+                            missing_children(&children.required, &process.child_pids, snapshot);
+                        if missing.is_empty() {
+                            process.missing_children_cycles = 0;
+                            None
+                        } else {
+                            process.missing_children_cycles += 1;
+                            warn!(
+                                "Required child processes of {} not running ({}/{}): {}",
+                                process.item.name,
+                                process.missing_children_cycles,
+                                children.failure_threshold,
+                                missing.join(", ")
+                            );
+                            (process.missing_children_cycles >= children.failure_threshold.max(1))
+                                .then(|| {
+                                    format!(
+                                        "required child processes not running: {}",
+                                        missing.join(", ")
+                                    )
+                                })
+                        }
+                    }
+                    _ => None,
+                };
+                let services = if process.item.restart_on_service_restart
+                    && !process.item.requires_services.is_empty()
+                {
+                    match service_deps::check_running(&process.item.requires_services) {
+                        Ok(pids) => {
+                            let bounced = service_deps::bounced(&process.service_pids, &pids);
+                            (!bounced.is_empty()).then(|| {
+                                format!("required service restarted: {}", bounced.join(", "))
+                            })
+                        }
+                        Err(e) => Some(format!("required service unavailable: {}", e)),
+                    }
+                } else {
+                    None
+                };
+
+                match &process.item.health_expression {
+                    Some(expr) => {
+                        let check_failure = |name: &str| match name {
+                            "heartbeat" if !heartbeat_ok => Some("heartbeat timeout".to_string()),
+                            "http_probe" => http.clone(),
+                            "health_command" => command.clone(),
+                            "log_check" => log.clone(),
+                            "window_check" => window.clone(),
+                            "port_check" => port.clone(),
+                            "event_log" => event_log.clone(),
+                            "children" => children.clone(),
+                            "services" => services.clone(),
+                            _ => None,
+                        };
+                        health_expr::evaluate(expr, &check_failure).err()
+                    }
+                    None => http
+                        .or(command)
+                        .or(log)
+                        .or(window)
+                        .or(port)
+                        .or(event_log)
+                        .or(children)
+                        .or(services),
+                }
+            } else {
+                None
+            };
+            // 配置了健康表达式时，只有表达式引用的心跳才会触发重启
+            let heartbeat_enforced = match &process.item.health_expression {
+                Some(expr) => health_expr::references(expr, "heartbeat"),
+                None => true,
+            };
+            let heartbeat_failed = !heartbeat_ok && heartbeat_enforced && process_alive;
+
+            match process.process_id {
+                Some(pid) if process_alive => {
+                    sample_resource_usage(process, pid, snapshot);
+                    if let (true, Some(store), Some(resources)) =
+                        (sample_history, &self.event_store, process.resources)
+                    {
+                        let sample = ResourceSample {
+                            ts: now_unix_ms(),
+                            cpu_percent: process.cpu_percent,
+                            working_set_bytes: resources.working_set_bytes,
+                            private_bytes: resources.private_bytes,
+                            handle_count: resources.handle_count,
+                        };
+                        store.record_sample(&process.item.id, &sample);
+                    }
+                    if process.context.as_ref().map(|context| context.process_id) != Some(pid) {
+                        process.context = Some(query_process_context(pid));
+                    }
+                    if let Some(max_instances) = process.item.max_instances {
+                        self.enforce_instance_limit(
+                            process,
+                            pid,
+                            max_instances,
+                            &tracked_pids,
+                            snapshot,
+                        );
+                    }
+                    if let Some(store) = &self.event_store {
+                        store.extend_uptime(&process.item.id);
+                    }
+                }
+                _ => {
+                    process.resources = None;
+                    process.cpu_percent = None;
+                    process.context = None;
+                }
+            }
+
+            info!(
+                "Check [{}]: PID={:?}, alive={}, heartbeat_ok={} (last_heartbeat={:.1}s ago, timeout={}ms, startup={:.1}s ago)",
+                process.item.name,
+                process.process_id,
+                process_alive,
+                heartbeat_ok,
+                process.last_heartbeat.elapsed().as_secs_f64(),
+                process.item.heartbeat_timeout_ms,
+                startup_elapsed.as_secs_f64()
+            );
+
+            let unhealthy = match &process.item.health_expression {
+                Some(_) => probe_failure.is_some(),
+                None => heartbeat_failed || probe_failure.is_some(),
+            };
+            let requested = if process_alive {
+                None
+            } else {
+                process.restart_requested.take()
+            };
+            if process_alive && !unhealthy {
+                process.observed_action = None;
+                process.restart_due = None;
+            }
+            if !process_alive || unhealthy {
+                let cause = match requested {
+                    Some(cause) => cause,
+                    None if !process_alive => RestartReason::ProcessExited,
+                    None if heartbeat_failed
+                        && (process.item.health_expression.is_none()
+                            || probe_failure.as_deref() == Some("heartbeat timeout")) =>
+                    {
+                        RestartReason::HeartbeatTimeout
+                    }
+                    None => RestartReason::HealthCheckFailed,
+                };
+                let reason = match cause {
+                    RestartReason::ProcessExited => "process not alive".to_string(),
+                    RestartReason::HeartbeatTimeout => "heartbeat timeout".to_string(),
+                    RestartReason::HealthCheckFailed => probe_failure.clone().unwrap_or_default(),
+                    RestartReason::ManualRestart => "killed by operator".to_string(),
+                    RestartReason::ScheduledRestart => "scheduled restart".to_string(),
+                    RestartReason::Escalation => "switched to fallback program".to_string(),
+                    other => other.as_str().replace('_', " "),
+                };
+                // 人为或计划内的重启不计入崩溃循环
+                let intentional = cause.is_intentional();
+
+                if self.observing(&process.item) {
+                    // 进程可能已由他人重新启动，接管后继续观察
+                    if (process_alive || !self.adopt_running_instance(process))
+                        && self.report_observed(process, "restart", &reason)
+                        && !intentional
+                    {
+                        self.track_crash_loop(
+                            process,
+                            crash_loop_restarts,
+                            crash_loop_window,
+                            &forensics,
+                        );
+                    }
+                    self.evaluate_alerts(process);
+                    process.last_check = Instant::now();
+                    continue;
+                }
+                if cause == RestartReason::ProcessExited && !Self::restart_delay_passed(process) {
+                    process.last_check = Instant::now();
+                    continue;
+                }
+
+                if heartbeat_failed {
+                    let elapsed_ms = process.last_heartbeat.elapsed().as_millis();
+                    let timeout_ms = process.item.heartbeat_timeout_ms;
+                    debug!(
+                        "Heartbeat timeout detail: name={}, elapsed={}ms, timeout={}ms, delta={}ms",
+                        process.item.name,
+                        elapsed_ms,
+                        timeout_ms,
+                        elapsed_ms as i64 - timeout_ms as i64
+                    );
+                }
+
+                warn!(
+                    "Process unhealthy or intentionally controlled: name={}, reason={}, pid={:?}",
+                    process.item.name, reason, process.process_id
+                );
+                warn!(
+                    "Process {} needs restart because {} (cause={}, restart_count={})",
+                    process.item.name,
+                    reason,
+                    cause.as_str(),
+                    process.restart_count
+                );
+
+                if let Some(store) = &self.event_store {
+                    store.end_uptime(&process.item.id);
+                }
+                self.record_event(
+                    &process.item.id,
+                    match cause {
+                        _ if intentional => EventKind::Stopped,
+                        RestartReason::ProcessExited => EventKind::ProcessExited,
+                        RestartReason::HeartbeatTimeout => EventKind::HeartbeatTimeout,
+                        _ => EventKind::HealthCheckFailed,
+                    },
+                    &format!("pid={:?} {}", process.process_id, reason),
+                );
+
+                if let Some(pid) = self.live_pid(process) {
+                    info!(
+                        "Stopping monitored process: {}, PID: {}, reason: restart required",
+                        process.item.name, pid
+                    );
+                    // 等待旧进程退出时不持有 processes 锁，遍历结束后再终止并重新启动
+                    relaunches.push((
+                        process.item.clone(),
+                        pid,
+                        process.process_created,
+                        cause,
+                        reason,
+                    ));
+                    continue;
+                }
+
+                self.relaunch(process, cause, &reason, &mut restarted);
+                if !intentional {
+                    self.track_crash_loop(
+                        process,
+                        crash_loop_restarts,
+                        crash_loop_window,
+                        &forensics,
+                    );
+                }
+            }
+
+            self.evaluate_alerts(process);
+            process.last_check = Instant::now();
+        }
+
+        if !relaunches.is_empty() {
+            drop(processes);
+            for (item, pid, created, _, _) in &relaunches {
+                self.terminate_before_relaunch(item, *pid, *created);
+            }
+            processes = self.processes.lock().unwrap();
+            for (item, _, _, cause, reason) in relaunches {
+                // 等待期间被停止、暂停或移除的项不再启动
+                let Some(process) = processes
+                    .get_mut(&item.id)
+                    .filter(|process| process.item.enabled)
+                else {
+                    continue;
+                };
+                self.relaunch(process, cause, &reason, &mut restarted);
+                if !cause.is_intentional() {
+                    self.track_crash_loop(
+                        process,
+                        crash_loop_restarts,
+                        crash_loop_window,
+                        &forensics,
+                    );
+                }
+                self.evaluate_alerts(process);
+                process.last_check = Instant::now();
+            }
+        }
+
+        // 依赖项重启造成的重启由最初的重启一次性传递，避免循环依赖反复重启
+        for origin in restarted {
+            let items: Vec<&MonitorItem> =
+                processes.values().map(|process| &process.item).collect();
+            for id in dependents_to_restart(&items, &origin) {
+                if let Some(process) = processes.get_mut(&id) {
+                    info!(
+                        "Restarting {} because its dependency {} restarted",
+                        process.item.name, origin
+                    );
+                    self.request_restart(process, RestartReason::DependencyRestarted);
+                }
+            }
+        }
+    }
+
+    /// 终止旧进程后重新启动监控项：结束 kill_on_restart 的子进程、执行 cleanup，成功时更新重启计数与告警
+    fn relaunch(
+        &self,
+        process: &mut MonitoredProcess,
+        cause: RestartReason,
+        reason: &str,
+        restarted: &mut Vec<String>,
+    ) {
+        if process
+            .item
+            .children
+            .as_ref()
+            .is_some_and(|c| c.kill_on_restart)
+        {
+            for child in std::mem::take(&mut process.child_pids) {
+                if self.backend.is_alive(child) {
+                    info!(
+                        "Stopping child process of {}: PID {}",
+                        process.item.name, child
+                    );
+                    self.backend.kill(child, None);
+                }
+            }
+        }
+
+        self.clean_up_before_relaunch(&process.item, process.exe_path());
+        if let Err(e) = self.start_process_internal(process) {
+            error!("Failed to restart process {}: {}", process.item.name, e);
+        } else {
+            process.restart_count += 1;
+            if cause != RestartReason::DependencyRestarted {
+                restarted.push(process.item.id.clone());
+            }
+            process.recheck_at = Some(Instant::now() + RESTART_RECHECK_DELAY);
+            process.last_restart_reason = Some(cause);
+            *process.restarts_by_reason.entry(cause).or_insert(0) += 1;
+            if let Some(store) = &self.event_store {
+                store.record_restart(&process.item.id, cause, reason);
+            }
+            self.item_logs.write(
+                &process.item.id,
+                EventKind::Restarted.as_str(),
+                &format!("reason={} {}", cause.as_str(), reason),
+            );
+            info!(
+                "Process {} restarted successfully (restart_count={})",
+                process.item.name, process.restart_count
+            );
+            if restart_alert_due(
+                &mut process.alert_restarts,
+                Instant::now(),
+                process.item.alerts.as_ref(),
+            ) {
+                self.notify(Notification::process_restarted(
+                    &process.item,
+                    cause,
+                    reason,
+                    process.restart_count,
+                ));
+            }
+            if process.item.notify_user_on_restart {
+                crate::toast::show_restart_toast(&process.item.name);
+            }
+        }
+    }
+
+    /// 每分钟执行一次到期的定时任务；启停与重启转为待处理变更，在本周期内生效
+    fn run_scheduled_tasks(&self) {
+        let (minute, now) = crate::scheduler::current_minute();
+        {
+            let mut last = self.last_task_minute.lock().unwrap();
+            if *last == Some(minute) {
+                return;
+            }
+            *last = Some(minute);
+        }
+
+        let items = self.config.lock().unwrap().items.clone();
+        for (id, action) in crate::scheduler::due_tasks(&items, now) {
+            let Some(mut item) = items.iter().find(|item| item.id == id).cloned() else {
+                continue;
+            };
+            info!("Running scheduled {} for {}", action.as_str(), item.name);
+
+            let change_type = match action {
+                // 已启用的项由守护循环保持运行
+                TaskAction::Start if item.enabled => continue,
+                TaskAction::Start => {
+                    item.enabled = true;
+                    ChangeType::Start
+                }
+                TaskAction::Stop => ChangeType::Stop,
+                TaskAction::Restart if item.enabled => {
+                    if let Some(process) = self.processes.lock().unwrap().get_mut(&id) {
+                        self.request_restart(process, RestartReason::ScheduledRestart);
+                    }
+                    continue;
+                }
+                TaskAction::Restart => {
+                    info!("Skipping scheduled restart of disabled item {}", item.name);
+                    continue;
+                }
+                TaskAction::HealthCheck => {
+                    if let Some(process) = self.processes.lock().unwrap().get(&id) {
+                        for state in [
+                            &process.http_probe_state,
+                            &process.health_command_state,
+                            &process.log_check_state,
+                            &process.window_check_state,
+                            &process.port_check_state,
+                            &process.event_log_state,
+                        ] {
+                            state.lock().unwrap().make_due();
+                        }
+                    }
+                    continue;
+                }
+                TaskAction::TrimMemory => {
+                    if let Err(e) = self.trim_memory(&id) {
+                        warn!("Scheduled trim_memory for {} skipped: {}", item.name, e);
+                    }
+                    continue;
+                }
+            };
+            self.add_change(ConfigChange { item, change_type });
+        }
+    }
+
+    /// 终止进程并记录原因，守护循环在下个周期按该原因重启
+    fn request_restart(&self, process: &mut MonitoredProcess, reason: RestartReason) {
+        match self.live_pid(process) {
+            Some(pid) => {
+                info!(
+                    "Stopping monitored process: {}, PID: {}, reason: {}",
+                    process.item.name,
+                    pid,
+                    reason.as_str()
+                );
+                self.stop_process(&process.item, pid, process.process_created);
+                process.restart_requested = Some(reason);
+            }
+            None => info!(
+                "Restart ({}) requested for {} but it is not running",
+                reason.as_str(),
+                process.item.name
+            ),
+        }
+    }
+
+    fn process_pending_changes(&self) {
+        let mut pending = self.pending_changes.lock().unwrap();
+        if pending.is_empty() {
+            return;
+        }
+
+        let changes: Vec<ConfigChange> = pending.drain(..).collect();
+        drop(pending);
+
+        info!("Processing {} pending changes", changes.len());
+
+        let count = changes.len();
+        let mut config_changed = false;
+        for change in changes {
+            config_changed |= self.apply_change(change);
+        }
+        // 同一批变更（例如一次 bulk 请求）只保存一次配置
+        if config_changed {
+            let config = self.config.lock().unwrap();
+            if let Err(e) = self.save_config(&config) {
+                error!("Failed to persist config changes: {}", e);
+            } else {
+                info!("Saved config after {} change(s)", count);
+            }
+        }
+    }
+
+    /// 应用一项变更，返回内存中的配置是否被修改（由调用方统一保存）
+    fn apply_change(&self, change: ConfigChange) -> bool {
+        // 托盘等管道以外的入口同样不能在安全模式下启动或重启监控项
+        if self.safe_mode()
+            && (change.change_type.has_flag(ChangeType::Start)
+                || change.change_type.has_flag(ChangeType::Kill))
+        {
+            warn!(
+                "Ignoring {:?} of {} in safe mode",
+                change.change_type, change.item.id
+            );
+            return false;
+        }
+        // 停止进程可能要等待其退出，在获取 processes 锁之前完成
+        if should_kill_process_for_change(change.change_type) {
+            self.stop_for_change(&change);
+        }
+        let mut config_changed = false;
+        let mut processes = self.processes.lock().unwrap();
+        let mut config = self.config.lock().unwrap();
+
+        info!(
+            "Applying config change: {} ({:?})",
+            change.item.id, change.change_type
+        );
+        // 管道添加、更新已先写入 config
+        self.refresh_item_maps(&config);
+
+        if change.change_type.has_flag(ChangeType::Stop)
+            || change.change_type.has_flag(ChangeType::Pause)
+        {
+            let should_kill = should_kill_process_for_change(change.change_type);
+
+            if !should_kill
+                && processes.contains_key(&change.item.id)
+                && apply_pause_state(&mut processes, &mut config, &change.item.id)
+            {
+                info!(
+                    "Pausing monitor item while keeping process alive: {} ({})",
+                    change.item.name, change.item.id
+                );
+            }
+
+            if let Some(store) = &self.event_store {
+                store.end_uptime(&change.item.id);
+            }
+            self.record_event(
+                &change.item.id,
+                EventKind::Stopped,
+                if should_kill {
+                    "user stop"
+                } else {
+                    "user pause"
+                },
+            );
+
+            if should_kill {
+                if let Some(process) = processes.get_mut(&change.item.id) {
+                    process.process_id = None;
+                    process.item.enabled = false;
+                    info!(
+                        "Disabled monitor item at runtime: {} ({})",
+                        process.item.name, change.item.id
+                    );
+                }
+
+                if let Some(item) = config.items.iter_mut().find(|i| i.id == change.item.id) {
+                    item.enabled = false;
+                }
+            }
+            config_changed = true;
+        }
+
+        if change.change_type.has_flag(ChangeType::Kill) {
+            if let Some(process) = processes.get_mut(&change.item.id) {
+                self.request_restart(process, RestartReason::ManualRestart);
+            }
+        }
+
+        if change.change_type.has_flag(ChangeType::Remove) {
+            if let Some(process) = processes.remove(&change.item.id) {
                 info!(
-                    "Starting monitored process {} ({})",
-                    process.item.name, process.item.exe_path
+                    "Removed monitor item from runtime: {} ({})",
+                    process.item.name, change.item.id
                 );
-                if let Err(e) = self.start_process_internal(&mut process) {
-                    error!("Failed to start monitored process {}: {}", process.item.name, e);
+            }
+            self.stop_events.lock().unwrap().remove(&change.item.id);
+            if change.item.firewall.is_some() {
+                if let Err(e) = crate::firewall::remove_rule(&change.item.id) {
+                    warn!("{}", e);
+                }
+            }
+            config.items.retain(|i| i.id != change.item.id);
+            self.refresh_item_maps(&config);
+            self.pipe_stats.retain_items(|id| id != change.item.id);
+            config_changed = true;
+            info!("Removed monitor item from config: {}", change.item.id);
+        }
+
+        if change.change_type.has_flag(ChangeType::Start) {
+            let mut monitored = MonitoredProcess::from_item(change.item.clone());
+            // 更新时去掉了 firewall 配置
+            let had_firewall = config
+                .items
+                .iter()
+                .chain(processes.get(&change.item.id).map(|process| &process.item))
+                .any(|item| item.id == change.item.id && item.firewall.is_some());
+            if had_firewall && change.item.firewall.is_none() {
+                if let Err(e) = crate::firewall::remove_rule(&change.item.id) {
+                    warn!("{}", e);
+                }
+            }
+
+            if let Err(e) = self.start_process_internal(&mut monitored) {
+                error!("Failed to start process {}: {}", change.item.name, e);
+            } else {
+                processes.insert(change.item.id.clone(), monitored);
+
+                if let Some(item) = config.items.iter_mut().find(|i| i.id == change.item.id) {
+                    item.enabled = true;
                 } else {
-                    let mut procs = self.processes.lock().unwrap();
-                    procs.insert(id, process);
+                    config.items.push(change.item.clone());
+                }
+                config_changed = true;
+
+                info!(
+                    "Started monitoring {} ({})",
+                    change.item.name, change.item.id
+                );
+            }
+        }
+        config_changed
+    }
+
+    /// 按变更停止监控项的进程；同时带 Start 时按重启流程终止并执行 cleanup。
+    /// 只在读取 PID 时短暂持有 processes 锁
+    fn stop_for_change(&self, change: &ConfigChange) {
+        let target = self
+            .processes
+            .lock()
+            .unwrap()
+            .get(&change.item.id)
+            .and_then(|process| {
+                self.live_pid(process).map(|pid| {
+                    (
+                        process.item.clone(),
+                        pid,
+                        process.process_created,
+                        process.exe_path().to_string(),
+                    )
+                })
+            });
+        let Some((item, pid, created, exe_path)) = target else {
+            return;
+        };
+        warn!(
+            "Process {} will be terminated because monitoring was stopped by user, pid={}",
+            item.name, pid
+        );
+        info!(
+            "Stopping monitored process: {}, PID: {}, reason: user stop",
+            item.name, pid
+        );
+        if change.change_type.has_flag(ChangeType::Start) {
+            self.terminate_before_relaunch(&item, pid, created);
+            self.clean_up_before_relaunch(&item, &exe_path);
+        } else {
+            self.stop_process(&item, pid, created);
+        }
+    }
+
+    fn start_process(&self, process: &mut MonitoredProcess) -> Result<(), String> {
+        self.start_process_internal(process)
+    }
+
+    fn start_process_internal(&self, process: &mut MonitoredProcess) -> Result<(), String> {
+        if process.item.mode == ItemMode::Forbid {
+            self.enforce_forbidden(process);
+            return Ok(());
+        }
+
+        if !Self::storage_gate_passed(process)
+            || !Self::network_gate_passed(process)
+            || !Self::service_gate_passed(process)
+            || !Self::unlock_gate_passed(process)
+            || !Self::session_backoff_passed(process)
+        {
+            return Ok(());
+        }
+
+        if let Err(e) = Self::resolve_exe_path(process) {
+            error!("{}", e);
+            process.last_start_error = Some(e.clone());
+            self.record_event(&process.item.id, EventKind::StartFailed, &e);
+            return Err(e);
+        }
+        // 手动编辑 config.json 时不经过管道校验
+        if let Err(e) = crate::guard_target::check(&process.item) {
+            error!("{}", e);
+            if process.last_start_error.as_deref() != Some(e.as_str()) {
+                self.record_event(&process.item.id, EventKind::StartFailed, &e);
+            }
+            process.last_start_error = Some(e.clone());
+            return Err(e);
+        }
+        let duplicate_of = self
+            .duplicate_items
+            .lock()
+            .unwrap()
+            .get(&process.item.id)
+            .cloned();
+        if let Some(kept) = duplicate_of {
+            let e = format!(
+                "Item {} has the same exe_path as item {} and is not started (duplicate_exe_paths = reject)",
+                process.item.id, kept
+            );
+            error!("{}", e);
+            if process.last_start_error.as_deref() != Some(e.as_str()) {
+                self.record_event(&process.item.id, EventKind::StartFailed, &e);
+            }
+            process.last_start_error = Some(e.clone());
+            return Err(e);
+        }
+
+        let rejection = match Self::verify_item_hash(&process.item, process.exe_path()) {
+            Err(e) => Some((Notification::hash_mismatch(&process.item, &e), e)),
+            Ok(()) => Self::verify_item_signature(&process.item, process.exe_path())
+                .err()
+                .map(|e| (Notification::signature_rejected(&process.item, &e), e)),
+        };
+        if let Some((notification, e)) = rejection {
+            error!("{}", e);
+            // 同一原因只告警一次，避免每个检查周期重复推送
+            if process.last_start_error.as_deref() != Some(e.as_str()) {
+                self.notify(notification);
+                self.record_event(&process.item.id, EventKind::SecurityRejected, &e);
+            }
+            process.last_start_error = Some(e.clone());
+            return Err(e);
+        }
+
+        if self.observing(&process.item) && self.find_running_instance(process).is_none() {
+            self.report_observed(process, "start", "process not running");
+            return Ok(());
+        }
+        if process.item.existing_instance == ExistingInstance::Fail
+            && !self.observing(&process.item)
+        {
+            if let Some(pid) = self.find_running_instance(process) {
+                let e = format!(
+                    "{} is already running (PID {}) and is not started again (existing_instance = fail)",
+                    process.item.name, pid
+                );
+                if process.last_start_error.as_deref() != Some(e.as_str()) {
+                    error!("{}", e);
+                    self.record_event(&process.item.id, EventKind::StartFailed, &e);
+                }
+                // 与 orphan_policy = alert 相同，等已有实例退出后再启动
+                process.orphan_pid = Some(pid);
+                process.last_start_error = Some(e.clone());
+                return Err(e);
+            }
+        }
+
+        if let Some(rule) = &process.item.firewall {
+            if let Err(e) = crate::firewall::ensure_rule(&process.item.id, process.exe_path(), rule)
+            {
+                warn!("{}", e);
+            }
+        }
+        self.arm_stop_event(&process.item);
+        let result = self.launch_process(process);
+
+        process.last_start_error = result.as_ref().err().cloned();
+
+        match &result {
+            Ok(()) => {
+                process.http_probe_state.lock().unwrap().reset();
+                process.health_command_state.lock().unwrap().reset();
+                process.log_check_state.lock().unwrap().reset();
+                process.window_check_state.lock().unwrap().reset();
+                process.port_check_state.lock().unwrap().reset();
+                process.event_log_state.lock().unwrap().reset();
+                process.missing_children_cycles = 0;
+                process.heartbeat_lost = false;
+                process.log_check_origin = process.item.log_check.as_ref().map(log_check_origin);
+                process.event_log_origin = process
+                    .item
+                    .event_log_check
+                    .as_ref()
+                    .map(|check| crate::event_log_check::latest_record(&check.log));
+                Self::clear_session_failures(process);
+                if let Some(store) = &self.event_store {
+                    store.begin_uptime(&process.item.id);
+                }
+                self.record_event(
+                    &process.item.id,
+                    EventKind::Started,
+                    &format!("pid={:?}", process.process_id),
+                );
+            }
+            Err(e) => {
+                self.record_event(&process.item.id, EventKind::StartFailed, e);
+                self.note_session_failure(process, e);
+            }
+        }
+
+        result
+    }
+
+    /// 进程自行退出后按 restart_delay_ms 推迟重启；首次发现退出时开始计时，由检查循环到期后重启
+    fn restart_delay_passed(process: &mut MonitoredProcess) -> bool {
+        if process.item.restart_delay_ms == 0 {
+            return true;
+        }
+        let due = match process.restart_due {
+            Some(due) => due,
+            None => {
+                info!(
+                    "{} exited, restarting in {}ms (restart_delay_ms)",
+                    process.item.name, process.item.restart_delay_ms
+                );
+                let due = Instant::now() + Duration::from_millis(process.item.restart_delay_ms);
+                process.restart_due = Some(due);
+                due
+            }
+        };
+        if Instant::now() < due {
+            return false;
+        }
+        process.restart_due = None;
+        true
+    }
+
+    /// 处于“无用户会话”退避时，未到重试时间前不启动
+    fn session_backoff_passed(process: &MonitoredProcess) -> bool {
+        process
+            .session_blocked_until
+            .map(|until| Instant::now() >= until)
+            .unwrap_or(true)
+    }
+
+    fn clear_session_failures(process: &mut MonitoredProcess) {
+        if process.session_blocked_until.take().is_some() {
+            info!(
+                "{} launched in a user session again after {} failed attempts",
+                process.item.name, process.session_failures
+            );
+        }
+        process.session_failures = 0;
+    }
+
+    /// 统计连续的用户会话令牌失败；达到阈值后改为按退避间隔重试，并只通知一次
+    fn note_session_failure(&self, process: &mut MonitoredProcess, error: &str) {
+        if !crate::session0::is_user_session_error(error) {
+            process.session_failures = 0;
+            return;
+        }
+        process.session_failures += 1;
+        if process.session_failures < SESSION_FAILURE_THRESHOLD {
+            return;
+        }
+        let delay = session_retry_delay(process.session_failures - SESSION_FAILURE_THRESHOLD);
+        if process.session_blocked_until.is_none() {
+            warn!(
+                "{} is blocked: no user session after {} consecutive failures, retrying in {}s: {}",
+                process.item.name,
+                process.session_failures,
+                delay.as_secs(),
+                error
+            );
+            self.notify(Notification::session_blocked(
+                &process.item,
+                process.session_failures,
+                delay,
+                error,
+            ));
+        }
+        process.session_blocked_until = Some(Instant::now() + delay);
+    }
+
+    /// 配置了 defer_while_locked 时，目标会话锁屏期间暂不启动，解锁后的下一个周期启动
+    fn unlock_gate_passed(process: &mut MonitoredProcess) -> bool {
+        if !process.item.defer_while_locked {
+            return true;
+        }
+        match crate::session0::locked_console_session() {
+            Some(session_id) => {
+                if process.unlock_wait_since.is_none() {
+                    info!(
+                        "Session {} is locked, deferring launch of {} until it unlocks",
+                        session_id, process.item.name
+                    );
+                    process.unlock_wait_since = Some(Instant::now());
+                }
+                false
+            }
+            None => {
+                if let Some(since) = process.unlock_wait_since.take() {
+                    info!(
+                        "Session unlocked after {:.1}s, starting {}",
+                        since.elapsed().as_secs_f64(),
+                        process.item.name
+                    );
                 }
+                true
             }
         }
-
-        info!("Finished starting monitored processes");
     }
 
-    fn check_processes(&self) {
-        let mut processes = self.processes.lock().unwrap();
+    /// 配置了 requires_network 时检查网络；未就绪返回 false，由检查循环稍后重试
+    fn network_gate_passed(process: &mut MonitoredProcess) -> bool {
+        let Some(gate) = process.item.requires_network.clone() else {
+            return true;
+        };
+        let first_attempt = process.network_wait_since.is_none();
+        let waited = process
+            .network_wait_since
+            .get_or_insert_with(Instant::now)
+            .elapsed();
+        let check = network::check(&gate);
 
-        for process in processes.values_mut() {
-            if !process.item.enabled {
-                debug!("Process {} is disabled, skipping check", process.item.name);
-                continue;
+        match network::gate_state(check.is_ok(), waited, gate.timeout_ms) {
+            GateState::Ready => {
+                if !first_attempt {
+                    info!(
+                        "Network available for {} after {:.1}s, starting",
+                        process.item.name,
+                        waited.as_secs_f64()
+                    );
+                }
+                process.network_wait_since = None;
+                true
             }
-
-            let startup_elapsed = process.startup_time.elapsed();
-            let in_grace_period = startup_elapsed < Duration::from_secs(5);
-
-            if in_grace_period {
-                debug!(
-                    "Process {} is in startup grace period ({:.1}s), skipping checks",
+            GateState::Waiting => {
+                if first_attempt {
+                    info!(
+                        "Waiting for network before starting {}: {}",
+                        process.item.name,
+                        check.unwrap_err()
+                    );
+                }
+                false
+            }
+            GateState::TimedOut => {
+                warn!(
+                    "Network still unavailable for {} after {:.1}s, starting anyway: {}",
                     process.item.name,
-                    startup_elapsed.as_secs_f64()
+                    waited.as_secs_f64(),
+                    check.unwrap_err()
                 );
-                continue;
+                process.network_wait_since = None;
+                true
             }
+        }
+    }
 
-            let process_alive = process
-                .process_id
-                .map_or(false, check_process_alive);
-            let heartbeat_ok = !process.is_heartbeat_timeout();
-
-            info!(
-                "Check [{}]: PID={:?}, alive={}, heartbeat_ok={} (last_heartbeat={:.1}s ago, timeout={}ms, startup={:.1}s ago)",
-                process.item.name,
-                process.process_id,
-                process_alive,
-                heartbeat_ok,
-                process.last_heartbeat.elapsed().as_secs_f64(),
-                process.item.heartbeat_timeout_ms,
-                startup_elapsed.as_secs_f64()
-            );
+    /// 失败状态与心跳缺失告警，每次进入该状态只通知一次
+    fn evaluate_alerts(&self, process: &mut MonitoredProcess) {
+        let Some(alerts) = process.item.alerts.clone() else {
+            return;
+        };
 
-            if !process_alive || !heartbeat_ok {
-                let reason = if !process_alive {
-                    "process not alive"
+        if alerts.on_failed {
+            let alive = self.live_pid(process).is_some();
+            if !process.is_failed(alive) {
+                process.failed_alerted = false;
+            } else if !process.failed_alerted {
+                process.failed_alerted = true;
+                let reason = if process.in_crash_loop {
+                    "crash loop".to_string()
+                } else if let Some(e) = &process.last_start_error {
+                    e.clone()
                 } else {
-                    "heartbeat timeout"
+                    "process not running".to_string()
                 };
-
-                if !heartbeat_ok && process_alive {
-                    let elapsed_ms = process.last_heartbeat.elapsed().as_millis();
-                    let timeout_ms = process.item.heartbeat_timeout_ms;
-                    debug!(
-                        "Heartbeat timeout detail: name={}, elapsed={}ms, timeout={}ms, delta={}ms",
-                        process.item.name,
-                        elapsed_ms,
-                        timeout_ms,
-                        elapsed_ms as i64 - timeout_ms as i64
-                    );
-                }
-
                 warn!(
-                    "Process unhealthy or intentionally controlled: name={}, reason={}, pid={:?}",
-                    process.item.name, reason, process.process_id
+                    "Item {} entered failed state: {}",
+                    process.item.name, reason
                 );
+                self.notify(Notification::item_failed(&process.item, &reason));
+            }
+        }
+
+        if alerts.heartbeat_missing_secs > 0 && process.heartbeat_enabled() {
+            let missing = process.last_heartbeat.elapsed();
+            if missing < Duration::from_secs(alerts.heartbeat_missing_secs) {
+                process.heartbeat_alerted = false;
+            } else if !process.heartbeat_alerted {
+                process.heartbeat_alerted = true;
                 warn!(
-                    "Process {} needs restart because {} (restart_count={})",
-                    process.item.name, reason, process.restart_count
+                    "No heartbeat from {} for {}s",
+                    process.item.name,
+                    missing.as_secs()
                 );
+                self.notify(Notification::heartbeat_missing(&process.item, missing));
+            }
+        }
+    }
 
-                if let Some(pid) = process.process_id {
-                    if check_process_alive(pid) {
-                        info!(
-                            "Stopping monitored process: {}, PID: {}, reason: restart required",
-                            process.item.name, pid
+    /// 统计非人为的重启，达到 crash_loop_restarts 时进入崩溃循环：收集现场、通知并升级；
+    /// 观察模式下照常统计，只报告将要执行的升级
+    fn track_crash_loop(
+        &self,
+        process: &mut MonitoredProcess,
+        crash_loop_restarts: usize,
+        crash_loop_window: Duration,
+        forensics: &ForensicsConfig,
+    ) {
+        let restarts_in_window = record_restart_in_window(
+            &mut process.recent_restarts,
+            Instant::now(),
+            crash_loop_window,
+        );
+        if crash_loop_restarts > 0 && restarts_in_window >= crash_loop_restarts {
+            if !process.in_crash_loop {
+                process.in_crash_loop = true;
+                error!(
+                    "Process {} is crash-looping: {} restarts within {}s",
+                    process.item.name,
+                    restarts_in_window,
+                    crash_loop_window.as_secs()
+                );
+                self.item_logs.write(
+                    &process.item.id,
+                    "crash_loop",
+                    &format!(
+                        "{} restarts within {}s",
+                        restarts_in_window,
+                        crash_loop_window.as_secs()
+                    ),
+                );
+                let bundle = if forensics.enabled {
+                    let input = CrashLoopInput {
+                        item: &process.item,
+                        restarts_in_window,
+                        window: crash_loop_window,
+                        restart_count: process.restart_count,
+                        last_start_error: process.last_start_error.as_deref(),
+                        output: process.output.as_ref(),
+                    };
+                    forensics::collect(forensics, &input, self.event_store.as_deref())
+                        .map_err(|e| warn!("{}", e))
+                        .ok()
+                } else {
+                    None
+                };
+                self.notify(Notification::crash_loop(
+                    &process.item,
+                    restarts_in_window,
+                    crash_loop_window,
+                    bundle.as_deref(),
+                ));
+                if self.observing(&process.item) {
+                    if process.item.escalation.is_some() {
+                        let reason = format!(
+                            "{} restarts within {}s",
+                            restarts_in_window,
+                            crash_loop_window.as_secs()
                         );
-                        kill_process(pid);
+                        // 升级只在进入崩溃循环时报告一次；不覆盖 observed_action，避免重复报告 restart
+                        self.announce_observed(&process.item, "escalate", &reason);
                     }
-                }
-
-                if let Err(e) = self.start_process_internal(process) {
-                    error!("Failed to restart process {}: {}", process.item.name, e);
                 } else {
-                    process.restart_count += 1;
-                    info!(
-                        "Process {} restarted successfully (restart_count={})",
-                        process.item.name, process.restart_count
-                    );
+                    self.escalate(process);
                 }
             }
-
-            process.last_check = Instant::now();
+        } else {
+            process.in_crash_loop = false;
         }
     }
 
-    fn process_pending_changes(&self) {
-        let mut pending = self.pending_changes.lock().unwrap();
-        if pending.is_empty() {
+    /// 首次进入崩溃循环时执行配置的升级动作
+    fn escalate(&self, process: &mut MonitoredProcess) {
+        let Some(action) = process.item.escalation.clone() else {
+            return;
+        };
+        if std::mem::replace(&mut process.escalated, true) {
+            info!(
+                "Escalation for {} already ran during this service run",
+                process.item.name
+            );
             return;
         }
+        warn!(
+            "Escalating crash loop of {}: {}",
+            process.item.name,
+            action.as_str()
+        );
 
-        let changes: Vec<ConfigChange> = pending.drain(..).collect();
-        drop(pending);
-
-        info!("Processing {} pending changes", changes.len());
+        let EscalationAction::Fallback { exe_path, args } = action else {
+            crate::escalation::run_external(
+                &process.item,
+                self.event_store.clone(),
+                self.notifier.clone(),
+            );
+            return;
+        };
 
-        for change in changes {
-            self.apply_change(change);
+        let previous = std::mem::replace(&mut process.item.exe_path, exe_path.clone());
+        process.item.args = args;
+        // 备用程序重新开始统计崩溃循环
+        process.recent_restarts.clear();
+        if let Some(pid) = self.live_pid(process) {
+            self.backend.kill(pid, process.process_created);
+            process.restart_requested = Some(RestartReason::Escalation);
         }
-    }
-
-    fn apply_change(&self, change: ConfigChange) {
-        let mut processes = self.processes.lock().unwrap();
-        let mut config = self.config.lock().unwrap();
-
-        info!(
-            "Applying config change: {} ({:?})",
-            change.item.id, change.change_type
+        crate::escalation::report_fallback(
+            &process.item,
+            Ok(format!("switched from {} to {}", previous, exe_path)),
+            self.event_store.as_deref(),
+            &self.notifier,
         );
+    }
 
-        if change.change_type.has_flag(ChangeType::Stop)
-            || change.change_type.has_flag(ChangeType::Pause)
-        {
-            let should_kill = should_kill_process_for_change(change.change_type);
+    /// 配置了 requires_storage 时检查路径是否可访问，超时后照常启动
+    fn storage_gate_passed(process: &mut MonitoredProcess) -> bool {
+        let Some(gate) = &process.item.requires_storage else {
+            return true;
+        };
+        let unavailable = storage_paths(gate, &process.item.exe_path)
+            .into_iter()
+            .find(|path| std::fs::metadata(path).is_err());
+        let first_attempt = process.storage_wait_since.is_none();
+        let waited = process
+            .storage_wait_since
+            .get_or_insert_with(Instant::now)
+            .elapsed();
 
-            if let Some(process) = processes.get(&change.item.id) {
-                if should_kill {
-                    if let Some(pid) = process.process_id {
-                        if check_process_alive(pid) {
-                            warn!(
-                                "Process {} will be terminated because monitoring was stopped by user, pid={}",
-                                process.item.name, pid
-                            );
-                            info!(
-                                "Stopping monitored process: {}, PID: {}, reason: user stop",
-                                process.item.name, pid
-                            );
-                            kill_process(pid);
-                        }
-                    }
-                } else if apply_pause_state(&mut processes, &mut config, &change.item.id) {
+        match network::gate_state(unavailable.is_none(), waited, gate.timeout_ms) {
+            GateState::Ready => {
+                if !first_attempt {
                     info!(
-                        "Pausing monitor item while keeping process alive: {} ({})",
-                        change.item.name, change.item.id
+                        "Storage available for {} after {:.1}s, starting",
+                        process.item.name,
+                        waited.as_secs_f64()
                     );
                 }
+                process.storage_wait_since = None;
+                true
             }
-
-            if should_kill {
-                if let Some(process) = processes.get_mut(&change.item.id) {
-                    process.process_id = None;
-                    process.item.enabled = false;
+            GateState::Waiting => {
+                if first_attempt {
                     info!(
-                        "Disabled monitor item at runtime: {} ({})",
-                        process.item.name, change.item.id
+                        "Waiting for {} to become accessible before starting {}",
+                        unavailable.unwrap_or_default(),
+                        process.item.name
                     );
                 }
-
-                if let Some(item) = config.items.iter_mut().find(|i| i.id == change.item.id) {
-                    item.enabled = false;
-                }
+                false
             }
-
-            if let Err(e) = crate::config::save_config(&config) {
-                error!("Failed to persist disabled config: {}", e);
-            } else {
-                info!("Saved disabled monitor item config: {}", change.item.id);
+            GateState::TimedOut => {
+                warn!(
+                    "{} still not accessible for {} after {:.1}s, starting anyway",
+                    unavailable.unwrap_or_default(),
+                    process.item.name,
+                    waited.as_secs_f64()
+                );
+                process.storage_wait_since = None;
+                true
             }
         }
+    }
 
-        if change.change_type.has_flag(ChangeType::Remove) {
-            if let Some(process) = processes.remove(&change.item.id) {
-                info!(
-                    "Removed monitor item from runtime: {} ({})",
-                    process.item.name, change.item.id
-                );
+    /// 依赖服务未全部运行时返回 false；通过时记录各服务进程 ID
+    fn service_gate_passed(process: &mut MonitoredProcess) -> bool {
+        if process.item.requires_services.is_empty() {
+            return true;
+        }
+        match service_deps::check_running(&process.item.requires_services) {
+            Ok(pids) => {
+                if let Some(since) = process.service_wait_since.take() {
+                    info!(
+                        "Required services of {} are running after {:.1}s, starting",
+                        process.item.name,
+                        since.elapsed().as_secs_f64()
+                    );
+                }
+                process.service_pids = pids;
+                true
             }
-            config.items.retain(|i| i.id != change.item.id);
-            if let Err(e) = crate::config::save_config(&config) {
-                error!("Failed to persist removal: {}", e);
+            Err(e) => {
+                if process.service_wait_since.is_none() {
+                    info!(
+                        "Waiting for services before starting {}: {}",
+                        process.item.name, e
+                    );
+                    process.service_wait_since = Some(Instant::now());
+                }
+                false
             }
-            info!("Removed monitor item from config: {}", change.item.id);
         }
+    }
 
-        if change.change_type.has_flag(ChangeType::Start) {
-            let mut monitored = MonitoredProcess::from_item(change.item.clone());
+    /// 终止 forbid 项匹配到的所有进程；事件与通知按 BLOCK_REPORT_INTERVAL 限频
+    fn enforce_forbidden(&self, process: &mut MonitoredProcess) {
+        let pids = find_processes_by_image(&process.item.exe_path);
+        if pids.is_empty() {
+            return;
+        }
+        if self.observing(&process.item) {
+            self.report_observed(
+                process,
+                "kill",
+                &format!("forbidden process running, pids={:?}", pids),
+            );
+            return;
+        }
 
-            if let Err(e) = self.start_process_internal(&mut monitored) {
-                error!("Failed to start process {}: {}", change.item.name, e);
-            } else {
-                processes.insert(change.item.id.clone(), monitored);
+        let killed: Vec<u32> = pids
+            .into_iter()
+            .filter(|pid| self.backend.kill(*pid, None))
+            .collect();
+        if killed.is_empty() {
+            return;
+        }
+        process.blocked_count += killed.len() as u32;
 
-                if let Some(item) = config.items.iter_mut().find(|i| i.id == change.item.id) {
-                    item.enabled = true;
-                    if let Err(e) = crate::config::save_config(&config) {
-                        error!("Failed to persist enabled config: {}", e);
-                    } else {
-                        info!("Saved enabled monitor item config: {}", change.item.id);
-                    }
-                } else {
-                    config.items.push(change.item.clone());
-                    if let Err(e) = crate::config::save_config(&config) {
-                        error!("Failed to persist added config: {}", e);
-                    }
-                }
+        let now = Instant::now();
+        if block_report_due(process.last_block_report, now) {
+            warn!(
+                "Terminated forbidden process {} (PIDs {:?}, {} more since last report)",
+                process.item.name, killed, process.suppressed_blocks
+            );
+            self.record_event(
+                &process.item.id,
+                EventKind::Blocked,
+                &format!("pids={:?} suppressed={}", killed, process.suppressed_blocks),
+            );
+            self.notify(Notification::process_blocked(
+                &process.item,
+                &killed,
+                process.suppressed_blocks,
+            ));
+            process.last_block_report = Some(now);
+            process.suppressed_blocks = 0;
+        } else {
+            debug!(
+                "Terminated forbidden process {} again (PIDs {:?})",
+                process.item.name, killed
+            );
+            process.suppressed_blocks += killed.len() as u32;
+        }
+    }
 
-                info!(
-                    "Started monitoring {} ({})",
-                    change.item.name, change.item.id
+    /// 检查同一程序的额外实例（如用户手动多开），按 kill_surplus_instances 终止或只记录
+    ///
+    /// 被守护进程的子孙进程与其他监控项守护的进程不计入。
+    fn enforce_instance_limit(
+        &self,
+        process: &mut MonitoredProcess,
+        pid: u32,
+        max_instances: u32,
+        tracked_pids: &HashSet<u32>,
+        snapshot: &HashMap<u32, ProcessEntry>,
+    ) {
+        let descendants = find_descendants(pid, snapshot);
+        let others: Vec<(u32, Option<u64>)> = find_processes_by_image(process.exe_path())
+            .into_iter()
+            .filter(|other| !tracked_pids.contains(other) && !descendants.contains(other))
+            .map(|other| (other, self.backend.creation_time(other)))
+            .collect();
+        let created: HashMap<u32, Option<u64>> = others.iter().copied().collect();
+        let surplus = surplus_instances(others, max_instances);
+        if surplus.is_empty() {
+            process.surplus_pids.clear();
+            return;
+        }
+
+        let detail = format!("max_instances={}, pids={:?}", max_instances, surplus);
+        if !process.item.kill_surplus_instances {
+            if surplus != process.surplus_pids {
+                warn!(
+                    "{} has {} instance(s) over the limit of {}: PIDs {:?}",
+                    process.item.name,
+                    surplus.len(),
+                    max_instances,
+                    surplus
                 );
+                self.record_event(&process.item.id, EventKind::SurplusInstances, &detail);
             }
+            process.surplus_pids = surplus;
+            return;
+        }
+        if self.observing(&process.item) {
+            self.report_observed(process, "kill", &format!("surplus instances, {}", detail));
+            process.surplus_pids = surplus;
+            return;
         }
-    }
 
-    fn start_process(&self, process: &mut MonitoredProcess) -> Result<(), String> {
-        self.start_process_internal(process)
+        let killed: Vec<u32> = surplus
+            .into_iter()
+            .filter(|other| {
+                self.backend
+                    .kill(*other, created.get(other).copied().flatten())
+            })
+            .collect();
+        process.surplus_pids.clear();
+        if killed.is_empty() {
+            return;
+        }
+        warn!(
+            "Terminated {} surplus instance(s) of {} (limit {}): PIDs {:?}",
+            killed.len(),
+            process.item.name,
+            max_instances,
+            killed
+        );
+        self.record_event(
+            &process.item.id,
+            EventKind::SurplusInstances,
+            &format!("max_instances={}, killed={:?}", max_instances, killed),
+        );
     }
 
-    fn start_process_internal(&self, process: &mut MonitoredProcess) -> Result<(), String> {
-        let exe_path = &process.item.exe_path;
+    /// exe_path 含通配符时解析为最新的匹配文件，每次启动前重新解析
+    fn resolve_exe_path(process: &mut MonitoredProcess) -> Result<(), String> {
+        if !exe_glob::is_pattern(&process.item.exe_path) {
+            process.resolved_exe = None;
+            return Ok(());
+        }
+        let resolved = exe_glob::resolve(&process.item.exe_path)
+            .map_err(|e| format!("Cannot resolve {}: {}", process.item.name, e))?;
+        if process.resolved_exe.as_deref() != Some(resolved.as_str()) {
+            info!(
+                "Resolved {} ({}) to {}",
+                process.item.name, process.item.exe_path, resolved
+            );
+            process.resolved_exe = Some(resolved);
+        }
+        Ok(())
+    }
 
-        info!("Starting process: {}", exe_path);
+    /// 配置了 expected_sha256 时校验文件哈希
+    fn verify_item_hash(item: &MonitorItem, exe_path: &str) -> Result<(), String> {
+        match &item.expected_sha256 {
+            Some(expected) if std::path::Path::new(exe_path).exists() => {
+                check_file_hash(exe_path, expected)
+            }
+            _ => Ok(()),
+        }
+    }
 
+    /// require_signed 或 expected_publisher 设置时在启动前校验签名
+    fn verify_item_signature(item: &MonitorItem, exe_path: &str) -> Result<(), String> {
+        if !item.require_signed && item.expected_publisher.is_none() {
+            return Ok(());
+        }
+        // 文件不存在由 launch_process 统一报告
         if !std::path::Path::new(exe_path).exists() {
-            error!("Executable not found: {}", exe_path);
-            return Err(format!("Executable not found: {}", exe_path));
+            return Ok(());
         }
+        check_signature(exe_path, item.expected_publisher.as_deref())
+    }
 
-        if let Some(existing_pid) = find_process_by_path(exe_path) {
-            info!(
-                "Found running process {} (PID: {}), reusing it",
-                process.item.name, existing_pid
-            );
-            process.process_id = Some(existing_pid);
-            process.last_heartbeat = Instant::now();
-            process.startup_time = Instant::now();
+    /// 已有相同程序在运行时直接接管，返回 true
+    fn adopt_running_instance(&self, process: &mut MonitoredProcess) -> bool {
+        let Some(existing_pid) = self.find_running_instance(process) else {
+            return false;
+        };
+        info!(
+            "Found running process {} (PID: {}), reusing it",
+            process.item.name, existing_pid
+        );
+        process.process_id = Some(existing_pid);
+        process.process_created = self.backend.creation_time(existing_pid);
+        process.last_heartbeat = Instant::now();
+        process.startup_time = Instant::now();
+        true
+    }
+
+    fn launch_process(&self, process: &mut MonitoredProcess) -> Result<(), String> {
+        let exe_path = process.exe_path().to_string();
+
+        info!("Starting process: {}", exe_path);
+
+        if self.adopt_running_instance(process) {
             return Ok(());
         }
 
-        let working_dir = std::path::Path::new(exe_path)
+        let working_dir = std::path::Path::new(&exe_path)
             .parent()
             .and_then(|p| p.to_str())
             .map(|s| s.to_string());
 
         let args = process.item.args.as_deref();
 
-        let proc_info = start_process_in_session0(
-            exe_path,
-            working_dir.as_deref(),
-            args,
-            process.item.minimize,
-            process.item.no_window,
-        )?;
+        let app_container = process
+            .item
+            .app_container
+            .as_ref()
+            .map(AppContainerLaunch::prepare)
+            .transpose()?;
+
+        let capacity = process.item.output_buffer_kb as usize * 1024;
+        let output = if process.item.capture_output {
+            let buffer = process
+                .output
+                .get_or_insert_with(|| Arc::new(OutputBuffer::new(capacity)));
+            buffer.set_capacity(capacity);
+            buffer.push(format!("--- starting {} ---\n", process.item.name).as_bytes());
+            Some(buffer.clone())
+        } else {
+            process.output = None;
+            None
+        };
+
+        let pid = self
+            .backend
+            .spawn(&SpawnRequest {
+                exe_path: &exe_path,
+                working_dir: working_dir.as_deref(),
+                args,
+                minimize: process.item.minimize,
+                no_window: process.item.no_window,
+                app_container: app_container.as_ref(),
+                block_child_processes: process.item.block_child_processes,
+                desktop: DesktopOptions {
+                    desktop: LaunchDesktop::parse(process.item.desktop.as_deref()),
+                    ui_access: process.item.ui_access,
+                },
+                placement: process.item.placement.as_ref(),
+                priority_class: process.item.priority_class,
+                io_priority: process.item.io_priority,
+                output: output.as_ref(),
+            })
+            .map_err(|e| {
+                error!("{}", e);
+                e
+            })?;
 
-        process.process_id = Some(proc_info.process_id);
+        process.process_id = Some(pid);
+        process.process_created = self.backend.creation_time(pid);
         process.last_heartbeat = Instant::now();
         process.startup_time = Instant::now();
+        process.worker_wait_since = process.item.worker.as_ref().map(|_| Instant::now());
 
         info!(
             "Started monitored process {} with PID {}",
-            process.item.name, proc_info.process_id
+            process.item.name, pid
         );
+        self.record_launch(&process.item, pid, &exe_path);
 
         Ok(())
     }
 
+    /// 记录本次启动实际使用的程序、命令行与环境；与上一次启动不同时告警，便于排查重启后的行为变化
+    fn record_launch(&self, item: &MonitorItem, pid: u32, exe_path: &str) {
+        let info = crate::launch_info::collect(pid, exe_path);
+        self.item_logs.write(
+            &item.id,
+            "launch",
+            &serde_json::to_string(&info).unwrap_or_default(),
+        );
+        let Some(store) = &self.event_store else {
+            return;
+        };
+        let previous = store
+            .launches(&item.id, 1)
+            .ok()
+            .and_then(|launches| launches.into_iter().next());
+        if let Some(previous) = previous {
+            let changed = info.changes_from(&previous.info);
+            if !changed.is_empty() {
+                warn!(
+                    "{} was launched with a different {} than its previous launch (PID {})",
+                    item.name,
+                    changed.join(", "),
+                    previous.pid
+                );
+            }
+        }
+        store.record_launch(&item.id, pid, &info);
+    }
+
+    /// capture_output 开启的监控项的输出缓冲
+    pub fn output_buffer(&self, item_id: &str) -> Result<Arc<OutputBuffer>, String> {
+        let processes = self.processes.lock().unwrap();
+        let process = processes
+            .get(item_id)
+            .ok_or_else(|| format!("no item {}", item_id))?;
+        process
+            .output
+            .clone()
+            .ok_or_else(|| format!("output capture is not enabled for {}", process.item.name))
+    }
+
+    /// capture_output 开启的监控项最近的输出
+    pub fn get_output(&self, item_id: &str) -> Result<serde_json::Value, String> {
+        let processes = self.processes.lock().unwrap();
+        let process = processes
+            .get(item_id)
+            .ok_or_else(|| format!("no item {}", item_id))?;
+        let buffer = process
+            .output
+            .as_ref()
+            .ok_or_else(|| format!("output capture is not enabled for {}", process.item.name))?;
+        let (output, truncated) = buffer.snapshot();
+        Ok(serde_json::json!({
+            "id": item_id,
+            "name": process.item.name,
+            "process_id": process.process_id,
+            "truncated": truncated,
+            "output": output,
+        }))
+    }
+
     pub fn get_status(&self) -> serde_json::Value {
+        let (paused, paused_remaining) = self.pause_state();
+        let table = self.process_table();
         let processes = self.processes.lock().unwrap();
         let items: Vec<serde_json::Value> = processes
             .iter()
             .map(|(id, p)| {
-                serde_json::json!({
+                let mut status = serde_json::json!({
                     "id": id,
                     "name": p.item.name,
                     "exe_path": p.item.exe_path,
+                    "resolved_exe_path": p.resolved_exe,
                     "enabled": p.item.enabled,
                     "process_id": p.process_id,
                     "last_heartbeat_ms": p.last_heartbeat.elapsed().as_millis(),
                     "heartbeat_timeout_ms": p.item.heartbeat_timeout_ms,
+                    "heartbeat_required": p.heartbeat_enabled(),
+                    "mode": p.item.mode.as_str(),
+                    "blocked_count": p.blocked_count,
+                    "orphan_pid": p.orphan_pid,
+                    "suspended": p.suspended,
+                    "last_restart_reason": p.last_restart_reason,
+                    "restarts_by_reason": p.restarts_by_reason.iter().map(|(reason, count)| (reason.as_str(), *count)).collect::<BTreeMap<_, _>>(),
+                    "deferred_secs": p.deferred_until.map(|until| until.saturating_duration_since(Instant::now()).as_secs()),
+                    "outside_schedule": p.outside_schedule,
+                    "ha_standby": p.item.ha && !self.is_ha_active(),
+                    "condition_unmet": self.inactive_items.lock().unwrap().get(&p.item.id).cloned(),
+                    "escalated": p.escalated,
+                    "observing": p.item.observe || self.observe_only.load(Ordering::Relaxed),
+                    "observed_action": p.observed_action,
+                    "waiting_for_network_secs": p.network_wait_since.map(|since| since.elapsed().as_secs()),
+                    "waiting_for_services_secs": p.service_wait_since.map(|since| since.elapsed().as_secs()),
+                    "waiting_on_storage_secs": p.storage_wait_since.map(|since| since.elapsed().as_secs()),
+                    "waiting_for_unlock_secs": p.unlock_wait_since.map(|since| since.elapsed().as_secs()),
+                    "waiting_for_worker_secs": p.worker_wait_since.map(|since| since.elapsed().as_secs()),
+                    "session_blocked": p.session_blocked_until.is_some(),
+                    "session_retry_secs": p.session_blocked_until
+                        .map(|until| until.saturating_duration_since(Instant::now()).as_secs()),
+                    "child_pids": p.item.children.as_ref().map(|_| &p.child_pids),
+                    "surplus_pids": p.item.max_instances.map(|_| &p.surplus_pids),
                     "restart_count": p.restart_count,
-                    "is_alive": p.process_id.map_or(false, check_process_alive),
+                    "is_alive": table.is_tracked_alive(p),
                     "is_heartbeat_ok": !p.is_heartbeat_timeout(),
-                })
+                    "missed_heartbeats": p.missed_heartbeats(),
+                    "resources": p.resources,
+                    "context": p.context,
+                    "cpu_percent": p.cpu_percent,
+                    "last_start_error": p.last_start_error,
+                });
+                if let Some(status) = status.as_object_mut() {
+                    status.extend(health_check_status(p));
+                }
+                status
             })
             .collect();
 
         serde_json::json!({
             "service_running": true,
+            "paused": paused,
+            "safe_mode": self.safe_mode(),
+            "paused_remaining_secs": paused_remaining.map(|remaining| remaining.as_secs()),
+            "clock_settle_remaining_secs": self.settle_remaining().map(|remaining| remaining.as_secs()),
+            "exit_events_active": self.exit_events_active.load(Ordering::Relaxed),
+            "pipe": self.pipe_stats.snapshot(),
             "total_items": items.len(),
             "items": items,
         })
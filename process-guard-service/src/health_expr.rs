@@ -0,0 +1,105 @@
+use crate::models::HealthExpr;
+
+/// 表达式中可引用的检查名称
+pub const CHECK_NAMES: [&str; 10] = [
+    "alive",
+    "heartbeat",
+    "http_probe",
+    "health_command",
+    "log_check",
+    "window_check",
+    "port_check",
+    "event_log",
+    "children",
+    "services",
+];
+
+/// 按表达式汇总各检查结果，不健康时返回原因
+///
+/// `check_failure` 返回单项检查的失败原因；未配置的检查返回 None，视为通过。
+pub fn evaluate(
+    expr: &HealthExpr,
+    check_failure: &dyn Fn(&str) -> Option<String>,
+) -> Result<(), String> {
+    match expr {
+        HealthExpr::Check(name) => match check_failure(name) {
+            Some(reason) => Err(reason),
+            None => Ok(()),
+        },
+        HealthExpr::All { all } => all.iter().try_for_each(|e| evaluate(e, check_failure)),
+        HealthExpr::Any { any } => {
+            let mut reasons = Vec::new();
+            for e in any {
+                match evaluate(e, check_failure) {
+                    Ok(()) => return Ok(()),
+                    Err(reason) => reasons.push(reason),
+                }
+            }
+            if reasons.is_empty() {
+                Ok(())
+            } else {
+                Err(format!("all alternatives failed: {}", reasons.join("; ")))
+            }
+        }
+    }
+}
+
+/// 表达式是否引用了指定检查
+pub fn references(expr: &HealthExpr, name: &str) -> bool {
+    match expr {
+        HealthExpr::Check(check) => check == name,
+        HealthExpr::All { all: children } | HealthExpr::Any { any: children } => {
+            children.iter().any(|e| references(e, name))
+        }
+    }
+}
+
+/// 表达式中无法识别的检查名称
+pub fn unknown_checks(expr: &HealthExpr) -> Vec<String> {
+    match expr {
+        HealthExpr::Check(check) if !CHECK_NAMES.contains(&check.as_str()) => vec![check.clone()],
+        HealthExpr::Check(_) => Vec::new(),
+        HealthExpr::All { all: children } | HealthExpr::Any { any: children } => {
+            children.iter().flat_map(unknown_checks).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{evaluate, references, unknown_checks};
+    use crate::models::HealthExpr;
+
+    fn parse(json: &str) -> HealthExpr {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn failing(name: &str) -> Option<String> {
+        matches!(name, "http_probe" | "log_check").then(|| format!("{} failed", name))
+    }
+
+    #[test]
+    fn any_of_tolerates_a_single_flaky_probe() {
+        let expr =
+            parse(r#"{"all": ["alive", "heartbeat", {"any": ["http_probe", "port_check"]}]}"#);
+        assert!(evaluate(&expr, &failing).is_ok());
+
+        let expr = parse(r#"{"any": ["http_probe", "log_check"]}"#);
+        let err = evaluate(&expr, &failing).unwrap_err();
+        assert!(err.contains("http_probe failed") && err.contains("log_check failed"));
+    }
+
+    #[test]
+    fn all_of_reports_first_failure() {
+        let expr = parse(r#"{"all": ["heartbeat", "log_check", "http_probe"]}"#);
+        assert_eq!(evaluate(&expr, &failing).unwrap_err(), "log_check failed");
+    }
+
+    #[test]
+    fn finds_referenced_and_unknown_checks() {
+        let expr = parse(r#"{"all": ["alive", {"any": ["heartbeat", "tcp"]}]}"#);
+        assert!(references(&expr, "heartbeat"));
+        assert!(!references(&expr, "http_probe"));
+        assert_eq!(unknown_checks(&expr), vec!["tcp".to_string()]);
+    }
+}
@@ -0,0 +1,113 @@
+use crate::guardian::Guardian;
+use crate::models::CHECK_INTERVAL_MS;
+use crate::service::ServiceLiveness;
+use serde::Serialize;
+use std::time::Duration;
+
+/// 守护循环超过该倍数的检查间隔未完成一轮即视为卡死
+const STALL_CHECK_INTERVALS: u64 = 10;
+
+/// /healthz 的检查结果
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct HealthReport {
+    pub healthy: bool,
+    pub guardian_alive: bool,
+    pub pipe_alive: bool,
+    pub last_cycle_age_secs: f64,
+    pub failed_items: Vec<String>,
+    pub max_failed_items: usize,
+    pub reasons: Vec<String>,
+}
+
+pub fn evaluate_health(
+    guardian_alive: bool,
+    pipe_alive: bool,
+    last_cycle_age: Duration,
+    failed_items: Vec<String>,
+    max_failed_items: usize,
+) -> HealthReport {
+    let stall_limit = Duration::from_millis(CHECK_INTERVAL_MS * STALL_CHECK_INTERVALS);
+    let mut reasons = Vec::new();
+
+    if !guardian_alive {
+        reasons.push("guardian thread is not running".to_string());
+    } else if last_cycle_age > stall_limit {
+        reasons.push(format!(
+            "guardian loop stalled for {}s",
+            last_cycle_age.as_secs()
+        ));
+    }
+    if !pipe_alive {
+        reasons.push("pipe server thread is not running".to_string());
+    }
+    if failed_items.len() >= max_failed_items.max(1) {
+        reasons.push(format!(
+            "{} monitor item(s) failed: {}",
+            failed_items.len(),
+            failed_items.join(", ")
+        ));
+    }
+
+    HealthReport {
+        healthy: reasons.is_empty(),
+        guardian_alive,
+        pipe_alive,
+        last_cycle_age_secs: last_cycle_age.as_secs_f64(),
+        failed_items,
+        max_failed_items,
+        reasons,
+    }
+}
+
+pub fn collect_health(
+    guardian: &Guardian,
+    liveness: &ServiceLiveness,
+    max_failed_items: usize,
+) -> HealthReport {
+    let mut failed_items: Vec<String> = {
+        let table = guardian.process_table();
+        let processes = guardian.get_processes();
+        let processes = processes.lock().unwrap();
+        processes
+            .iter()
+            .filter(|(_, p)| p.is_failed(table.is_tracked_alive(p)))
+            .map(|(id, _)| id.clone())
+            .collect()
+    };
+    failed_items.sort();
+
+    evaluate_health(
+        liveness.guardian_alive(),
+        liveness.pipe_alive(),
+        guardian.last_cycle_age(),
+        failed_items,
+        max_failed_items,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::evaluate_health;
+    use std::time::Duration;
+
+    #[test]
+    fn healthy_when_threads_alive_and_no_failures() {
+        let report = evaluate_health(true, true, Duration::from_secs(1), Vec::new(), 1);
+        assert!(report.healthy);
+        assert!(report.reasons.is_empty());
+    }
+
+    #[test]
+    fn failed_items_at_threshold_are_unhealthy() {
+        let failed = vec!["a".to_string(), "b".to_string()];
+        assert!(evaluate_health(true, true, Duration::ZERO, failed.clone(), 3).healthy);
+        assert!(!evaluate_health(true, true, Duration::ZERO, failed, 2).healthy);
+    }
+
+    #[test]
+    fn stalled_guardian_loop_is_unhealthy() {
+        let report = evaluate_health(true, true, Duration::from_secs(600), Vec::new(), 1);
+        assert!(!report.healthy);
+        assert!(report.reasons[0].contains("stalled"));
+    }
+}
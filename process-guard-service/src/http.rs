@@ -0,0 +1,245 @@
+use crate::session0::to_wide_string;
+use std::ffi::OsStr;
+use std::os::windows::ffi::OsStrExt;
+use std::time::Duration;
+use windows::core::PCWSTR;
+use windows::Win32::Networking::WinHttp::{
+    WinHttpCloseHandle, WinHttpConnect, WinHttpOpen, WinHttpOpenRequest, WinHttpQueryDataAvailable,
+    WinHttpQueryHeaders, WinHttpReadData, WinHttpReceiveResponse, WinHttpSendRequest,
+    WinHttpSetTimeouts, WINHTTP_ACCESS_TYPE_DEFAULT_PROXY, WINHTTP_FLAG_SECURE,
+    WINHTTP_OPEN_REQUEST_FLAGS, WINHTTP_QUERY_FLAG_NUMBER, WINHTTP_QUERY_STATUS_CODE,
+};
+
+const USER_AGENT: &str = "ProcessGuardService";
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedUrl {
+    pub secure: bool,
+    pub host: String,
+    pub port: u16,
+    pub path: String,
+}
+
+/// 解析 http(s)://host[:port][/path] 形式的 URL
+pub fn parse_url(url: &str) -> Result<ParsedUrl, String> {
+    let (secure, rest) = if let Some(rest) = url.strip_prefix("https://") {
+        (true, rest)
+    } else if let Some(rest) = url.strip_prefix("http://") {
+        (false, rest)
+    } else {
+        return Err(format!("Unsupported URL scheme: {}", url));
+    };
+
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+
+    let default_port = if secure { 443 } else { 80 };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') || host.ends_with(']') => {
+            let port = port
+                .parse::<u16>()
+                .map_err(|_| format!("Invalid port in URL: {}", url))?;
+            (host, port)
+        }
+        _ => (authority, default_port),
+    };
+
+    if host.is_empty() {
+        return Err(format!("Missing host in URL: {}", url));
+    }
+
+    Ok(ParsedUrl {
+        secure,
+        host: host.to_string(),
+        port,
+        path: path.to_string(),
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+struct InternetHandle(*mut std::ffi::c_void);
+
+impl InternetHandle {
+    fn new(raw: *mut std::ffi::c_void, what: &str) -> Result<Self, String> {
+        if raw.is_null() {
+            Err(format!(
+                "{} failed: {:?}",
+                what,
+                windows::core::Error::from_win32()
+            ))
+        } else {
+            Ok(Self(raw))
+        }
+    }
+}
+
+impl Drop for InternetHandle {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = WinHttpCloseHandle(self.0);
+        }
+    }
+}
+
+/// 通过 WinHTTP 发送同步请求，读取至多 64KB 的响应体
+pub fn request(
+    method: &str,
+    url: &str,
+    headers: &[(String, String)],
+    body: Option<&[u8]>,
+    timeout: Duration,
+) -> Result<HttpResponse, String> {
+    let parsed = parse_url(url)?;
+    let timeout_ms = timeout.as_millis().min(i32::MAX as u128) as i32;
+
+    let agent = to_wide_string(USER_AGENT);
+    let host = to_wide_string(&parsed.host);
+    let verb = to_wide_string(method);
+    let path = to_wide_string(&parsed.path);
+
+    let header_block: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}: {}\r\n", name, value))
+        .collect();
+    let header_wide: Vec<u16> = OsStr::new(&header_block).encode_wide().collect();
+
+    unsafe {
+        let session = InternetHandle::new(
+            WinHttpOpen(
+                PCWSTR(agent.as_ptr()),
+                WINHTTP_ACCESS_TYPE_DEFAULT_PROXY,
+                PCWSTR::null(),
+                PCWSTR::null(),
+                0,
+            ),
+            "WinHttpOpen",
+        )?;
+        WinHttpSetTimeouts(session.0, timeout_ms, timeout_ms, timeout_ms, timeout_ms)
+            .map_err(|e| format!("WinHttpSetTimeouts failed: {:?}", e))?;
+
+        let connection = InternetHandle::new(
+            WinHttpConnect(session.0, PCWSTR(host.as_ptr()), parsed.port, 0),
+            "WinHttpConnect",
+        )?;
+
+        let flags = if parsed.secure {
+            WINHTTP_FLAG_SECURE
+        } else {
+            WINHTTP_OPEN_REQUEST_FLAGS(0)
+        };
+        let request = InternetHandle::new(
+            WinHttpOpenRequest(
+                connection.0,
+                PCWSTR(verb.as_ptr()),
+                PCWSTR(path.as_ptr()),
+                PCWSTR::null(),
+                PCWSTR::null(),
+                std::ptr::null(),
+                flags,
+            ),
+            "WinHttpOpenRequest",
+        )?;
+
+        let body = body.unwrap_or(&[]);
+        WinHttpSendRequest(
+            request.0,
+            if header_wide.is_empty() {
+                None
+            } else {
+                Some(&header_wide)
+            },
+            if body.is_empty() {
+                None
+            } else {
+                Some(body.as_ptr().cast())
+            },
+            body.len() as u32,
+            body.len() as u32,
+            0,
+        )
+        .map_err(|e| format!("WinHttpSendRequest failed: {:?}", e))?;
+
+        WinHttpReceiveResponse(request.0, std::ptr::null_mut())
+            .map_err(|e| format!("WinHttpReceiveResponse failed: {:?}", e))?;
+
+        let mut status: u32 = 0;
+        let mut status_size = std::mem::size_of::<u32>() as u32;
+        WinHttpQueryHeaders(
+            request.0,
+            WINHTTP_QUERY_STATUS_CODE | WINHTTP_QUERY_FLAG_NUMBER,
+            PCWSTR::null(),
+            Some((&mut status as *mut u32).cast()),
+            &mut status_size,
+            std::ptr::null_mut(),
+        )
+        .map_err(|e| format!("WinHttpQueryHeaders failed: {:?}", e))?;
+
+        let mut response_body = Vec::new();
+        while response_body.len() < MAX_BODY_BYTES {
+            let mut available: u32 = 0;
+            if WinHttpQueryDataAvailable(request.0, &mut available).is_err() || available == 0 {
+                break;
+            }
+
+            let to_read = (available as usize).min(MAX_BODY_BYTES - response_body.len());
+            let mut chunk = vec![0u8; to_read];
+            let mut read: u32 = 0;
+            if WinHttpReadData(
+                request.0,
+                chunk.as_mut_ptr().cast(),
+                to_read as u32,
+                &mut read,
+            )
+            .is_err()
+                || read == 0
+            {
+                break;
+            }
+            response_body.extend_from_slice(&chunk[..read as usize]);
+        }
+
+        Ok(HttpResponse {
+            status: status as u16,
+            body: String::from_utf8_lossy(&response_body).into_owned(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_url, ParsedUrl};
+
+    #[test]
+    fn parses_https_url_with_default_port() {
+        assert_eq!(
+            parse_url("https://hooks.example.com/services/abc").unwrap(),
+            ParsedUrl {
+                secure: true,
+                host: "hooks.example.com".to_string(),
+                port: 443,
+                path: "/services/abc".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn parses_explicit_port_and_missing_path() {
+        let parsed = parse_url("http://127.0.0.1:8080").unwrap();
+        assert_eq!(parsed.port, 8080);
+        assert_eq!(parsed.path, "/");
+        assert!(!parsed.secure);
+    }
+
+    #[test]
+    fn rejects_unknown_scheme() {
+        assert!(parse_url("ftp://example.com/").is_err());
+    }
+}
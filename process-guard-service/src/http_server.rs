@@ -0,0 +1,167 @@
+use crate::guardian::Guardian;
+use crate::healthz::collect_health;
+use crate::metrics::{collect_metrics, render_prometheus};
+use crate::service::ServiceLiveness;
+use log::{debug, error, info, warn};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const ACCEPT_POLL_INTERVAL_MS: u64 = 200;
+const CLIENT_TIMEOUT_MS: u64 = 2000;
+
+/// 某个监听地址上启用的路由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HttpRoutes {
+    pub metrics: bool,
+    pub health: bool,
+}
+
+/// 提供 /metrics 与 /healthz 的轻量 HTTP 服务
+pub struct StatusHttpServer {
+    guardian: Arc<Guardian>,
+    running: Arc<Mutex<bool>>,
+    liveness: Arc<ServiceLiveness>,
+    listen_addr: String,
+    routes: HttpRoutes,
+    health_max_failed_items: usize,
+}
+
+impl StatusHttpServer {
+    pub fn new(
+        guardian: Arc<Guardian>,
+        running: Arc<Mutex<bool>>,
+        liveness: Arc<ServiceLiveness>,
+        listen_addr: String,
+        routes: HttpRoutes,
+        health_max_failed_items: usize,
+    ) -> Self {
+        Self {
+            guardian,
+            running,
+            liveness,
+            listen_addr,
+            routes,
+            health_max_failed_items,
+        }
+    }
+
+    pub fn run(&self) {
+        let listener = match TcpListener::bind(&self.listen_addr) {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!(
+                    "Failed to bind HTTP listener on {}: {}",
+                    self.listen_addr, e
+                );
+                return;
+            }
+        };
+
+        if let Err(e) = listener.set_nonblocking(true) {
+            error!("Failed to configure HTTP listener: {}", e);
+            return;
+        }
+
+        if self.routes.metrics {
+            info!(
+                "Metrics exporter listening on http://{}/metrics",
+                self.listen_addr
+            );
+        }
+        if self.routes.health {
+            info!(
+                "Health endpoint listening on http://{}/healthz",
+                self.listen_addr
+            );
+        }
+
+        loop {
+            if !*self.running.lock().unwrap() {
+                info!("HTTP listener on {} stopping", self.listen_addr);
+                break;
+            }
+
+            match listener.accept() {
+                Ok((stream, peer)) => {
+                    debug!("HTTP request from {}", peer);
+                    if let Err(e) = self.handle_connection(stream) {
+                        warn!("Failed to serve HTTP request from {}: {}", peer, e);
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    std::thread::sleep(Duration::from_millis(ACCEPT_POLL_INTERVAL_MS));
+                }
+                Err(e) => {
+                    warn!("HTTP listener accept failed: {}", e);
+                    std::thread::sleep(Duration::from_millis(ACCEPT_POLL_INTERVAL_MS));
+                }
+            }
+        }
+    }
+
+    fn route(&self, method: &str, path: &str) -> (&'static str, &'static str, String) {
+        if method != "GET" {
+            return (
+                "404 Not Found",
+                "text/plain; charset=utf-8",
+                "not found\n".to_string(),
+            );
+        }
+
+        match path {
+            "/metrics" if self.routes.metrics => {
+                let (service, items) = collect_metrics(&self.guardian);
+                (
+                    "200 OK",
+                    "text/plain; version=0.0.4; charset=utf-8",
+                    render_prometheus(&service, &items),
+                )
+            }
+            "/healthz" if self.routes.health => {
+                let report =
+                    collect_health(&self.guardian, &self.liveness, self.health_max_failed_items);
+                let status = if report.healthy {
+                    "200 OK"
+                } else {
+                    "503 Service Unavailable"
+                };
+                let body = serde_json::to_string(&report).unwrap_or_default();
+                (status, "application/json; charset=utf-8", body)
+            }
+            _ => (
+                "404 Not Found",
+                "text/plain; charset=utf-8",
+                "not found\n".to_string(),
+            ),
+        }
+    }
+
+    fn handle_connection(&self, stream: TcpStream) -> std::io::Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(Duration::from_millis(CLIENT_TIMEOUT_MS)))?;
+        stream.set_write_timeout(Some(Duration::from_millis(CLIENT_TIMEOUT_MS)))?;
+
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or("");
+        let path = parts.next().unwrap_or("");
+
+        let (status, content_type, body) = self.route(method, path);
+
+        let mut stream = stream;
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            content_type,
+            body.len(),
+            body
+        )?;
+        stream.flush()
+    }
+}
@@ -0,0 +1,454 @@
+use crate::config::{add_item, load_config, update_item};
+use crate::models::{DuplicatePathPolicy, MonitorItem, PipeRequest};
+use crate::session0::to_wide_string;
+use std::collections::HashMap;
+use std::path::Path;
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::ERROR_SUCCESS;
+use windows::Win32::System::Registry::{
+    RegGetValueW, HKEY_LOCAL_MACHINE, REG_ROUTINE_FLAGS, RRF_RT_REG_DWORD, RRF_RT_REG_SZ,
+};
+
+/// 导入得到的监控项，以及无法对应到本服务配置的原设置说明
+#[derive(Debug)]
+pub struct Imported {
+    pub item: MonitorItem,
+    pub notes: Vec<String>,
+}
+
+/// 读取注册表值的原始字节；值不存在时返回 None
+fn read_registry(subkey: &str, value: &str, flags: REG_ROUTINE_FLAGS) -> Option<Vec<u8>> {
+    let subkey = to_wide_string(subkey);
+    let value = to_wide_string(value);
+    let mut size = 0u32;
+    let status = unsafe {
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            PCWSTR(subkey.as_ptr()),
+            PCWSTR(value.as_ptr()),
+            flags,
+            None,
+            None,
+            Some(&mut size),
+        )
+    };
+    if status != ERROR_SUCCESS || size == 0 {
+        return None;
+    }
+    let mut data = vec![0u8; size as usize];
+    let status = unsafe {
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            PCWSTR(subkey.as_ptr()),
+            PCWSTR(value.as_ptr()),
+            flags,
+            None,
+            Some(data.as_mut_ptr().cast()),
+            Some(&mut size),
+        )
+    };
+    if status != ERROR_SUCCESS {
+        return None;
+    }
+    data.truncate(size as usize);
+    Some(data)
+}
+
+/// 读取字符串值（REG_EXPAND_SZ 会展开环境变量）
+pub(crate) fn read_registry_string(subkey: &str, value: &str) -> Option<String> {
+    let data = read_registry(subkey, value, RRF_RT_REG_SZ)?;
+    let wide: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .take_while(|c| *c != 0)
+        .collect();
+    let text = String::from_utf16_lossy(&wide);
+    (!text.is_empty()).then_some(text)
+}
+
+pub(crate) fn read_registry_dword(subkey: &str, value: &str) -> Option<u32> {
+    let data = read_registry(subkey, value, RRF_RT_REG_DWORD)?;
+    let bytes: [u8; 4] = data.get(..4)?.try_into().ok()?;
+    Some(u32::from_le_bytes(bytes))
+}
+
+/// NSSM 保存在 Services\<服务名>\Parameters 下的值
+const NSSM_STRINGS: &[&str] = &[
+    "Application",
+    "AppParameters",
+    "AppDirectory",
+    "AppStdout",
+    "AppStderr",
+    "AppEnvironmentExtra",
+];
+const NSSM_DWORDS: &[&str] = &[
+    "AppRestartDelay",
+    "AppStopMethodSkip",
+    "AppStopMethodConsole",
+    "AppStopMethodWindow",
+    "AppStopMethodThreads",
+];
+
+/// 从注册表读取 NSSM 服务定义
+pub fn import_nssm(service: &str) -> Result<Imported, String> {
+    let service_key = format!(r"SYSTEM\CurrentControlSet\Services\{}", service);
+    let parameters_key = format!(r"{}\Parameters", service_key);
+
+    let mut values = HashMap::new();
+    for name in NSSM_STRINGS {
+        if let Some(value) = read_registry_string(&parameters_key, name) {
+            values.insert(name.to_string(), value);
+        }
+    }
+    for name in NSSM_DWORDS {
+        if let Some(value) = read_registry_dword(&parameters_key, name) {
+            values.insert(name.to_string(), value.to_string());
+        }
+    }
+    // AppExit 子键的默认值是对所有退出码的处理方式
+    if let Some(action) = read_registry_string(&format!(r"{}\AppExit", parameters_key), "") {
+        values.insert("AppExit".to_string(), action);
+    }
+    if values.is_empty() {
+        return Err(format!(
+            "service {} has no NSSM parameters (HKLM\\{})",
+            service, parameters_key
+        ));
+    }
+
+    let display_name = read_registry_string(&service_key, "DisplayName");
+    nssm_item(service, display_name.as_deref(), &values)
+}
+
+/// 把 NSSM 参数转换为监控项
+fn nssm_item(
+    service: &str,
+    display_name: Option<&str>,
+    values: &HashMap<String, String>,
+) -> Result<Imported, String> {
+    let get = |name: &str| {
+        values
+            .get(name)
+            .map(String::as_str)
+            .filter(|v| !v.is_empty())
+    };
+    let ms = |name: &str| get(name).and_then(|v| v.parse::<u64>().ok());
+
+    let exe_path = get("Application")
+        .ok_or_else(|| format!("service {} has no Application value", service))?
+        .to_string();
+    let mut item = MonitorItem::new(exe_path, display_name.unwrap_or(service).to_string());
+    item.args = get("AppParameters").map(str::to_string);
+    item.heartbeat_required = false;
+
+    let mut notes = Vec::new();
+    if let Some(delay) = ms("AppRestartDelay") {
+        item.restart_delay_ms = delay;
+    }
+    let stop_wait = [
+        "AppStopMethodConsole",
+        "AppStopMethodWindow",
+        "AppStopMethodThreads",
+    ]
+    .iter()
+    .filter_map(|name| ms(name))
+    .max();
+    if let Some(wait) = stop_wait {
+        item.kill_wait_ms = wait;
+    }
+    if let Some(dir) = get("AppDirectory") {
+        note_working_dir(&item.exe_path, dir, &mut notes);
+    }
+    if let Some(action) = get("AppExit") {
+        if !action.eq_ignore_ascii_case("Restart") {
+            notes.push(format!(
+                "AppExit={} is not supported; the process will always be restarted",
+                action
+            ));
+        }
+    }
+    for name in ["AppStdout", "AppStderr"] {
+        if let Some(path) = get(name) {
+            notes.push(format!(
+                "{}={} is not supported; output is not redirected",
+                name, path
+            ));
+        }
+    }
+    if get("AppEnvironmentExtra").is_some() {
+        notes.push(
+            "AppEnvironmentExtra is not supported; set the variables system-wide".to_string(),
+        );
+    }
+    Ok(Imported { item, notes })
+}
+
+/// 读取 WinSW 的 XML 服务定义
+pub fn import_winsw(xml_path: &Path) -> Result<Imported, String> {
+    let xml = std::fs::read_to_string(xml_path)
+        .map_err(|e| format!("cannot read {}: {}", xml_path.display(), e))?;
+    let base = xml_path
+        .canonicalize()
+        .ok()
+        .and_then(|path| path.parent().map(|dir| dir.to_string_lossy().to_string()))
+        .unwrap_or_default();
+    winsw_item(&xml, base.trim_start_matches(r"\\?\"))
+}
+
+/// 把 WinSW 定义转换为监控项；base 为 XML 所在目录，对应 %BASE%
+fn winsw_item(xml: &str, base: &str) -> Result<Imported, String> {
+    let expand = |text: &str| expand_variables(&text.replace("%BASE%", base));
+
+    let exe_path = element_text(xml, "executable")
+        .map(|text| expand(&text))
+        .ok_or("missing <executable> element")?;
+    let name = element_text(xml, "name")
+        .or_else(|| element_text(xml, "id"))
+        .unwrap_or_else(|| exe_path.clone());
+    let mut item = MonitorItem::new(exe_path, name);
+    item.args = element_text(xml, "arguments")
+        .or_else(|| element_text(xml, "startarguments"))
+        .map(|text| expand(&text));
+    item.heartbeat_required = false;
+
+    let mut notes = Vec::new();
+    if let Some(wait) = element_text(xml, "stoptimeout").and_then(|text| parse_duration_ms(&text)) {
+        item.kill_wait_ms = wait;
+    }
+    if let Some(attributes) = element_attributes(xml, "onfailure") {
+        match attributes.get("action").map(String::as_str) {
+            Some("restart") | None => {
+                if let Some(delay) = attributes.get("delay").and_then(|d| parse_duration_ms(d)) {
+                    item.restart_delay_ms = delay;
+                }
+            }
+            Some(action) => notes.push(format!(
+                "onfailure action=\"{}\" is not supported; the process will always be restarted",
+                action
+            )),
+        }
+    }
+    if let Some(dir) = element_text(xml, "workingdirectory") {
+        note_working_dir(&item.exe_path, &expand(&dir), &mut notes);
+    }
+    for tag in ["env", "logpath", "log", "depend", "serviceaccount"] {
+        if find_open_tag(xml, tag).is_some() {
+            notes.push(format!("<{}> is not supported and was ignored", tag));
+        }
+    }
+    Ok(Imported { item, notes })
+}
+
+/// 进程总是以可执行文件所在目录为工作目录启动
+fn note_working_dir(exe_path: &str, dir: &str, notes: &mut Vec<String>) {
+    let exe_dir = Path::new(exe_path)
+        .parent()
+        .map(|p| p.to_string_lossy().to_lowercase());
+    let dir = dir.trim_end_matches('\\');
+    if exe_dir.as_deref() != Some(dir.to_lowercase().as_str()) {
+        notes.push(format!(
+            "working directory {} differs from the executable's directory, which will be used instead",
+            dir
+        ));
+    }
+}
+
+/// 第一个指定元素的文本内容（去除首尾空白并还原常用实体）
+fn element_text(xml: &str, tag: &str) -> Option<String> {
+    let open = find_open_tag(xml, tag)?;
+    let start = open + xml[open..].find('>')? + 1;
+    if xml[..start].ends_with("/>") {
+        return None;
+    }
+    let end = start + xml[start..].find(&format!("</{}>", tag))?;
+    let text = unescape(xml[start..end].trim());
+    (!text.is_empty()).then_some(text)
+}
+
+/// 第一个指定元素的属性
+fn element_attributes(xml: &str, tag: &str) -> Option<HashMap<String, String>> {
+    let open = find_open_tag(xml, tag)?;
+    let end = open + xml[open..].find('>')?;
+    let mut rest = xml[open + tag.len() + 1..end].trim_end_matches('/');
+    let mut attributes = HashMap::new();
+    while let Some(eq) = rest.find('=') {
+        let name = rest[..eq].trim().to_string();
+        let value = rest[eq + 1..].trim_start();
+        let quote = value.chars().next()?;
+        let value_end = value[1..].find(quote)? + 1;
+        attributes.insert(name, unescape(&value[1..value_end]));
+        rest = &value[value_end + 1..];
+    }
+    Some(attributes)
+}
+
+/// `<tag` 后紧跟空白、`>` 或 `/` 的位置，跳过前缀相同的其他元素
+fn find_open_tag(xml: &str, tag: &str) -> Option<usize> {
+    let pattern = format!("<{}", tag);
+    let mut from = 0;
+    while let Some(pos) = xml[from..].find(&pattern) {
+        let pos = from + pos;
+        match xml[pos + pattern.len()..].chars().next() {
+            Some(c) if c.is_whitespace() || c == '>' || c == '/' => return Some(pos),
+            _ => from = pos + pattern.len(),
+        }
+    }
+    None
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// 展开 %NAME% 形式的环境变量，未定义的保持原样
+fn expand_variables(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('%') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('%') {
+            Some(end) => match std::env::var(&after[..end]) {
+                Ok(value) if end > 0 => {
+                    result.push_str(&value);
+                    rest = &after[end + 1..];
+                }
+                _ => {
+                    result.push('%');
+                    rest = after;
+                }
+            },
+            None => {
+                result.push('%');
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// WinSW 的时长格式："10 sec"、"1500 ms"、"2 min"、"1 hour"，无单位时按毫秒
+fn parse_duration_ms(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let value: f64 = text[..split].parse().ok()?;
+    let factor = match text[split..].trim().to_lowercase().as_str() {
+        "" | "ms" => 1.0,
+        "sec" | "secs" | "s" => 1000.0,
+        "min" | "mins" | "m" => 60_000.0,
+        "hour" | "hours" | "h" => 3_600_000.0,
+        _ => return None,
+    };
+    Some((value * factor) as u64)
+}
+
+/// 命令行导入（服务运行中）：通过管道 add 交给服务，由服务按 duplicate_exe_paths 处理并保存配置
+pub fn add_to_service(mut imported: Imported) -> Result<Imported, String> {
+    let response = crate::pipe_client::send(&PipeRequest::Add {
+        config: imported.item.clone(),
+    })?;
+    if let Some(message) = response.message {
+        imported.notes.push(message);
+    }
+    Ok(imported)
+}
+
+/// 命令行导入（服务未运行）：追加到 config.json，按 duplicate_exe_paths 处理已监控的路径，返回说明供打印
+pub fn add_to_config(mut imported: Imported) -> Result<Imported, String> {
+    let mut config = load_config();
+    let allow = config.settings.allow_duplicate_exe_paths;
+    let key = imported.item.instance_key(allow);
+    let existing = config
+        .items
+        .iter()
+        .find(|item| item.instance_key(allow) == key)
+        .map(|item| item.id.clone());
+    if let Some(existing) = existing {
+        match config.settings.duplicate_exe_paths {
+            DuplicatePathPolicy::Reject => {
+                return Err(format!(
+                    "{} is already monitored in config.json",
+                    imported.item.exe_path
+                ));
+            }
+            DuplicatePathPolicy::Merge => {
+                imported
+                    .notes
+                    .push(format!("merged into existing item {}", existing));
+                imported.item.id = existing;
+                update_item(&mut config, imported.item.clone()).map_err(|e| e.to_string())?;
+                return Ok(imported);
+            }
+            DuplicatePathPolicy::KeepAll => {}
+        }
+    }
+    add_item(&mut config, imported.item.clone()).map_err(|e| e.to_string())?;
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{nssm_item, parse_duration_ms, winsw_item};
+    use std::collections::HashMap;
+
+    #[test]
+    fn parses_winsw_durations() {
+        assert_eq!(parse_duration_ms("10 sec"), Some(10_000));
+        assert_eq!(parse_duration_ms("1500"), Some(1500));
+        assert_eq!(parse_duration_ms("1.5 min"), Some(90_000));
+        assert_eq!(parse_duration_ms("soon"), None);
+    }
+
+    #[test]
+    fn converts_winsw_definition() {
+        let xml = r#"<service>
+  <id>myapp</id>
+  <name>My App</name>
+  <executable>%BASE%\myapp.exe</executable>
+  <arguments>--port 8080 --tag &quot;a b&quot;</arguments>
+  <stoptimeout>15 sec</stoptimeout>
+  <onfailure action="restart" delay="10 sec"/>
+  <logpath>%BASE%\logs</logpath>
+</service>"#;
+        let imported = winsw_item(xml, r"C:\apps\myapp").unwrap();
+        assert_eq!(imported.item.name, "My App");
+        assert_eq!(imported.item.exe_path, r"C:\apps\myapp\myapp.exe");
+        assert_eq!(
+            imported.item.args.as_deref(),
+            Some(r#"--port 8080 --tag "a b""#)
+        );
+        assert_eq!(imported.item.kill_wait_ms, 15_000);
+        assert_eq!(imported.item.restart_delay_ms, 10_000);
+        assert_eq!(imported.notes.len(), 1);
+        assert!(winsw_item("<service><id>x</id></service>", "").is_err());
+    }
+
+    #[test]
+    fn converts_nssm_parameters() {
+        let values: HashMap<String, String> = [
+            ("Application", r"C:\tools\agent.exe"),
+            ("AppParameters", "-c agent.yml"),
+            ("AppDirectory", r"C:\tools\"),
+            ("AppRestartDelay", "2000"),
+            ("AppStopMethodConsole", "3000"),
+            ("AppStopMethodWindow", "8000"),
+            ("AppExit", "Exit"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let imported = nssm_item("agent", Some("Agent"), &values).unwrap();
+        assert_eq!(imported.item.name, "Agent");
+        assert_eq!(imported.item.args.as_deref(), Some("-c agent.yml"));
+        assert_eq!(imported.item.restart_delay_ms, 2000);
+        assert_eq!(imported.item.kill_wait_ms, 8000);
+        assert_eq!(imported.notes.len(), 1);
+    }
+}
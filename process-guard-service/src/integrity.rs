@@ -0,0 +1,89 @@
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, Read};
+
+const READ_CHUNK_BYTES: usize = 64 * 1024;
+
+/// 计算文件的 SHA-256，返回小写十六进制字符串
+pub fn sha256_file(path: &str) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; READ_CHUNK_BYTES];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// 计算数据的 SHA-256，返回小写十六进制字符串
+pub fn sha256_hex(data: &[u8]) -> String {
+    to_hex(&Sha256::digest(data))
+}
+
+fn to_hex(digest: &[u8]) -> String {
+    let mut hex = String::with_capacity(64);
+    for byte in digest {
+        let _ = write!(hex, "{:02x}", byte);
+    }
+    hex
+}
+
+/// 统一配置中的哈希写法：去掉空白与可选的 "sha256:" 前缀，转为小写
+fn normalize_hash(hash: &str) -> String {
+    let hash = hash.trim();
+    let hash = hash
+        .get(..7)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("sha256:"))
+        .map_or(hash, |_| &hash[7..]);
+    hash.chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+/// 校验文件哈希是否与配置中固定的值一致
+pub fn check_file_hash(path: &str, expected: &str) -> Result<(), String> {
+    let expected = normalize_hash(expected);
+    let actual = sha256_file(path).map_err(|e| format!("Failed to hash {}: {}", path, e))?;
+
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(format!(
+            "SHA-256 mismatch for {}: expected {}, got {}",
+            path, expected, actual
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_file_hash, normalize_hash, sha256_file};
+
+    #[test]
+    fn normalizes_prefix_case_and_whitespace() {
+        assert_eq!(normalize_hash(" SHA256:AB cd\n"), "abcd");
+        assert_eq!(normalize_hash("ABCD"), "abcd");
+    }
+
+    #[test]
+    fn hashes_file_contents() {
+        let path = std::env::temp_dir().join(format!("pg-integrity-{}.bin", std::process::id()));
+        std::fs::write(&path, b"abc").unwrap();
+        let path = path.to_str().unwrap();
+
+        let expected = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert_eq!(sha256_file(path).unwrap(), expected);
+        assert!(check_file_hash(path, &expected.to_uppercase()).is_ok());
+        assert!(check_file_hash(path, "00").is_err());
+
+        let _ = std::fs::remove_file(path);
+    }
+}
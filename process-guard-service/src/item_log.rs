@@ -0,0 +1,115 @@
+use crate::config::get_config_dir;
+use crate::models::ItemLogConfig;
+use log::warn;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use time::OffsetDateTime;
+
+const LOG_FILE_NAME: &str = "lifecycle.log";
+
+/// 目录名中的监控项 ID 只保留可用于文件名的字符
+fn dir_name(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// 轮转时的重命名顺序：先把最旧的编号向后移，最后把当前文件改为 .1
+fn rotation_plan(keep: usize) -> Vec<(String, String)> {
+    let name = |index: usize| match index {
+        0 => LOG_FILE_NAME.to_string(),
+        n => format!("lifecycle.{}.log", n),
+    };
+    (0..keep).rev().map(|i| (name(i), name(i + 1))).collect()
+}
+
+fn timestamp() -> String {
+    let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+    now.format(time::macros::format_description!(
+        "[year]-[month]-[day] [hour]:[minute]:[second].[subsecond digits:3]"
+    ))
+    .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// 每个监控项单独的生命周期日志（logs\<item-id>\lifecycle.log），只记录启动、退出、
+/// 重启、心跳丢失与恢复等事件，按大小轮转
+pub struct ItemLogs {
+    root: PathBuf,
+    config: Mutex<ItemLogConfig>,
+}
+
+impl ItemLogs {
+    pub fn new(config: ItemLogConfig) -> Self {
+        Self {
+            root: get_config_dir().join("logs"),
+            config: Mutex::new(config),
+        }
+    }
+
+    /// reload 后更新设置
+    pub fn configure(&self, config: ItemLogConfig) {
+        *self.config.lock().unwrap() = config;
+    }
+
+    pub fn write(&self, item_id: &str, event: &str, detail: &str) {
+        let config = self.config.lock().unwrap();
+        if !config.enabled {
+            return;
+        }
+        let dir = self.root.join(dir_name(item_id));
+        let line = if detail.is_empty() {
+            format!("{} {}\n", timestamp(), event)
+        } else {
+            format!("{} {} {}\n", timestamp(), event, detail)
+        };
+        if let Err(e) = append(&dir, &line, &config) {
+            warn!("Failed to write lifecycle log for {}: {}", item_id, e);
+        }
+    }
+}
+
+fn append(dir: &Path, line: &str, config: &ItemLogConfig) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(LOG_FILE_NAME);
+    let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    if size > 0 && size + line.len() as u64 > config.max_kb.max(1) * 1024 {
+        for (from, to) in rotation_plan(config.keep) {
+            let _ = fs::remove_file(dir.join(&to));
+            let _ = fs::rename(dir.join(&from), dir.join(&to));
+        }
+        if config.keep == 0 {
+            let _ = fs::remove_file(&path);
+        }
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)?
+        .write_all(line.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dir_name, rotation_plan};
+
+    #[test]
+    fn rotation_shifts_the_oldest_files_first() {
+        assert_eq!(
+            rotation_plan(2),
+            vec![
+                ("lifecycle.1.log".to_string(), "lifecycle.2.log".to_string()),
+                ("lifecycle.log".to_string(), "lifecycle.1.log".to_string()),
+            ]
+        );
+        assert!(rotation_plan(0).is_empty());
+        assert_eq!(dir_name(r"..\evil:id"), "___evil_id");
+    }
+}
@@ -0,0 +1,152 @@
+use crate::integrity::{sha256_file, sha256_hex};
+use crate::session0::to_wide_string;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+use windows::core::PCWSTR;
+use windows::Win32::Storage::FileSystem::{
+    GetFileVersionInfoSizeW, GetFileVersionInfoW, VerQueryValueW, VS_FIXEDFILEINFO,
+};
+
+/// 一次启动实际使用的程序与参数，写入事件库的 launches 表
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct LaunchInfo {
+    pub exe_path: String,
+    pub file_version: Option<String>,
+    pub product_version: Option<String>,
+    pub sha256: Option<String>,
+    pub command_line: Option<String>,
+    /// 环境变量按名称排序后的 SHA-256 前 16 位，只用于比较，不保存变量值
+    pub env_fingerprint: Option<String>,
+}
+
+impl LaunchInfo {
+    /// 与上一次启动相比发生变化的字段
+    pub fn changes_from(&self, previous: &LaunchInfo) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        if self.exe_path != previous.exe_path {
+            changed.push("exe_path");
+        }
+        if self.file_version != previous.file_version {
+            changed.push("file_version");
+        }
+        if self.sha256.is_some() && previous.sha256.is_some() && self.sha256 != previous.sha256 {
+            changed.push("sha256");
+        }
+        if self.command_line != previous.command_line {
+            changed.push("command_line");
+        }
+        if self.env_fingerprint != previous.env_fingerprint {
+            changed.push("environment");
+        }
+        changed
+    }
+}
+
+fn format_version(ms: u32, ls: u32) -> String {
+    format!("{}.{}.{}.{}", ms >> 16, ms & 0xFFFF, ls >> 16, ls & 0xFFFF)
+}
+
+/// 版本资源中的文件版本与产品版本；没有版本资源时返回 None
+fn file_version(exe_path: &str) -> Option<(String, String)> {
+    let path = to_wide_string(exe_path);
+    unsafe {
+        let size = GetFileVersionInfoSizeW(PCWSTR(path.as_ptr()), None);
+        if size == 0 {
+            return None;
+        }
+        let mut data = vec![0u8; size as usize];
+        GetFileVersionInfoW(PCWSTR(path.as_ptr()), 0, size, data.as_mut_ptr() as *mut _).ok()?;
+
+        let root = to_wide_string("\\");
+        let mut info: *mut std::ffi::c_void = std::ptr::null_mut();
+        let mut len = 0u32;
+        let found = VerQueryValueW(
+            data.as_ptr() as *const _,
+            PCWSTR(root.as_ptr()),
+            &mut info,
+            &mut len,
+        );
+        if !found.as_bool()
+            || info.is_null()
+            || (len as usize) < std::mem::size_of::<VS_FIXEDFILEINFO>()
+        {
+            return None;
+        }
+        let info = std::ptr::read_unaligned(info as *const VS_FIXEDFILEINFO);
+        Some((
+            format_version(info.dwFileVersionMS, info.dwFileVersionLS),
+            format_version(info.dwProductVersionMS, info.dwProductVersionLS),
+        ))
+    }
+}
+
+/// 小写路径 -> (文件大小, 修改时间, SHA-256)
+type HashCache = Mutex<HashMap<String, (u64, SystemTime, String)>>;
+
+/// 程序文件的 SHA-256；按大小与修改时间缓存，崩溃循环中反复启动时不重复计算
+fn cached_sha256(exe_path: &str) -> Option<String> {
+    static CACHE: OnceLock<HashCache> = OnceLock::new();
+    let metadata = std::fs::metadata(exe_path).ok()?;
+    let modified = metadata.modified().ok()?;
+    let key = exe_path.to_lowercase();
+    let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some((len, at, hash)) = cache.lock().unwrap().get(&key) {
+        if *len == metadata.len() && *at == modified {
+            return Some(hash.clone());
+        }
+    }
+    let hash = sha256_file(exe_path).ok()?;
+    cache
+        .lock()
+        .unwrap()
+        .insert(key, (metadata.len(), modified, hash.clone()));
+    Some(hash)
+}
+
+/// 环境变量指纹：名称不区分大小写排序，忽略以 '=' 开头的驱动器当前目录变量
+fn env_fingerprint(vars: &[String]) -> String {
+    let mut vars: Vec<&String> = vars.iter().filter(|var| !var.starts_with('=')).collect();
+    vars.sort_by_key(|var| var.to_lowercase());
+    let joined: Vec<&str> = vars.iter().map(|var| var.as_str()).collect();
+    sha256_hex(joined.join("\0").as_bytes())[..16].to_string()
+}
+
+/// 读取刚启动的进程实际使用的程序、命令行与环境
+pub fn collect(pid: u32, exe_path: &str) -> LaunchInfo {
+    let (file_version, product_version) = match file_version(exe_path) {
+        Some((file, product)) => (Some(file), Some(product)),
+        None => (None, None),
+    };
+    LaunchInfo {
+        exe_path: exe_path.to_string(),
+        file_version,
+        product_version,
+        sha256: cached_sha256(exe_path),
+        command_line: crate::session0::process_command_line(pid),
+        env_fingerprint: crate::session0::process_environment(pid)
+            .map(|vars| env_fingerprint(&vars)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{env_fingerprint, format_version};
+
+    #[test]
+    fn env_fingerprint_ignores_order_and_drive_variables() {
+        let a = ["Path=C:\\Windows".to_string(), "TEMP=C:\\Temp".to_string()];
+        let b = [
+            "TEMP=C:\\Temp".to_string(),
+            "=C:=C:\\apps".to_string(),
+            "Path=C:\\Windows".to_string(),
+        ];
+        assert_eq!(env_fingerprint(&a), env_fingerprint(&b));
+        assert_eq!(env_fingerprint(&a).len(), 16);
+
+        let c = ["Path=C:\\Windows".to_string(), "TEMP=D:\\Temp".to_string()];
+        assert_ne!(env_fingerprint(&a), env_fingerprint(&c));
+        assert_eq!(format_version(0x0002_0001, 0x0003_0004), "2.1.3.4");
+    }
+}
@@ -0,0 +1,101 @@
+//! 进程守护引擎。
+//!
+//! `process-guard-service.exe` 只是该库外的一层命令行与 Windows 服务封装；其他程序（例如自己的安装服务）
+//! 可以直接嵌入守护引擎，而不必通过命令行调用服务程序：
+//!
+//! ```no_run
+//! use process_guard_core::guardian::Guardian;
+//! use std::sync::{Arc, Mutex};
+//!
+//! let running = Arc::new(Mutex::new(true));
+//! let guardian = Arc::new(Guardian::new(running.clone(), None));
+//! let worker = {
+//!     let guardian = guardian.clone();
+//!     std::thread::spawn(move || guardian.run())
+//! };
+//!
+//! // ... 宿主程序退出时
+//! *running.lock().unwrap() = false;
+//! worker.join().unwrap();
+//! guardian.stop_all_processes(|_, _| {});
+//! ```
+//!
+//! 公开接口：
+//! - [`guardian`]：守护循环、运行时状态与配置变更
+//! - [`backend`]：进程操作抽象（ProcessBackend），可替换为 MockBackend 在测试中使用
+//! - [`models`]：配置与运行时数据结构
+//! - [`config`]：配置文件的读取、校验与保存
+//! - [`session0`]：在交互式用户会话中启动进程及进程查询
+//! - [`pipe_client`]：向运行中的服务发送管道请求
+//! - [`pipe_server`]：管道服务，可使用独立管道名运行
+//! - [`startup_gate`]：启动闸门（StartupGate），推迟守护引擎拉起进程并报告启动进度
+//! - [`output`]：被守护进程 stdout/stderr 的捕获与环形缓冲
+//!
+//! [`service`]、[`bootstrap`]、[`export`]、[`import`]、[`window`] 供服务程序本身使用。
+
+mod access;
+mod acl;
+mod appcontainer;
+pub mod backend;
+#[doc(hidden)]
+pub mod bootstrap;
+mod cleanup;
+mod clock;
+mod condition;
+pub mod config;
+mod diagnostics;
+mod escalation;
+mod event_log_check;
+mod exe_glob;
+#[doc(hidden)]
+pub mod export;
+mod firewall;
+mod forensics;
+mod guard_target;
+pub mod guardian;
+mod health_expr;
+mod healthz;
+mod http;
+mod http_server;
+#[doc(hidden)]
+pub mod import;
+mod integrity;
+mod item_log;
+mod launch_info;
+mod log_stream;
+mod logging;
+pub mod messages;
+mod metrics;
+pub mod models;
+mod network;
+mod notify;
+pub mod output;
+mod peer;
+pub mod pipe_client;
+pub mod pipe_server;
+mod pipe_stats;
+pub mod powershell;
+mod probe;
+mod proc_attributes;
+mod process_table;
+mod schedule;
+mod scheduler;
+#[doc(hidden)]
+pub mod service;
+mod service_deps;
+pub mod session0;
+mod shutdown_notice;
+mod signature;
+pub mod startup_gate;
+mod stop_plan;
+mod store;
+#[doc(hidden)]
+pub mod task_export;
+#[cfg(feature = "test-support")]
+pub mod test_support;
+mod toast;
+mod tray;
+mod udp_heartbeat;
+#[doc(hidden)]
+pub mod window;
+mod wmi_events;
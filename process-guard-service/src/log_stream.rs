@@ -0,0 +1,100 @@
+use crate::messages::MessageId;
+use crate::models::PipeResponse;
+use crate::output::{take_lines, OutputBuffer};
+use log::debug;
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use windows::Win32::Foundation::{CloseHandle, HANDLE};
+use windows::Win32::Storage::FileSystem::WriteFile;
+use windows::Win32::System::Pipes::{DisconnectNamedPipe, PeekNamedPipe};
+
+/// 同时进行的日志订阅上限；每个订阅占用一个管道实例，需为普通请求留出余量
+pub const MAX_STREAMS: usize = 4;
+const POLL_INTERVAL_MS: u64 = 500;
+
+/// stream_logs 的日志来源：服务日志或某个监控项的输出
+pub struct LogSource {
+    /// "service" 或监控项 ID
+    pub name: String,
+    pub buffer: Arc<OutputBuffer>,
+}
+
+/// 订阅连接的管道句柄，推送结束时断开并关闭
+pub struct StreamPipe(pub HANDLE);
+
+unsafe impl Send for StreamPipe {}
+
+impl Drop for StreamPipe {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = DisconnectNamedPipe(self.0);
+            let _ = CloseHandle(self.0);
+        }
+    }
+}
+
+impl StreamPipe {
+    fn write_line(&self, line: &str) -> bool {
+        let mut data = line.to_string();
+        data.push('\n');
+        let mut written = 0u32;
+        unsafe { WriteFile(self.0, Some(data.as_bytes()), Some(&mut written), None) }.is_ok()
+    }
+
+    /// 客户端关闭管道后 PeekNamedPipe 返回 ERROR_BROKEN_PIPE；没有新输出时靠它发现断开
+    fn client_connected(&self) -> bool {
+        unsafe { PeekNamedPipe(self.0, None, 0, None, None, None) }.is_ok()
+    }
+}
+
+/// 占用一个订阅名额，Drop 时释放
+pub struct StreamSlot(Arc<AtomicUsize>);
+
+impl StreamSlot {
+    /// 名额已满时返回 None
+    pub fn acquire(active: &Arc<AtomicUsize>) -> Option<Self> {
+        active
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+                (count < MAX_STREAMS).then_some(count + 1)
+            })
+            .ok()
+            .map(|_| Self(active.clone()))
+    }
+}
+
+impl Drop for StreamSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// 先写一行成功响应，之后每出现完整的一行就推送 {"source", "line"}，直到客户端断开或服务停止
+pub fn serve(pipe: StreamPipe, source: LogSource, running: Arc<Mutex<bool>>, _slot: StreamSlot) {
+    let ack = PipeResponse::success(MessageId::StreamStarted.with("source", source.name.as_str()));
+    if !pipe.write_line(&serde_json::to_string(&ack).unwrap_or_default()) {
+        return;
+    }
+    debug!("Log stream for {} started", source.name);
+
+    let mut position = source.buffer.end();
+    let mut pending = Vec::new();
+    'stream: while *running.lock().unwrap() {
+        let (bytes, end) = source.buffer.read_from(position);
+        position = end;
+        pending.extend(bytes);
+        let lines = take_lines(&mut pending);
+        if lines.is_empty() && !pipe.client_connected() {
+            break;
+        }
+        for line in lines {
+            let event = json!({ "source": source.name, "line": line });
+            if !pipe.write_line(&event.to_string()) {
+                break 'stream;
+            }
+        }
+        std::thread::sleep(Duration::from_millis(POLL_INTERVAL_MS));
+    }
+    debug!("Log stream for {} ended", source.name);
+}
@@ -0,0 +1,416 @@
+use crate::acl::{harden_dir, UsersAccess};
+use crate::models::{LogSink, LogSinkKind, SERVICE_NAME};
+use crate::session0::to_wide_string;
+use log::{info, LevelFilter, Log, Metadata, Record};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use time::format_description::well_known::Rfc3339;
+use time::macros::offset;
+use time::OffsetDateTime;
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::HANDLE;
+use windows::Win32::System::Diagnostics::Debug::OutputDebugStringW;
+use windows::Win32::System::EventLog::{
+    DeregisterEventSource, RegisterEventSourceW, ReportEventW, EVENTLOG_ERROR_TYPE,
+    EVENTLOG_INFORMATION_TYPE, EVENTLOG_WARNING_TYPE,
+};
+
+const MAX_LOG_SIZE: u64 = 300 * 1024 * 1024; // 300MB
+const LOG_DIR_NAME: &str = "logs";
+
+/// 获取日志目录路径
+fn get_log_dir() -> PathBuf {
+    let exe_path = env::current_exe().unwrap_or_else(|_| PathBuf::from("."));
+    let exe_dir = exe_path.parent().unwrap_or(std::path::Path::new("."));
+    exe_dir.join(LOG_DIR_NAME)
+}
+
+fn now() -> OffsetDateTime {
+    OffsetDateTime::now_utc().to_offset(offset!(+8))
+}
+
+/// 当天正在写入的文本日志文件
+pub fn current_log_file_path() -> PathBuf {
+    get_log_file_path(&now(), "log")
+}
+
+/// 根据日期获取日志文件路径
+fn get_log_file_path(date: &OffsetDateTime, extension: &str) -> PathBuf {
+    let log_dir = get_log_dir();
+    let date_str = date
+        .format(time::macros::format_description!("[year]-[month]-[day]"))
+        .unwrap_or_else(|_| "unknown".to_string());
+    log_dir.join(format!("process-guard-service-{}.{}", date_str, extension))
+}
+
+/// 计算日志目录总大小
+fn get_total_log_size(log_dir: &Path) -> u64 {
+    if !log_dir.exists() {
+        return 0;
+    }
+
+    let mut total_size = 0u64;
+    if let Ok(entries) = fs::read_dir(log_dir) {
+        for entry in entries.flatten() {
+            if let Ok(metadata) = entry.metadata() {
+                if metadata.is_file() {
+                    total_size += metadata.len();
+                }
+            }
+        }
+    }
+    total_size
+}
+
+/// 清理旧的日志文件，直到总大小低于限制
+fn cleanup_old_logs(log_dir: &Path, max_size: u64) {
+    let mut total_size = get_total_log_size(log_dir);
+
+    if total_size <= max_size {
+        return;
+    }
+
+    // 收集所有日志文件及其修改时间
+    let mut log_files: Vec<(PathBuf, std::time::SystemTime)> = Vec::new();
+    if let Ok(entries) = fs::read_dir(log_dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if let Ok(metadata) = entry.metadata() {
+                if metadata.is_file() {
+                    if let Ok(modified) = metadata.modified() {
+                        log_files.push((path, modified));
+                    }
+                }
+            }
+        }
+    }
+
+    // 按修改时间排序（最老的在前）
+    log_files.sort_by_key(|(_, modified)| *modified);
+
+    // 删除最老的文件直到低于限制
+    for (path, _) in log_files {
+        if total_size <= max_size {
+            break;
+        }
+
+        if let Ok(metadata) = fs::metadata(&path) {
+            let file_size = metadata.len();
+            if fs::remove_file(&path).is_ok() {
+                total_size -= file_size;
+                eprintln!("已删除旧日志文件: {:?}", path);
+            }
+        }
+    }
+}
+
+/// 按天分割的日志文件，text 与 JSON Lines 两种输出共用
+struct DailyFile {
+    extension: &'static str,
+    current_date: Mutex<OffsetDateTime>,
+    file: Mutex<Option<File>>,
+}
+
+impl DailyFile {
+    fn open(extension: &'static str) -> Self {
+        let now = now();
+        let log_path = get_log_file_path(&now, extension);
+
+        // 打开或创建日志文件
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)
+            .ok();
+
+        if file.is_some() {
+            eprintln!("日志文件已打开: {:?}", log_path);
+        } else {
+            eprintln!("无法打开日志文件: {:?}", log_path);
+        }
+
+        Self {
+            extension,
+            current_date: Mutex::new(now),
+            file: Mutex::new(file),
+        }
+    }
+
+    /// 检查是否需要轮转（跨天）
+    fn check_and_rotate(&self, now: &OffsetDateTime) {
+        let current_date = *self.current_date.lock().unwrap();
+        if now.date() == current_date.date() {
+            return;
+        }
+
+        let new_log_path = get_log_file_path(now, self.extension);
+        // 尝试打开新文件
+        if let Ok(new_file) = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&new_log_path)
+        {
+            *self.file.lock().unwrap() = Some(new_file);
+            *self.current_date.lock().unwrap() = *now;
+            eprintln!("日志已轮转至新文件: {:?}", new_log_path);
+        }
+    }
+
+    fn write(&self, now: &OffsetDateTime, line: &str) {
+        self.check_and_rotate(now);
+        if let Ok(mut file_guard) = self.file.lock() {
+            if let Some(ref mut file) = *file_guard {
+                let _ = file.write_all(line.as_bytes());
+                let _ = file.flush();
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file_guard) = self.file.lock() {
+            if let Some(ref mut file) = *file_guard {
+                let _ = file.flush();
+            }
+        }
+    }
+}
+
+/// 应用程序事件日志的事件源句柄，ReportEventW 可在多个线程中并发调用
+struct EventSource(HANDLE);
+
+unsafe impl Send for EventSource {}
+unsafe impl Sync for EventSource {}
+
+impl EventSource {
+    fn register() -> Option<Self> {
+        let name = to_wide_string(SERVICE_NAME);
+        match unsafe { RegisterEventSourceW(PCWSTR::null(), PCWSTR(name.as_ptr())) } {
+            Ok(handle) => Some(Self(handle)),
+            Err(e) => {
+                eprintln!("注册事件源失败: {}", e);
+                None
+            }
+        }
+    }
+
+    fn report(&self, level: log::Level, message: &str) {
+        let event_type = match level {
+            log::Level::Error => EVENTLOG_ERROR_TYPE,
+            log::Level::Warn => EVENTLOG_WARNING_TYPE,
+            _ => EVENTLOG_INFORMATION_TYPE,
+        };
+        let message = to_wide_string(message);
+        let strings = [PCWSTR(message.as_ptr())];
+        unsafe {
+            let _ = ReportEventW(self.0, event_type, 0, 0, None, 0, Some(&strings), None);
+        }
+    }
+}
+
+impl Drop for EventSource {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = DeregisterEventSource(self.0);
+        }
+    }
+}
+
+/// 直接写入应用程序事件日志，不受 log_sinks 配置影响
+pub fn report_event(level: log::Level, message: &str) {
+    if let Some(source) = EventSource::register() {
+        source.report(level, message);
+    }
+}
+
+enum Sink {
+    File(DailyFile),
+    JsonFile(DailyFile),
+    EventLog(EventSource),
+    DebugOutput,
+}
+
+/// 同时写入多个输出的日志实现；每个输出有自己的级别
+struct GuardLogger {
+    log_dir: PathBuf,
+    sinks: Vec<(LevelFilter, Sink)>,
+}
+
+/// 未配置 log_sinks 时的默认输出：与以往一致的 debug 级别文本日志
+fn default_sinks() -> Vec<LogSink> {
+    vec![LogSink {
+        kind: LogSinkKind::File,
+        level: "debug".to_string(),
+    }]
+}
+
+fn parse_level(level: &str) -> Option<LevelFilter> {
+    LevelFilter::from_str(level).ok()
+}
+
+/// 按 settings.log_sinks 初始化日志；在服务启动时调用一次
+pub fn init(config: &[LogSink]) {
+    let log_dir = get_log_dir();
+
+    // 创建日志目录
+    if let Err(e) = fs::create_dir_all(&log_dir) {
+        eprintln!("创建日志目录失败: {:?}", e);
+    }
+
+    // 日志允许普通用户只读，但不允许篡改
+    if let Err(e) = harden_dir(&log_dir, UsersAccess::Read) {
+        eprintln!("设置日志目录权限失败: {}", e);
+    }
+
+    // 清理旧日志
+    cleanup_old_logs(&log_dir, MAX_LOG_SIZE);
+
+    let config = if config.is_empty() {
+        default_sinks()
+    } else {
+        config.to_vec()
+    };
+    let mut invalid = Vec::new();
+    let sinks: Vec<(LevelFilter, Sink)> = config
+        .iter()
+        .filter_map(|sink| {
+            let level = parse_level(&sink.level).unwrap_or_else(|| {
+                invalid.push(sink.level.clone());
+                LevelFilter::Debug
+            });
+            let output = match sink.kind {
+                LogSinkKind::File => Sink::File(DailyFile::open("log")),
+                LogSinkKind::JsonFile => Sink::JsonFile(DailyFile::open("jsonl")),
+                LogSinkKind::EventLog => Sink::EventLog(EventSource::register()?),
+                LogSinkKind::DebugOutput => Sink::DebugOutput,
+            };
+            Some((level, output))
+        })
+        .collect();
+    let max_level = sinks
+        .iter()
+        .map(|(level, _)| *level)
+        .max()
+        .unwrap_or(LevelFilter::Off);
+
+    let logger = GuardLogger {
+        log_dir: log_dir.clone(),
+        sinks,
+    };
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(max_level);
+        info!("日志初始化完成, 日志目录: {:?}", log_dir);
+        for level in invalid {
+            log::warn!("无效的日志级别 {:?}, 已按 debug 处理", level);
+        }
+    } else {
+        eprintln!("日志初始化失败");
+    }
+}
+
+/// 格式化文本日志记录
+fn format_text(now: &OffsetDateTime, record: &Record) -> String {
+    let time_str = now
+        .format(time::macros::format_description!(
+            "[year]-[month]-[day] [hour]:[minute]:[second]"
+        ))
+        .unwrap_or_else(|_| "unknown".to_string());
+
+    format!("[{}] [{}] {}\n", time_str, record.level(), record.args())
+}
+
+/// JSON Lines 日志记录，时间为带时区的 RFC 3339
+fn format_json(now: &OffsetDateTime, record: &Record) -> String {
+    let line = serde_json::json!({
+        "ts": now.format(&Rfc3339).unwrap_or_default(),
+        "level": record.level().as_str(),
+        "target": record.target(),
+        "message": record.args().to_string(),
+    });
+    format!("{}\n", line)
+}
+
+impl Log for GuardLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.sinks
+            .iter()
+            .any(|(level, _)| metadata.level() <= *level)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let now = now();
+        let text = format_text(&now, record);
+        let mut rotated = false;
+        for (level, sink) in &self.sinks {
+            if record.level() > *level {
+                continue;
+            }
+            match sink {
+                Sink::File(file) => {
+                    file.write(&now, &text);
+                    rotated = true;
+                }
+                Sink::JsonFile(file) => {
+                    file.write(&now, &format_json(&now, record));
+                    rotated = true;
+                }
+                Sink::EventLog(source) => source.report(record.level(), &record.args().to_string()),
+                Sink::DebugOutput => {
+                    let line = to_wide_string(&format!("{}: {}", SERVICE_NAME, text));
+                    unsafe { OutputDebugStringW(PCWSTR(line.as_ptr())) };
+                }
+            }
+        }
+        // 检查容量限制
+        if rotated {
+            cleanup_old_logs(&self.log_dir, MAX_LOG_SIZE);
+        }
+
+        // 供 stream_logs 订阅者实时读取
+        crate::output::service_log().push(text.as_bytes());
+
+        // 同时输出到控制台（用于调试）
+        eprintln!("{}", text.trim());
+    }
+
+    fn flush(&self) {
+        for (_, sink) in &self.sinks {
+            if let Sink::File(file) | Sink::JsonFile(file) = sink {
+                file.flush();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_json, parse_level};
+    use log::{Level, LevelFilter, Record};
+    use time::macros::datetime;
+
+    #[test]
+    fn json_lines_carry_level_target_and_message() {
+        let now = datetime!(2024-05-01 08:30:00 +8);
+        let line = format_json(
+            &now,
+            &Record::builder()
+                .level(Level::Warn)
+                .target("process_guard_core::guardian")
+                .args(format_args!("Process {} exited", "App"))
+                .build(),
+        );
+        let value: serde_json::Value = serde_json::from_str(line.trim_end()).unwrap();
+        assert_eq!(value["ts"], "2024-05-01T08:30:00+08:00");
+        assert_eq!(value["level"], "WARN");
+        assert_eq!(value["message"], "Process App exited");
+        assert_eq!(parse_level("Info"), Some(LevelFilter::Info));
+        assert_eq!(parse_level("verbose"), None);
+    }
+}
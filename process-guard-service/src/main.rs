@@ -1,23 +1,169 @@
-mod config;
-mod guardian;
-mod models;
-mod pipe_server;
-mod service;
-mod session0;
-
+use process_guard_core::bootstrap::{self, InitOptions};
+use process_guard_core::models::{PipeRequest, PIPE_NAME};
+use process_guard_core::{export, import, pipe_client, powershell, service, task_export, window};
 use std::env;
 
+/// 配合 --user 使用的远程账户密码，避免出现在命令行历史中
+const PASSWORD_ENV: &str = "PROCESSGUARD_PASSWORD";
+
 fn print_usage() {
     println!("Process Guard Service - Windows Process Guardian");
     println!();
     println!("Usage:");
     println!("  process-guard-service.exe              Run as Windows service");
+    println!(
+        "  process-guard-service.exe --init <folder|exe>... [--yes] [--heartbeat] [--install]"
+    );
+    println!("                                         Generate config.json from the programs found, optionally installing the service");
     println!("  process-guard-service.exe --install    Install the service");
     println!("  process-guard-service.exe --uninstall  Uninstall the service");
-    println!("  process-guard-service.exe --start      Start the service");
+    println!("  process-guard-service.exe --start [--safe-mode]  Start the service; --safe-mode starts no items and only answers queries");
     println!("  process-guard-service.exe --stop       Stop the service");
     println!("  process-guard-service.exe --status     Check service status");
+    println!("  process-guard-service.exe --dump-status <file>  Write a diagnostic snapshot to a JSON file");
+    println!("  process-guard-service.exe --reload     Apply config.json changes without restarting the service");
+    println!("  process-guard-service.exe --import-nssm <service>  Add an item from an NSSM service definition");
+    println!(
+        "  process-guard-service.exe --import-winsw <xml>     Add an item from a WinSW XML file"
+    );
+    println!("  process-guard-service.exe --export-tasks <dir>  Write at-logon Task Scheduler fallbacks for each item");
+    println!("  process-guard-service.exe --export-powershell <dir>  Write the ProcessGuard PowerShell module (Get-GuardedProcess, ...)");
+    println!("  process-guard-service.exe --list       List items and their state");
+    println!("  process-guard-service.exe --restart <item>  Restart an item by id or name");
+    println!("  process-guard-service.exe --tail [item]     Follow the service log, or an item's captured output");
+    println!("  process-guard-service.exe --pause [minutes] Pause all monitoring actions, optionally resuming automatically");
+    println!("  process-guard-service.exe --resume     Resume monitoring after --pause");
     println!("  process-guard-service.exe --help       Show this help message");
+    println!();
+    println!("--list, --restart, --reload, --pause, --resume and --tail accept --host HOST[,HOST...] to manage remote guardians,");
+    println!(
+        "and --user DOMAIN\\name with the password in {} to connect as another account.",
+        PASSWORD_ENV
+    );
+}
+
+/// 命令行中 `--name value` 形式的选项
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+/// --host 指定的主机（逗号分隔），未指定时为本机；同时指定 --user 时先以该账户连接各主机
+fn target_hosts(args: &[String]) -> Result<Vec<Option<String>>, String> {
+    let Some(hosts) = option_value(args, "--host") else {
+        return Ok(vec![None]);
+    };
+    let hosts: Vec<String> = hosts
+        .split(',')
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .collect();
+    if let Some(user) = option_value(args, "--user") {
+        let password = env::var(PASSWORD_ENV)
+            .map_err(|_| format!("set {} to the password for {}", PASSWORD_ENV, user))?;
+        for host in &hosts {
+            pipe_client::connect_host(host, user, &password)?;
+        }
+    }
+    Ok(hosts.into_iter().map(Some).collect())
+}
+
+/// 对每个目标主机执行一次操作，输出以主机名分组
+fn for_each_host(args: &[String], action: impl Fn(Option<&str>) -> Result<(), String>) {
+    let hosts = match target_hosts(args) {
+        Ok(hosts) => hosts,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    for host in &hosts {
+        if hosts.len() > 1 || host.is_some() {
+            println!("[{}]", host.as_deref().unwrap_or("local"));
+        }
+        if let Err(e) = action(host.as_deref()) {
+            eprintln!("  {}", e);
+        }
+    }
+}
+
+fn status_items(host: Option<&str>) -> Result<Vec<serde_json::Value>, String> {
+    let response = pipe_client::send_to(host, &PipeRequest::Status)?;
+    Ok(response
+        .data
+        .and_then(|data| {
+            data.get("items")
+                .and_then(|items| items.as_array().cloned())
+        })
+        .unwrap_or_default())
+}
+
+fn print_items(host: Option<&str>) -> Result<(), String> {
+    for item in status_items(host)? {
+        let field = |name: &str| item.get(name).cloned().unwrap_or_default();
+        let context = field("context");
+        let state = if field("is_alive").as_bool() == Some(true) {
+            match (context["session_id"].as_u64(), context["user"].as_str()) {
+                (Some(session), Some(user)) => format!(
+                    "running pid={} session={} user={}",
+                    field("process_id"),
+                    session,
+                    user
+                ),
+                _ => format!("running pid={}", field("process_id")),
+            }
+        } else if field("enabled").as_bool() == Some(true) {
+            "down".to_string()
+        } else {
+            "disabled".to_string()
+        };
+        println!(
+            "  {} ({})  {}  restarts={}",
+            field("name").as_str().unwrap_or_default(),
+            field("id").as_str().unwrap_or_default(),
+            state,
+            field("restart_count")
+        );
+    }
+    Ok(())
+}
+
+/// 按 id 或名称（不区分大小写）查找监控项 ID
+fn find_item_id(host: Option<&str>, target: &str) -> Result<String, String> {
+    status_items(host)?
+        .iter()
+        .find(|item| {
+            item.get("id").and_then(|v| v.as_str()) == Some(target)
+                || item
+                    .get("name")
+                    .and_then(|v| v.as_str())
+                    .is_some_and(|name| name.eq_ignore_ascii_case(target))
+        })
+        .and_then(|item| item.get("id").and_then(|v| v.as_str()).map(str::to_string))
+        .ok_or_else(|| format!("no item {}", target))
+}
+
+/// 请求服务终止监控项进程并重新拉起它
+fn restart_item(host: Option<&str>, target: &str) -> Result<(), String> {
+    let id = find_item_id(host, target)?;
+    let response = pipe_client::send_to(host, &PipeRequest::Kill { id })?;
+    println!("  {}", response.message.unwrap_or_default());
+    Ok(())
+}
+
+/// 持续输出服务日志（未指定监控项时）或监控项捕获的输出，直到服务断开；只使用第一个 --host
+fn tail_logs(args: &[String], target: Option<&str>) -> Result<(), String> {
+    let host = target_hosts(args)?.into_iter().next().flatten();
+    let id = target
+        .map(|target| find_item_id(host.as_deref(), target))
+        .transpose()?;
+    let request = PipeRequest::StreamLogs { id };
+    pipe_client::stream_on(host.as_deref(), PIPE_NAME, &request, |event| {
+        println!("{}", event["line"].as_str().unwrap_or_default());
+        true
+    })
 }
 
 fn main() {
@@ -25,42 +171,195 @@ fn main() {
 
     if args.len() > 1 {
         match args[1].as_str() {
+            "--init" => {
+                let sources: Vec<std::path::PathBuf> = args[2..]
+                    .iter()
+                    .filter(|a| !a.starts_with("--"))
+                    .map(std::path::PathBuf::from)
+                    .collect();
+                if sources.is_empty() {
+                    eprintln!("Usage: process-guard-service.exe --init <folder|exe>... [--yes] [--heartbeat] [--install]");
+                } else {
+                    let options = InitOptions {
+                        sources,
+                        assume_yes: args.iter().any(|a| a == "--yes"),
+                        require_heartbeat: args.iter().any(|a| a == "--heartbeat"),
+                        install: args.iter().any(|a| a == "--install"),
+                    };
+                    if let Err(e) = bootstrap::run(&options) {
+                        eprintln!("Failed to initialize: {}", e);
+                    }
+                }
+            }
             "--install" => {
                 let exe_path = env::current_exe()
                     .map(|p| p.to_string_lossy().to_string())
                     .unwrap_or_else(|_| "process-guard-service.exe".to_string());
-                
+
                 match service::install_service(&exe_path) {
                     Ok(_) => println!("Service installed successfully"),
                     Err(e) => eprintln!("Failed to install service: {}", e),
                 }
             }
-            "--uninstall" => {
-                match service::uninstall_service() {
-                    Ok(_) => println!("Service uninstalled successfully"),
-                    Err(e) => eprintln!("Failed to uninstall service: {}", e),
-                }
-            }
+            "--uninstall" => match service::uninstall_service() {
+                Ok(_) => println!("Service uninstalled successfully"),
+                Err(e) => eprintln!("Failed to uninstall service: {}", e),
+            },
             "--start" => {
-                match service::start_service() {
+                let safe_mode = args.iter().any(|a| a == service::SAFE_MODE_ARG);
+                match service::start_service(safe_mode) {
+                    Ok(_) if safe_mode => println!(
+                        "Service started in safe mode; send resume_all (--resume) to start items"
+                    ),
                     Ok(_) => println!("Service started successfully"),
                     Err(e) => eprintln!("Failed to start service: {}", e),
                 }
             }
-            "--stop" => {
-                match service::stop_service() {
-                    Ok(_) => println!("Service stopped successfully"),
-                    Err(e) => eprintln!("Failed to stop service: {}", e),
-                }
-            }
+            "--stop" => match service::stop_service() {
+                Ok(_) => println!("Service stopped successfully"),
+                Err(e) => eprintln!("Failed to stop service: {}", e),
+            },
             "--status" => {
                 let installed = service::is_service_installed();
                 let running = service::is_service_running();
-                
+
                 println!("Service Status:");
                 println!("  Installed: {}", if installed { "Yes" } else { "No" });
                 println!("  Running: {}", if running { "Yes" } else { "No" });
             }
+            "--reload" => for_each_host(&args, |host| {
+                let response = pipe_client::send_to(host, &PipeRequest::Reload)
+                    .map_err(|e| format!("Failed to reload config: {}", e))?;
+                println!("{}", response.message.unwrap_or_default());
+                if let Some(data) = response.data {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&data).unwrap_or_default()
+                    );
+                }
+                Ok(())
+            }),
+            "--list" => for_each_host(&args, print_items),
+            "--restart" => match args.get(2).filter(|a| !a.starts_with("--")) {
+                Some(target) => for_each_host(&args, |host| restart_item(host, target)),
+                None => eprintln!(
+                    "Usage: process-guard-service.exe --restart <item> [--host HOST[,HOST...]]"
+                ),
+            },
+            "--pause" => {
+                let minutes = args.get(2).filter(|a| !a.starts_with("--"));
+                match minutes.map(|m| m.parse::<u32>()).transpose() {
+                    Ok(minutes) => for_each_host(&args, |host| {
+                        let response = pipe_client::send_to(host, &PipeRequest::PauseAll { minutes })?;
+                        println!("  {}", response.message.unwrap_or_default());
+                        Ok(())
+                    }),
+                    Err(_) => eprintln!("Usage: process-guard-service.exe --pause [minutes] [--host HOST[,HOST...]]"),
+                }
+            }
+            "--resume" => for_each_host(&args, |host| {
+                let response = pipe_client::send_to(host, &PipeRequest::ResumeAll)?;
+                println!("  {}", response.message.unwrap_or_default());
+                Ok(())
+            }),
+            "--tail" => {
+                let target = args.get(2).filter(|a| !a.starts_with("--"));
+                if let Err(e) = tail_logs(&args, target.map(String::as_str)) {
+                    eprintln!("Failed to stream logs: {}", e);
+                }
+            }
+            "--dump-status" => match args.get(2) {
+                Some(path) => match export::dump_status(std::path::Path::new(path)) {
+                    Ok(()) => println!("Diagnostic snapshot written to {}", path),
+                    Err(e) => eprintln!("Failed to export status: {}", e),
+                },
+                None => eprintln!("Usage: process-guard-service.exe --dump-status <file>"),
+            },
+            "--import-nssm" | "--import-winsw" => match args.get(2) {
+                Some(source) => {
+                    let imported = if args[1] == "--import-nssm" {
+                        import::import_nssm(source)
+                    } else {
+                        import::import_winsw(std::path::Path::new(source))
+                    };
+                    // 服务运行时直接改写 config.json 会被服务下次保存覆盖，改为经管道添加
+                    let running = service::is_service_running();
+                    let added = imported.and_then(|imported| {
+                        if running {
+                            import::add_to_service(imported)
+                        } else {
+                            import::add_to_config(imported)
+                        }
+                    });
+                    match added {
+                        Ok(imported) => {
+                            println!(
+                                "Added {} ({}) to {}",
+                                imported.item.name,
+                                imported.item.id,
+                                if running {
+                                    "the running service"
+                                } else {
+                                    "config.json"
+                                }
+                            );
+                            for note in &imported.notes {
+                                println!("  note: {}", note);
+                            }
+                            if !running {
+                                println!("The service is not running; the item is applied when it starts (run --reload if it was started meanwhile)");
+                            }
+                        }
+                        Err(e) => eprintln!("Failed to import {}: {}", source, e),
+                    }
+                }
+                None => eprintln!("Usage: process-guard-service.exe {} <source>", args[1]),
+            },
+            "--export-tasks" => match args.get(2) {
+                Some(dir) => match task_export::export_tasks(std::path::Path::new(dir)) {
+                    Ok((files, notes)) => {
+                        for note in &notes {
+                            println!("note: {}", note);
+                        }
+                        println!(
+                            "Wrote {} task definitions. Register them with:",
+                            files.len()
+                        );
+                        for file in &files {
+                            let name = file.file_stem().unwrap_or_default().to_string_lossy();
+                            println!(
+                                "  schtasks /Create /TN \"{}\\{}\" /XML \"{}\" /F",
+                                task_export::TASK_FOLDER,
+                                name,
+                                file.display()
+                            );
+                        }
+                    }
+                    Err(e) => eprintln!("Failed to export tasks: {}", e),
+                },
+                None => eprintln!("Usage: process-guard-service.exe --export-tasks <dir>"),
+            },
+            "--export-powershell" => match args.get(2) {
+                Some(dir) => match powershell::export_module(std::path::Path::new(dir)) {
+                    Ok(module_dir) => {
+                        println!(
+                            "Wrote PowerShell module to {}. Load it with:",
+                            module_dir.display()
+                        );
+                        println!("  Import-Module \"{}\"", module_dir.display());
+                    }
+                    Err(e) => eprintln!("Failed to export PowerShell module: {}", e),
+                },
+                None => eprintln!("Usage: process-guard-service.exe --export-powershell <dir>"),
+            },
+            // 内部使用：由服务在用户会话中启动，用于窗口存在性检查
+            window::HELPER_ARG => {
+                std::process::exit(window::run_helper(&args[2..]) as i32);
+            }
+            // 内部使用：服务停止时请求用户会话中的窗口关闭
+            window::CLOSE_HELPER_ARG => {
+                std::process::exit(window::run_close_helper(&args[2..]) as i32);
+            }
             "--help" | "-h" | "/?" => {
                 print_usage();
             }
@@ -0,0 +1,282 @@
+use serde_json::{Map, Value};
+
+/// 管道响应消息目录。id 保持稳定，客户端按 id 与参数本地化；模板是默认的中文文本，
+/// 其中的 {名称} 由同名参数替换
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageId {
+    InvalidRequest,
+    PermissionDenied,
+    SafeMode,
+    HostNotAuthorized,
+    NoRoleGranted,
+    CallerUnknown,
+    TooManyStreams,
+    StreamNeedsConnection,
+    StreamStarted,
+    TargetRejected,
+    InvalidFirewall,
+    InvalidCleanup,
+    UnknownHealthChecks,
+    ItemNotFound,
+    ItemIdExists,
+    ItemIdEmpty,
+    DuplicateInstance,
+    ExePathMonitored,
+    InvalidOverrides,
+    SaveConfigFailed,
+    HeartbeatUpdated,
+    PrepareShutdown,
+    ShutdownAcknowledged,
+    NoPendingShutdown,
+    ItemAdded,
+    ItemUpdated,
+    ItemRemoved,
+    ItemStopped,
+    ItemStarted,
+    ItemPaused,
+    RestartScheduled,
+    ItemDisabled,
+    BulkSelectorEmpty,
+    BulkApplied,
+    ProcessSuspended,
+    ProcessResumed,
+    SuspendFailed,
+    ResumeFailed,
+    MemoryTrimmed,
+    TrimMemoryFailed,
+    DeferCleared,
+    Deferred,
+    DeferFailed,
+    PausedAllFor,
+    PausedAll,
+    PauseFailed,
+    ResumedAll,
+    NotPaused,
+    ItemList,
+    ServiceStatus,
+    ConfigReloaded,
+    ReloadFailed,
+    RecentOutput,
+    OutputUnavailable,
+    DiagnosticsPassed,
+    DiagnosticsFailed,
+    DiagnosticsNotRun,
+    PeerStatus,
+    StatusSnapshot,
+    EventStoreDisabled,
+    MetricsHistory,
+    MetricsHistoryFailed,
+    LaunchHistory,
+    LaunchHistoryFailed,
+    InvalidDays,
+    UptimeReport,
+    UptimeReportFailed,
+}
+
+impl MessageId {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageId::InvalidRequest => "invalid_request",
+            MessageId::PermissionDenied => "permission_denied",
+            MessageId::SafeMode => "safe_mode",
+            MessageId::HostNotAuthorized => "host_not_authorized",
+            MessageId::NoRoleGranted => "no_role_granted",
+            MessageId::CallerUnknown => "caller_unknown",
+            MessageId::TooManyStreams => "too_many_streams",
+            MessageId::StreamNeedsConnection => "stream_needs_connection",
+            MessageId::StreamStarted => "stream_started",
+            MessageId::TargetRejected => "target_rejected",
+            MessageId::InvalidFirewall => "invalid_firewall",
+            MessageId::InvalidCleanup => "invalid_cleanup",
+            MessageId::UnknownHealthChecks => "unknown_health_checks",
+            MessageId::ItemNotFound => "item_not_found",
+            MessageId::ItemIdExists => "item_id_exists",
+            MessageId::ItemIdEmpty => "item_id_empty",
+            MessageId::DuplicateInstance => "duplicate_instance",
+            MessageId::ExePathMonitored => "exe_path_monitored",
+            MessageId::InvalidOverrides => "invalid_overrides",
+            MessageId::SaveConfigFailed => "save_config_failed",
+            MessageId::HeartbeatUpdated => "heartbeat_updated",
+            MessageId::PrepareShutdown => "prepare_shutdown",
+            MessageId::ShutdownAcknowledged => "shutdown_acknowledged",
+            MessageId::NoPendingShutdown => "no_pending_shutdown",
+            MessageId::ItemAdded => "item_added",
+            MessageId::ItemUpdated => "item_updated",
+            MessageId::ItemRemoved => "item_removed",
+            MessageId::ItemStopped => "item_stopped",
+            MessageId::ItemStarted => "item_started",
+            MessageId::ItemPaused => "item_paused",
+            MessageId::RestartScheduled => "restart_scheduled",
+            MessageId::ItemDisabled => "item_disabled",
+            MessageId::BulkSelectorEmpty => "bulk_selector_empty",
+            MessageId::BulkApplied => "bulk_applied",
+            MessageId::ProcessSuspended => "process_suspended",
+            MessageId::ProcessResumed => "process_resumed",
+            MessageId::SuspendFailed => "suspend_failed",
+            MessageId::ResumeFailed => "resume_failed",
+            MessageId::MemoryTrimmed => "memory_trimmed",
+            MessageId::TrimMemoryFailed => "trim_memory_failed",
+            MessageId::DeferCleared => "defer_cleared",
+            MessageId::Deferred => "deferred",
+            MessageId::DeferFailed => "defer_failed",
+            MessageId::PausedAllFor => "paused_all_for",
+            MessageId::PausedAll => "paused_all",
+            MessageId::PauseFailed => "pause_failed",
+            MessageId::ResumedAll => "resumed_all",
+            MessageId::NotPaused => "not_paused",
+            MessageId::ItemList => "item_list",
+            MessageId::ServiceStatus => "service_status",
+            MessageId::ConfigReloaded => "config_reloaded",
+            MessageId::ReloadFailed => "reload_failed",
+            MessageId::RecentOutput => "recent_output",
+            MessageId::OutputUnavailable => "output_unavailable",
+            MessageId::DiagnosticsPassed => "diagnostics_passed",
+            MessageId::DiagnosticsFailed => "diagnostics_failed",
+            MessageId::DiagnosticsNotRun => "diagnostics_not_run",
+            MessageId::PeerStatus => "peer_status",
+            MessageId::StatusSnapshot => "status_snapshot",
+            MessageId::EventStoreDisabled => "event_store_disabled",
+            MessageId::MetricsHistory => "metrics_history",
+            MessageId::MetricsHistoryFailed => "metrics_history_failed",
+            MessageId::LaunchHistory => "launch_history",
+            MessageId::LaunchHistoryFailed => "launch_history_failed",
+            MessageId::InvalidDays => "invalid_days",
+            MessageId::UptimeReport => "uptime_report",
+            MessageId::UptimeReportFailed => "uptime_report_failed",
+        }
+    }
+
+    /// 默认文本模板
+    pub fn template(&self) -> &'static str {
+        match self {
+            MessageId::InvalidRequest => "JSON格式错误: {detail}",
+            MessageId::PermissionDenied => "权限不足: {request} 请求需要 {role} 角色",
+            MessageId::SafeMode => {
+                "服务处于安全模式, 不执行 {request} 请求; 发送 resume_all 退出安全模式"
+            }
+            MessageId::HostNotAuthorized => "拒绝访问: 主机未被授权远程管理",
+            MessageId::NoRoleGranted => "拒绝访问: 调用方未被授予任何角色",
+            MessageId::CallerUnknown => "拒绝访问: 无法识别调用方身份",
+            MessageId::TooManyStreams => "最多同时 {max} 个日志订阅",
+            MessageId::StreamNeedsConnection => "stream_logs 需要单独的连接",
+            MessageId::StreamStarted => "streaming {source}",
+            MessageId::TargetRejected => "{detail}",
+            MessageId::InvalidFirewall => "{detail}",
+            MessageId::InvalidCleanup => "{detail}",
+            MessageId::UnknownHealthChecks => "健康表达式包含未知检查: {checks}",
+            MessageId::ItemNotFound => "未找到监控项",
+            MessageId::ItemIdExists => "该ID的监控项已存在",
+            MessageId::ItemIdEmpty => "新监控项ID不能为空",
+            MessageId::DuplicateInstance => "相同路径与参数的监控项已存在",
+            MessageId::ExePathMonitored => "可执行文件路径已被监控",
+            MessageId::InvalidOverrides => "覆盖字段无效: {detail}",
+            MessageId::SaveConfigFailed => "保存配置失败: {detail}",
+            MessageId::HeartbeatUpdated => "心跳已更新",
+            MessageId::PrepareShutdown => "即将重启",
+            MessageId::ShutdownAcknowledged => "已确认",
+            MessageId::NoPendingShutdown => "没有待确认的退出通知",
+            MessageId::ItemAdded => "监控项已添加",
+            MessageId::ItemUpdated => "监控项已更新",
+            MessageId::ItemRemoved => "监控项已移除",
+            MessageId::ItemStopped => "监控项已停止",
+            MessageId::ItemStarted => "监控项已启动",
+            MessageId::ItemPaused => "监控项已暂停",
+            MessageId::RestartScheduled => "进程将被终止并由守护重新启动",
+            MessageId::ItemDisabled => "监控项未启用，无法重启",
+            MessageId::BulkSelectorEmpty => "bulk 请求需要至少指定 tag、group 或 name 之一",
+            MessageId::BulkApplied => "已对 {count} 个监控项执行 {action}（匹配 {matched} 个）",
+            MessageId::ProcessSuspended => "进程已挂起 (PID {pid})",
+            MessageId::ProcessResumed => "进程已恢复 (PID {pid})",
+            MessageId::SuspendFailed => "挂起失败: {detail}",
+            MessageId::ResumeFailed => "恢复失败: {detail}",
+            MessageId::MemoryTrimmed => "工作集已收缩 (PID {pid}): {before_kb} KB -> {after_kb} KB",
+            MessageId::TrimMemoryFailed => "收缩工作集失败: {detail}",
+            MessageId::DeferCleared => "已恢复守护",
+            MessageId::Deferred => "{minutes} 分钟内不会重启该监控项",
+            MessageId::DeferFailed => "暂缓失败: {detail}",
+            MessageId::PausedAllFor => "守护已暂停，{minutes} 分钟后自动恢复",
+            MessageId::PausedAll => "守护已暂停，需发送 resume_all 恢复",
+            MessageId::PauseFailed => "暂停失败: {detail}",
+            MessageId::ResumedAll => "守护已恢复",
+            MessageId::NotPaused => "守护未处于暂停状态",
+            MessageId::ItemList => "监控项列表",
+            MessageId::ServiceStatus => "服务状态",
+            MessageId::ConfigReloaded => "配置已重新加载",
+            MessageId::ReloadFailed => "重新加载配置失败: {detail}",
+            MessageId::RecentOutput => "最近输出",
+            MessageId::OutputUnavailable => "{detail}",
+            MessageId::DiagnosticsPassed => "自检通过",
+            MessageId::DiagnosticsFailed => "自检发现问题",
+            MessageId::DiagnosticsNotRun => "自检尚未运行",
+            MessageId::PeerStatus => "节点状态",
+            MessageId::StatusSnapshot => "诊断快照",
+            MessageId::EventStoreDisabled => "事件库未启用",
+            MessageId::MetricsHistory => "资源使用历史",
+            MessageId::MetricsHistoryFailed => "查询资源使用历史失败: {detail}",
+            MessageId::LaunchHistory => "启动记录",
+            MessageId::LaunchHistoryFailed => "查询启动记录失败: {detail}",
+            MessageId::InvalidDays => "days 必须大于 0",
+            MessageId::UptimeReport => "在线率报告",
+            MessageId::UptimeReportFailed => "查询在线率报告失败: {detail}",
+        }
+    }
+
+    /// 附带一个参数
+    pub fn with(self, key: &str, value: impl Into<Value>) -> Message {
+        Message::from(self).with(key, value)
+    }
+}
+
+/// 带参数的目录消息
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub id: MessageId,
+    pub params: Map<String, Value>,
+}
+
+impl Message {
+    pub fn with(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.params.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// 用参数填充模板得到默认文本；缺少的参数保留占位符
+    pub fn text(&self) -> String {
+        let mut text = self.id.template().to_string();
+        for (key, value) in &self.params {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            text = text.replace(&format!("{{{}}}", key), &value);
+        }
+        text
+    }
+}
+
+impl From<MessageId> for Message {
+    fn from(id: MessageId) -> Self {
+        Self {
+            id,
+            params: Map::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MessageId;
+
+    #[test]
+    fn text_fills_template_parameters() {
+        let message = MessageId::PermissionDenied
+            .with("request", "remove")
+            .with("role", "admin");
+        assert_eq!(message.text(), "权限不足: remove 请求需要 admin 角色");
+
+        let message = MessageId::ProcessSuspended
+            .with("pid", 42)
+            .with("id", "api");
+        assert_eq!(message.text(), "进程已挂起 (PID 42)");
+    }
+}
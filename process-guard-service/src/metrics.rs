@@ -0,0 +1,408 @@
+use crate::guardian::Guardian;
+use std::fmt::Write as _;
+
+/// 单个监控项的指标快照
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemMetrics {
+    pub id: String,
+    pub name: String,
+    pub up: bool,
+    pub enabled: bool,
+    pub restart_total: u32,
+    /// 按原因统计的重启次数（RestartReason 的 snake_case 名称）
+    pub restarts_by_reason: Vec<(&'static str, u32)>,
+    pub heartbeat_age_seconds: f64,
+    pub memory_bytes: Option<u64>,
+    pub cpu_percent: Option<f64>,
+    /// 经管道收到的心跳数，以及最近 60 秒的每秒心跳数
+    pub pipe_heartbeats_total: u64,
+    pub pipe_heartbeats_per_second: f64,
+}
+
+/// 服务级指标快照
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceMetrics {
+    pub uptime_seconds: f64,
+    pub check_cycles_total: u64,
+    pub items_total: usize,
+    pub pipe: PipeMetrics,
+}
+
+/// 管道服务指标，速率与耗时按最近 60 秒计算
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PipeMetrics {
+    pub connections_total: u64,
+    pub connections_per_second: f64,
+    pub parse_failures_total: u64,
+    pub client_errors_total: u64,
+    pub latency_avg_seconds: f64,
+    pub latency_max_seconds: f64,
+}
+
+pub fn collect_metrics(guardian: &Guardian) -> (ServiceMetrics, Vec<ItemMetrics>) {
+    let table = guardian.process_table();
+    let pipe = guardian.pipe_stats().snapshot();
+    let processes = guardian.get_processes();
+    let processes = processes.lock().unwrap();
+
+    let mut items: Vec<ItemMetrics> = processes
+        .iter()
+        .map(|(id, p)| ItemMetrics {
+            id: id.clone(),
+            name: p.item.name.clone(),
+            up: table.is_tracked_alive(p),
+            enabled: p.item.enabled,
+            restart_total: p.restart_count,
+            restarts_by_reason: p
+                .restarts_by_reason
+                .iter()
+                .map(|(reason, count)| (reason.as_str(), *count))
+                .collect(),
+            heartbeat_age_seconds: p.last_heartbeat.elapsed().as_secs_f64(),
+            memory_bytes: p.resources.map(|r| r.working_set_bytes),
+            cpu_percent: p.cpu_percent,
+            pipe_heartbeats_total: pipe.heartbeats.get(id).map_or(0, |h| h.total),
+            pipe_heartbeats_per_second: pipe.heartbeats.get(id).map_or(0.0, |h| h.per_second),
+        })
+        .collect();
+    items.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let service = ServiceMetrics {
+        uptime_seconds: guardian.uptime().as_secs_f64(),
+        check_cycles_total: guardian.check_cycles(),
+        items_total: items.len(),
+        pipe: PipeMetrics {
+            connections_total: pipe.connections_total,
+            connections_per_second: pipe.connections_per_second,
+            parse_failures_total: pipe.parse_failures_total,
+            client_errors_total: pipe.client_errors_total,
+            latency_avg_seconds: pipe.latency_avg_ms / 1000.0,
+            latency_max_seconds: pipe.latency_max_ms / 1000.0,
+        },
+    };
+
+    (service, items)
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn write_family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// 按 Prometheus 文本格式（0.0.4）输出指标
+pub fn render_prometheus(service: &ServiceMetrics, items: &[ItemMetrics]) -> String {
+    let mut out = String::new();
+
+    write_family(
+        &mut out,
+        "processguard_up",
+        "gauge",
+        "Whether the guardian service is running.",
+    );
+    let _ = writeln!(out, "processguard_up 1");
+    write_family(
+        &mut out,
+        "processguard_uptime_seconds",
+        "gauge",
+        "Seconds since the guardian was initialized.",
+    );
+    let _ = writeln!(
+        out,
+        "processguard_uptime_seconds {:.3}",
+        service.uptime_seconds
+    );
+    write_family(
+        &mut out,
+        "processguard_check_cycles_total",
+        "counter",
+        "Number of completed guardian check cycles.",
+    );
+    let _ = writeln!(
+        out,
+        "processguard_check_cycles_total {}",
+        service.check_cycles_total
+    );
+    write_family(
+        &mut out,
+        "processguard_items",
+        "gauge",
+        "Number of configured monitor items.",
+    );
+    let _ = writeln!(out, "processguard_items {}", service.items_total);
+
+    let pipe = &service.pipe;
+    write_family(
+        &mut out,
+        "processguard_pipe_connections_total",
+        "counter",
+        "Number of accepted pipe connections.",
+    );
+    let _ = writeln!(
+        out,
+        "processguard_pipe_connections_total {}",
+        pipe.connections_total
+    );
+    write_family(
+        &mut out,
+        "processguard_pipe_connections_per_second",
+        "gauge",
+        "Pipe connections per second over the last minute.",
+    );
+    let _ = writeln!(
+        out,
+        "processguard_pipe_connections_per_second {:.3}",
+        pipe.connections_per_second
+    );
+    write_family(
+        &mut out,
+        "processguard_pipe_parse_failures_total",
+        "counter",
+        "Number of pipe requests that were not valid request JSON.",
+    );
+    let _ = writeln!(
+        out,
+        "processguard_pipe_parse_failures_total {}",
+        pipe.parse_failures_total
+    );
+    write_family(
+        &mut out,
+        "processguard_pipe_client_errors_total",
+        "counter",
+        "Number of failed pipe reads, writes and error responses.",
+    );
+    let _ = writeln!(
+        out,
+        "processguard_pipe_client_errors_total {}",
+        pipe.client_errors_total
+    );
+    write_family(
+        &mut out,
+        "processguard_pipe_request_latency_seconds",
+        "gauge",
+        "Pipe request handling time over the last minute.",
+    );
+    let _ = writeln!(
+        out,
+        "processguard_pipe_request_latency_seconds{{stat=\"avg\"}} {:.6}",
+        pipe.latency_avg_seconds
+    );
+    let _ = writeln!(
+        out,
+        "processguard_pipe_request_latency_seconds{{stat=\"max\"}} {:.6}",
+        pipe.latency_max_seconds
+    );
+
+    let labels = |item: &ItemMetrics| {
+        format!(
+            "id=\"{}\",name=\"{}\"",
+            escape_label_value(&item.id),
+            escape_label_value(&item.name)
+        )
+    };
+
+    write_family(
+        &mut out,
+        "processguard_item_up",
+        "gauge",
+        "Whether the guarded process is alive.",
+    );
+    for item in items {
+        let _ = writeln!(
+            out,
+            "processguard_item_up{{{}}} {}",
+            labels(item),
+            item.up as u8
+        );
+    }
+
+    write_family(
+        &mut out,
+        "processguard_item_enabled",
+        "gauge",
+        "Whether monitoring is enabled for the item.",
+    );
+    for item in items {
+        let _ = writeln!(
+            out,
+            "processguard_item_enabled{{{}}} {}",
+            labels(item),
+            item.enabled as u8
+        );
+    }
+
+    write_family(
+        &mut out,
+        "processguard_item_restart_total",
+        "counter",
+        "Number of restarts performed by the guardian.",
+    );
+    for item in items {
+        let _ = writeln!(
+            out,
+            "processguard_item_restart_total{{{}}} {}",
+            labels(item),
+            item.restart_total
+        );
+    }
+
+    write_family(
+        &mut out,
+        "processguard_item_restarts_by_reason_total",
+        "counter",
+        "Number of restarts performed by the guardian, by restart reason.",
+    );
+    for item in items {
+        for (reason, count) in &item.restarts_by_reason {
+            let _ = writeln!(
+                out,
+                "processguard_item_restarts_by_reason_total{{{},reason=\"{}\"}} {}",
+                labels(item),
+                reason,
+                count
+            );
+        }
+    }
+
+    write_family(
+        &mut out,
+        "processguard_item_heartbeat_age_seconds",
+        "gauge",
+        "Seconds since the last heartbeat was received.",
+    );
+    for item in items {
+        let _ = writeln!(
+            out,
+            "processguard_item_heartbeat_age_seconds{{{}}} {:.3}",
+            labels(item),
+            item.heartbeat_age_seconds
+        );
+    }
+
+    write_family(
+        &mut out,
+        "processguard_item_pipe_heartbeats_total",
+        "counter",
+        "Number of heartbeats received over the pipe.",
+    );
+    for item in items {
+        let _ = writeln!(
+            out,
+            "processguard_item_pipe_heartbeats_total{{{}}} {}",
+            labels(item),
+            item.pipe_heartbeats_total
+        );
+    }
+
+    write_family(
+        &mut out,
+        "processguard_item_pipe_heartbeats_per_second",
+        "gauge",
+        "Pipe heartbeats per second over the last minute.",
+    );
+    for item in items {
+        let _ = writeln!(
+            out,
+            "processguard_item_pipe_heartbeats_per_second{{{}}} {:.3}",
+            labels(item),
+            item.pipe_heartbeats_per_second
+        );
+    }
+
+    write_family(
+        &mut out,
+        "processguard_item_memory_bytes",
+        "gauge",
+        "Working set of the guarded process in bytes.",
+    );
+    for item in items {
+        if let Some(bytes) = item.memory_bytes {
+            let _ = writeln!(
+                out,
+                "processguard_item_memory_bytes{{{}}} {}",
+                labels(item),
+                bytes
+            );
+        }
+    }
+
+    write_family(
+        &mut out,
+        "processguard_item_cpu_percent",
+        "gauge",
+        "CPU usage of the guarded process over the last check interval.",
+    );
+    for item in items {
+        if let Some(percent) = item.cpu_percent {
+            let _ = writeln!(
+                out,
+                "processguard_item_cpu_percent{{{}}} {:.2}",
+                labels(item),
+                percent
+            );
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_label_value, render_prometheus, ItemMetrics, PipeMetrics, ServiceMetrics};
+
+    fn sample_item() -> ItemMetrics {
+        ItemMetrics {
+            id: "worker-1".to_string(),
+            name: "Worker \"A\"".to_string(),
+            up: true,
+            enabled: true,
+            restart_total: 3,
+            restarts_by_reason: vec![("process_exited", 2), ("manual_restart", 1)],
+            heartbeat_age_seconds: 1.5,
+            memory_bytes: Some(4096),
+            cpu_percent: None,
+            pipe_heartbeats_total: 12,
+            pipe_heartbeats_per_second: 0.2,
+        }
+    }
+
+    #[test]
+    fn label_values_are_escaped() {
+        assert_eq!(escape_label_value(r#"C:\a"b"#), r#"C:\\a\"b"#);
+    }
+
+    #[test]
+    fn renders_item_series_and_skips_missing_samples() {
+        let service = ServiceMetrics {
+            uptime_seconds: 10.0,
+            check_cycles_total: 4,
+            items_total: 1,
+            pipe: PipeMetrics {
+                parse_failures_total: 2,
+                ..PipeMetrics::default()
+            },
+        };
+        let text = render_prometheus(&service, &[sample_item()]);
+
+        assert!(text.contains("processguard_check_cycles_total 4"));
+        assert!(text.contains(r#"processguard_item_up{id="worker-1",name="Worker \"A\""} 1"#));
+        assert!(text
+            .contains(r#"processguard_item_restart_total{id="worker-1",name="Worker \"A\""} 3"#));
+        assert!(text.contains(
+            r#"processguard_item_restarts_by_reason_total{id="worker-1",name="Worker \"A\"",reason="process_exited"} 2"#
+        ));
+        assert!(text
+            .contains(r#"processguard_item_memory_bytes{id="worker-1",name="Worker \"A\""} 4096"#));
+        assert!(!text.contains("processguard_item_cpu_percent{"));
+        assert!(text.contains("processguard_pipe_parse_failures_total 2"));
+        assert!(text.contains(
+            r#"processguard_item_pipe_heartbeats_total{id="worker-1",name="Worker \"A\""} 12"#
+        ));
+    }
+}
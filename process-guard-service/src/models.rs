@@ -1,25 +1,953 @@
+use crate::messages::Message;
+use crate::output::OutputBuffer;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
 use std::ops::BitOr;
-use std::time::Instant;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
 use uuid::Uuid;
 
+/// 只保存在 config.json 中、不对外输出的监控项字段
+const SECRET_ITEM_FIELDS: [&str; 1] = ["heartbeat_token"];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MonitorItem {
     pub id: String,
+    #[serde(alias = "exePath")]
     pub exe_path: String,
     pub args: Option<String>,
     pub name: String,
     pub minimize: bool,
+    #[serde(alias = "noWindow")]
     pub no_window: bool,
     pub enabled: bool,
-    #[serde(default = "default_heartbeat_timeout")]
+    /// 引用 Config.templates 中的模板，未填写的字段取模板中的值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// 标签，用于 bulk 请求按标签批量操作
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// 分组名称，用于 bulk 请求按分组批量操作
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// keep：保持进程运行（默认）；forbid：禁止该进程运行，出现即终止
+    #[serde(default, skip_serializing_if = "ItemMode::is_keep")]
+    pub mode: ItemMode,
+    /// 服务启动时发现已在运行的同路径进程的处理方式
+    #[serde(default, skip_serializing_if = "OrphanPolicy::is_adopt")]
+    pub orphan_policy: OrphanPolicy,
+    /// 启动时发现同路径进程已在运行的处理方式，用于不能接管或终止手动启动的调试实例的程序
+    #[serde(default, skip_serializing_if = "ExistingInstance::is_adopt")]
+    pub existing_instance: ExistingInstance,
+    #[serde(default = "default_heartbeat_timeout", alias = "heartbeatTimeoutMs")]
     pub heartbeat_timeout_ms: u64,
+    /// 是否要求进程通过管道发送心跳；为 false 或超时为 0 时只检查进程是否存活
+    #[serde(default = "default_heartbeat_required", alias = "heartbeatRequired")]
+    pub heartbeat_required: bool,
+    /// 文件心跳：程序本来就会更新的文件（临时文件、PID 文件、自身日志），修改即视为一次心跳，
+    /// 用于无法改造为管道心跳的程序；与管道心跳可同时使用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heartbeat_file: Option<String>,
+    /// UDP 心跳令牌；设置后可向 settings.udp_heartbeat_port 发送 "<id>:<令牌>" 数据报作为心跳
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heartbeat_token: Option<String>,
+    /// 心跳超时的处理方式，与进程退出分开配置；不设置时首次超时即重启
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_heartbeat_missed: Option<HeartbeatPolicy>,
+    /// 重启后在用户会话中弹出气泡提示
+    #[serde(default)]
+    pub notify_user_on_restart: bool,
+    /// 启动前要求可执行文件具有有效的 Authenticode 签名
+    #[serde(default)]
+    pub require_signed: bool,
+    /// 期望的签名发布者（证书显示名称），设置后隐含 require_signed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_publisher: Option<String>,
+    /// 固定的可执行文件 SHA-256（十六进制），每次启动前重新校验
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_sha256: Option<String>,
+    /// 在 AppContainer 沙箱中启动，用于不受信任的第三方程序
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_container: Option<AppContainerConfig>,
+    /// 禁止进程创建子进程（进程缓解策略），用于不能启动任意程序的插件宿主
+    #[serde(default)]
+    pub block_child_processes: bool,
+    /// 启动到的桌面：default（WinSta0\Default）、secure（安全桌面，以 SYSTEM 身份启动）或已存在的自定义桌面名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub desktop: Option<String>,
+    /// 以 UIAccess 令牌启动，程序须在清单中声明 uiAccess、已签名并位于受保护目录
+    #[serde(default)]
+    pub ui_access: bool,
+    /// 目标会话锁屏期间不启动或重新拉起（部分 GUI 程序在锁定的桌面上启动会崩溃），解锁后再启动
+    #[serde(default)]
+    pub defer_while_locked: bool,
+    /// 启动到指定 NUMA 节点 / 处理器组，每次重启都重新应用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub placement: Option<PlacementConfig>,
+    /// 进程优先级类别，每次启动后设置；未设置时为 normal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority_class: Option<PriorityClass>,
+    /// 进程的 I/O 优先级，每次启动后设置；未设置时为系统默认（normal）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub io_priority: Option<IoPriority>,
+    /// 为该程序创建的 Windows 防火墙入站放行规则，启动时创建、删除监控项时移除
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub firewall: Option<FirewallRule>,
+    /// HTTP(S) 健康探测，连续失败达到阈值时重启进程
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_probe: Option<HttpProbeConfig>,
+    /// 自定义健康检查命令，非零退出码视为不健康
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_command: Option<HealthCommandConfig>,
+    /// 日志文件新鲜度 / 关键字检查
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_check: Option<LogCheckConfig>,
+    /// GUI 程序的窗口存在性与响应检查
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window_check: Option<WindowCheckConfig>,
+    /// 运行时间窗口（本地时间），窗口外优雅停止进程；为空时始终运行
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schedule: Vec<ScheduleWindow>,
+    /// 按 cron 表达式定时执行的运维动作
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tasks: Vec<ScheduledTask>,
+    /// 跟踪子进程，可要求指定的工作子进程必须存在
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub children: Option<ChildrenConfig>,
+    /// exe_path 只是启动器：拉起工作进程后退出，之后改为跟踪匹配的子孙进程
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub worker: Option<WorkerConfig>,
+    /// TCP 端口连通性检查
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port_check: Option<PortCheckConfig>,
+    /// Windows 事件日志检查：程序写入指定事件即视为不健康
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_log_check: Option<EventLogCheckConfig>,
+    /// 组合健康表达式；未设置时任一已配置的检查失败即重启
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_expression: Option<HealthExpr>,
+    /// 启动前等待网络可用，适用于网卡未就绪时会立即崩溃的程序
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires_network: Option<NetworkGate>,
+    /// 启动前等待这些 Windows 服务进入 Running 状态
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires_services: Vec<String>,
+    /// 依赖的服务停止或重启后重启本进程
+    #[serde(default)]
+    pub restart_on_service_restart: bool,
+    /// 依赖的其他监控项 id
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// depends_on 中的监控项重启后随之重启本进程
+    #[serde(default)]
+    pub restart_on_dependency_restart: bool,
+    /// 启动前等待路径（映射盘、UNC 共享等）可访问
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires_storage: Option<StorageGate>,
+    /// 进入崩溃循环后的升级处理
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escalation: Option<EscalationAction>,
+    /// 告警阈值；未设置时每次重启都发送通知
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alerts: Option<AlertThresholds>,
+    /// 进程运行超过该时间后按计划重启的方式回收，0 或未设置表示不限
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_lifetime_ms: Option<u64>,
+    /// 启动优先级，数值小的先启动、后停止；相同优先级保持配置顺序
+    #[serde(default)]
+    pub start_priority: i32,
+    /// 重启前终止旧进程后，等待其退出的最长时间
+    #[serde(default = "default_kill_wait_ms")]
+    pub kill_wait_ms: u64,
+    /// 旧进程退出后、启动新进程前的额外等待，用于等待文件锁、端口等释放
+    #[serde(default = "default_post_kill_delay_ms")]
+    pub post_kill_delay_ms: u64,
+    /// 进程自行退出后到重新启动前的等待时间，用于退出后立即重启会再次失败的程序
+    #[serde(default)]
+    pub restart_delay_ms: u64,
+    /// 重启时终止旧进程之后、启动新进程之前清理的文件（如崩溃实例残留的锁文件）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cleanup: Vec<CleanupRule>,
+    /// 重启前先通知程序保存状态，收到确认或超时后再终止
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prepare_shutdown: Option<PrepareShutdownConfig>,
+    /// 服务创建命名事件 Global\PG_Stop_<id>，停止或重启时置位，程序等待该事件后自行退出
+    #[serde(default)]
+    pub stop_event: bool,
+    /// 置位停止事件后等待进程退出的最长时间，超时再强制终止
+    #[serde(default = "default_stop_event_timeout_ms")]
+    pub stop_event_timeout_ms: u64,
+    /// 停止序列：重启、停止命令与服务停止时按顺序执行，进程退出后不再执行后续步骤；
+    /// 全部执行后仍在运行则强制终止。设置后代替 stop_event 的默认停止方式
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop_sequence: Vec<StopStep>,
+    /// 观察模式：照常检查，只记录和通知将要执行的动作，不启动、重启或终止进程
+    #[serde(default)]
+    pub observe: bool,
+    /// 主备模式下只在主节点运行；未配置 peer 时无效
+    #[serde(default)]
+    pub ha: bool,
+    /// 本机条件：加载配置时求值，不满足的监控项在本机不启动
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<MachineCondition>,
+    /// 同一程序允许同时运行的实例数（含被守护的实例）；未设置时不检查
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_instances: Option<u32>,
+    /// 超出 max_instances 时终止多余的实例；为 false 时只记录
+    #[serde(default)]
+    pub kill_surplus_instances: bool,
+    /// 捕获控制台程序的 stdout/stderr，保留最近 output_buffer_kb KB 供 get_output 查询
+    #[serde(default)]
+    pub capture_output: bool,
+    #[serde(default = "default_output_buffer_kb")]
+    pub output_buffer_kb: u32,
+    /// 不认识的字段（例如第三方工具保存的元数据），原样保留并在保存时写回
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// 监控项类型
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemMode {
+    #[default]
+    Keep,
+    /// 黑名单：exe_path 为完整路径时按路径匹配，否则按进程映像名匹配
+    Forbid,
+}
+
+impl ItemMode {
+    pub fn is_keep(&self) -> bool {
+        *self == ItemMode::Keep
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ItemMode::Keep => "keep",
+            ItemMode::Forbid => "forbid",
+        }
+    }
+}
+
+/// 错过心跳后执行的动作
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeartbeatAction {
+    #[default]
+    Restart,
+    /// 记录事件并发送 heartbeat_missing 通知，不重启
+    Notify,
+    /// 只记录日志与事件
+    Log,
+}
+
+impl HeartbeatAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HeartbeatAction::Restart => "restart",
+            HeartbeatAction::Notify => "notify",
+            HeartbeatAction::Log => "log",
+        }
+    }
+}
+
+/// 心跳超时策略；进程退出总是立即重启，不受此影响
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeartbeatPolicy {
+    #[serde(default)]
+    pub action: HeartbeatAction,
+    /// 连续错过多少个心跳周期（heartbeat_timeout_ms）后执行 action
+    #[serde(default = "default_consecutive_misses", alias = "unhealthy_threshold")]
+    pub consecutive_misses: u32,
+}
+
+fn default_consecutive_misses() -> u32 {
+    1
+}
+
+/// 服务启动时已在运行的进程（如服务升级前启动的实例）的处理策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrphanPolicy {
+    /// 接管已有进程继续守护
+    #[default]
+    Adopt,
+    /// 终止已有进程后重新启动
+    Restart,
+    /// 不干预并发送告警，进程退出后再恢复守护
+    Alert,
+}
+
+impl OrphanPolicy {
+    pub fn is_adopt(&self) -> bool {
+        *self == OrphanPolicy::Adopt
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrphanPolicy::Adopt => "adopt",
+            OrphanPolicy::Restart => "restart",
+            OrphanPolicy::Alert => "alert",
+        }
+    }
+}
+
+/// 启动前发现已有实例时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExistingInstance {
+    /// 接管已有进程继续守护
+    #[default]
+    Adopt,
+    /// 不启动、不接管也不终止，记为启动失败，已有实例退出后再启动
+    Fail,
+}
+
+impl ExistingInstance {
+    pub fn is_adopt(&self) -> bool {
+        *self == ExistingInstance::Adopt
+    }
+}
+
+/// HTTP 健康探测配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HttpProbeConfig {
+    pub url: String,
+    /// 期望的状态码，未设置时接受任意 2xx
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_status: Option<u16>,
+    /// 响应体中必须包含的文本
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_substring: Option<String>,
+    #[serde(default = "default_probe_timeout_ms")]
+    pub timeout_ms: u64,
+    #[serde(default = "default_probe_interval_ms")]
+    pub interval_ms: u64,
+    /// 连续失败多少次后判定为不健康
+    #[serde(
+        default = "default_probe_failure_threshold",
+        alias = "unhealthy_threshold"
+    )]
+    pub failure_threshold: u32,
+}
+
+fn default_probe_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_probe_interval_ms() -> u64 {
+    10000
+}
+
+fn default_probe_failure_threshold() -> u32 {
+    3
+}
+
+/// 健康检查命令配置，以服务账户身份在后台运行
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthCommandConfig {
+    pub exe_path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub args: Option<String>,
+    #[serde(default = "default_health_command_timeout_ms")]
+    pub timeout_ms: u64,
+    #[serde(default = "default_health_command_interval_ms")]
+    pub interval_ms: u64,
+    #[serde(
+        default = "default_health_command_failure_threshold",
+        alias = "unhealthy_threshold"
+    )]
+    pub failure_threshold: u32,
+}
+
+fn default_health_command_timeout_ms() -> u64 {
+    10000
+}
+
+fn default_health_command_interval_ms() -> u64 {
+    30000
+}
+
+fn default_health_command_failure_threshold() -> u32 {
+    1
+}
+
+/// 日志文件检查配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogCheckConfig {
+    pub path: String,
+    /// 超过该秒数未修改视为不健康
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_secs: Option<u64>,
+    /// 在最近写入的内容中出现即视为不健康的正则表达式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(default = "default_log_tail_bytes")]
+    pub tail_bytes: u64,
+    #[serde(default = "default_log_check_interval_ms")]
+    pub interval_ms: u64,
+    #[serde(
+        default = "default_health_command_failure_threshold",
+        alias = "unhealthy_threshold"
+    )]
+    pub failure_threshold: u32,
+}
+
+fn default_log_tail_bytes() -> u64 {
+    64 * 1024
+}
+
+fn default_log_check_interval_ms() -> u64 {
+    10000
+}
+
+/// 窗口检查配置，类名与标题至少应配置一项
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowCheckConfig {
+    /// 窗口类名（完全匹配，忽略大小写）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class_name: Option<String>,
+    /// 窗口标题包含的文本（忽略大小写）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// 窗口处理 WM_NULL 的超时时间，超时视为无响应
+    #[serde(default = "default_window_hung_timeout_ms")]
+    pub hung_timeout_ms: u64,
+    #[serde(default = "default_log_check_interval_ms")]
+    pub interval_ms: u64,
+    /// 连续多少次检查失败后重启
+    #[serde(
+        default = "default_probe_failure_threshold",
+        alias = "unhealthy_threshold"
+    )]
+    pub failure_threshold: u32,
+}
+
+fn default_window_hung_timeout_ms() -> u64 {
+    5000
+}
+
+/// TCP 端口检查配置，能在超时内建立连接即视为健康
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortCheckConfig {
+    #[serde(default = "default_port_check_host")]
+    pub host: String,
+    pub port: u16,
+    #[serde(default = "default_port_check_timeout_ms")]
+    pub timeout_ms: u64,
+    #[serde(default = "default_probe_interval_ms")]
+    pub interval_ms: u64,
+    #[serde(
+        default = "default_probe_failure_threshold",
+        alias = "unhealthy_threshold"
+    )]
+    pub failure_threshold: u32,
+}
+
+/// Windows 事件日志检查：进程本次启动后写入了匹配的事件即计为一次失败
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventLogCheckConfig {
+    /// 日志名称，如 Application、System
+    #[serde(default = "default_event_log_name")]
+    pub log: String,
+    /// 事件来源（忽略大小写）；未设置时匹配任意来源
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// 匹配的事件 ID；为空时匹配错误级别的事件
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub event_ids: Vec<u32>,
+    #[serde(default = "default_log_check_interval_ms")]
+    pub interval_ms: u64,
+    #[serde(
+        default = "default_health_command_failure_threshold",
+        alias = "unhealthy_threshold"
+    )]
+    pub failure_threshold: u32,
+}
+
+fn default_event_log_name() -> String {
+    "Application".to_string()
+}
+
+fn default_port_check_host() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_port_check_timeout_ms() -> u64 {
+    2000
+}
+
+/// 网络门控：通过 NLM 判断连接级别，可选再 ping 指定主机
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkGate {
+    #[serde(default)]
+    pub level: NetworkLevel,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ping_host: Option<String>,
+    #[serde(default = "default_ping_timeout_ms")]
+    pub ping_timeout_ms: u32,
+    /// 超过该时间仍无网络则照常启动，0 表示一直等待
+    #[serde(default = "default_network_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_ping_timeout_ms() -> u32 {
+    1000
+}
+
+fn default_network_timeout_ms() -> u64 {
+    120_000
+}
+
+/// 存储门控：paths 为空时检查 exe_path 本身
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StorageGate {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// 超过该时间仍不可访问则照常启动，0 表示一直等待
+    #[serde(default = "default_network_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+/// 监控项生效的机器条件，已配置的各项须全部满足
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MachineCondition {
+    /// 计算机名通配符（* 与 ?，不区分大小写）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<EnvCondition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry: Option<RegistryCondition>,
+}
+
+/// 服务进程的环境变量；未设置 equals 时只要求变量存在
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnvCondition {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub equals: Option<String>,
+}
+
+/// HKEY_LOCAL_MACHINE 下的字符串或 DWORD 值；未设置 equals 时只要求值存在
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegistryCondition {
+    /// 子键，如 SOFTWARE\Contoso\Deploy；可带 HKLM\ 前缀
+    pub key: String,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub equals: Option<String>,
+}
+
+/// 重启前通知：下一次心跳响应携带 prepare_shutdown，配置了 event 时同时置位该命名事件。
+/// 程序发送 shutdown_ready 请求或置位 `<event>_ack` 即视为确认
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrepareShutdownConfig {
+    /// 等待确认的最长时间
+    #[serde(default = "default_prepare_shutdown_timeout_secs")]
+    pub timeout_secs: u64,
+    /// 程序创建的命名事件；不含命名空间时使用 Global\
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<String>,
+}
+
+fn default_prepare_shutdown_timeout_secs() -> u64 {
+    10
+}
+
+/// 停止序列中的一步
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum StopStep {
+    /// 请求程序的关闭接口，如 POST http://127.0.0.1:8080/shutdown
+    Http {
+        url: String,
+        #[serde(default = "default_stop_http_method")]
+        method: String,
+        #[serde(default = "default_probe_timeout_ms")]
+        timeout_ms: u64,
+    },
+    /// 在用户会话中向进程的顶层窗口发送 WM_CLOSE
+    CloseWindow,
+    /// 置位 stop_event 停止事件，需同时启用 stop_event
+    StopEvent,
+    /// 等待进程退出，最长 ms 毫秒
+    Wait { ms: u64 },
+    /// 强制终止进程
+    Kill,
+    /// 强制终止进程及其所有子孙进程
+    KillTree,
+}
+
+impl StopStep {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StopStep::Http { .. } => "http",
+            StopStep::CloseWindow => "close_window",
+            StopStep::StopEvent => "stop_event",
+            StopStep::Wait { .. } => "wait",
+            StopStep::Kill => "kill",
+            StopStep::KillTree => "kill_tree",
+        }
+    }
+}
+
+fn default_stop_http_method() -> String {
+    "POST".to_string()
+}
+
+/// 单个监控项的告警阈值
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertThresholds {
+    /// window_minutes 内重启达到该次数才发送 process_restarted，0 表示不发送
+    #[serde(default = "default_alert_restarts")]
+    pub restarts: u32,
+    #[serde(default = "default_alert_window_minutes")]
+    pub window_minutes: u64,
+    /// 首次进入失败状态时发送 item_failed
+    #[serde(default)]
+    pub on_failed: bool,
+    /// 心跳缺失超过该秒数时发送 heartbeat_missing，0 表示不检查
+    #[serde(default)]
+    pub heartbeat_missing_secs: u64,
+}
+
+fn default_alert_restarts() -> u32 {
+    1
+}
+
+fn default_alert_window_minutes() -> u64 {
+    60
+}
+
+/// 崩溃循环升级动作，每次服务运行期间每个监控项最多执行一次
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum EscalationAction {
+    /// 运行恢复命令或脚本
+    Command {
+        exe_path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        args: Option<String>,
+        #[serde(default = "default_health_command_timeout_ms")]
+        timeout_ms: u64,
+    },
+    /// 重启计算机，需显式配置
+    Reboot {
+        #[serde(default = "default_reboot_delay_secs")]
+        delay_secs: u32,
+        /// 开机时间不足该值时跳过，防止反复重启
+        #[serde(default = "default_reboot_min_uptime_secs")]
+        min_uptime_secs: u64,
+    },
+    /// 改用备用程序（仅运行时生效，不写回配置）
+    Fallback {
+        exe_path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        args: Option<String>,
+    },
+}
+
+impl EscalationAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EscalationAction::Command { .. } => "command",
+            EscalationAction::Reboot { .. } => "reboot",
+            EscalationAction::Fallback { .. } => "fallback",
+        }
+    }
+}
+
+fn default_reboot_delay_secs() -> u32 {
+    60
+}
+
+fn default_reboot_min_uptime_secs() -> u64 {
+    1800
+}
+
+/// 重启前清理匹配通配符的文件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CleanupRule {
+    /// 文件通配符，相对路径相对于程序所在目录（即工作目录）
+    pub pattern: String,
+    #[serde(default)]
+    pub action: CleanupAction,
+    /// archive 的目标目录，相对路径同上；默认为程序目录下的 cleanup-archive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_dir: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CleanupAction {
+    #[default]
+    Delete,
+    /// 移动到 archive_dir 下以时间命名的子目录
+    Archive,
+}
+
+/// 重复路径策略：多个监控项的实例标识（见 instance_key）相同时如何处理
+///
+/// config.json 中已有的重复项一律保留排在最前的一项：reject 只启动它，merge 只保留它
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicatePathPolicy {
+    /// 添加时拒绝；config.json 中已有的重复项只启动第一个，并在自检中报告
+    #[default]
+    Reject,
+    /// 添加时合并到已有监控项；加载时在内存中只保留第一个，不改写文件
+    Merge,
+    /// 不检查，全部保留并启动
+    KeepAll,
+}
+
+/// 进程退出检测方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckEngine {
+    /// 每个检查周期（3 秒）遍历进程快照
+    #[default]
+    Poll,
+    /// 订阅 WMI Win32_ProcessStopTrace，受守护进程退出时立即检查，完整检查按 wmi_poll_interval_ms 进行
+    WmiEvents,
+}
+
+fn default_wmi_poll_interval_ms() -> u64 {
+    15_000
+}
+
+/// 监控项的防火墙入站放行规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FirewallRule {
+    #[serde(default)]
+    pub protocol: FirewallProtocol,
+    /// 本地端口，如 "8080" 或 "8000-8010,9000"；需要 protocol 为 tcp 或 udp，未设置时不限端口
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local_ports: Option<String>,
+    /// 允许的远程地址，如 "LocalSubnet" 或 "10.0.0.0/8"；未设置时不限
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_addresses: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FirewallProtocol {
+    #[default]
+    Any,
+    Tcp,
+    Udp,
+}
+
+/// 要求的连接级别
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkLevel {
+    /// 本地网络或子网连通即可
+    #[default]
+    Local,
+    Internet,
+}
+
+impl NetworkLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NetworkLevel::Local => "local",
+            NetworkLevel::Internet => "internet",
+        }
+    }
+}
+
+/// 运行时间窗口，如 {"days": ["mon", "fri"], "start": "07:00", "end": "19:00"}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleWindow {
+    /// 星期（mon..sun），为空表示每天
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<String>,
+    /// "HH:MM"，结束早于开始表示跨越午夜
+    pub start: String,
+    pub end: String,
+}
+
+/// 定时任务：cron 为 5 段表达式（分 时 日 月 星期），按本地时间计算
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledTask {
+    pub cron: String,
+    pub action: TaskAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskAction {
+    Start,
+    Stop,
+    Restart,
+    /// 立即执行一次已配置的健康检查
+    HealthCheck,
+    /// 清空进程工作集，同 trim_memory 请求
+    TrimMemory,
+}
+
+impl TaskAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskAction::Start => "start",
+            TaskAction::Stop => "stop",
+            TaskAction::Restart => "restart",
+            TaskAction::HealthCheck => "health_check",
+            TaskAction::TrimMemory => "trim_memory",
+        }
+    }
+}
+
+/// 子进程跟踪配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChildrenConfig {
+    /// 必须在子孙进程中存在的映像名（如 worker.exe），缺失视为不健康
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required: Vec<String>,
+    /// 连续多少个检查周期缺失后判定为不健康
+    #[serde(
+        default = "default_children_failure_threshold",
+        alias = "unhealthy_threshold"
+    )]
+    pub failure_threshold: u32,
+    /// 重启主进程时一并终止其子孙进程
+    #[serde(default = "default_true")]
+    pub kill_on_restart: bool,
+}
+
+/// 启动器拉起的工作进程；心跳、健康检查、重启与终止都针对该进程
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkerConfig {
+    /// 工作进程映像名，支持 * 与 ? 通配符，不区分大小写
+    pub image: String,
+    /// 启动后等待工作进程出现的最长时间，超时按启动失败处理
+    #[serde(default = "default_worker_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_worker_timeout_ms() -> u64 {
+    30_000
+}
+
+fn default_children_failure_threshold() -> u32 {
+    2
+}
+
+/// 组合健康表达式：检查名称，或 `{"all": [...]}` / `{"any": [...]}` 嵌套
+///
+/// 检查名称为 alive、heartbeat、http_probe、health_command、log_check、
+/// window_check、port_check、event_log、children，各检查沿用自身的失败阈值。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum HealthExpr {
+    Check(String),
+    All { all: Vec<HealthExpr> },
+    Any { any: Vec<HealthExpr> },
+}
+
+/// 日志检查起点，进程（重新）启动时记录
+#[derive(Debug, Clone, Copy)]
+pub struct LogCheckOrigin {
+    pub offset: u64,
+    pub since: SystemTime,
+}
+
+/// 后台探测线程与守护循环共享的探测状态
+#[derive(Debug, Clone, Default)]
+pub struct ProbeState {
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    pub last_started: Option<Instant>,
+    pub in_flight: bool,
+    /// 每次重置递增，用于丢弃重启前发起的探测结果
+    pub generation: u64,
+}
+
+impl ProbeState {
+    /// 下次轮询时立即执行检查，不影响已累计的失败次数
+    pub fn make_due(&mut self) {
+        self.last_started = None;
+    }
+
+    pub fn reset(&mut self) {
+        self.consecutive_failures = 0;
+        self.last_error = None;
+        self.last_started = None;
+        self.in_flight = false;
+        self.generation += 1;
+    }
+}
+
+/// AppContainer 沙箱配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppContainerConfig {
+    /// 配置文件名称，同名监控项共用一个沙箱
+    pub name: String,
+    /// 授予的能力，名称（如 internetClient）或 SID 字符串
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+}
+
+/// 多路服务器上的进程放置：NUMA 节点、处理器组与组内亲和性掩码
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlacementConfig {
+    /// 首选 NUMA 节点，同时把进程限制在该节点的处理器上
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub numa_node: Option<u16>,
+    /// 处理器组；与 numa_node 同时设置时必须是该节点所在的组
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub processor_group: Option<u16>,
+    /// 组内的处理器掩码，与节点或组的处理器取交集
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub affinity_mask: Option<u64>,
+}
+
+/// 进程优先级类别；不提供 realtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriorityClass {
+    Idle,
+    BelowNormal,
+    Normal,
+    AboveNormal,
+    High,
+}
+
+/// I/O 优先级；high 需要额外特权，不提供
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IoPriority {
+    VeryLow,
+    Low,
+    Normal,
 }
 
 fn default_heartbeat_timeout() -> u64 {
     10000
 }
 
+fn default_output_buffer_kb() -> u32 {
+    64
+}
+
+fn default_stop_event_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_kill_wait_ms() -> u64 {
+    5000
+}
+
+fn default_post_kill_delay_ms() -> u64 {
+    500
+}
+
+fn default_heartbeat_required() -> bool {
+    true
+}
+
 impl MonitorItem {
     pub fn new(exe_path: String, name: String) -> Self {
         Self {
@@ -30,9 +958,142 @@ impl MonitorItem {
             minimize: false,
             no_window: false,
             enabled: true,
+            template: None,
+            tags: Vec::new(),
+            group: None,
+            mode: ItemMode::Keep,
+            orphan_policy: OrphanPolicy::Adopt,
+            existing_instance: ExistingInstance::Adopt,
             heartbeat_timeout_ms: 10000,
+            heartbeat_required: true,
+            heartbeat_file: None,
+            heartbeat_token: None,
+            on_heartbeat_missed: None,
+            notify_user_on_restart: false,
+            require_signed: false,
+            expected_publisher: None,
+            expected_sha256: None,
+            app_container: None,
+            block_child_processes: false,
+            desktop: None,
+            ui_access: false,
+            defer_while_locked: false,
+            placement: None,
+            priority_class: None,
+            io_priority: None,
+            firewall: None,
+            http_probe: None,
+            health_command: None,
+            log_check: None,
+            window_check: None,
+            schedule: Vec::new(),
+            tasks: Vec::new(),
+            children: None,
+            worker: None,
+            port_check: None,
+            event_log_check: None,
+            health_expression: None,
+            requires_network: None,
+            requires_services: Vec::new(),
+            restart_on_service_restart: false,
+            depends_on: Vec::new(),
+            restart_on_dependency_restart: false,
+            requires_storage: None,
+            escalation: None,
+            alerts: None,
+            max_lifetime_ms: None,
+            start_priority: 0,
+            kill_wait_ms: default_kill_wait_ms(),
+            post_kill_delay_ms: default_post_kill_delay_ms(),
+            restart_delay_ms: 0,
+            cleanup: Vec::new(),
+            prepare_shutdown: None,
+            stop_event: false,
+            stop_event_timeout_ms: default_stop_event_timeout_ms(),
+            stop_sequence: Vec::new(),
+            observe: false,
+            ha: false,
+            condition: None,
+            max_instances: None,
+            kill_surplus_instances: false,
+            capture_output: false,
+            output_buffer_kb: default_output_buffer_kb(),
+            extra: serde_json::Map::new(),
+        }
+    }
+
+    /// 通过管道、诊断快照与取证包对外输出的配置：去掉 heartbeat_token 等凭据，
+    /// 否则任何能执行 list 的调用方都可以伪造心跳
+    pub fn to_public_value(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).unwrap_or(serde_json::Value::Null);
+        if let Some(object) = value.as_object_mut() {
+            for field in SECRET_ITEM_FIELDS {
+                object.remove(field);
+            }
+        }
+        value
+    }
+
+    /// 监控项的实例标识：默认仅为小写路径；允许重复路径时再加上启动参数
+    pub fn instance_key(&self, allow_duplicate_exe_paths: bool) -> (String, String) {
+        let args = if allow_duplicate_exe_paths {
+            self.args.as_deref().unwrap_or_default().trim().to_string()
+        } else {
+            String::new()
+        };
+        (self.exe_path.to_lowercase(), args)
+    }
+}
+
+/// 重启原因分类，用于日志、事件历史、状态与通知中按原因统计
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestartReason {
+    ProcessExited,
+    HeartbeatTimeout,
+    MemoryLimit,
+    HealthCheckFailed,
+    /// kill 请求
+    ManualRestart,
+    /// tasks 中的 restart 任务
+    ScheduledRestart,
+    BinaryUpdated,
+    /// 崩溃循环升级切换到备用程序
+    Escalation,
+    /// depends_on 中的监控项已重启
+    DependencyRestarted,
+    /// 运行时间达到 max_lifetime_ms
+    MaxLifetime,
+}
+
+impl RestartReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RestartReason::ProcessExited => "process_exited",
+            RestartReason::HeartbeatTimeout => "heartbeat_timeout",
+            RestartReason::MemoryLimit => "memory_limit",
+            RestartReason::HealthCheckFailed => "health_check_failed",
+            RestartReason::ManualRestart => "manual_restart",
+            RestartReason::ScheduledRestart => "scheduled_restart",
+            RestartReason::BinaryUpdated => "binary_updated",
+            RestartReason::Escalation => "escalation",
+            RestartReason::DependencyRestarted => "dependency_restarted",
+            RestartReason::MaxLifetime => "max_lifetime",
         }
     }
+
+    /// 人为或计划内的重启不计入崩溃循环
+    pub fn is_intentional(&self) -> bool {
+        matches!(
+            self,
+            RestartReason::ManualRestart
+                | RestartReason::ScheduledRestart
+                | RestartReason::BinaryUpdated
+                | RestartReason::Escalation
+                | RestartReason::DependencyRestarted
+                | RestartReason::MaxLifetime
+        )
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -43,6 +1104,125 @@ pub struct MonitoredProcess {
     pub last_check: Instant,
     pub restart_count: u32,
     pub startup_time: Instant, // 进程启动时间，用于计算启动宽限期
+    pub resources: Option<ProcessResources>,
+    pub cpu_percent: Option<f64>,
+    pub cpu_sample: Option<CpuSample>,
+    pub recent_restarts: VecDeque<Instant>,
+    pub in_crash_loop: bool,
+    pub last_start_error: Option<String>,
+    pub http_probe_state: Arc<Mutex<ProbeState>>,
+    pub health_command_state: Arc<Mutex<ProbeState>>,
+    pub log_check_state: Arc<Mutex<ProbeState>>,
+    pub log_check_origin: Option<LogCheckOrigin>,
+    pub window_check_state: Arc<Mutex<ProbeState>>,
+    pub port_check_state: Arc<Mutex<ProbeState>>,
+    pub event_log_state: Arc<Mutex<ProbeState>>,
+    /// 事件日志检查的起点：进程启动时日志中最新记录的编号
+    pub event_log_origin: Option<u32>,
+    /// forbid 项累计终止的进程数
+    pub blocked_count: u32,
+    /// forbid 项上次记录拦截事件的时间，用于限制日志与通知频率
+    pub last_block_report: Option<Instant>,
+    pub suppressed_blocks: u32,
+    /// 最近一次检查到的子孙进程
+    pub child_pids: Vec<u32>,
+    /// 配置了 worker 时，启动器启动后开始等待工作进程的时间，找到后清除
+    pub worker_wait_since: Option<Instant>,
+    pub missing_children_cycles: u32,
+    /// 当前处于运行时间窗口之外
+    pub outside_schedule: bool,
+    /// 窗口关闭后等待进程优雅退出的截止时间
+    pub schedule_stop_deadline: Option<Instant>,
+    /// 已通过 suspend 请求挂起，期间跳过心跳与健康检查
+    pub suspended: bool,
+    /// 通过 defer 请求暂缓守护的截止时间，期间不检查、不重启
+    pub deferred_until: Option<Instant>,
+    /// 由服务主动终止（kill 请求、定时重启、升级切换），下个周期按该原因重启
+    pub restart_requested: Option<RestartReason>,
+    /// orphan_policy 为 alert 或 existing_instance 为 fail 时暂不干预的已有进程，退出后恢复守护
+    pub orphan_pid: Option<u32>,
+    /// 开始等待网络的时间，网络就绪或超时后清除
+    pub network_wait_since: Option<Instant>,
+    /// 开始等待依赖服务的时间，服务全部运行后清除
+    pub service_wait_since: Option<Instant>,
+    /// 启动时依赖服务的进程 ID，用于发现服务重启
+    pub service_pids: BTreeMap<String, u32>,
+    /// 开始等待存储路径的时间，路径可访问或超时后清除
+    pub storage_wait_since: Option<Instant>,
+    /// 因会话锁屏开始推迟启动的时间，解锁后清除
+    pub unlock_wait_since: Option<Instant>,
+    /// 连续因用户会话令牌失败而无法启动的次数，启动成功后清零
+    pub session_failures: u32,
+    /// 连续失败达到阈值后进入“无用户会话”退避，在此之前不再尝试启动
+    pub session_blocked_until: Option<Instant>,
+    /// 进程自行退出后按 restart_delay_ms 推迟重启的截止时间，到期前不重新启动
+    pub restart_due: Option<Instant>,
+    /// 重启后提前复查新进程是否存活的时间，不等下个检查周期
+    pub recheck_at: Option<Instant>,
+    /// 复查发现新进程在启动期内退出，下个周期不受启动宽限期限制
+    pub died_after_restart: bool,
+    /// 本次服务运行期间已执行过升级动作
+    pub escalated: bool,
+    /// 按 alerts.window_minutes 统计的重启时间，发送告警后清空
+    pub alert_restarts: VecDeque<Instant>,
+    /// 最近一次重启的原因与本次服务运行期间按原因统计的重启次数
+    pub last_restart_reason: Option<RestartReason>,
+    pub restarts_by_reason: BTreeMap<RestartReason, u32>,
+    /// 已发送 item_failed / heartbeat_missing，恢复后清除
+    pub failed_alerted: bool,
+    pub heartbeat_alerted: bool,
+    /// 已按 on_heartbeat_missed 报告过本次心跳超时（不重启的动作），收到心跳后清除
+    pub heartbeat_miss_reported: bool,
+    /// 已在生命周期日志中记录心跳丢失，恢复时记录 heartbeat_resumed
+    pub heartbeat_lost: bool,
+    /// exe_path 含通配符时最近一次启动解析到的路径
+    pub resolved_exe: Option<String>,
+    /// process_id 对应进程的创建时间（FILETIME），用于识别被系统复用的 PID
+    pub process_created: Option<u64>,
+    /// 观察模式下最近一次报告的动作，相同动作不重复报告
+    pub observed_action: Option<String>,
+    /// 最近一次检查到的超出 max_instances 的实例，集合变化时才记录事件
+    pub surplus_pids: Vec<u32>,
+    /// 当前进程的会话、用户与命令行
+    pub context: Option<ProcessContext>,
+    /// capture_output 开启后的输出缓冲，跨重启保留
+    pub output: Option<Arc<OutputBuffer>>,
+}
+
+/// 进程所在的会话与用户上下文，用于终端服务器上区分是谁的会话；PID 变化时重新查询
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ProcessContext {
+    #[serde(skip)]
+    pub process_id: u32,
+    pub session_id: Option<u32>,
+    /// DOMAIN\user
+    pub user: Option<String>,
+    pub window_station: Option<String>,
+    pub desktop: Option<String>,
+    pub start_time_unix_ms: Option<i64>,
+    pub command_line: Option<String>,
+}
+
+/// 进程资源占用快照
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct ProcessResources {
+    pub working_set_bytes: u64,
+    pub private_bytes: u64,
+    /// 内核态 + 用户态累计 CPU 时间，单位 100ns
+    #[serde(skip)]
+    pub cpu_time_100ns: u64,
+    pub handle_count: Option<u32>,
+    pub thread_count: Option<u32>,
+    pub start_time_unix_ms: Option<i64>,
+    pub session_id: Option<u32>,
+}
+
+/// 上一次 CPU 采样，用于计算两次检查之间的 CPU 占用率
+#[derive(Debug, Clone, Copy)]
+pub struct CpuSample {
+    pub process_id: u32,
+    pub taken_at: Instant,
+    pub cpu_time_100ns: u64,
 }
 
 impl MonitoredProcess {
@@ -54,82 +1234,738 @@ impl MonitoredProcess {
             last_check: Instant::now(),
             restart_count: 0,
             startup_time: Instant::now(),
+            resources: None,
+            cpu_percent: None,
+            cpu_sample: None,
+            recent_restarts: VecDeque::new(),
+            in_crash_loop: false,
+            last_start_error: None,
+            http_probe_state: Arc::new(Mutex::new(ProbeState::default())),
+            health_command_state: Arc::new(Mutex::new(ProbeState::default())),
+            log_check_state: Arc::new(Mutex::new(ProbeState::default())),
+            log_check_origin: None,
+            window_check_state: Arc::new(Mutex::new(ProbeState::default())),
+            port_check_state: Arc::new(Mutex::new(ProbeState::default())),
+            event_log_state: Arc::new(Mutex::new(ProbeState::default())),
+            event_log_origin: None,
+            blocked_count: 0,
+            last_block_report: None,
+            suppressed_blocks: 0,
+            orphan_pid: None,
+            network_wait_since: None,
+            service_wait_since: None,
+            service_pids: BTreeMap::new(),
+            storage_wait_since: None,
+            unlock_wait_since: None,
+            session_failures: 0,
+            session_blocked_until: None,
+            restart_due: None,
+            recheck_at: None,
+            died_after_restart: false,
+            escalated: false,
+            alert_restarts: VecDeque::new(),
+            last_restart_reason: None,
+            restarts_by_reason: BTreeMap::new(),
+            failed_alerted: false,
+            heartbeat_alerted: false,
+            heartbeat_miss_reported: false,
+            heartbeat_lost: false,
+            resolved_exe: None,
+            process_created: None,
+            observed_action: None,
+            surplus_pids: Vec::new(),
+            context: None,
+            output: None,
+            restart_requested: None,
+            suspended: false,
+            deferred_until: None,
+            outside_schedule: false,
+            schedule_stop_deadline: None,
+            child_pids: Vec::new(),
+            worker_wait_since: None,
+            missing_children_cycles: 0,
         }
     }
 
+    /// 实际使用的可执行文件路径：exe_path 含通配符时为解析结果
+    pub fn exe_path(&self) -> &str {
+        self.resolved_exe.as_deref().unwrap_or(&self.item.exe_path)
+    }
+
+    /// 心跳检查是否生效
+    pub fn heartbeat_enabled(&self) -> bool {
+        self.item.heartbeat_required && self.item.heartbeat_timeout_ms > 0
+    }
+
     pub fn is_heartbeat_timeout(&self) -> bool {
+        if !self.heartbeat_enabled() {
+            return false;
+        }
         let timeout = std::time::Duration::from_millis(self.item.heartbeat_timeout_ms);
         self.last_heartbeat.elapsed() > timeout
     }
 
+    /// 自最后一次心跳起已错过的完整心跳周期数
+    pub fn missed_heartbeats(&self) -> u32 {
+        if !self.heartbeat_enabled() {
+            return 0;
+        }
+        let missed =
+            self.last_heartbeat.elapsed().as_millis() / u128::from(self.item.heartbeat_timeout_ms);
+        missed.min(u128::from(u32::MAX)) as u32
+    }
+
+    /// 心跳超时且达到 on_heartbeat_missed 的连续次数时返回应执行的动作
+    pub fn due_heartbeat_action(&self) -> Option<HeartbeatAction> {
+        if !self.is_heartbeat_timeout() {
+            return None;
+        }
+        let (action, consecutive) = match &self.item.on_heartbeat_missed {
+            Some(policy) => (policy.action, policy.consecutive_misses.max(1)),
+            None => (HeartbeatAction::Restart, 1),
+        };
+        (self.missed_heartbeats() >= consecutive).then_some(action)
+    }
+
     pub fn update_heartbeat(&mut self) {
         self.last_heartbeat = Instant::now();
     }
+
+    /// 已启用但进程不在、启动失败或处于崩溃循环；forbid 项与运行窗口外的项不参与判定
+    pub fn is_failed(&self, alive: bool) -> bool {
+        self.item.enabled
+            && self.item.mode.is_keep()
+            && !self.outside_schedule
+            && self.network_wait_since.is_none()
+            && self.service_wait_since.is_none()
+            && self.storage_wait_since.is_none()
+            && self.unlock_wait_since.is_none()
+            && (!alive || self.in_crash_loop || self.last_start_error.is_some())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub items: Vec<MonitorItem>,
+    #[serde(default)]
+    pub settings: Settings,
+    /// 命名模板：监控项字段的默认值，读取配置时合并到引用该模板的监控项
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub templates: BTreeMap<String, serde_json::Map<String, serde_json::Value>>,
+    /// 不认识的顶层字段，原样保留并在保存时写回
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 impl Config {
     pub fn new() -> Self {
-        Self { items: Vec::new() }
+        Self {
+            items: Vec::new(),
+            settings: Settings::default(),
+            templates: BTreeMap::new(),
+            extra: serde_json::Map::new(),
+        }
+    }
+}
+
+/// 服务级全局设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Settings {
+    /// Prometheus 指标监听地址，例如 "127.0.0.1:9184"；为空时不启动监听
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics_listen: Option<String>,
+    /// 是否将重启事件与在线时长写入 SQLite 事件库
+    #[serde(default = "default_true")]
+    pub event_store: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<WebhookConfig>,
+    /// 在 crash_loop_window_secs 内重启达到该次数即视为崩溃循环
+    #[serde(default = "default_crash_loop_restarts")]
+    pub crash_loop_restarts: u32,
+    #[serde(default = "default_crash_loop_window_secs")]
+    pub crash_loop_window_secs: u64,
+    /// /healthz 监听地址；与 metrics_listen 相同时共用一个监听
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_listen: Option<String>,
+    /// 处于失败状态的监控项数量达到该值时 /healthz 返回 503
+    #[serde(default = "default_health_max_failed_items")]
+    pub health_max_failed_items: usize,
+    /// 未设置时不做角色校验，保持原有行为
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pipe_access: Option<PipeAccessConfig>,
+    /// 服务停止时按启动顺序的逆序优雅停止受守护进程，默认保持进程运行
+    #[serde(default)]
+    pub stop_processes_on_shutdown: bool,
+    /// 每个进程优雅退出的等待时间，超时后强制终止
+    #[serde(default = "default_shutdown_timeout_ms")]
+    pub shutdown_timeout_ms: u64,
+    /// 服务启动时分批错开启动进程，避免开机时同时拉起所有进程
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub startup_stagger: Option<StartupStagger>,
+    /// 允许多个监控项使用同一可执行文件（按路径+参数区分实例）
+    #[serde(default)]
+    pub allow_duplicate_exe_paths: bool,
+    /// 多个监控项的实例标识相同时的处理方式
+    #[serde(default)]
+    pub duplicate_exe_paths: DuplicatePathPolicy,
+    /// 进程退出的检测方式。修改后需重启服务
+    #[serde(default)]
+    pub check_engine: CheckEngine,
+    /// check_engine 为 wmi_events 时完整检查周期的间隔
+    #[serde(default = "default_wmi_poll_interval_ms")]
+    pub wmi_poll_interval_ms: u64,
+    /// 对所有监控项启用观察模式，用于在已有生产服务器上试运行
+    #[serde(default)]
+    pub observe_only: bool,
+    /// 允许远程管理的客户端主机；为空时拒绝所有远程管道连接
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remote_hosts: Vec<RemoteHost>,
+    /// 主备模式：与另一台守护服务交换状态，只有主节点启动 ha 监控项
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peer: Option<PeerConfig>,
+    /// 在 127.0.0.1 的该端口接收 UDP 心跳，适用于大量高频心跳的小进程；未设置时不监听。修改后需重启服务
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub udp_heartbeat_port: Option<u16>,
+    /// 用户会话中的托盘程序通道；未设置时不开放
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tray: Option<TrayConfig>,
+    /// 进入崩溃循环时收集取证包
+    #[serde(default)]
+    pub forensics: ForensicsConfig,
+    /// 每个监控项单独的生命周期日志
+    #[serde(default)]
+    pub item_logs: ItemLogConfig,
+    /// 定期将各监控项的 CPU 与内存写入事件库，供 metrics_history 查询
+    #[serde(default)]
+    pub metrics_history: MetricsHistoryConfig,
+    /// 系统从睡眠/休眠恢复或系统时间大幅调整后，该秒数内不判定心跳超时；0 表示不抑制
+    #[serde(default = "default_clock_settle_secs")]
+    pub clock_settle_secs: u64,
+    /// 日志输出目标，可同时配置多个；为空时只写按天轮转的日志文件（debug 级别）。修改后需重启服务
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub log_sinks: Vec<LogSink>,
+}
+
+/// 一个日志输出目标及其级别
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogSink {
+    #[serde(rename = "type")]
+    pub kind: LogSinkKind,
+    /// off、error、warn、info、debug 或 trace
+    #[serde(default = "default_log_sink_level")]
+    pub level: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogSinkKind {
+    /// logs 目录下按天轮转的文本日志
+    File,
+    /// logs 目录下按天轮转的 JSON Lines 日志，每行一个 {"ts","level","target","message"}
+    JsonFile,
+    /// Windows 应用程序事件日志，事件源为服务名
+    EventLog,
+    /// OutputDebugString，可用 DebugView 查看
+    DebugOutput,
+}
+
+fn default_log_sink_level() -> String {
+    "debug".to_string()
+}
+
+/// 崩溃循环取证包：最近输出、崩溃转储、事件日志摘录、监控项配置与重启历史
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForensicsConfig {
+    /// 默认关闭：每个取证包可能包含数百 MB 的崩溃转储
+    #[serde(default)]
+    pub enabled: bool,
+    /// 保存目录；为空时为服务程序目录下的 forensics
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dir: Option<String>,
+    /// 每个监控项保留的取证包数量，超出后删除最早的
+    #[serde(default = "default_forensics_keep")]
+    pub keep: usize,
+}
+
+impl Default for ForensicsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: None,
+            keep: default_forensics_keep(),
+        }
+    }
+}
+
+fn default_forensics_keep() -> usize {
+    5
+}
+
+/// logs\<item-id>\lifecycle.log 的设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemLogConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// 单个文件的最大大小（KB），超过后轮转
+    #[serde(default = "default_item_log_max_kb")]
+    pub max_kb: u64,
+    /// 保留的轮转文件数
+    #[serde(default = "default_item_log_keep")]
+    pub keep: usize,
+}
+
+impl Default for ItemLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_kb: default_item_log_max_kb(),
+            keep: default_item_log_keep(),
+        }
+    }
+}
+
+fn default_item_log_max_kb() -> u64 {
+    1024
+}
+
+fn default_item_log_keep() -> usize {
+    3
+}
+
+/// 资源使用历史采样的设置；需同时启用 event_store
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricsHistoryConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 采样间隔（秒），实际间隔不小于检查周期
+    #[serde(default = "default_metrics_history_interval_secs")]
+    pub interval_secs: u64,
+    /// 采样保留天数，更早的记录在采样时删除
+    #[serde(default = "default_metrics_history_retention_days")]
+    pub retention_days: u32,
+}
+
+impl Default for MetricsHistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: default_metrics_history_interval_secs(),
+            retention_days: default_metrics_history_retention_days(),
+        }
+    }
+}
+
+fn default_metrics_history_interval_secs() -> u64 {
+    60
+}
+
+fn default_metrics_history_retention_days() -> u32 {
+    7
+}
+
+/// 托盘程序通道与启动器
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrayConfig {
+    /// 在每个交互式会话中启动并保持运行的托盘程序；为空时只开放通道
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exe_path: Option<String>,
+    /// 允许托盘程序重启、暂停监控项；默认只能订阅状态
+    #[serde(default)]
+    pub allow_actions: bool,
+}
+
+/// 主备模式的对端节点
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerConfig {
+    /// 对端计算机名，通过 \\host\pipe\ProcessGuardService 查询其状态
+    pub host: String,
+    /// 两个节点都可作为主节点时数值小的优先；相同时按计算机名
+    #[serde(default)]
+    pub priority: u32,
+    #[serde(default = "default_peer_interval_ms")]
+    pub interval_ms: u64,
+    /// 超过该时间联系不上对端即接管
+    #[serde(default = "default_peer_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_peer_interval_ms() -> u64 {
+    2000
+}
+
+fn default_peer_timeout_ms() -> u64 {
+    10000
+}
+
+/// 允许通过 \\主机\pipe\ProcessGuardService 连接的远程主机
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteHost {
+    /// 客户端计算机名（不区分大小写）；"*" 匹配任意主机
+    pub host: String,
+    /// 来自该主机的调用方最多获得的角色：observer / operator / admin
+    #[serde(default = "default_remote_max_role")]
+    pub max_role: String,
+}
+
+fn default_remote_max_role() -> String {
+    "observer".to_string()
+}
+
+/// 启动错峰配置：每批最多启动 max_concurrent 个，批次之间等待 interval_ms 加随机抖动
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartupStagger {
+    #[serde(default)]
+    pub interval_ms: u64,
+    /// 在 interval_ms 之上随机增加 0..=jitter_ms 毫秒
+    #[serde(default)]
+    pub jitter_ms: u64,
+    #[serde(default = "default_stagger_max_concurrent")]
+    pub max_concurrent: u32,
+}
+
+fn default_stagger_max_concurrent() -> u32 {
+    1
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            metrics_listen: None,
+            event_store: true,
+            webhooks: Vec::new(),
+            crash_loop_restarts: default_crash_loop_restarts(),
+            crash_loop_window_secs: default_crash_loop_window_secs(),
+            health_listen: None,
+            health_max_failed_items: default_health_max_failed_items(),
+            pipe_access: None,
+            stop_processes_on_shutdown: false,
+            shutdown_timeout_ms: default_shutdown_timeout_ms(),
+            startup_stagger: None,
+            allow_duplicate_exe_paths: false,
+            duplicate_exe_paths: DuplicatePathPolicy::Reject,
+            check_engine: CheckEngine::Poll,
+            wmi_poll_interval_ms: default_wmi_poll_interval_ms(),
+            observe_only: false,
+            remote_hosts: Vec::new(),
+            peer: None,
+            udp_heartbeat_port: None,
+            tray: None,
+            forensics: ForensicsConfig::default(),
+            item_logs: ItemLogConfig::default(),
+            metrics_history: MetricsHistoryConfig::default(),
+            clock_settle_secs: default_clock_settle_secs(),
+            log_sinks: Vec::new(),
+        }
     }
 }
 
+fn default_true() -> bool {
+    true
+}
+
+fn default_crash_loop_restarts() -> u32 {
+    5
+}
+
+fn default_crash_loop_window_secs() -> u64 {
+    300
+}
+
+fn default_health_max_failed_items() -> usize {
+    1
+}
+
+fn default_shutdown_timeout_ms() -> u64 {
+    10000
+}
+
+fn default_clock_settle_secs() -> u64 {
+    60
+}
+
+/// Webhook 通知目标
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// 订阅的事件名，为空表示全部事件
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<String>,
+    #[serde(default = "default_webhook_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_webhook_timeout_ms() -> u64 {
+    5000
+}
+
+/// 管道访问控制：按 Windows 组（名称或 SID 字符串）授予角色
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PipeAccessConfig {
+    /// 可执行 list/status/heartbeat/uptime_report/metrics_history
+    #[serde(default)]
+    pub observer_groups: Vec<String>,
+    /// 另可执行 start/stop/pause
+    #[serde(default)]
+    pub operator_groups: Vec<String>,
+    /// 可执行全部请求，包括 add/update/remove
+    #[serde(default)]
+    pub admin_groups: Vec<String>,
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// 管道请求，按 "type" 字段区分；缺少必需字段（例如 add 不带 config）的请求在解析时即被拒绝
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PipeRequest {
-    #[serde(rename = "type")]
-    pub request_type: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub id: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub config: Option<MonitorItem>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub item_id: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub timestamp: Option<i64>,
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PipeRequest {
+    Heartbeat {
+        item_id: String,
+        /// 客户端发送时间（毫秒），仅用于排查
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timestamp: Option<i64>,
+    },
+    /// 程序收到 prepare_shutdown 后已保存状态，可以终止
+    ShutdownReady {
+        item_id: String,
+    },
+    Add {
+        config: MonitorItem,
+    },
+    /// 以现有监控项为蓝本新建监控项，overrides 中的字段覆盖原值
+    Clone {
+        id: String,
+        new_id: String,
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        overrides: Option<serde_json::Map<String, serde_json::Value>>,
+    },
+    Update {
+        config: MonitorItem,
+    },
+    Remove {
+        id: String,
+    },
+    Pause {
+        id: String,
+    },
+    Stop {
+        id: String,
+    },
+    Start {
+        id: String,
+    },
+    Kill {
+        id: String,
+    },
+    Suspend {
+        id: String,
+    },
+    Resume {
+        id: String,
+    },
+    /// 清空进程工作集，把不常用的页换出到页面文件
+    TrimMemory {
+        id: String,
+    },
+    /// 在接下来的 minutes 分钟内不检查、不重启该监控项，0 表示立即恢复
+    Defer {
+        id: String,
+        minutes: u32,
+    },
+    /// 暂停全部守护动作，minutes 为自动恢复时间，不填时需 resume_all 恢复
+    PauseAll {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        minutes: Option<u32>,
+    },
+    ResumeAll,
+    List,
+    Status,
+    UptimeReport {
+        /// 统计天数；不填时同时返回 7 天与 30 天
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        days: Option<u32>,
+    },
+    /// 查询监控项最近的资源使用采样
+    MetricsHistory {
+        id: String,
+        /// 查询最近多少分钟，默认 60
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        minutes: Option<u32>,
+    },
+    /// 查询监控项最近的启动记录（程序版本、哈希、命令行与环境指纹）
+    LaunchHistory {
+        id: String,
+        /// 最多返回多少条，默认 20
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        limit: Option<u32>,
+    },
+    ExportStatus,
+    Reload,
+    PeerStatus,
+    /// 服务启动时的自检结果
+    Diagnostics,
+    GetOutput {
+        id: String,
+    },
+    /// 对 selector 匹配的所有监控项执行 enable、disable 或 restart
+    Bulk {
+        action: BulkAction,
+        selector: ItemSelector,
+    },
+    /// 不带 id 时订阅服务日志
+    StreamLogs {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+    },
+}
+
+impl PipeRequest {
+    /// 请求的 "type" 取值，用于权限检查与日志
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PipeRequest::Heartbeat { .. } => "heartbeat",
+            PipeRequest::ShutdownReady { .. } => "shutdown_ready",
+            PipeRequest::Add { .. } => "add",
+            PipeRequest::Clone { .. } => "clone",
+            PipeRequest::Update { .. } => "update",
+            PipeRequest::Remove { .. } => "remove",
+            PipeRequest::Pause { .. } => "pause",
+            PipeRequest::Stop { .. } => "stop",
+            PipeRequest::Start { .. } => "start",
+            PipeRequest::Kill { .. } => "kill",
+            PipeRequest::Suspend { .. } => "suspend",
+            PipeRequest::Resume { .. } => "resume",
+            PipeRequest::TrimMemory { .. } => "trim_memory",
+            PipeRequest::Defer { .. } => "defer",
+            PipeRequest::PauseAll { .. } => "pause_all",
+            PipeRequest::ResumeAll => "resume_all",
+            PipeRequest::List => "list",
+            PipeRequest::Status => "status",
+            PipeRequest::UptimeReport { .. } => "uptime_report",
+            PipeRequest::MetricsHistory { .. } => "metrics_history",
+            PipeRequest::LaunchHistory { .. } => "launch_history",
+            PipeRequest::ExportStatus => "export_status",
+            PipeRequest::Reload => "reload",
+            PipeRequest::PeerStatus => "peer_status",
+            PipeRequest::Diagnostics => "diagnostics",
+            PipeRequest::GetOutput { .. } => "get_output",
+            PipeRequest::Bulk { .. } => "bulk",
+            PipeRequest::StreamLogs { .. } => "stream_logs",
+        }
+    }
+}
+
+/// bulk 请求对匹配的监控项执行的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkAction {
+    /// 启用并启动未启用的监控项
+    Enable,
+    /// 停止并禁用已启用的监控项
+    Disable,
+    /// 重启已启用的监控项，同 kill
+    Restart,
+}
+
+impl BulkAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BulkAction::Enable => "enable",
+            BulkAction::Disable => "disable",
+            BulkAction::Restart => "restart",
+        }
+    }
+}
+
+/// 按标签、分组与名称通配符选择监控项，填写的条件须同时满足
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemSelector {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// 匹配 name 或 id 的通配符（* 与 ?），不区分大小写
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl ItemSelector {
+    /// 未填写任何条件；为避免误操作全部监控项，这样的请求会被拒绝
+    pub fn is_empty(&self) -> bool {
+        self.tag.is_none() && self.group.is_none() && self.name.is_none()
+    }
+
+    pub fn matches(&self, item: &MonitorItem) -> bool {
+        if let Some(tag) = &self.tag {
+            if !item.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                return false;
+            }
+        }
+        if let Some(group) = &self.group {
+            if !item
+                .group
+                .as_deref()
+                .is_some_and(|g| g.eq_ignore_ascii_case(group))
+            {
+                return false;
+            }
+        }
+        if let Some(pattern) = &self.name {
+            if !crate::exe_glob::wildcard_match(pattern, &item.name)
+                && !crate::exe_glob::wildcard_match(pattern, &item.id)
+            {
+                return false;
+            }
+        }
+        true
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PipeResponse {
     pub success: bool,
+    /// 默认（中文）文本
     #[serde(skip_serializing_if = "Option::is_none")]
     pub message: Option<String>,
+    /// 消息目录中的 id，客户端按 id 与 params 本地化
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<String>,
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub params: serde_json::Map<String, serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<serde_json::Value>,
 }
 
 impl PipeResponse {
-    pub fn success(message: &str) -> Self {
+    fn from_message(success: bool, message: Message) -> Self {
         Self {
-            success: true,
-            message: Some(message.to_string()),
+            success,
+            message: Some(message.text()),
+            message_id: Some(message.id.as_str().to_string()),
+            params: message.params,
             data: None,
         }
     }
 
-    pub fn success_with_data(message: &str, data: serde_json::Value) -> Self {
+    pub fn success(message: impl Into<Message>) -> Self {
+        Self::from_message(true, message.into())
+    }
+
+    pub fn success_with_data(message: impl Into<Message>, data: serde_json::Value) -> Self {
         Self {
-            success: true,
-            message: Some(message.to_string()),
             data: Some(data),
+            ..Self::from_message(true, message.into())
         }
     }
 
-    pub fn error(message: &str) -> Self {
-        Self {
-            success: false,
-            message: Some(message.to_string()),
-            data: None,
-        }
+    pub fn error(message: impl Into<Message>) -> Self {
+        Self::from_message(false, message.into())
     }
 }
 
@@ -140,6 +1976,8 @@ pub enum ChangeType {
     Stop = 2,
     Remove = 4,
     Pause = 8,
+    /// 终止进程但保持启用，由守护循环在下个周期重启
+    Kill = 16,
 }
 
 impl BitOr for ChangeType {
@@ -164,7 +2002,78 @@ pub struct ConfigChange {
 
 pub const SERVICE_NAME: &str = "ProcessGuardService";
 pub const PIPE_NAME: &str = "ProcessGuardService";
+/// 托盘程序通道的管道名
+pub const TRAY_PIPE_NAME: &str = "ProcessGuardTray";
 pub const CONFIG_FILE_NAME: &str = "config.json";
 pub const CONFIG_BACKUP_FILE_NAME: &str = "config_bak.json";
+pub const EVENT_DB_FILE_NAME: &str = "events.db";
 pub const CHECK_INTERVAL_MS: u64 = 3000;
 pub const DEFAULT_HEARTBEAT_TIMEOUT_MS: u64 = 10000;
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        BulkAction, HeartbeatAction, HeartbeatPolicy, ItemSelector, MonitorItem, PipeRequest,
+        PortCheckConfig,
+    };
+
+    #[test]
+    fn pipe_requests_are_tagged_by_type() {
+        let request: PipeRequest =
+            serde_json::from_str(r#"{"type":"heartbeat","item_id":"app","timestamp":1}"#).unwrap();
+        assert_eq!(request.kind(), "heartbeat");
+        let json = serde_json::to_value(PipeRequest::Kill {
+            id: "app".to_string(),
+        })
+        .unwrap();
+        assert_eq!(json, serde_json::json!({ "type": "kill", "id": "app" }));
+        // 旧客户端在无参数请求中附带的多余字段被忽略
+        assert!(serde_json::from_str::<PipeRequest>(r#"{"type":"list","id":null}"#).is_ok());
+    }
+
+    #[test]
+    fn requests_missing_required_fields_are_rejected() {
+        assert!(serde_json::from_str::<PipeRequest>(r#"{"type":"add"}"#).is_err());
+        assert!(serde_json::from_str::<PipeRequest>(r#"{"type":"stop","item_id":"app"}"#).is_err());
+        assert!(serde_json::from_str::<PipeRequest>(r#"{"type":"no_such_request"}"#).is_err());
+    }
+
+    #[test]
+    fn bulk_selector_requires_every_given_criterion() {
+        let request: PipeRequest = serde_json::from_str(
+            r#"{"type":"bulk","action":"disable","selector":{"tag":"Edge","name":"kiosk-*"}}"#,
+        )
+        .unwrap();
+        let PipeRequest::Bulk { action, selector } = request else {
+            panic!("not a bulk request");
+        };
+        assert_eq!(action, BulkAction::Disable);
+
+        let item = |id: &str, tags: &[&str]| MonitorItem {
+            id: id.to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            group: Some("lobby".to_string()),
+            ..MonitorItem::new(format!(r"C:\{}.exe", id), format!("Display {}", id))
+        };
+        assert!(selector.matches(&item("kiosk-01", &["edge", "display"])));
+        assert!(!selector.matches(&item("kiosk-02", &["display"])));
+        assert!(!selector.matches(&item("signage-01", &["edge"])));
+
+        let group = ItemSelector {
+            group: Some("LOBBY".to_string()),
+            ..ItemSelector::default()
+        };
+        assert!(group.matches(&item("signage-01", &[])));
+        assert!(ItemSelector::default().is_empty());
+    }
+
+    #[test]
+    fn unhealthy_threshold_is_accepted_for_every_check() {
+        let check: PortCheckConfig =
+            serde_json::from_str(r#"{"port":80,"unhealthy_threshold":5}"#).unwrap();
+        assert_eq!(check.failure_threshold, 5);
+        let policy: HeartbeatPolicy = serde_json::from_str(r#"{"unhealthy_threshold":3}"#).unwrap();
+        assert_eq!(policy.consecutive_misses, 3);
+        assert_eq!(policy.action, HeartbeatAction::Restart);
+    }
+}
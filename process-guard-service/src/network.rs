@@ -0,0 +1,145 @@
+use crate::models::{NetworkGate, NetworkLevel};
+use std::net::{IpAddr, ToSocketAddrs};
+use std::time::Duration;
+use windows::Win32::NetworkManagement::IpHelper::{
+    IcmpCloseHandle, IcmpCreateFile, IcmpSendEcho, ICMP_ECHO_REPLY,
+};
+use windows::Win32::Networking::NetworkListManager::{
+    INetworkListManager, NetworkListManager, NLM_CONNECTIVITY_IPV4_INTERNET,
+    NLM_CONNECTIVITY_IPV4_LOCALNETWORK, NLM_CONNECTIVITY_IPV4_SUBNET,
+    NLM_CONNECTIVITY_IPV6_INTERNET, NLM_CONNECTIVITY_IPV6_LOCALNETWORK,
+    NLM_CONNECTIVITY_IPV6_SUBNET,
+};
+use windows::Win32::System::Com::{
+    CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_ALL, COINIT_MULTITHREADED,
+};
+
+/// 网络门控的判定结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GateState {
+    Ready,
+    Waiting,
+    /// 等待超时，不再阻止启动
+    TimedOut,
+}
+
+/// timeout_ms 为 0 表示一直等待
+pub fn gate_state(ready: bool, waited: Duration, timeout_ms: u64) -> GateState {
+    if ready {
+        GateState::Ready
+    } else if timeout_ms > 0 && waited >= Duration::from_millis(timeout_ms) {
+        GateState::TimedOut
+    } else {
+        GateState::Waiting
+    }
+}
+
+/// NLM_CONNECTIVITY 位标志是否满足要求的连接级别
+fn connectivity_satisfies(flags: i32, level: NetworkLevel) -> bool {
+    let internet = NLM_CONNECTIVITY_IPV4_INTERNET.0 | NLM_CONNECTIVITY_IPV6_INTERNET.0;
+    let required = match level {
+        NetworkLevel::Local => {
+            internet
+                | NLM_CONNECTIVITY_IPV4_SUBNET.0
+                | NLM_CONNECTIVITY_IPV4_LOCALNETWORK.0
+                | NLM_CONNECTIVITY_IPV6_SUBNET.0
+                | NLM_CONNECTIVITY_IPV6_LOCALNETWORK.0
+        }
+        NetworkLevel::Internet => internet,
+    };
+    flags & required != 0
+}
+
+/// 通过 Network List Manager 查询当前连接状态
+fn query_connectivity() -> Result<i32, String> {
+    unsafe {
+        let initialized = CoInitializeEx(None, COINIT_MULTITHREADED).is_ok();
+        let result =
+            CoCreateInstance::<_, INetworkListManager>(&NetworkListManager, None, CLSCTX_ALL)
+                .and_then(|nlm| nlm.GetConnectivity())
+                .map(|connectivity| connectivity.0)
+                .map_err(|e| format!("NetworkListManager query failed: {}", e));
+        if initialized {
+            CoUninitialize();
+        }
+        result
+    }
+}
+
+/// 向主机发送一次 ICMP echo，仅支持 IPv4
+fn ping(host: &str, timeout_ms: u32) -> Result<(), String> {
+    let address = (host, 0)
+        .to_socket_addrs()
+        .map_err(|e| format!("cannot resolve {}: {}", host, e))?
+        .find_map(|addr| match addr.ip() {
+            IpAddr::V4(ip) => Some(ip),
+            IpAddr::V6(_) => None,
+        })
+        .ok_or_else(|| format!("{} has no IPv4 address", host))?;
+
+    let payload = *b"ProcessGuard";
+    let mut reply = vec![0u8; std::mem::size_of::<ICMP_ECHO_REPLY>() + payload.len() + 8];
+    unsafe {
+        let handle = IcmpCreateFile().map_err(|e| format!("IcmpCreateFile failed: {}", e))?;
+        let replies = IcmpSendEcho(
+            handle,
+            u32::from_ne_bytes(address.octets()),
+            payload.as_ptr() as *const _,
+            payload.len() as u16,
+            None,
+            reply.as_mut_ptr() as *mut _,
+            reply.len() as u32,
+            timeout_ms,
+        );
+        let _ = IcmpCloseHandle(handle);
+        if replies == 0 {
+            return Err(format!("no ICMP reply from {}", host));
+        }
+        let echo = std::ptr::read_unaligned(reply.as_ptr() as *const ICMP_ECHO_REPLY);
+        if echo.Status != 0 {
+            return Err(format!(
+                "ICMP echo to {} failed with status {}",
+                host, echo.Status
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 检查网络是否满足监控项的启动条件，不满足时返回原因
+pub fn check(gate: &NetworkGate) -> Result<(), String> {
+    let flags = query_connectivity()?;
+    if !connectivity_satisfies(flags, gate.level) {
+        return Err(format!("no {} connectivity", gate.level.as_str()));
+    }
+    match &gate.ping_host {
+        Some(host) => ping(host, gate.ping_timeout_ms),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{connectivity_satisfies, gate_state, GateState};
+    use crate::models::NetworkLevel;
+    use std::time::Duration;
+
+    #[test]
+    fn waits_until_ready_or_timeout() {
+        let waited = Duration::from_secs(30);
+        assert_eq!(gate_state(true, waited, 10_000), GateState::Ready);
+        assert_eq!(gate_state(false, waited, 60_000), GateState::Waiting);
+        assert_eq!(gate_state(false, waited, 30_000), GateState::TimedOut);
+        assert_eq!(gate_state(false, waited, 0), GateState::Waiting);
+    }
+
+    #[test]
+    fn internet_level_needs_internet_flag() {
+        // IPV4_LOCALNETWORK | IPV4_NOTRAFFIC
+        let local = 0x20 | 0x1;
+        assert!(connectivity_satisfies(local, NetworkLevel::Local));
+        assert!(!connectivity_satisfies(local, NetworkLevel::Internet));
+        assert!(connectivity_satisfies(0x40, NetworkLevel::Internet));
+        assert!(!connectivity_satisfies(0x1, NetworkLevel::Local));
+    }
+}
@@ -0,0 +1,359 @@
+use crate::models::{MonitorItem, RestartReason, WebhookConfig, SERVICE_NAME};
+use crate::store::now_unix_ms;
+use log::{debug, error, info, warn};
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// 可投递给通知渠道的守护事件
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub event: &'static str,
+    pub message: String,
+    pub item: Option<MonitorItem>,
+    pub details: serde_json::Value,
+}
+
+impl Notification {
+    pub fn service_started(item_count: usize) -> Self {
+        Self {
+            event: "service_started",
+            message: format!("{} started with {} monitor items", SERVICE_NAME, item_count),
+            item: None,
+            details: serde_json::json!({ "total_items": item_count }),
+        }
+    }
+
+    pub fn service_stopped() -> Self {
+        Self {
+            event: "service_stopped",
+            message: format!("{} stopped", SERVICE_NAME),
+            item: None,
+            details: serde_json::Value::Null,
+        }
+    }
+
+    pub fn process_restarted(
+        item: &MonitorItem,
+        cause: RestartReason,
+        reason: &str,
+        restart_count: u32,
+    ) -> Self {
+        Self {
+            event: "process_restarted",
+            message: format!("{} was restarted: {}", item.name, reason),
+            item: Some(item.clone()),
+            details: serde_json::json!({
+                "reason": reason,
+                "cause": cause,
+                "restart_count": restart_count,
+            }),
+        }
+    }
+
+    pub fn item_failed(item: &MonitorItem, reason: &str) -> Self {
+        Self {
+            event: "item_failed",
+            message: format!("{} entered failed state: {}", item.name, reason),
+            item: Some(item.clone()),
+            details: serde_json::json!({ "reason": reason }),
+        }
+    }
+
+    pub fn heartbeat_missing(item: &MonitorItem, missing: Duration) -> Self {
+        Self {
+            event: "heartbeat_missing",
+            message: format!(
+                "{} has not sent a heartbeat for {}s",
+                item.name,
+                missing.as_secs()
+            ),
+            item: Some(item.clone()),
+            details: serde_json::json!({ "missing_secs": missing.as_secs() }),
+        }
+    }
+
+    pub fn crash_loop(
+        item: &MonitorItem,
+        restarts: usize,
+        window: Duration,
+        forensic_bundle: Option<&Path>,
+    ) -> Self {
+        Self {
+            event: "crash_loop",
+            message: format!(
+                "{} restarted {} times within {}s",
+                item.name,
+                restarts,
+                window.as_secs()
+            ),
+            item: Some(item.clone()),
+            details: serde_json::json!({
+                "restarts_in_window": restarts,
+                "window_secs": window.as_secs(),
+                "forensic_bundle": forensic_bundle,
+            }),
+        }
+    }
+
+    pub fn signature_rejected(item: &MonitorItem, reason: &str) -> Self {
+        Self {
+            event: "signature_rejected",
+            message: format!("Refused to launch {}: {}", item.name, reason),
+            item: Some(item.clone()),
+            details: serde_json::json!({ "reason": reason }),
+        }
+    }
+
+    pub fn hash_mismatch(item: &MonitorItem, reason: &str) -> Self {
+        Self {
+            event: "hash_mismatch",
+            message: format!("Refused to launch {}: {}", item.name, reason),
+            item: Some(item.clone()),
+            details: serde_json::json!({ "reason": reason }),
+        }
+    }
+
+    pub fn process_blocked(item: &MonitorItem, pids: &[u32], suppressed: u32) -> Self {
+        Self {
+            event: "process_blocked",
+            message: format!(
+                "Terminated forbidden process {} (PID {:?})",
+                item.name, pids
+            ),
+            item: Some(item.clone()),
+            details: serde_json::json!({ "pids": pids, "suppressed_since_last": suppressed }),
+        }
+    }
+
+    pub fn orphan_detected(item: &MonitorItem, pid: u32, policy: &str) -> Self {
+        Self {
+            event: "orphan_detected",
+            message: format!(
+                "{} was already running at service start (PID {}), policy {}",
+                item.name, pid, policy
+            ),
+            item: Some(item.clone()),
+            details: serde_json::json!({ "pid": pid, "policy": policy }),
+        }
+    }
+
+    pub fn escalation(item: &MonitorItem, action: &str, succeeded: bool, detail: &str) -> Self {
+        Self {
+            event: "escalation",
+            message: format!(
+                "Escalation {} for crash-looping {} {}: {}",
+                action,
+                item.name,
+                if succeeded { "succeeded" } else { "failed" },
+                detail
+            ),
+            item: Some(item.clone()),
+            details: serde_json::json!({
+                "action": action,
+                "succeeded": succeeded,
+                "detail": detail,
+            }),
+        }
+    }
+
+    pub fn observed(item: &MonitorItem, action: &str, reason: &str) -> Self {
+        Self {
+            event: "observed_action",
+            message: format!("Observe mode: would {} {} ({})", action, item.name, reason),
+            item: Some(item.clone()),
+            details: serde_json::json!({
+                "action": action,
+                "reason": reason,
+            }),
+        }
+    }
+
+    pub fn session_blocked(
+        item: &MonitorItem,
+        failures: u32,
+        retry: Duration,
+        error: &str,
+    ) -> Self {
+        Self {
+            event: "session_blocked",
+            message: format!(
+                "{} is blocked: no user session ({} consecutive failures, retrying every {}s): {}",
+                item.name,
+                failures,
+                retry.as_secs(),
+                error
+            ),
+            item: Some(item.clone()),
+            details: serde_json::json!({
+                "failures": failures,
+                "retry_secs": retry.as_secs(),
+                "error": error,
+            }),
+        }
+    }
+
+    pub fn ha_role_changed(active: bool, reason: &str) -> Self {
+        let role = if active { "active" } else { "standby" };
+        Self {
+            event: "ha_role_changed",
+            message: format!("{} is now {}: {}", SERVICE_NAME, role, reason),
+            item: None,
+            details: serde_json::json!({ "role": role, "reason": reason }),
+        }
+    }
+
+    pub fn to_payload(&self) -> serde_json::Value {
+        let host = std::env::var("COMPUTERNAME").unwrap_or_default();
+        serde_json::json!({
+            "event": self.event,
+            "service": SERVICE_NAME,
+            "host": host,
+            "timestamp": now_unix_ms(),
+            "message": self.message,
+            "item": self.item.as_ref().map(|item| serde_json::json!({
+                "id": item.id,
+                "name": item.name,
+                "exe_path": item.exe_path,
+            })),
+            "details": self.details,
+        })
+    }
+}
+
+fn webhook_accepts(webhook: &WebhookConfig, event: &str) -> bool {
+    webhook.events.is_empty() || webhook.events.iter().any(|e| e == event || e == "*")
+}
+
+fn deliver(webhooks: &[WebhookConfig], notification: &Notification) {
+    let body = notification.to_payload().to_string();
+
+    for webhook in webhooks
+        .iter()
+        .filter(|w| webhook_accepts(w, notification.event))
+    {
+        let mut headers: Vec<(String, String)> = webhook
+            .headers
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        if !headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+        {
+            headers.push(("Content-Type".to_string(), "application/json".to_string()));
+        }
+
+        match crate::http::request(
+            "POST",
+            &webhook.url,
+            &headers,
+            Some(body.as_bytes()),
+            Duration::from_millis(webhook.timeout_ms),
+        ) {
+            Ok(response) if (200..300).contains(&response.status) => {
+                debug!(
+                    "Webhook {} accepted {} event",
+                    webhook.url, notification.event
+                );
+            }
+            Ok(response) => {
+                warn!(
+                    "Webhook {} rejected {} event with HTTP {}: {}",
+                    webhook.url,
+                    notification.event,
+                    response.status,
+                    response.body.trim()
+                );
+            }
+            Err(e) => {
+                error!(
+                    "Failed to deliver {} event to {}: {}",
+                    notification.event, webhook.url, e
+                );
+            }
+        }
+    }
+}
+
+/// 在后台线程中投递通知，避免阻塞守护循环
+pub struct Notifier {
+    sender: Mutex<Option<Sender<Notification>>>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Notifier {
+    pub fn new(webhooks: Vec<WebhookConfig>) -> Self {
+        let (sender, receiver) = mpsc::channel::<Notification>();
+
+        let worker = std::thread::spawn(move || {
+            for notification in receiver {
+                deliver(&webhooks, &notification);
+            }
+        });
+
+        Self {
+            sender: Mutex::new(Some(sender)),
+            worker: Mutex::new(Some(worker)),
+        }
+    }
+
+    pub fn notify(&self, notification: Notification) {
+        info!(
+            "Notification: [{}] {}",
+            notification.event, notification.message
+        );
+        if let Some(sender) = self.sender.lock().unwrap().as_ref() {
+            let _ = sender.send(notification);
+        }
+    }
+
+    /// 关闭通道并等待已排队的通知投递完成
+    pub fn shutdown(&self) {
+        self.sender.lock().unwrap().take();
+        if let Some(worker) = self.worker.lock().unwrap().take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{webhook_accepts, Notification};
+    use crate::models::{MonitorItem, WebhookConfig};
+    use std::time::Duration;
+
+    fn webhook(events: &[&str]) -> WebhookConfig {
+        WebhookConfig {
+            url: "https://example.com/hook".to_string(),
+            headers: Default::default(),
+            events: events.iter().map(|e| e.to_string()).collect(),
+            timeout_ms: 5000,
+        }
+    }
+
+    #[test]
+    fn empty_event_filter_accepts_everything() {
+        assert!(webhook_accepts(&webhook(&[]), "crash_loop"));
+    }
+
+    #[test]
+    fn event_filter_limits_delivery() {
+        let hook = webhook(&["service_stopped"]);
+        assert!(webhook_accepts(&hook, "service_stopped"));
+        assert!(!webhook_accepts(&hook, "process_restarted"));
+    }
+
+    #[test]
+    fn crash_loop_payload_includes_item_details() {
+        let item = MonitorItem::new(r"C:\App.exe".to_string(), "App".to_string());
+        let payload =
+            Notification::crash_loop(&item, 5, Duration::from_secs(300), None).to_payload();
+
+        assert_eq!(payload["event"], "crash_loop");
+        assert_eq!(payload["item"]["name"], "App");
+        assert_eq!(payload["details"]["restarts_in_window"], 5);
+    }
+}
@@ -0,0 +1,203 @@
+use log::debug;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, OnceLock};
+use windows::Win32::Foundation::{
+    CloseHandle, SetHandleInformation, HANDLE, HANDLE_FLAGS, HANDLE_FLAG_INHERIT,
+};
+use windows::Win32::Security::SECURITY_ATTRIBUTES;
+use windows::Win32::Storage::FileSystem::ReadFile;
+use windows::Win32::System::Pipes::CreatePipe;
+
+/// 服务日志缓冲的容量，供 stream_logs 推送新日志
+const SERVICE_LOG_BUFFER_BYTES: usize = 256 * 1024;
+
+static SERVICE_LOG: OnceLock<Arc<OutputBuffer>> = OnceLock::new();
+
+/// 服务日志的最近部分；只有服务程序自己的日志实现会写入，嵌入为库时为空
+pub fn service_log() -> &'static Arc<OutputBuffer> {
+    SERVICE_LOG.get_or_init(|| Arc::new(OutputBuffer::new(SERVICE_LOG_BUFFER_BYTES)))
+}
+
+/// 从 pending 中取出所有完整的行（去掉行尾 \r\n），不完整的部分留待下次
+pub fn take_lines(pending: &mut Vec<u8>) -> Vec<String> {
+    let Some(last_newline) = pending.iter().rposition(|b| *b == b'\n') else {
+        return Vec::new();
+    };
+    let complete: Vec<u8> = pending.drain(..=last_newline).collect();
+    String::from_utf8_lossy(&complete)
+        .lines()
+        .map(|line| line.trim_end_matches('\r').to_string())
+        .collect()
+}
+
+/// 保留最近 capacity 字节输出的环形缓冲，跨重启保留，用于查看崩溃前的最后几行
+#[derive(Debug)]
+pub struct OutputBuffer {
+    inner: Mutex<OutputInner>,
+}
+
+#[derive(Debug)]
+struct OutputInner {
+    data: VecDeque<u8>,
+    capacity: usize,
+    /// 因超出容量被丢弃的字节数
+    dropped: u64,
+}
+
+impl OutputBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(OutputInner {
+                data: VecDeque::with_capacity(capacity.min(64 * 1024)),
+                capacity,
+                dropped: 0,
+            }),
+        }
+    }
+
+    pub fn push(&self, bytes: &[u8]) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.data.extend(bytes);
+        inner.trim();
+    }
+
+    /// 修改容量；缩小时丢弃最早的输出
+    pub fn set_capacity(&self, capacity: usize) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.capacity = capacity;
+        inner.trim();
+    }
+
+    /// 写入位置：自创建以来写入的总字节数
+    pub fn end(&self) -> u64 {
+        let inner = self.inner.lock().unwrap();
+        inner.dropped + inner.data.len() as u64
+    }
+
+    /// position 之后写入的内容与新的写入位置；position 之后的部分已被丢弃时从缓冲开头读起
+    pub fn read_from(&self, position: u64) -> (Vec<u8>, u64) {
+        let inner = self.inner.lock().unwrap();
+        let end = inner.dropped + inner.data.len() as u64;
+        let skip = position
+            .saturating_sub(inner.dropped)
+            .min(inner.data.len() as u64) as usize;
+        (inner.data.iter().skip(skip).copied().collect(), end)
+    }
+
+    /// 当前内容；发生过截断时从第一个完整行开始，第二项表示是否截断
+    pub fn snapshot(&self) -> (String, bool) {
+        let inner = self.inner.lock().unwrap();
+        let bytes: Vec<u8> = inner.data.iter().copied().collect();
+        let truncated = inner.dropped > 0;
+        let start = if truncated {
+            bytes
+                .iter()
+                .position(|b| *b == b'\n')
+                .map_or(0, |newline| newline + 1)
+        } else {
+            0
+        };
+        (
+            String::from_utf8_lossy(&bytes[start..]).into_owned(),
+            truncated,
+        )
+    }
+}
+
+impl OutputInner {
+    fn trim(&mut self) {
+        let excess = self.data.len().saturating_sub(self.capacity);
+        if excess > 0 {
+            self.data.drain(..excess);
+            self.dropped += excess as u64;
+        }
+    }
+}
+
+/// 交给读取线程的管道读端
+struct PipeReader(HANDLE);
+
+unsafe impl Send for PipeReader {}
+
+impl Drop for PipeReader {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseHandle(self.0);
+        }
+    }
+}
+
+/// 创建匿名管道并启动读取线程把输出写入缓冲，返回供子进程 stdout/stderr 使用的可继承写端。
+///
+/// 调用方在子进程创建后（无论成功与否）必须关闭写端，子进程退出时读取线程随之结束。
+pub fn capture_into(buffer: Arc<OutputBuffer>, name: &str) -> Result<HANDLE, String> {
+    let attributes = SECURITY_ATTRIBUTES {
+        nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+        lpSecurityDescriptor: std::ptr::null_mut(),
+        bInheritHandle: true.into(),
+    };
+    let mut read = HANDLE::default();
+    let mut write = HANDLE::default();
+    unsafe {
+        CreatePipe(&mut read, &mut write, Some(&attributes), 0)
+            .map_err(|e| format!("Failed to create output pipe for {}: {}", name, e))?;
+        // 读端留在服务进程中，不能被子进程继承
+        let _ = SetHandleInformation(read, HANDLE_FLAG_INHERIT.0, HANDLE_FLAGS(0));
+    }
+
+    let reader = PipeReader(read);
+    let name = name.to_string();
+    std::thread::spawn(move || {
+        // 整体移入线程，而不是只捕获其中的 HANDLE 字段
+        let reader = reader;
+        let mut chunk = [0u8; 4096];
+        loop {
+            let mut read = 0u32;
+            let result = unsafe { ReadFile(reader.0, Some(&mut chunk), Some(&mut read), None) };
+            if result.is_err() || read == 0 {
+                break;
+            }
+            buffer.push(&chunk[..read as usize]);
+        }
+        debug!("Output capture for {} finished", name);
+    });
+    Ok(write)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{take_lines, OutputBuffer};
+
+    #[test]
+    fn keeps_only_the_most_recent_complete_lines() {
+        let buffer = OutputBuffer::new(16);
+        buffer.push(b"first\n");
+        assert_eq!(buffer.snapshot(), ("first\n".to_string(), false));
+
+        buffer.push(b"second\nthird\n");
+        assert_eq!(buffer.snapshot(), ("second\nthird\n".to_string(), true));
+
+        buffer.set_capacity(4);
+        buffer.push(b"ab\ncd");
+        assert_eq!(buffer.snapshot(), ("cd".to_string(), true));
+    }
+
+    #[test]
+    fn streams_only_new_complete_lines() {
+        let buffer = OutputBuffer::new(1024);
+        buffer.push(b"old\n");
+        let position = buffer.end();
+        buffer.push(b"one\r\ntw");
+
+        let (bytes, position) = buffer.read_from(position);
+        let mut pending = bytes;
+        assert_eq!(take_lines(&mut pending), vec!["one"]);
+        assert_eq!(pending, b"tw");
+
+        buffer.push(b"o\n");
+        let (bytes, _) = buffer.read_from(position);
+        pending.extend(bytes);
+        assert_eq!(take_lines(&mut pending), vec!["two"]);
+        assert!(pending.is_empty());
+    }
+}
@@ -0,0 +1,176 @@
+use crate::guardian::Guardian;
+use crate::models::{PeerConfig, PipeRequest, PIPE_NAME};
+use log::{debug, info, warn};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 对端通过 peer_status 报告的状态
+#[derive(Debug, Clone, PartialEq)]
+struct PeerView {
+    node: String,
+    priority: u32,
+    active: bool,
+}
+
+/// 本机计算机名，用于优先级相同时决定主节点
+pub fn node_name() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_default()
+}
+
+/// 根据对端状态决定本节点是否应为主节点
+///
+/// 已有主节点时不抢占；双主时保留优先级高（priority 小、其次计算机名小）的一方；
+/// 联系不上对端超过超时时间即接管。
+fn decide_active(
+    current: bool,
+    me: (u32, &str),
+    peer: Option<&PeerView>,
+    peer_silent: bool,
+) -> bool {
+    let Some(peer) = peer else {
+        return current || peer_silent;
+    };
+    let preferred = (me.0, me.1.to_lowercase()) < (peer.priority, peer.node.to_lowercase());
+    match (current, peer.active) {
+        (true, true) => preferred,
+        (false, true) => false,
+        (true, false) => true,
+        (false, false) => preferred,
+    }
+}
+
+/// 定期查询对端守护服务并切换本节点的主备角色
+pub struct PeerMonitor {
+    guardian: Arc<Guardian>,
+    running: Arc<Mutex<bool>>,
+    peer: PeerConfig,
+}
+
+impl PeerMonitor {
+    pub fn new(guardian: Arc<Guardian>, running: Arc<Mutex<bool>>, peer: PeerConfig) -> Self {
+        Self {
+            guardian,
+            running,
+            peer,
+        }
+    }
+
+    fn query_peer(&self) -> Result<PeerView, String> {
+        let response = crate::pipe_client::request_on(
+            Some(&self.peer.host),
+            PIPE_NAME,
+            &PipeRequest::PeerStatus,
+        )?;
+        if !response.success {
+            return Err(response.message.unwrap_or_default());
+        }
+        let data = response.data.unwrap_or_default();
+        Ok(PeerView {
+            node: data["node"].as_str().unwrap_or(&self.peer.host).to_string(),
+            priority: data["priority"].as_u64().unwrap_or_default() as u32,
+            active: data["active"].as_bool().unwrap_or(false),
+        })
+    }
+
+    pub fn run(&self) {
+        let me = node_name();
+        let timeout = Duration::from_millis(self.peer.timeout_ms);
+        let interval = Duration::from_millis(self.peer.interval_ms.max(100));
+        info!(
+            "HA peer monitor started: {} (priority {}) paired with {}",
+            me, self.peer.priority, self.peer.host
+        );
+
+        // 启动时视同刚联系过对端，避免对端仍在运行时抢先接管
+        let mut last_contact = Instant::now();
+        while *self.running.lock().unwrap() {
+            let peer = match self.query_peer() {
+                Ok(peer) => {
+                    last_contact = Instant::now();
+                    debug!("HA peer {} active={}", peer.node, peer.active);
+                    Some(peer)
+                }
+                Err(e) => {
+                    debug!("HA peer {} unreachable: {}", self.peer.host, e);
+                    None
+                }
+            };
+            let silent = last_contact.elapsed() >= timeout;
+            let current = self.guardian.is_ha_active();
+            let active = decide_active(current, (self.peer.priority, &me), peer.as_ref(), silent);
+            if active != current {
+                let reason = match &peer {
+                    None => format!("peer {} silent for {}s", self.peer.host, timeout.as_secs()),
+                    Some(peer) if peer.active => format!("peer {} is also active", peer.node),
+                    Some(peer) => format!("peer {} is standby", peer.node),
+                };
+                if !active {
+                    warn!("Yielding to HA peer: {}", reason);
+                }
+                self.guardian.set_ha_active(active, &reason);
+            }
+            std::thread::sleep(interval);
+        }
+        info!("HA peer monitor stopped");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decide_active, PeerView};
+
+    fn peer(priority: u32, active: bool) -> PeerView {
+        PeerView {
+            node: "NODE-B".to_string(),
+            priority,
+            active,
+        }
+    }
+
+    #[test]
+    fn takes_over_only_when_peer_is_silent_or_standby() {
+        assert!(!decide_active(false, (0, "NODE-A"), None, false));
+        assert!(decide_active(false, (0, "NODE-A"), None, true));
+        assert!(decide_active(true, (5, "NODE-A"), None, false));
+        assert!(!decide_active(
+            false,
+            (0, "NODE-A"),
+            Some(&peer(5, true)),
+            false
+        ));
+        assert!(decide_active(
+            false,
+            (0, "NODE-A"),
+            Some(&peer(5, false)),
+            false
+        ));
+        assert!(!decide_active(
+            false,
+            (5, "NODE-A"),
+            Some(&peer(0, false)),
+            false
+        ));
+    }
+
+    #[test]
+    fn split_brain_keeps_the_preferred_node() {
+        assert!(decide_active(
+            true,
+            (0, "NODE-A"),
+            Some(&peer(5, true)),
+            false
+        ));
+        assert!(!decide_active(
+            true,
+            (5, "NODE-A"),
+            Some(&peer(0, true)),
+            false
+        ));
+        assert!(decide_active(
+            true,
+            (1, "node-a"),
+            Some(&peer(1, true)),
+            false
+        ));
+    }
+}
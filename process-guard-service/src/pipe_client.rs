@@ -0,0 +1,208 @@
+use crate::models::{PipeRequest, PipeResponse, PIPE_NAME};
+use crate::session0::to_wide_string;
+use windows::core::{PCWSTR, PWSTR};
+use windows::Win32::Foundation::{CloseHandle, ERROR_SUCCESS, GENERIC_READ, GENERIC_WRITE};
+use windows::Win32::NetworkManagement::WNet::{
+    WNetAddConnection2W, CONNECT_TEMPORARY, NETRESOURCEW, RESOURCETYPE_ANY,
+};
+use windows::Win32::Storage::FileSystem::{
+    CreateFileW, ReadFile, WriteFile, FILE_FLAGS_AND_ATTRIBUTES, FILE_SHARE_NONE, OPEN_EXISTING,
+};
+
+/// 管道完整路径；host 为 None 时连接本机
+pub fn pipe_path(host: Option<&str>, pipe_name: &str) -> String {
+    let host = host
+        .map(|h| h.trim_start_matches('\\'))
+        .filter(|h| !h.is_empty())
+        .unwrap_or(".");
+    format!("\\\\{}\\pipe\\{}", host, pipe_name)
+}
+
+/// 命令行向运行中的服务发送管道请求；服务返回失败时转为 Err
+pub fn send(request: &PipeRequest) -> Result<PipeResponse, String> {
+    send_to(None, request)
+}
+
+/// 向本机或远程主机上的服务发送请求；服务返回失败时转为 Err
+pub fn send_to(host: Option<&str>, request: &PipeRequest) -> Result<PipeResponse, String> {
+    let response = request_on(host, PIPE_NAME, request)?;
+    if !response.success {
+        return Err(response.message.unwrap_or_default());
+    }
+    Ok(response)
+}
+
+/// 以指定账户建立到远程主机 IPC$ 的临时连接，之后的管道连接使用该凭据；
+/// 不调用时使用当前登录账户
+pub fn connect_host(host: &str, user: &str, password: &str) -> Result<(), String> {
+    let mut remote = to_wide_string(&format!("\\\\{}\\IPC$", host.trim_start_matches('\\')));
+    let user = to_wide_string(user);
+    let password = to_wide_string(password);
+    let resource = NETRESOURCEW {
+        dwType: RESOURCETYPE_ANY,
+        lpRemoteName: PWSTR(remote.as_mut_ptr()),
+        ..Default::default()
+    };
+    let status = unsafe {
+        WNetAddConnection2W(
+            &resource,
+            PCWSTR(password.as_ptr()),
+            PCWSTR(user.as_ptr()),
+            CONNECT_TEMPORARY,
+        )
+    };
+    if status == ERROR_SUCCESS {
+        Ok(())
+    } else {
+        Err(format!("cannot connect to {}: {:?}", host, status))
+    }
+}
+
+/// 向本机指定名称的管道发送一个请求并读取响应；服务返回的失败响应原样返回
+pub fn request(pipe_name: &str, request: &PipeRequest) -> Result<PipeResponse, String> {
+    request_on(None, pipe_name, request)
+}
+
+/// 同 request，host 为 Some 时连接 \\host\pipe\pipe_name
+pub fn request_on(
+    host: Option<&str>,
+    pipe_name: &str,
+    request: &PipeRequest,
+) -> Result<PipeResponse, String> {
+    let request = serde_json::to_string(request).map_err(|e| e.to_string())?;
+    exchange(host, pipe_name, &request)
+}
+
+/// 发送原始 JSON 请求，用于测试服务对非法请求的处理
+pub fn request_raw(pipe_name: &str, request: &str) -> Result<PipeResponse, String> {
+    exchange(None, pipe_name, request)
+}
+
+fn exchange(host: Option<&str>, pipe_name: &str, request: &str) -> Result<PipeResponse, String> {
+    let pipe_name = to_wide_string(&pipe_path(host, pipe_name));
+    let mut response = Vec::new();
+    unsafe {
+        let handle = CreateFileW(
+            PCWSTR(pipe_name.as_ptr()),
+            (GENERIC_READ | GENERIC_WRITE).0,
+            FILE_SHARE_NONE,
+            None,
+            OPEN_EXISTING,
+            FILE_FLAGS_AND_ATTRIBUTES(0),
+            None,
+        )
+        .map_err(|e| format!("cannot connect to service pipe: {}", e))?;
+
+        let mut written = 0u32;
+        let write = WriteFile(handle, Some(request.as_bytes()), Some(&mut written), None);
+        if let Err(e) = write {
+            let _ = CloseHandle(handle);
+            return Err(format!("cannot send request: {}", e));
+        }
+
+        // 服务写完响应后断开管道，读到错误或 0 字节即结束
+        let mut buffer = vec![0u8; 65536];
+        loop {
+            let mut read = 0u32;
+            let result = ReadFile(handle, Some(&mut buffer), Some(&mut read), None);
+            response.extend_from_slice(&buffer[..read as usize]);
+            if result.is_err() || read == 0 {
+                break;
+            }
+        }
+        let _ = CloseHandle(handle);
+    }
+
+    serde_json::from_slice(&response).map_err(|e| format!("invalid response from service: {}", e))
+}
+
+/// 发送 stream_logs 之类的订阅请求：服务先返回一行响应，之后每行一个 JSON 事件。
+/// on_event 返回 false 或服务断开时结束；服务拒绝订阅时返回 Err
+pub fn stream_on(
+    host: Option<&str>,
+    pipe_name: &str,
+    request: &PipeRequest,
+    mut on_event: impl FnMut(&serde_json::Value) -> bool,
+) -> Result<(), String> {
+    let pipe_name = to_wide_string(&pipe_path(host, pipe_name));
+    let request = serde_json::to_string(request).map_err(|e| e.to_string())?;
+
+    unsafe {
+        let handle = CreateFileW(
+            PCWSTR(pipe_name.as_ptr()),
+            (GENERIC_READ | GENERIC_WRITE).0,
+            FILE_SHARE_NONE,
+            None,
+            OPEN_EXISTING,
+            FILE_FLAGS_AND_ATTRIBUTES(0),
+            None,
+        )
+        .map_err(|e| format!("cannot connect to service pipe: {}", e))?;
+
+        let mut written = 0u32;
+        let write = WriteFile(handle, Some(request.as_bytes()), Some(&mut written), None);
+        if let Err(e) = write {
+            let _ = CloseHandle(handle);
+            return Err(format!("cannot send request: {}", e));
+        }
+
+        let mut buffer = vec![0u8; 65536];
+        let mut pending = Vec::new();
+        let mut acknowledged = false;
+        let result = 'read: loop {
+            let mut read = 0u32;
+            let result = ReadFile(handle, Some(&mut buffer), Some(&mut read), None);
+            pending.extend_from_slice(&buffer[..read as usize]);
+            // 被拒绝时服务只写一个不带换行的响应后断开
+            let ended = result.is_err() || read == 0;
+            if ended && !acknowledged && !pending.is_empty() {
+                pending.push(b'\n');
+            }
+            for line in crate::output::take_lines(&mut pending) {
+                if !acknowledged {
+                    let response: PipeResponse = match serde_json::from_str(&line) {
+                        Ok(response) => response,
+                        Err(e) => break 'read Err(format!("invalid response from service: {}", e)),
+                    };
+                    if !response.success {
+                        break 'read Err(response.message.unwrap_or_default());
+                    }
+                    acknowledged = true;
+                    continue;
+                }
+                if let Ok(event) = serde_json::from_str(&line) {
+                    if !on_event(&event) {
+                        break 'read Ok(());
+                    }
+                }
+            }
+            if ended {
+                break if acknowledged {
+                    Ok(())
+                } else {
+                    Err("service closed the pipe".to_string())
+                };
+            }
+        };
+        let _ = CloseHandle(handle);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::pipe_path;
+
+    #[test]
+    fn builds_local_and_remote_pipe_paths() {
+        assert_eq!(
+            pipe_path(None, "ProcessGuardService"),
+            r"\\.\pipe\ProcessGuardService"
+        );
+        assert_eq!(
+            pipe_path(Some("SRV01"), "ProcessGuardService"),
+            r"\\SRV01\pipe\ProcessGuardService"
+        );
+        assert_eq!(pipe_path(Some(r"\\SRV01"), "x"), r"\\SRV01\pipe\x");
+    }
+}
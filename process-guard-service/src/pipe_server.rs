@@ -1,54 +1,114 @@
+use crate::access::{
+    allowed_in_safe_mode, caller_role, client_role_cap, pipe_client, pipe_security, required_role,
+    PipeClient, Role,
+};
 use crate::guardian::Guardian;
-use crate::models::{ChangeType, ConfigChange, PipeRequest, PipeResponse, PIPE_NAME};
-use log::{debug, error, info};
-use std::ffi::OsStr;
-use std::os::windows::ffi::OsStrExt;
+use crate::log_stream::{LogSource, StreamPipe, StreamSlot};
+use crate::messages::MessageId;
+use crate::models::{
+    BulkAction, ChangeType, ConfigChange, DuplicatePathPolicy, ItemSelector, MonitorItem,
+    PipeAccessConfig, PipeRequest, PipeResponse, PIPE_NAME,
+};
+use crate::session0::to_wide_string;
+use log::{debug, error, info, warn};
+use std::sync::atomic::AtomicUsize;
 use std::sync::Arc;
 use windows::core::PCWSTR;
-use windows::Win32::Foundation::CloseHandle;
-use windows::Win32::Storage::FileSystem::{
-    ReadFile, WriteFile, FILE_FLAGS_AND_ATTRIBUTES,
-};
+use windows::Win32::Foundation::{CloseHandle, HANDLE};
+use windows::Win32::Storage::FileSystem::{ReadFile, WriteFile, FILE_FLAGS_AND_ATTRIBUTES};
 use windows::Win32::System::Pipes::{
-    ConnectNamedPipe, CreateNamedPipeW, DisconnectNamedPipe, PIPE_READMODE_BYTE, PIPE_TYPE_BYTE,
-    PIPE_WAIT,
+    ConnectNamedPipe, CreateNamedPipeW, DisconnectNamedPipe, PIPE_READMODE_BYTE,
+    PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_WAIT,
 };
 
 const BUFFER_SIZE: u32 = 65536;
 const MAX_INSTANCES: u32 = 10;
 const TIMEOUT_MS: u32 = 0;
 const PIPE_ACCESS_DUPLEX: u32 = 0x00000003;
+/// metrics_history 单次最多返回的采样数
+const MAX_HISTORY_SAMPLES: u32 = 10000;
+
+/// 拒绝监控服务自身与 Windows 关键系统进程
+fn validate_target(item: &MonitorItem) -> Result<(), PipeResponse> {
+    crate::guard_target::check(item).map_err(|e| {
+        error!("拒绝监控项 {}: {}", item.id, e);
+        PipeResponse::error(MessageId::TargetRejected.with("detail", e))
+    })
+}
+
+fn validate_firewall(item: &MonitorItem) -> Result<(), PipeResponse> {
+    crate::firewall::validate(item).map_err(|e| {
+        error!("防火墙配置无效 {}: {}", item.id, e);
+        PipeResponse::error(MessageId::InvalidFirewall.with("detail", e))
+    })
+}
+
+fn validate_cleanup(item: &MonitorItem) -> Result<(), PipeResponse> {
+    crate::cleanup::validate(&item.cleanup).map_err(|e| {
+        error!("清理规则无效 {}: {}", item.id, e);
+        PipeResponse::error(MessageId::InvalidCleanup.with("detail", e))
+    })
+}
 
-fn to_wide_string(s: &str) -> Vec<u16> {
-    OsStr::new(s)
-        .encode_wide()
-        .chain(std::iter::once(0))
-        .collect()
+/// 拒绝引用未知检查名称的健康表达式
+fn validate_health_expression(item: &MonitorItem) -> Result<(), PipeResponse> {
+    let Some(expr) = &item.health_expression else {
+        return Ok(());
+    };
+    let unknown = crate::health_expr::unknown_checks(expr);
+    if unknown.is_empty() {
+        Ok(())
+    } else {
+        error!("健康表达式包含未知检查: {:?}", unknown);
+        Err(PipeResponse::error(
+            MessageId::UnknownHealthChecks.with("checks", unknown.join(", ")),
+        ))
+    }
+}
+
+/// 请求中的 type 字段，用于记录客户端错误；请求不是 JSON 时返回 None
+fn request_type(request_data: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(request_data).ok()?;
+    value.get("type")?.as_str().map(str::to_string)
 }
 
 pub struct PipeServer {
     guardian: Arc<Guardian>,
     running: Arc<std::sync::Mutex<bool>>,
-    ready_signal: Option<Arc<crate::service::StartupGate>>,
+    ready_signal: Option<Arc<crate::startup_gate::StartupGate>>,
+    pipe_name: String,
+    /// 正在进行的 stream_logs 订阅数
+    active_streams: Arc<AtomicUsize>,
 }
 
 impl PipeServer {
     pub fn new(
         guardian: Arc<Guardian>,
         running: Arc<std::sync::Mutex<bool>>,
-        ready_signal: Option<Arc<crate::service::StartupGate>>,
+        ready_signal: Option<Arc<crate::startup_gate::StartupGate>>,
     ) -> Self {
         Self {
             guardian,
             running,
             ready_signal,
+            pipe_name: PIPE_NAME.to_string(),
+            active_streams: Arc::new(AtomicUsize::new(0)),
         }
     }
 
+    /// 使用其他管道名，便于测试时与已安装的服务并存
+    pub fn with_pipe_name(mut self, pipe_name: &str) -> Self {
+        self.pipe_name = pipe_name.to_string();
+        self
+    }
+
     pub fn run(&self) {
-        let pipe_name = format!("\\\\.\\pipe\\{}", PIPE_NAME);
+        let pipe_name = format!("\\\\.\\pipe\\{}", self.pipe_name);
         let pipe_name_wide = to_wide_string(&pipe_name);
         let mut ready_notified = false;
+        // 管道 DACL 随 pipe_access 与 remote_hosts 重新生成，配置不变时复用
+        let mut security_for: Option<(Option<PipeAccessConfig>, bool)> = None;
+        let mut security = None;
 
         info!("正在启动管道服务: {}", pipe_name);
 
@@ -59,16 +119,38 @@ impl PipeServer {
                 break;
             }
 
+            let (access, remote) = {
+                let config = self.guardian.get_config();
+                let config = config.lock().unwrap();
+                (
+                    config.settings.pipe_access.clone(),
+                    !config.settings.remote_hosts.is_empty(),
+                )
+            };
+
+            // 未配置 remote_hosts 时由系统直接拒绝远程连接
+            let mut pipe_mode = PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT;
+            if !remote {
+                pipe_mode |= PIPE_REJECT_REMOTE_CLIENTS;
+            }
+
+            let key = (access, remote);
+            if security_for.as_ref() != Some(&key) {
+                security = pipe_security(key.0.as_ref(), remote);
+                security_for = Some(key);
+            }
+            let attributes = security.as_ref().map(|s| s.attributes());
+
             let pipe_handle = unsafe {
                 CreateNamedPipeW(
                     PCWSTR(pipe_name_wide.as_ptr()),
                     FILE_FLAGS_AND_ATTRIBUTES(PIPE_ACCESS_DUPLEX),
-                    PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT,
+                    pipe_mode,
                     MAX_INSTANCES,
                     BUFFER_SIZE,
                     BUFFER_SIZE,
                     TIMEOUT_MS,
-                    None,
+                    attributes.as_ref().map(|a| a as *const _),
                 )
             };
 
@@ -101,6 +183,8 @@ impl PipeServer {
             }
 
             //   info!("客户端已连接到管道服务");
+            let stats = self.guardian.pipe_stats();
+            stats.record_connection();
 
             let mut buffer = vec![0u8; BUFFER_SIZE as usize];
             let mut bytes_read: u32 = 0;
@@ -110,6 +194,7 @@ impl PipeServer {
 
             if read_result.is_err() || bytes_read == 0 {
                 debug!("从管道读取失败或请求为空");
+                stats.record_client_error(None, "读取请求失败或请求为空");
                 unsafe {
                     let _ = DisconnectNamedPipe(pipe_handle);
                     let _ = CloseHandle(pipe_handle);
@@ -120,7 +205,30 @@ impl PipeServer {
             let request_data = String::from_utf8_lossy(&buffer[..bytes_read as usize]);
             //    info!("接收到请求: {}", request_data);
 
-            let response = self.handle_request(&request_data);
+            let received_at = std::time::Instant::now();
+            let response = match self.resolve_caller_role(pipe_handle) {
+                Ok(role) => match self.open_stream(&request_data, role) {
+                    // 订阅连接交给独立线程持续推送，由其负责断开与关闭管道
+                    Some(Ok((source, slot))) => {
+                        let pipe = StreamPipe(pipe_handle);
+                        let running = self.running.clone();
+                        std::thread::spawn(move || {
+                            crate::log_stream::serve(pipe, source, running, slot)
+                        });
+                        continue;
+                    }
+                    Some(Err(response)) => response,
+                    None => self.handle_request(&request_data, role),
+                },
+                Err(response) => response,
+            };
+            stats.record_request(received_at.elapsed());
+            if !response.success {
+                stats.record_client_error(
+                    request_type(&request_data).as_deref(),
+                    response.message.as_deref().unwrap_or_default(),
+                );
+            }
             let response_data = serde_json::to_string(&response).unwrap_or_default();
 
             let mut bytes_written: u32 = 0;
@@ -135,6 +243,7 @@ impl PipeServer {
 
             if write_result.is_err() {
                 error!("向管道写入响应失败");
+                stats.record_client_error(request_type(&request_data).as_deref(), "写入响应失败");
             } else {
                 // debug!("响应已发送: {}", response_data);
             }
@@ -150,255 +259,810 @@ impl PipeServer {
         info!("管道服务已停止");
     }
 
-    fn handle_request(&self, request_data: &str) -> PipeResponse {
+    /// 未配置 pipe_access 时视为 admin；配置后按调用方所属组确定角色。
+    /// 远程调用方的角色不超过其所在主机在 remote_hosts 中的 max_role
+    fn resolve_caller_role(&self, pipe_handle: HANDLE) -> Result<Role, PipeResponse> {
+        let (access, remote_hosts) = {
+            let config = self.guardian.get_config();
+            let config = config.lock().unwrap();
+            (
+                config.settings.pipe_access.clone(),
+                config.settings.remote_hosts.clone(),
+            )
+        };
+
+        let client = pipe_client(pipe_handle);
+        let cap = match (client_role_cap(&client, &remote_hosts), &client) {
+            (Some(cap), PipeClient::Local) => cap,
+            (Some(cap), PipeClient::Remote(host)) => {
+                info!("远程管道调用方: {} (最高角色 {})", host, cap.as_str());
+                cap
+            }
+            (Some(cap), PipeClient::Unknown(code)) => {
+                warn!(
+                    "无法确认管道调用方是否为本机 (错误 {}), 最高角色 {}",
+                    code,
+                    cap.as_str()
+                );
+                cap
+            }
+            (None, _) => {
+                warn!("拒绝来自未授权主机的远程管道连接: {:?}", client);
+                return Err(PipeResponse::error(MessageId::HostNotAuthorized));
+            }
+        };
+
+        let Some(access) = access else {
+            return Ok(cap);
+        };
+
+        match caller_role(pipe_handle, &access) {
+            Ok(Some(role)) => Ok(role.min(cap)),
+            Ok(None) => {
+                warn!("管道调用方不属于任何已授权的组, 拒绝访问");
+                Err(PipeResponse::error(MessageId::NoRoleGranted))
+            }
+            Err(e) => {
+                error!("识别管道调用方身份失败: {}", e);
+                Err(PipeResponse::error(MessageId::CallerUnknown))
+            }
+        }
+    }
+
+    /// stream_logs 请求：不带 id 时订阅服务日志，带 id 时订阅该监控项的输出；其他请求返回 None
+    fn open_stream(
+        &self,
+        request_data: &str,
+        caller_role: Role,
+    ) -> Option<Result<(LogSource, StreamSlot), PipeResponse>> {
+        let request: PipeRequest = serde_json::from_str(request_data).ok()?;
+        let PipeRequest::StreamLogs { id } = &request else {
+            return None;
+        };
+        let required = required_role(request.kind());
+        if caller_role < required {
+            warn!("拒绝 stream_logs 请求: 调用方为 {}", caller_role.as_str());
+            return Some(Err(PipeResponse::error(
+                MessageId::PermissionDenied
+                    .with("request", request.kind())
+                    .with("role", required.as_str()),
+            )));
+        }
+
+        let source = match id {
+            None => LogSource {
+                name: "service".to_string(),
+                buffer: crate::output::service_log().clone(),
+            },
+            Some(id) => match self.guardian.output_buffer(id) {
+                Ok(buffer) => LogSource {
+                    name: id.clone(),
+                    buffer,
+                },
+                Err(e) => {
+                    return Some(Err(PipeResponse::error(
+                        MessageId::OutputUnavailable.with("detail", e),
+                    )))
+                }
+            },
+        };
+        let Some(slot) = StreamSlot::acquire(&self.active_streams) else {
+            warn!("日志订阅数已达上限, 拒绝新的订阅");
+            return Some(Err(PipeResponse::error(
+                MessageId::TooManyStreams.with("max", crate::log_stream::MAX_STREAMS),
+            )));
+        };
+        info!("开始推送日志: {}", source.name);
+        Some(Ok((source, slot)))
+    }
+
+    fn handle_request(&self, request_data: &str, caller_role: Role) -> PipeResponse {
         let request: PipeRequest = match serde_json::from_str(request_data) {
             Ok(r) => r,
             Err(e) => {
                 error!("解析请求失败: {}", e);
-                return PipeResponse::error(&format!("JSON格式错误: {}", e));
+                self.guardian.pipe_stats().record_parse_failure();
+                return PipeResponse::error(
+                    MessageId::InvalidRequest.with("detail", e.to_string()),
+                );
             }
         };
 
-        //  info!("正在处理请求类型: {}", request.request_type);
+        //  info!("正在处理请求类型: {}", request.kind());
+
+        let required = required_role(request.kind());
+        if caller_role < required {
+            warn!(
+                "拒绝 {} 请求: 需要 {} 角色, 调用方为 {}",
+                request.kind(),
+                required.as_str(),
+                caller_role.as_str()
+            );
+            return PipeResponse::error(
+                MessageId::PermissionDenied
+                    .with("request", request.kind())
+                    .with("role", required.as_str()),
+            );
+        }
+        if self.guardian.safe_mode() && !allowed_in_safe_mode(request.kind()) {
+            warn!("安全模式下拒绝 {} 请求", request.kind());
+            return PipeResponse::error(MessageId::SafeMode.with("request", request.kind()));
+        }
 
-        match request.request_type.as_str() {
-            "heartbeat" => self.handle_heartbeat(&request),
-            "add" => self.handle_add(&request),
-            "update" => self.handle_update(&request),
-            "remove" => self.handle_remove(&request),
-            "pause" => self.handle_pause(&request),
-            "stop" => self.handle_stop(&request),
-            "start" => self.handle_start(&request),
-            "list" => self.handle_list(),
-            "status" => self.handle_status(),
-            _ => PipeResponse::error(&format!("未知的请求类型: {}", request.request_type)),
+        match &request {
+            PipeRequest::Heartbeat { item_id, .. } => self.handle_heartbeat(item_id),
+            PipeRequest::ShutdownReady { item_id } => self.handle_shutdown_ready(item_id),
+            PipeRequest::Add { config } => self.handle_add(config),
+            PipeRequest::Clone {
+                id,
+                new_id,
+                name,
+                overrides,
+            } => self.handle_clone(id, new_id, name, overrides.as_ref()),
+            PipeRequest::Update { config } => self.handle_update(config),
+            PipeRequest::Remove { id } => self.handle_remove(id),
+            PipeRequest::Pause { id } => self.handle_pause(id),
+            PipeRequest::Stop { id } => self.handle_stop(id),
+            PipeRequest::Start { id } => self.handle_start(id),
+            PipeRequest::Kill { id } => self.handle_kill(id),
+            PipeRequest::Suspend { id } => self.handle_suspend(id, true),
+            PipeRequest::Resume { id } => self.handle_suspend(id, false),
+            PipeRequest::TrimMemory { id } => self.handle_trim_memory(id),
+            PipeRequest::Defer { id, minutes } => self.handle_defer(id, *minutes),
+            PipeRequest::PauseAll { minutes } => self.handle_pause_all(*minutes),
+            PipeRequest::ResumeAll => self.handle_resume_all(),
+            PipeRequest::List => self.handle_list(),
+            PipeRequest::Status => self.handle_status(),
+            PipeRequest::UptimeReport { days } => self.handle_uptime_report(*days),
+            PipeRequest::MetricsHistory { id, minutes } => {
+                self.handle_metrics_history(id, minutes.unwrap_or(60))
+            }
+            PipeRequest::LaunchHistory { id, limit } => {
+                self.handle_launch_history(id, limit.unwrap_or(20))
+            }
+            PipeRequest::ExportStatus => self.handle_export_status(),
+            PipeRequest::Reload => self.handle_reload(),
+            PipeRequest::PeerStatus => self.handle_peer_status(),
+            PipeRequest::Diagnostics => self.handle_diagnostics(),
+            PipeRequest::GetOutput { id } => self.handle_get_output(id),
+            PipeRequest::Bulk { action, selector } => self.handle_bulk(*action, selector),
+            // 订阅请求由 open_stream 在单独的线程中处理
+            PipeRequest::StreamLogs { .. } => PipeResponse::error(MessageId::StreamNeedsConnection),
         }
     }
 
-    fn handle_heartbeat(&self, request: &PipeRequest) -> PipeResponse {
-        if let Some(item_id) = &request.item_id {
-            if self.guardian.update_heartbeat(item_id) {
-                //    debug!("监控项心跳已更新: {}", item_id);
-                PipeResponse::success("心跳已更新")
-            } else {
-                error!("心跳更新失败, 未找到监控项: {}", item_id);
-                PipeResponse::error("未找到监控项")
-            }
+    fn handle_heartbeat(&self, item_id: &str) -> PipeResponse {
+        // 等待确认期间守护循环持有进程表锁，直接返回通知，不更新心跳
+        if self.guardian.shutdown_requested(item_id) {
+            info!("通知监控项准备退出: {}", item_id);
+            return PipeResponse::success_with_data(
+                MessageId::PrepareShutdown,
+                serde_json::json!({ "prepare_shutdown": true }),
+            );
+        }
+        if self.guardian.update_heartbeat(item_id) {
+            //    debug!("监控项心跳已更新: {}", item_id);
+            self.guardian.pipe_stats().record_heartbeat(item_id);
+            PipeResponse::success(MessageId::HeartbeatUpdated)
         } else {
-            PipeResponse::error("缺少item_id")
+            error!("心跳更新失败, 未找到监控项: {}", item_id);
+            PipeResponse::error(MessageId::ItemNotFound.with("id", item_id))
         }
     }
 
-    fn handle_add(&self, request: &PipeRequest) -> PipeResponse {
-        if let Some(config) = &request.config {
-            info!("正在添加监控项: {} ({})", config.name, config.exe_path);
+    fn handle_shutdown_ready(&self, item_id: &str) -> PipeResponse {
+        if self.guardian.acknowledge_shutdown(item_id) {
+            info!("监控项已确认可以退出: {}", item_id);
+            PipeResponse::success(MessageId::ShutdownAcknowledged)
+        } else {
+            warn!("监控项没有待确认的退出通知: {}", item_id);
+            PipeResponse::error(MessageId::NoPendingShutdown)
+        }
+    }
 
-            let config_arc = self.guardian.get_config();
-            let mut cfg = config_arc.lock().unwrap();
+    fn handle_add(&self, config: &MonitorItem) -> PipeResponse {
+        info!("正在添加监控项: {} ({})", config.name, config.exe_path);
 
-            if cfg.items.iter().any(|i| i.id == config.id) {
-                error!("ID为 {} 的监控项已存在", config.id);
-                return PipeResponse::error("该ID的监控项已存在");
+        if let Err(response) = validate_target(config)
+            .and_then(|_| validate_health_expression(config))
+            .and_then(|_| validate_firewall(config))
+            .and_then(|_| validate_cleanup(config))
+        {
+            return response;
+        }
+
+        let config_arc = self.guardian.get_config();
+        let mut cfg = config_arc.lock().unwrap();
+
+        if cfg.items.iter().any(|i| i.id == config.id) {
+            error!("ID为 {} 的监控项已存在", config.id);
+            return PipeResponse::error(MessageId::ItemIdExists.with("id", config.id.as_str()));
+        }
+
+        let allow_duplicates = cfg.settings.allow_duplicate_exe_paths;
+        let key = config.instance_key(allow_duplicates);
+        let existing = cfg
+            .items
+            .iter()
+            .find(|i| i.instance_key(allow_duplicates) == key)
+            .map(|i| i.id.clone());
+        if let Some(existing) = existing {
+            match cfg.settings.duplicate_exe_paths {
+                DuplicatePathPolicy::Reject if allow_duplicates => {
+                    error!("相同路径与参数的监控项已存在: {}", config.exe_path);
+                    return PipeResponse::error(
+                        MessageId::DuplicateInstance
+                            .with("exe_path", config.exe_path.as_str())
+                            .with("existing_id", existing),
+                    );
+                }
+                DuplicatePathPolicy::Reject => {
+                    error!("可执行文件路径已被监控: {}", config.exe_path);
+                    return PipeResponse::error(
+                        MessageId::ExePathMonitored
+                            .with("exe_path", config.exe_path.as_str())
+                            .with("existing_id", existing),
+                    );
+                }
+                DuplicatePathPolicy::Merge => {
+                    drop(cfg);
+                    info!("相同路径的监控项已存在，合并到监控项: {}", existing);
+                    let mut merged = config.clone();
+                    merged.id = existing;
+                    return self.handle_update(&merged);
+                }
+                DuplicatePathPolicy::KeepAll => {}
             }
+        }
+
+        cfg.items.push(config.clone());
+
+        if let Err(e) = self.guardian.save_config(&cfg) {
+            error!("保存配置失败: {}", e);
+            return PipeResponse::error(MessageId::SaveConfigFailed.with("detail", e.to_string()));
+        }
+
+        drop(cfg);
+
+        let change = ConfigChange {
+            item: config.clone(),
+            change_type: ChangeType::Start,
+        };
+        self.guardian.add_change(change);
+
+        info!("监控项添加成功: {} ({})", config.name, config.id);
+        PipeResponse::success(MessageId::ItemAdded.with("id", config.id.as_str()))
+    }
 
-            if cfg
-                .items
-                .iter()
-                .any(|i| i.exe_path.to_lowercase() == config.exe_path.to_lowercase())
-            {
-                error!("可执行文件路径已被监控: {}", config.exe_path);
-                return PipeResponse::error("可执行文件路径已被监控");
+    fn handle_clone(
+        &self,
+        id: &str,
+        new_id: &str,
+        name: &str,
+        overrides: Option<&serde_json::Map<String, serde_json::Value>>,
+    ) -> PipeResponse {
+        info!("正在复制监控项: {} -> {} ({})", id, new_id, name);
+
+        if new_id.trim().is_empty() {
+            return PipeResponse::error(MessageId::ItemIdEmpty);
+        }
+
+        let source = {
+            let config_arc = self.guardian.get_config();
+            let cfg = config_arc.lock().unwrap();
+            cfg.items.iter().find(|i| i.id == id).cloned()
+        };
+        let Some(source) = source else {
+            error!("未找到要复制的监控项: {}", id);
+            return PipeResponse::error(MessageId::ItemNotFound.with("id", id));
+        };
+
+        match crate::config::clone_item(&source, new_id, name, overrides) {
+            Ok(item) => self.handle_add(&item),
+            Err(e) => {
+                error!("复制监控项失败: {}", e);
+                PipeResponse::error(MessageId::InvalidOverrides.with("detail", e))
             }
+        }
+    }
+
+    fn handle_update(&self, config: &MonitorItem) -> PipeResponse {
+        info!("正在更新监控项: {} ({})", config.name, config.id);
+
+        if let Err(response) = validate_target(config)
+            .and_then(|_| validate_health_expression(config))
+            .and_then(|_| validate_firewall(config))
+            .and_then(|_| validate_cleanup(config))
+        {
+            return response;
+        }
+
+        let config_arc = self.guardian.get_config();
+        let mut cfg = config_arc.lock().unwrap();
 
-            cfg.items.push(config.clone());
+        if let Some(existing) = cfg.items.iter_mut().find(|i| i.id == config.id) {
+            // 不了解附加字段的旧客户端更新时不会带上它们，保留原值
+            let mut config = config.clone();
+            for (key, value) in std::mem::take(&mut existing.extra) {
+                config.extra.entry(key).or_insert(value);
+            }
+            // list 不返回心跳令牌，按 list 结果修改后提交的配置不带令牌
+            if config.heartbeat_token.is_none() {
+                config.heartbeat_token = existing.heartbeat_token.take();
+            }
+            *existing = config.clone();
 
-            if let Err(e) = crate::config::save_config(&cfg) {
+            if let Err(e) = self.guardian.save_config(&cfg) {
                 error!("保存配置失败: {}", e);
-                return PipeResponse::error(&format!("保存配置失败: {}", e));
+                return PipeResponse::error(
+                    MessageId::SaveConfigFailed.with("detail", e.to_string()),
+                );
             }
 
             drop(cfg);
 
             let change = ConfigChange {
                 item: config.clone(),
-                change_type: ChangeType::Start,
+                change_type: ChangeType::Stop | ChangeType::Start,
             };
             self.guardian.add_change(change);
 
-            info!("监控项添加成功: {} ({})", config.name, config.id);
-            PipeResponse::success("监控项已添加")
+            info!("监控项更新成功: {} ({})", config.name, config.id);
+            PipeResponse::success(MessageId::ItemUpdated.with("id", config.id.as_str()))
         } else {
-            PipeResponse::error("缺少配置")
+            error!("未找到要更新的监控项: {}", config.id);
+            PipeResponse::error(MessageId::ItemNotFound.with("id", config.id.as_str()))
         }
     }
 
-    fn handle_update(&self, request: &PipeRequest) -> PipeResponse {
-        if let Some(config) = &request.config {
-            info!("正在更新监控项: {} ({})", config.name, config.id);
+    fn handle_remove(&self, id: &str) -> PipeResponse {
+        info!("正在移除监控项: {}", id);
 
-            let config_arc = self.guardian.get_config();
-            let mut cfg = config_arc.lock().unwrap();
+        let config_arc = self.guardian.get_config();
+        let mut cfg = config_arc.lock().unwrap();
 
-            if let Some(existing) = cfg.items.iter_mut().find(|i| i.id == config.id) {
-                *existing = config.clone();
+        let item = cfg.items.iter().find(|i| i.id == id).cloned();
 
-                if let Err(e) = crate::config::save_config(&cfg) {
-                    error!("保存配置失败: {}", e);
-                    return PipeResponse::error(&format!("保存配置失败: {}", e));
-                }
+        if let Some(item) = item {
+            cfg.items.retain(|i| i.id != id);
 
-                drop(cfg);
+            if let Err(e) = self.guardian.save_config(&cfg) {
+                error!("保存配置失败: {}", e);
+                return PipeResponse::error(
+                    MessageId::SaveConfigFailed.with("detail", e.to_string()),
+                );
+            }
 
-                let change = ConfigChange {
-                    item: config.clone(),
-                    change_type: ChangeType::Stop | ChangeType::Start,
-                };
-                self.guardian.add_change(change);
+            drop(cfg);
 
-                info!("监控项更新成功: {} ({})", config.name, config.id);
-                PipeResponse::success("监控项已更新")
-            } else {
-                error!("未找到要更新的监控项: {}", config.id);
-                PipeResponse::error("未找到监控项")
-            }
+            let change = ConfigChange {
+                item,
+                change_type: ChangeType::Stop | ChangeType::Remove,
+            };
+            self.guardian.add_change(change);
+
+            info!("监控项移除成功: {}", id);
+            PipeResponse::success(MessageId::ItemRemoved.with("id", id))
         } else {
-            PipeResponse::error("缺少配置")
+            error!("未找到要移除的监控项: {}", id);
+            PipeResponse::error(MessageId::ItemNotFound.with("id", id))
         }
     }
 
-    fn handle_remove(&self, request: &PipeRequest) -> PipeResponse {
-        if let Some(id) = &request.id {
-            info!("正在移除监控项: {}", id);
+    fn handle_stop(&self, id: &str) -> PipeResponse {
+        info!("正在停止监控项: {}", id);
 
-            let config_arc = self.guardian.get_config();
-            let mut cfg = config_arc.lock().unwrap();
+        let config_arc = self.guardian.get_config();
+        let cfg = config_arc.lock().unwrap();
+        let item = cfg.items.iter().find(|i| i.id == id).cloned();
+        drop(cfg);
 
-            let item = cfg.items.iter().find(|i| &i.id == id).cloned();
+        if let Some(item) = item {
+            let change = ConfigChange {
+                item,
+                change_type: ChangeType::Stop,
+            };
+            self.guardian.add_change(change);
 
-            if let Some(item) = item {
-                cfg.items.retain(|i| &i.id != id);
+            info!("监控项停止命令已发送: {}", id);
+            PipeResponse::success(MessageId::ItemStopped.with("id", id))
+        } else {
+            error!("未找到要停止的监控项: {}", id);
+            PipeResponse::error(MessageId::ItemNotFound.with("id", id))
+        }
+    }
 
-                if let Err(e) = crate::config::save_config(&cfg) {
-                    error!("保存配置失败: {}", e);
-                    return PipeResponse::error(&format!("保存配置失败: {}", e));
-                }
+    fn handle_start(&self, id: &str) -> PipeResponse {
+        info!("正在启动监控项: {}", id);
 
-                drop(cfg);
+        let config_arc = self.guardian.get_config();
+        let cfg = config_arc.lock().unwrap();
+        let item = cfg.items.iter().find(|i| i.id == id).cloned();
+        drop(cfg);
 
-                let change = ConfigChange {
-                    item,
-                    change_type: ChangeType::Stop | ChangeType::Remove,
-                };
-                self.guardian.add_change(change);
+        if let Some(mut item) = item {
+            item.enabled = true;
+            let change = ConfigChange {
+                item,
+                change_type: ChangeType::Start,
+            };
+            self.guardian.add_change(change);
 
-                info!("监控项移除成功: {}", id);
-                PipeResponse::success("监控项已移除")
-            } else {
-                error!("未找到要移除的监控项: {}", id);
-                PipeResponse::error("未找到监控项")
-            }
+            info!("监控项启动命令已发送: {}", id);
+            PipeResponse::success(MessageId::ItemStarted.with("id", id))
         } else {
-            PipeResponse::error("缺少id")
+            error!("未找到要启动的监控项: {}", id);
+            PipeResponse::error(MessageId::ItemNotFound.with("id", id))
         }
     }
 
-    fn handle_stop(&self, request: &PipeRequest) -> PipeResponse {
-        if let Some(id) = &request.id {
-            info!("正在停止监控项: {}", id);
+    fn handle_kill(&self, id: &str) -> PipeResponse {
+        info!("正在终止监控项进程: {}", id);
 
-            let config_arc = self.guardian.get_config();
-            let cfg = config_arc.lock().unwrap();
-            let item = cfg.items.iter().find(|i| &i.id == id).cloned();
-            drop(cfg);
+        let config_arc = self.guardian.get_config();
+        let cfg = config_arc.lock().unwrap();
+        let item = cfg.items.iter().find(|i| i.id == id).cloned();
+        drop(cfg);
 
-            if let Some(item) = item {
+        match item {
+            Some(item) if item.enabled => {
                 let change = ConfigChange {
                     item,
-                    change_type: ChangeType::Stop,
+                    change_type: ChangeType::Kill,
                 };
                 self.guardian.add_change(change);
 
-                info!("监控项停止命令已发送: {}", id);
-                PipeResponse::success("监控项已停止")
-            } else {
-                error!("未找到要停止的监控项: {}", id);
-                PipeResponse::error("未找到监控项")
+                info!("监控项终止命令已发送: {}", id);
+                PipeResponse::success(MessageId::RestartScheduled.with("id", id))
+            }
+            Some(_) => PipeResponse::error(MessageId::ItemDisabled.with("id", id)),
+            None => {
+                error!("未找到要终止的监控项: {}", id);
+                PipeResponse::error(MessageId::ItemNotFound.with("id", id))
             }
-        } else {
-            PipeResponse::error("缺少id")
         }
     }
 
-    fn handle_start(&self, request: &PipeRequest) -> PipeResponse {
-        if let Some(id) = &request.id {
-            info!("正在启动监控项: {}", id);
+    fn handle_suspend(&self, id: &str, suspend: bool) -> PipeResponse {
+        let action = if suspend { "挂起" } else { "恢复" };
+        info!("正在{}监控项进程: {}", action, id);
+        match self.guardian.set_suspended(id, suspend) {
+            Ok(pid) => {
+                let message = if suspend {
+                    MessageId::ProcessSuspended
+                } else {
+                    MessageId::ProcessResumed
+                };
+                PipeResponse::success(message.with("id", id).with("pid", pid))
+            }
+            Err(e) => {
+                error!("{}监控项进程失败: {}", action, e);
+                let message = if suspend {
+                    MessageId::SuspendFailed
+                } else {
+                    MessageId::ResumeFailed
+                };
+                PipeResponse::error(message.with("id", id).with("detail", e))
+            }
+        }
+    }
 
-            let config_arc = self.guardian.get_config();
-            let cfg = config_arc.lock().unwrap();
-            let item = cfg.items.iter().find(|i| &i.id == id).cloned();
-            drop(cfg);
+    fn handle_trim_memory(&self, id: &str) -> PipeResponse {
+        info!("正在收缩监控项进程工作集: {}", id);
+        match self.guardian.trim_memory(id) {
+            Ok((pid, before, after)) => PipeResponse::success(
+                MessageId::MemoryTrimmed
+                    .with("id", id)
+                    .with("pid", pid)
+                    .with("before_kb", before / 1024)
+                    .with("after_kb", after / 1024),
+            ),
+            Err(e) => {
+                error!("收缩监控项进程工作集失败: {}", e);
+                PipeResponse::error(MessageId::TrimMemoryFailed.with("id", id).with("detail", e))
+            }
+        }
+    }
 
-            if let Some(mut item) = item {
-                item.enabled = true;
-                let change = ConfigChange {
-                    item,
-                    change_type: ChangeType::Start,
-                };
-                self.guardian.add_change(change);
+    fn handle_defer(&self, id: &str, minutes: u32) -> PipeResponse {
+        info!("正在暂缓守护监控项: {} ({} 分钟)", id, minutes);
+        match self.guardian.set_deferred(id, minutes) {
+            Ok(()) if minutes == 0 => PipeResponse::success(MessageId::DeferCleared.with("id", id)),
+            Ok(()) => {
+                PipeResponse::success(MessageId::Deferred.with("id", id).with("minutes", minutes))
+            }
+            Err(e) => {
+                error!("暂缓守护监控项失败: {}", e);
+                PipeResponse::error(MessageId::DeferFailed.with("detail", e))
+            }
+        }
+    }
 
-                info!("监控项启动命令已发送: {}", id);
-                PipeResponse::success("监控项已启动")
-            } else {
-                error!("未找到要启动的监控项: {}", id);
-                PipeResponse::error("未找到监控项")
+    fn handle_pause_all(&self, minutes: Option<u32>) -> PipeResponse {
+        match self.guardian.pause_all(minutes) {
+            Ok(()) => {
+                let message = match minutes {
+                    Some(minutes) => MessageId::PausedAllFor.with("minutes", minutes),
+                    None => MessageId::PausedAll.into(),
+                };
+                info!("{}", message.text());
+                PipeResponse::success(message)
+            }
+            Err(e) => {
+                error!("暂停守护失败: {}", e);
+                PipeResponse::error(MessageId::PauseFailed.with("detail", e))
             }
+        }
+    }
+
+    fn handle_resume_all(&self) -> PipeResponse {
+        if self.guardian.resume_all() {
+            PipeResponse::success(MessageId::ResumedAll)
         } else {
-            PipeResponse::error("缺少id")
+            PipeResponse::success(MessageId::NotPaused)
         }
     }
 
-    fn handle_pause(&self, request: &PipeRequest) -> PipeResponse {
-        if let Some(id) = &request.id {
-            info!("姝ｅ湪鏆傚仠鐩戞帶椤? {}", id);
+    fn handle_pause(&self, id: &str) -> PipeResponse {
+        info!("正在暂停监控项: {}", id);
 
-            let config_arc = self.guardian.get_config();
-            let cfg = config_arc.lock().unwrap();
-            let item = cfg.items.iter().find(|i| &i.id == id).cloned();
-            drop(cfg);
+        let config_arc = self.guardian.get_config();
+        let cfg = config_arc.lock().unwrap();
+        let item = cfg.items.iter().find(|i| i.id == id).cloned();
+        drop(cfg);
 
-            if let Some(item) = item {
-                let change = ConfigChange {
-                    item,
-                    change_type: ChangeType::Pause,
-                };
-                self.guardian.add_change(change);
+        if let Some(item) = item {
+            let change = ConfigChange {
+                item,
+                change_type: ChangeType::Pause,
+            };
+            self.guardian.add_change(change);
 
-                info!("鐩戞帶椤规殏鍋滃懡浠ゅ凡鍙戦€? {}", id);
-                PipeResponse::success("鐩戞帶椤瑰凡鏆傚仠")
-            } else {
-                error!("鏈壘鍒拌鏆傚仠鐨勭洃鎺ч」: {}", id);
-                PipeResponse::error("鏈壘鍒扮洃鎺ч」")
-            }
+            info!("监控项暂停命令已发送: {}", id);
+            PipeResponse::success(MessageId::ItemPaused.with("id", id))
         } else {
-            PipeResponse::error("缂哄皯id")
+            error!("未找到要暂停的监控项: {}", id);
+            PipeResponse::error(MessageId::ItemNotFound.with("id", id))
         }
     }
 
+    fn handle_bulk(&self, action: BulkAction, selector: &ItemSelector) -> PipeResponse {
+        if selector.is_empty() {
+            return PipeResponse::error(MessageId::BulkSelectorEmpty);
+        }
+        info!("正在批量执行 {}: {:?}", action.as_str(), selector);
+
+        let config_arc = self.guardian.get_config();
+        let cfg = config_arc.lock().unwrap();
+        let matched: Vec<MonitorItem> = cfg
+            .items
+            .iter()
+            .filter(|item| selector.matches(item))
+            .cloned()
+            .collect();
+        drop(cfg);
+
+        if matched.is_empty() {
+            error!("没有匹配的监控项: {:?}", selector);
+            return PipeResponse::error(MessageId::ItemNotFound);
+        }
+
+        // enable 只处理未启用的项，disable 与 restart 只处理已启用的项
+        let (change_type, from_enabled) = match action {
+            BulkAction::Enable => (ChangeType::Start, false),
+            BulkAction::Disable => (ChangeType::Stop, true),
+            BulkAction::Restart => (ChangeType::Kill, true),
+        };
+        let (targets, skipped): (Vec<MonitorItem>, Vec<MonitorItem>) = matched
+            .into_iter()
+            .partition(|item| item.enabled == from_enabled);
+        let ids: Vec<String> = targets.iter().map(|item| item.id.clone()).collect();
+        let changes = targets
+            .into_iter()
+            .map(|mut item| {
+                if action == BulkAction::Enable {
+                    item.enabled = true;
+                }
+                ConfigChange { item, change_type }
+            })
+            .collect();
+        self.guardian.add_changes(changes);
+
+        info!("批量 {} 命令已发送: {:?}", action.as_str(), ids);
+        let skipped: Vec<String> = skipped.into_iter().map(|item| item.id).collect();
+        let message = MessageId::BulkApplied
+            .with("action", action.as_str())
+            .with("count", ids.len())
+            .with("matched", ids.len() + skipped.len());
+        PipeResponse::success_with_data(
+            message,
+            serde_json::json!({ "items": ids, "skipped": skipped }),
+        )
+    }
+
     fn handle_list(&self) -> PipeResponse {
         debug!("正在列出所有监控项");
 
         let config_arc = self.guardian.get_config();
         let cfg = config_arc.lock().unwrap();
-        let items = serde_json::to_value(&cfg.items).unwrap_or(serde_json::json!([]));
+        let mut items: Vec<serde_json::Value> =
+            cfg.items.iter().map(MonitorItem::to_public_value).collect();
+        drop(cfg);
+
+        // 统计来自事件库，未启用事件库时为 null
+        let store = self.guardian.get_event_store();
+        for item in &mut items {
+            let Some(object) = item.as_object_mut() else {
+                continue;
+            };
+            let id = object
+                .get("id")
+                .and_then(|id| id.as_str())
+                .unwrap_or_default();
+            let stats = store
+                .as_ref()
+                .and_then(|store| {
+                    store
+                        .item_stats(id)
+                        .map_err(|e| error!("统计监控项 {} 失败: {}", id, e))
+                        .ok()
+                })
+                .and_then(|stats| serde_json::to_value(stats).ok())
+                .unwrap_or(serde_json::Value::Null);
+            object.insert("stats".to_string(), stats);
+        }
 
-        debug!("找到 {} 个监控项", cfg.items.len());
-        PipeResponse::success_with_data("监控项列表", items)
+        debug!("找到 {} 个监控项", items.len());
+        PipeResponse::success_with_data(MessageId::ItemList, serde_json::Value::Array(items))
     }
 
     fn handle_status(&self) -> PipeResponse {
         debug!("正在获取服务状态");
 
         let status = self.guardian.get_status();
-        PipeResponse::success_with_data("服务状态", status)
+        PipeResponse::success_with_data(MessageId::ServiceStatus, status)
+    }
+
+    fn handle_reload(&self) -> PipeResponse {
+        info!("正在重新加载配置文件");
+        match self.guardian.reload_config() {
+            Ok(summary) => PipeResponse::success_with_data(MessageId::ConfigReloaded, summary),
+            Err(e) => {
+                error!("重新加载配置失败: {}", e);
+                PipeResponse::error(MessageId::ReloadFailed.with("detail", e))
+            }
+        }
+    }
+
+    fn handle_get_output(&self, id: &str) -> PipeResponse {
+        match self.guardian.get_output(id) {
+            Ok(output) => PipeResponse::success_with_data(MessageId::RecentOutput, output),
+            Err(e) => PipeResponse::error(MessageId::OutputUnavailable.with("detail", e)),
+        }
+    }
+
+    fn handle_diagnostics(&self) -> PipeResponse {
+        match self.guardian.diagnostics() {
+            Some(report) => PipeResponse::success_with_data(
+                if report.passed {
+                    MessageId::DiagnosticsPassed
+                } else {
+                    MessageId::DiagnosticsFailed
+                },
+                serde_json::to_value(report).unwrap_or_default(),
+            ),
+            None => PipeResponse::error(MessageId::DiagnosticsNotRun),
+        }
+    }
+
+    /// 主备模式下对端查询本节点状态
+    fn handle_peer_status(&self) -> PipeResponse {
+        PipeResponse::success_with_data(MessageId::PeerStatus, self.guardian.ha_status())
+    }
+
+    fn handle_export_status(&self) -> PipeResponse {
+        info!("正在导出诊断快照");
+        let snapshot = crate::export::build_snapshot(&self.guardian);
+        PipeResponse::success_with_data(MessageId::StatusSnapshot, snapshot)
+    }
+
+    fn handle_metrics_history(&self, id: &str, minutes: u32) -> PipeResponse {
+        let Some(store) = self.guardian.get_event_store() else {
+            return PipeResponse::error(MessageId::EventStoreDisabled);
+        };
+        let (name, settings) = {
+            let config_arc = self.guardian.get_config();
+            let cfg = config_arc.lock().unwrap();
+            let Some(item) = cfg.items.iter().find(|i| i.id == id) else {
+                return PipeResponse::error(MessageId::ItemNotFound.with("id", id));
+            };
+            (item.name.clone(), cfg.settings.metrics_history.clone())
+        };
+
+        let since = crate::store::now_unix_ms() - minutes as i64 * 60_000;
+        match store.resource_history(id, since, MAX_HISTORY_SAMPLES) {
+            Ok(samples) => PipeResponse::success_with_data(
+                MessageId::MetricsHistory,
+                serde_json::json!({
+                    "item_id": id,
+                    "name": name,
+                    "enabled": settings.enabled,
+                    "interval_secs": settings.interval_secs,
+                    "minutes": minutes,
+                    "samples": samples,
+                }),
+            ),
+            Err(e) => {
+                error!("查询资源使用历史失败: {}", e);
+                PipeResponse::error(MessageId::MetricsHistoryFailed.with("detail", e.to_string()))
+            }
+        }
+    }
+
+    fn handle_launch_history(&self, id: &str, limit: u32) -> PipeResponse {
+        let Some(store) = self.guardian.get_event_store() else {
+            return PipeResponse::error(MessageId::EventStoreDisabled);
+        };
+        match store.launches(id, limit) {
+            Ok(launches) => PipeResponse::success_with_data(
+                MessageId::LaunchHistory,
+                serde_json::json!({ "item_id": id, "launches": launches }),
+            ),
+            Err(e) => {
+                error!("查询启动记录失败: {}", e);
+                PipeResponse::error(MessageId::LaunchHistoryFailed.with("detail", e.to_string()))
+            }
+        }
+    }
+
+    fn handle_uptime_report(&self, days: Option<u32>) -> PipeResponse {
+        let Some(store) = self.guardian.get_event_store() else {
+            return PipeResponse::error(MessageId::EventStoreDisabled);
+        };
+
+        let config_arc = self.guardian.get_config();
+        let cfg = config_arc.lock().unwrap();
+        let names: Vec<(String, String)> = cfg
+            .items
+            .iter()
+            .map(|i| (i.id.clone(), i.name.clone()))
+            .collect();
+        drop(cfg);
+
+        let item_ids: Vec<String> = names.iter().map(|(id, _)| id.clone()).collect();
+        let windows: Vec<u32> = match days {
+            Some(days) if days > 0 => vec![days],
+            Some(_) => return PipeResponse::error(MessageId::InvalidDays),
+            None => vec![7, 30],
+        };
+
+        let mut results = Vec::with_capacity(windows.len());
+        for days in windows {
+            match store.uptime_report(&item_ids, days) {
+                Ok(reports) => {
+                    let items: Vec<serde_json::Value> = reports
+                        .iter()
+                        .zip(names.iter())
+                        .map(|(report, (_, name))| {
+                            let mut value = serde_json::to_value(report).unwrap_or_default();
+                            value["name"] = serde_json::json!(name);
+                            value
+                        })
+                        .collect();
+                    results.push(serde_json::json!({ "days": days, "items": items }));
+                }
+                Err(e) => {
+                    error!("查询在线率报告失败: {}", e);
+                    return PipeResponse::error(
+                        MessageId::UptimeReportFailed.with("detail", e.to_string()),
+                    );
+                }
+            }
+        }
+
+        PipeResponse::success_with_data(
+            MessageId::UptimeReport,
+            serde_json::json!({ "windows": results }),
+        )
     }
 }
@@ -0,0 +1,207 @@
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 计算速率与请求耗时的滑动窗口
+const WINDOW: Duration = Duration::from_secs(60);
+/// 保留的最近客户端错误条数
+const MAX_CLIENT_ERRORS: usize = 20;
+
+/// 窗口内的事件时间，用于计算每秒速率
+#[derive(Debug, Default)]
+struct RateWindow {
+    events: VecDeque<Instant>,
+}
+
+impl RateWindow {
+    fn record(&mut self, now: Instant) {
+        self.events.push_back(now);
+        self.trim(now);
+    }
+
+    fn trim(&mut self, now: Instant) {
+        while let Some(first) = self.events.front() {
+            if now.duration_since(*first) <= WINDOW {
+                break;
+            }
+            self.events.pop_front();
+        }
+    }
+
+    fn per_second(&mut self, now: Instant) -> f64 {
+        self.trim(now);
+        self.events.len() as f64 / WINDOW.as_secs_f64()
+    }
+}
+
+/// 返回给客户端的一次错误
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ClientError {
+    pub ts: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct HeartbeatStats {
+    pub total: u64,
+    pub per_second: f64,
+    pub last_age_secs: f64,
+}
+
+/// 管道服务统计快照，供 status 与 /metrics 使用
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PipeStatsSnapshot {
+    pub connections_total: u64,
+    pub connections_per_second: f64,
+    pub requests_total: u64,
+    pub parse_failures_total: u64,
+    pub client_errors_total: u64,
+    /// 最近 60 秒请求处理耗时的平均值与最大值
+    pub latency_avg_ms: f64,
+    pub latency_max_ms: f64,
+    pub heartbeats: HashMap<String, HeartbeatStats>,
+    pub last_client_errors: Vec<ClientError>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    connections_total: u64,
+    connections: RateWindow,
+    requests_total: u64,
+    parse_failures_total: u64,
+    client_errors_total: u64,
+    latencies: VecDeque<(Instant, Duration)>,
+    heartbeats: HashMap<String, (u64, RateWindow, Instant)>,
+    client_errors: VecDeque<ClientError>,
+}
+
+/// 管道服务的连接、心跳、请求耗时与客户端错误统计
+#[derive(Debug, Default)]
+pub struct PipeStats {
+    inner: Mutex<Inner>,
+}
+
+impl PipeStats {
+    pub fn record_connection(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.connections_total += 1;
+        inner.connections.record(Instant::now());
+    }
+
+    pub fn record_request(&self, elapsed: Duration) {
+        let now = Instant::now();
+        let mut inner = self.inner.lock().unwrap();
+        inner.requests_total += 1;
+        inner.latencies.push_back((now, elapsed));
+        while let Some((at, _)) = inner.latencies.front() {
+            if now.duration_since(*at) <= WINDOW {
+                break;
+            }
+            inner.latencies.pop_front();
+        }
+    }
+
+    pub fn record_parse_failure(&self) {
+        self.inner.lock().unwrap().parse_failures_total += 1;
+    }
+
+    pub fn record_heartbeat(&self, item_id: &str) {
+        let now = Instant::now();
+        let mut inner = self.inner.lock().unwrap();
+        let entry = inner
+            .heartbeats
+            .entry(item_id.to_string())
+            .or_insert_with(|| (0, RateWindow::default(), now));
+        entry.0 += 1;
+        entry.1.record(now);
+        entry.2 = now;
+    }
+
+    pub fn record_client_error(&self, request: Option<&str>, message: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.client_errors_total += 1;
+        if inner.client_errors.len() == MAX_CLIENT_ERRORS {
+            inner.client_errors.pop_front();
+        }
+        inner.client_errors.push_back(ClientError {
+            ts: crate::store::now_unix_ms(),
+            request: request.map(str::to_string),
+            message: message.to_string(),
+        });
+    }
+
+    /// 监控项被删除后不再保留其心跳统计
+    pub fn retain_items(&self, keep: impl Fn(&str) -> bool) {
+        self.inner
+            .lock()
+            .unwrap()
+            .heartbeats
+            .retain(|id, _| keep(id));
+    }
+
+    pub fn snapshot(&self) -> PipeStatsSnapshot {
+        let now = Instant::now();
+        let mut inner = self.inner.lock().unwrap();
+        let recent: Vec<Duration> = inner
+            .latencies
+            .iter()
+            .filter(|(at, _)| now.duration_since(*at) <= WINDOW)
+            .map(|(_, elapsed)| *elapsed)
+            .collect();
+        let latency_avg_ms = if recent.is_empty() {
+            0.0
+        } else {
+            recent.iter().map(|d| d.as_secs_f64()).sum::<f64>() * 1000.0 / recent.len() as f64
+        };
+        let latency_max_ms = recent
+            .iter()
+            .max()
+            .map(|d| d.as_secs_f64() * 1000.0)
+            .unwrap_or(0.0);
+        let heartbeats = inner
+            .heartbeats
+            .iter_mut()
+            .map(|(id, (total, window, last))| {
+                let stats = HeartbeatStats {
+                    total: *total,
+                    per_second: window.per_second(now),
+                    last_age_secs: now.duration_since(*last).as_secs_f64(),
+                };
+                (id.clone(), stats)
+            })
+            .collect();
+        PipeStatsSnapshot {
+            connections_total: inner.connections_total,
+            connections_per_second: inner.connections.per_second(now),
+            requests_total: inner.requests_total,
+            parse_failures_total: inner.parse_failures_total,
+            client_errors_total: inner.client_errors_total,
+            latency_avg_ms,
+            latency_max_ms,
+            heartbeats,
+            last_client_errors: inner.client_errors.iter().cloned().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RateWindow, WINDOW};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn rate_window_drops_events_older_than_window() {
+        let start = Instant::now();
+        let mut window = RateWindow::default();
+        for i in 0..30 {
+            window.record(start + Duration::from_secs(i));
+        }
+        assert_eq!(window.per_second(start + Duration::from_secs(30)), 0.5);
+        // 窗口移到第 45 秒之后，只剩第 15 秒起的事件
+        let later = start + WINDOW + Duration::from_secs(15);
+        assert_eq!(window.per_second(later), 15.0 / 60.0);
+    }
+}
@@ -0,0 +1,231 @@
+use crate::models::PIPE_NAME;
+use std::path::{Path, PathBuf};
+
+/// 生成的模块名，Import-Module 时使用
+pub const MODULE_NAME: &str = "ProcessGuard";
+
+/// 针对单个监控项的 cmdlet：(动词, 管道请求类型, 说明)
+const ITEM_COMMANDS: [(&str, &str, &str); 6] = [
+    ("Start", "start", "Resumes monitoring of the item."),
+    (
+        "Stop",
+        "stop",
+        "Stops monitoring of the item and terminates its process.",
+    ),
+    (
+        "Restart",
+        "kill",
+        "Terminates the process; the service relaunches it on the next cycle.",
+    ),
+    (
+        "Suspend",
+        "suspend",
+        "Suspends all threads of the process; health checks pause meanwhile.",
+    ),
+    ("Resume", "resume", "Resumes a suspended process."),
+    (
+        "Optimize",
+        "trim_memory",
+        "Trims the working set of the process (EmptyWorkingSet).",
+    ),
+];
+
+/// 模块中不随监控项命令变化的部分：管道请求与查询 cmdlet
+const MODULE_CORE: &str = r#"# Generated by process-guard-service.exe --export-powershell; regenerate after upgrading the service.
+
+function Invoke-ProcessGuardRequest {
+    <#
+    .SYNOPSIS
+    Sends a raw request to the ProcessGuard service pipe and returns the response.
+    .EXAMPLE
+    Invoke-ProcessGuardRequest @{ type = 'uptime_report'; days = 7 }
+    #>
+    [CmdletBinding()]
+    param(
+        [Parameter(Mandatory, Position = 0)] [hashtable] $Request,
+        [string] $ComputerName = '.',
+        [int] $TimeoutMs = 5000
+    )
+    $pipe = [System.IO.Pipes.NamedPipeClientStream]::new(
+        $ComputerName, '__PIPE_NAME__', [System.IO.Pipes.PipeDirection]::InOut)
+    try {
+        $pipe.Connect($TimeoutMs)
+        $bytes = [System.Text.Encoding]::UTF8.GetBytes((ConvertTo-Json -InputObject $Request -Compress -Depth 10))
+        $pipe.Write($bytes, 0, $bytes.Length)
+        $pipe.Flush()
+        $reader = [System.IO.StreamReader]::new($pipe, [System.Text.Encoding]::UTF8)
+        $response = $reader.ReadToEnd() | ConvertFrom-Json
+    }
+    finally {
+        $pipe.Dispose()
+    }
+    if (-not $response.success) {
+        throw "ProcessGuard on ${ComputerName}: $($response.message)"
+    }
+    $response
+}
+
+function Get-GuardedProcess {
+    <#
+    .SYNOPSIS
+    Lists monitored items with their live state, as returned by the status request.
+    .EXAMPLE
+    Get-GuardedProcess worker* | Where-Object { -not $_.is_alive }
+    .EXAMPLE
+    Get-GuardedProcess -ComputerName srv1, srv2 | Restart-GuardedProcess -WhatIf
+    #>
+    [CmdletBinding()]
+    param(
+        [Parameter(Position = 0)] [SupportsWildcards()] [string[]] $Name = '*',
+        [string] $Id,
+        [string[]] $ComputerName = '.'
+    )
+    foreach ($computer in $ComputerName) {
+        $status = Invoke-ProcessGuardRequest @{ type = 'status' } -ComputerName $computer
+        foreach ($item in $status.data.items) {
+            if ($Id -and $item.id -ne $Id) { continue }
+            if (-not ($Name | Where-Object { $item.name -like $_ -or $item.id -like $_ })) { continue }
+            $item.PSObject.TypeNames.Insert(0, 'ProcessGuard.GuardedProcess')
+            $item | Add-Member -NotePropertyName ComputerName -NotePropertyValue $computer -PassThru
+        }
+    }
+}
+
+function Get-GuardedProcessHistory {
+    <#
+    .SYNOPSIS
+    Returns recent CPU and memory samples of an item (requires settings.metrics_history).
+    #>
+    [CmdletBinding()]
+    param(
+        [Parameter(Mandatory, ValueFromPipelineByPropertyName)] [string] $Id,
+        [Parameter(ValueFromPipelineByPropertyName)] [string] $ComputerName = '.',
+        [int] $Minutes = 60
+    )
+    process {
+        $response = Invoke-ProcessGuardRequest @{ type = 'metrics_history'; id = $Id; minutes = $Minutes } -ComputerName $ComputerName
+        foreach ($sample in $response.data.samples) {
+            [pscustomobject]@{
+                Id              = $Id
+                Time            = [DateTimeOffset]::FromUnixTimeMilliseconds($sample.ts).LocalDateTime
+                CpuPercent      = $sample.cpu_percent
+                WorkingSetBytes = $sample.working_set_bytes
+                PrivateBytes    = $sample.private_bytes
+                HandleCount     = $sample.handle_count
+            }
+        }
+    }
+}
+
+Update-TypeData -TypeName ProcessGuard.GuardedProcess -DefaultDisplayPropertySet id, name, is_alive, process_id, restart_count, ComputerName -Force
+"#;
+
+/// 单个监控项命令的函数定义；Id 与 ComputerName 可从 Get-GuardedProcess 的输出按属性名绑定
+fn item_function(verb: &str, request_type: &str, synopsis: &str) -> String {
+    r#"
+function __VERB__-GuardedProcess {
+    <#
+    .SYNOPSIS
+    __SYNOPSIS__
+    #>
+    [CmdletBinding(SupportsShouldProcess)]
+    param(
+        [Parameter(Mandatory, Position = 0, ValueFromPipelineByPropertyName)] [string[]] $Id,
+        [Parameter(ValueFromPipelineByPropertyName)] [string] $ComputerName = '.'
+    )
+    process {
+        foreach ($itemId in $Id) {
+            if ($PSCmdlet.ShouldProcess("$itemId on $ComputerName", '__VERB__')) {
+                $response = Invoke-ProcessGuardRequest @{ type = '__REQUEST__'; id = $itemId } -ComputerName $ComputerName
+                Write-Verbose $response.message
+            }
+        }
+    }
+}
+"#
+    .replace("__VERB__", verb)
+    .replace("__REQUEST__", request_type)
+    .replace("__SYNOPSIS__", synopsis)
+}
+
+fn exported_functions() -> Vec<String> {
+    let mut functions = vec![
+        "Invoke-ProcessGuardRequest".to_string(),
+        "Get-GuardedProcess".to_string(),
+        "Get-GuardedProcessHistory".to_string(),
+    ];
+    functions.extend(
+        ITEM_COMMANDS
+            .iter()
+            .map(|(verb, _, _)| format!("{}-GuardedProcess", verb)),
+    );
+    functions
+}
+
+fn module_script() -> String {
+    let mut script = MODULE_CORE.replace("__PIPE_NAME__", PIPE_NAME);
+    for (verb, request_type, synopsis) in ITEM_COMMANDS {
+        script.push_str(&item_function(verb, request_type, synopsis));
+    }
+    script
+}
+
+fn module_manifest() -> String {
+    let functions = exported_functions()
+        .iter()
+        .map(|name| format!("'{}'", name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "@{{\r\n    RootModule        = '{module}.psm1'\r\n    ModuleVersion     = '{version}'\r\n    \
+         GUID              = '5b1f7c2e-8d4a-4f0e-9c63-2a7e4d91b0f5'\r\n    \
+         Description       = 'Manage ProcessGuard monitored items through the service pipe'\r\n    \
+         PowerShellVersion = '5.1'\r\n    FunctionsToExport = @({functions})\r\n    \
+         CmdletsToExport   = @()\r\n    AliasesToExport   = @()\r\n}}\r\n",
+        module = MODULE_NAME,
+        version = env!("CARGO_PKG_VERSION"),
+        functions = functions,
+    )
+}
+
+/// 在 dir 下生成 ProcessGuard\ProcessGuard.psm1 与 .psd1，返回模块目录
+pub fn export_module(dir: &Path) -> Result<PathBuf, String> {
+    let module_dir = dir.join(MODULE_NAME);
+    std::fs::create_dir_all(&module_dir)
+        .map_err(|e| format!("cannot create {}: {}", module_dir.display(), e))?;
+    let files = [
+        (
+            format!("{}.psm1", MODULE_NAME),
+            module_script().replace('\n', "\r\n"),
+        ),
+        (format!("{}.psd1", MODULE_NAME), module_manifest()),
+    ];
+    for (name, content) in files {
+        let path = module_dir.join(name);
+        std::fs::write(&path, content)
+            .map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+    }
+    Ok(module_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{module_manifest, module_script};
+
+    #[test]
+    fn every_exported_function_is_defined() {
+        let script = module_script();
+        assert!(script.contains("'ProcessGuardService'"));
+        assert!(script.contains("type = 'kill'; id = $itemId"));
+        assert!(!script.contains("__"));
+        let manifest = module_manifest();
+        for name in [
+            "Get-GuardedProcess",
+            "Restart-GuardedProcess",
+            "Resume-GuardedProcess",
+        ] {
+            assert!(script.contains(&format!("function {} {{", name)));
+            assert!(manifest.contains(&format!("'{}'", name)));
+        }
+    }
+}
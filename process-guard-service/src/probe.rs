@@ -0,0 +1,482 @@
+use crate::models::{
+    EventLogCheckConfig, HealthCommandConfig, HttpProbeConfig, LogCheckConfig, LogCheckOrigin,
+    PortCheckConfig, ProbeState, WindowCheckConfig,
+};
+use log::{debug, warn};
+use regex::Regex;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::os::windows::process::CommandExt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+const COMMAND_POLL_INTERVAL_MS: u64 = 100;
+
+/// 判断探测响应是否符合预期：未指定状态码时接受任意 2xx
+pub fn evaluate_probe_response(
+    config: &HttpProbeConfig,
+    status: u16,
+    body: &str,
+) -> Result<(), String> {
+    let status_ok = match config.expected_status {
+        Some(expected) => status == expected,
+        None => (200..300).contains(&status),
+    };
+    if !status_ok {
+        return Err(format!("unexpected HTTP status {}", status));
+    }
+
+    if let Some(expected) = &config.expected_substring {
+        if !body.contains(expected.as_str()) {
+            return Err(format!("response body does not contain {:?}", expected));
+        }
+    }
+
+    Ok(())
+}
+
+fn run_probe(config: &HttpProbeConfig) -> Result<(), String> {
+    let response = crate::http::request(
+        "GET",
+        &config.url,
+        &[],
+        None,
+        Duration::from_millis(config.timeout_ms),
+    )?;
+    evaluate_probe_response(config, response.status, &response.body)
+}
+
+/// 无窗口运行命令并等待退出，退出码非 0 或超时视为失败
+pub fn run_command(
+    label: &str,
+    exe_path: &str,
+    args: Option<&str>,
+    timeout_ms: u64,
+) -> Result<(), String> {
+    let mut command = Command::new(exe_path);
+    if let Some(args) = args {
+        command.raw_arg(args);
+    }
+    if let Some(dir) = Path::new(exe_path).parent() {
+        command.current_dir(dir);
+    }
+    let mut child = command
+        .creation_flags(CREATE_NO_WINDOW)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("failed to run {}: {}", exe_path, e))?;
+
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return Ok(()),
+            Ok(Some(status)) => {
+                return Err(format!(
+                    "{} exited with code {}",
+                    label,
+                    status
+                        .code()
+                        .map_or("unknown".to_string(), |c| c.to_string())
+                ))
+            }
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("{} timed out after {}ms", label, timeout_ms));
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(COMMAND_POLL_INTERVAL_MS)),
+            Err(e) => return Err(format!("failed to wait for {}: {}", label, e)),
+        }
+    }
+}
+
+fn run_health_command(config: &HealthCommandConfig) -> Result<(), String> {
+    run_command(
+        "health command",
+        &config.exe_path,
+        config.args.as_deref(),
+        config.timeout_ms,
+    )
+}
+
+fn run_port_check(config: &PortCheckConfig) -> Result<(), String> {
+    let target = format!("{}:{}", config.host, config.port);
+    let addresses = (config.host.as_str(), config.port)
+        .to_socket_addrs()
+        .map_err(|e| format!("cannot resolve {}: {}", target, e))?;
+
+    let timeout = Duration::from_millis(config.timeout_ms);
+    let mut last_error = format!("no address found for {}", target);
+    for address in addresses {
+        match TcpStream::connect_timeout(&address, timeout) {
+            Ok(_) => return Ok(()),
+            Err(e) => last_error = format!("cannot connect to {}: {}", target, e),
+        }
+    }
+    Err(last_error)
+}
+
+/// 记录日志检查的起点：当前文件长度与时间，重启前写入的内容不参与匹配
+pub fn log_check_origin(config: &LogCheckConfig) -> LogCheckOrigin {
+    LogCheckOrigin {
+        offset: std::fs::metadata(&config.path)
+            .map(|m| m.len())
+            .unwrap_or(0),
+        since: SystemTime::now(),
+    }
+}
+
+/// 文件心跳：距文件最后一次修改的时长；文件不存在或无法读取时返回 None
+pub fn file_heartbeat_age(path: &str) -> Option<Duration> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    Some(
+        SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default(),
+    )
+}
+
+/// 计算需要扫描的起始偏移：只看起点之后、且不超过最后 tail_bytes 字节的内容
+///
+/// 文件比起点还短说明已被轮转或截断，此时从头开始计算。
+fn log_scan_start(len: u64, origin_offset: u64, tail_bytes: u64) -> u64 {
+    let origin = if len < origin_offset {
+        0
+    } else {
+        origin_offset
+    };
+    origin.max(len.saturating_sub(tail_bytes))
+}
+
+/// 日志的最后活动时间取修改时间与检查起点中较晚者，避免刚启动的进程被判定为过期
+fn log_idle_for(modified: SystemTime, since: SystemTime, now: SystemTime) -> Duration {
+    now.duration_since(modified.max(since)).unwrap_or_default()
+}
+
+fn run_log_check(config: &LogCheckConfig, origin: LogCheckOrigin) -> Result<(), String> {
+    let metadata = std::fs::metadata(&config.path)
+        .map_err(|e| format!("cannot read log file {}: {}", config.path, e))?;
+
+    if let Some(max_age_secs) = config.max_age_secs {
+        let modified = metadata.modified().unwrap_or(origin.since);
+        let idle = log_idle_for(modified, origin.since, SystemTime::now());
+        if idle > Duration::from_secs(max_age_secs) {
+            return Err(format!(
+                "log file {} not modified for {}s",
+                config.path,
+                idle.as_secs()
+            ));
+        }
+    }
+
+    let Some(pattern) = &config.pattern else {
+        return Ok(());
+    };
+    let regex = match Regex::new(pattern) {
+        Ok(regex) => regex,
+        Err(e) => {
+            // 配置错误不应导致进程被反复重启
+            warn!("Invalid log_check pattern {:?}: {}", pattern, e);
+            return Ok(());
+        }
+    };
+
+    let start = log_scan_start(metadata.len(), origin.offset, config.tail_bytes);
+    let mut file = File::open(&config.path)
+        .map_err(|e| format!("cannot open log file {}: {}", config.path, e))?;
+    file.seek(SeekFrom::Start(start))
+        .map_err(|e| format!("cannot seek log file {}: {}", config.path, e))?;
+    let mut tail = Vec::new();
+    file.take(config.tail_bytes)
+        .read_to_end(&mut tail)
+        .map_err(|e| format!("cannot read log file {}: {}", config.path, e))?;
+
+    let text = String::from_utf8_lossy(&tail);
+    match regex.find(&text) {
+        Some(found) => Err(format!(
+            "log file {} matched {:?}: {}",
+            config.path,
+            pattern,
+            text[found.start()..].lines().next().unwrap_or("").trim()
+        )),
+        None => Ok(()),
+    }
+}
+
+/// 到期且没有正在进行的检查时在后台线程执行一次 `check`
+///
+/// 返回连续失败次数达到阈值时的失败原因。结果按代次写回，
+/// 进程重启后（reset）仍在进行的旧检查不会影响新进程的计数。
+fn poll_check<F>(
+    label: &'static str,
+    item_name: &str,
+    interval_ms: u64,
+    failure_threshold: u32,
+    state: &Arc<Mutex<ProbeState>>,
+    check: F,
+) -> Option<String>
+where
+    F: FnOnce() -> Result<(), String> + Send + 'static,
+{
+    let mut guard = state.lock().unwrap();
+
+    let interval = Duration::from_millis(interval_ms);
+    let due = match guard.last_started {
+        Some(started) => started.elapsed() >= interval,
+        None => true,
+    };
+    if due && !guard.in_flight {
+        guard.in_flight = true;
+        guard.last_started = Some(Instant::now());
+
+        let generation = guard.generation;
+        let state = state.clone();
+        let item_name = item_name.to_string();
+        std::thread::spawn(move || {
+            let result = check();
+            let mut guard = state.lock().unwrap();
+            if guard.generation != generation {
+                return;
+            }
+            guard.in_flight = false;
+            match result {
+                Ok(()) => {
+                    debug!("{} for {} succeeded", label, item_name);
+                    guard.consecutive_failures = 0;
+                    guard.last_error = None;
+                }
+                Err(e) => {
+                    guard.consecutive_failures += 1;
+                    warn!(
+                        "{} for {} failed ({}/{}): {}",
+                        label, item_name, guard.consecutive_failures, failure_threshold, e
+                    );
+                    guard.last_error = Some(e);
+                }
+            }
+        });
+    }
+
+    if guard.consecutive_failures >= failure_threshold.max(1) {
+        Some(format!(
+            "{} failed {} times: {}",
+            label,
+            guard.consecutive_failures,
+            guard.last_error.as_deref().unwrap_or("unknown error")
+        ))
+    } else {
+        None
+    }
+}
+
+pub fn poll_http_probe(
+    item_name: &str,
+    config: &HttpProbeConfig,
+    state: &Arc<Mutex<ProbeState>>,
+) -> Option<String> {
+    let probe = config.clone();
+    poll_check(
+        "http probe",
+        item_name,
+        config.interval_ms,
+        config.failure_threshold,
+        state,
+        move || run_probe(&probe),
+    )
+}
+
+pub fn poll_health_command(
+    item_name: &str,
+    config: &HealthCommandConfig,
+    state: &Arc<Mutex<ProbeState>>,
+) -> Option<String> {
+    let command = config.clone();
+    poll_check(
+        "health command",
+        item_name,
+        config.interval_ms,
+        config.failure_threshold,
+        state,
+        move || run_health_command(&command),
+    )
+}
+
+pub fn poll_log_check(
+    item_name: &str,
+    config: &LogCheckConfig,
+    origin: LogCheckOrigin,
+    state: &Arc<Mutex<ProbeState>>,
+) -> Option<String> {
+    let check = config.clone();
+    poll_check(
+        "log check",
+        item_name,
+        config.interval_ms,
+        config.failure_threshold,
+        state,
+        move || run_log_check(&check, origin),
+    )
+}
+
+pub fn poll_window_check(
+    item_name: &str,
+    pid: u32,
+    config: &WindowCheckConfig,
+    state: &Arc<Mutex<ProbeState>>,
+) -> Option<String> {
+    let check = config.clone();
+    poll_check(
+        "window check",
+        item_name,
+        config.interval_ms,
+        config.failure_threshold,
+        state,
+        move || crate::window::check_window_in_user_session(pid, &check),
+    )
+}
+
+pub fn poll_port_check(
+    item_name: &str,
+    config: &PortCheckConfig,
+    state: &Arc<Mutex<ProbeState>>,
+) -> Option<String> {
+    let check = config.clone();
+    poll_check(
+        "port check",
+        item_name,
+        config.interval_ms,
+        config.failure_threshold,
+        state,
+        move || run_port_check(&check),
+    )
+}
+
+pub fn poll_event_log_check(
+    item_name: &str,
+    config: &EventLogCheckConfig,
+    origin: u32,
+    state: &Arc<Mutex<ProbeState>>,
+) -> Option<String> {
+    let check = config.clone();
+    poll_check(
+        "event log check",
+        item_name,
+        config.interval_ms,
+        config.failure_threshold,
+        state,
+        move || crate::event_log_check::run(&check, origin),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        evaluate_probe_response, file_heartbeat_age, log_idle_for, log_scan_start,
+        run_health_command, run_log_check,
+    };
+    use crate::models::{HealthCommandConfig, HttpProbeConfig, LogCheckConfig, LogCheckOrigin};
+    use std::time::{Duration, SystemTime};
+
+    fn config() -> HttpProbeConfig {
+        HttpProbeConfig {
+            url: "http://127.0.0.1:8080/health".to_string(),
+            expected_status: None,
+            expected_substring: None,
+            timeout_ms: 5000,
+            interval_ms: 10000,
+            failure_threshold: 3,
+        }
+    }
+
+    #[test]
+    fn any_2xx_is_accepted_by_default() {
+        assert!(evaluate_probe_response(&config(), 204, "").is_ok());
+        assert!(evaluate_probe_response(&config(), 500, "").is_err());
+    }
+
+    #[test]
+    fn explicit_status_and_substring_must_match() {
+        let config = HttpProbeConfig {
+            expected_status: Some(200),
+            expected_substring: Some("\"ok\"".to_string()),
+            ..config()
+        };
+        assert!(evaluate_probe_response(&config, 200, r#"{"status":"ok"}"#).is_ok());
+        assert!(evaluate_probe_response(&config, 201, r#"{"status":"ok"}"#).is_err());
+        assert!(evaluate_probe_response(&config, 200, r#"{"status":"degraded"}"#).is_err());
+    }
+
+    #[test]
+    fn health_command_exit_code_decides_result() {
+        let command = |args: &str| HealthCommandConfig {
+            exe_path: r"C:\Windows\System32\cmd.exe".to_string(),
+            args: Some(args.to_string()),
+            timeout_ms: 5000,
+            interval_ms: 30000,
+            failure_threshold: 1,
+        };
+        assert!(run_health_command(&command("/c exit 0")).is_ok());
+        let err = run_health_command(&command("/c exit 3")).unwrap_err();
+        assert!(err.contains("code 3"));
+    }
+
+    #[test]
+    fn log_scan_skips_content_before_origin() {
+        assert_eq!(log_scan_start(1000, 900, 4096), 900);
+        assert_eq!(log_scan_start(10_000, 100, 4096), 10_000 - 4096);
+        // 文件被截断后从头扫描
+        assert_eq!(log_scan_start(50, 900, 4096), 0);
+    }
+
+    #[test]
+    fn log_idle_time_starts_at_origin() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+        let modified = start - Duration::from_secs(600);
+        let now = start + Duration::from_secs(30);
+        assert_eq!(log_idle_for(modified, start, now), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn log_pattern_only_matches_new_content() {
+        let path = std::env::temp_dir().join(format!("pg-logcheck-{}.log", std::process::id()));
+        std::fs::write(&path, "old FATAL line\n").unwrap();
+        let config = LogCheckConfig {
+            path: path.to_string_lossy().into_owned(),
+            max_age_secs: None,
+            pattern: Some("FATAL".to_string()),
+            tail_bytes: 4096,
+            interval_ms: 10000,
+            failure_threshold: 1,
+        };
+        let origin = LogCheckOrigin {
+            offset: std::fs::metadata(&path).unwrap().len(),
+            since: SystemTime::now(),
+        };
+
+        assert!(run_log_check(&config, origin).is_ok());
+        std::fs::write(&path, "old FATAL line\nnew FATAL: out of memory\n").unwrap();
+        let err = run_log_check(&config, origin).unwrap_err();
+        assert!(err.contains("out of memory"));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn file_heartbeat_age_follows_modification_time() {
+        let path = std::env::temp_dir().join(format!("pg-heartbeat-{}.tmp", std::process::id()));
+        let path_str = path.to_string_lossy().into_owned();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(file_heartbeat_age(&path_str), None);
+
+        std::fs::write(&path, "1").unwrap();
+        assert!(file_heartbeat_age(&path_str).is_some_and(|age| age < Duration::from_secs(60)));
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
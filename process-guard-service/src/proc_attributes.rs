@@ -0,0 +1,241 @@
+use crate::appcontainer::AppContainerLaunch;
+use crate::models::PlacementConfig;
+use windows::Win32::Foundation::HANDLE;
+use windows::Win32::Security::SECURITY_CAPABILITIES;
+use windows::Win32::System::SystemInformation::GROUP_AFFINITY;
+use windows::Win32::System::Threading::{
+    DeleteProcThreadAttributeList, GetActiveProcessorCount, GetNumaHighestNodeNumber,
+    GetNumaNodeProcessorMaskEx, InitializeProcThreadAttributeList, SetProcessAffinityMask,
+    UpdateProcThreadAttribute, LPPROC_THREAD_ATTRIBUTE_LIST,
+    PROC_THREAD_ATTRIBUTE_CHILD_PROCESS_POLICY, PROC_THREAD_ATTRIBUTE_GROUP_AFFINITY,
+    PROC_THREAD_ATTRIBUTE_PREFERRED_NODE, PROC_THREAD_ATTRIBUTE_SECURITY_CAPABILITIES,
+};
+
+/// PROCESS_CREATION_CHILD_PROCESS_RESTRICTED：进程无法再创建子进程
+const CHILD_PROCESS_RESTRICTED: u32 = 0x01;
+
+/// 组内 processors 个处理器全部可用时的掩码
+fn group_mask(processors: u32) -> usize {
+    if processors as usize >= usize::BITS as usize {
+        usize::MAX
+    } else {
+        (1usize << processors) - 1
+    }
+}
+
+/// 用配置的 affinity_mask 收窄节点或组的处理器掩码，交集为空视为配置错误
+fn narrow_mask(mask: usize, affinity_mask: Option<u64>) -> Result<usize, String> {
+    let Some(affinity_mask) = affinity_mask else {
+        return Ok(mask);
+    };
+    let narrowed = mask & affinity_mask as usize;
+    if narrowed == 0 {
+        return Err(format!(
+            "affinity_mask {:#x} selects no processors in {:#x}",
+            affinity_mask, mask
+        ));
+    }
+    Ok(narrowed)
+}
+
+/// 把 placement 解析为处理器组与组内掩码；只设置 affinity_mask 时使用处理器组 0
+fn resolve_placement(placement: &PlacementConfig) -> Result<GROUP_AFFINITY, String> {
+    let mut affinity = GROUP_AFFINITY::default();
+    if let Some(node) = placement.numa_node {
+        let mut highest = 0u32;
+        unsafe { GetNumaHighestNodeNumber(&mut highest) }
+            .map_err(|e| format!("GetNumaHighestNodeNumber failed: {:?}", e))?;
+        if u32::from(node) > highest {
+            return Err(format!(
+                "NUMA node {} does not exist (highest node is {})",
+                node, highest
+            ));
+        }
+        if !unsafe { GetNumaNodeProcessorMaskEx(node, &mut affinity) }.as_bool() {
+            return Err(format!(
+                "GetNumaNodeProcessorMaskEx({}) failed: {:?}",
+                node,
+                windows::core::Error::from_win32()
+            ));
+        }
+        if let Some(group) = placement.processor_group.filter(|g| *g != affinity.Group) {
+            return Err(format!(
+                "NUMA node {} is in processor group {}, not {}",
+                node, affinity.Group, group
+            ));
+        }
+    } else {
+        let group = placement.processor_group.unwrap_or(0);
+        let processors = unsafe { GetActiveProcessorCount(group) };
+        if processors == 0 {
+            return Err(format!("processor group {} does not exist", group));
+        }
+        affinity.Group = group;
+        affinity.Mask = group_mask(processors);
+    }
+    affinity.Mask = narrow_mask(affinity.Mask, placement.affinity_mask)?;
+    Ok(affinity)
+}
+
+/// 启动进程时通过 STARTUPINFOEXW 传入的属性：AppContainer 沙箱、禁止创建子进程与 NUMA 放置，
+/// 共用一个属性列表。属性值由本结构（或借用的 AppContainerLaunch）持有，析构时释放列表
+pub struct LaunchAttributes<'a> {
+    app_container: Option<&'a AppContainerLaunch>,
+    child_policy: Option<Box<u32>>,
+    preferred_node: Option<Box<u16>>,
+    group_affinity: Option<Box<GROUP_AFFINITY>>,
+    buffer: Vec<u8>,
+    ready: bool,
+}
+
+impl<'a> LaunchAttributes<'a> {
+    /// 没有需要设置的属性时返回 None
+    pub fn build(
+        app_container: Option<&'a AppContainerLaunch>,
+        block_child_processes: bool,
+        placement: Option<&PlacementConfig>,
+    ) -> Result<Option<Self>, String> {
+        let group_affinity = placement.map(resolve_placement).transpose()?;
+        let mut attributes = Self {
+            app_container,
+            child_policy: block_child_processes.then(|| Box::new(CHILD_PROCESS_RESTRICTED)),
+            preferred_node: placement.and_then(|p| p.numa_node).map(Box::new),
+            group_affinity: group_affinity.map(Box::new),
+            buffer: Vec::new(),
+            ready: false,
+        };
+        let count = attributes.app_container.is_some() as u32
+            + attributes.child_policy.is_some() as u32
+            + attributes.preferred_node.is_some() as u32
+            + attributes.group_affinity.is_some() as u32;
+        if count == 0 {
+            return Ok(None);
+        }
+
+        unsafe {
+            let mut size: usize = 0;
+            // 第一次调用仅用于获取所需缓冲区大小
+            let _ = InitializeProcThreadAttributeList(
+                LPPROC_THREAD_ATTRIBUTE_LIST::default(),
+                count,
+                0,
+                &mut size,
+            );
+            attributes.buffer = vec![0u8; size];
+            InitializeProcThreadAttributeList(attributes.list(), count, 0, &mut size)
+                .map_err(|e| format!("InitializeProcThreadAttributeList failed: {:?}", e))?;
+            attributes.ready = true;
+
+            if let Some(launch) = attributes.app_container {
+                let capabilities: *const SECURITY_CAPABILITIES = launch.security_capabilities();
+                UpdateProcThreadAttribute(
+                    attributes.list(),
+                    0,
+                    PROC_THREAD_ATTRIBUTE_SECURITY_CAPABILITIES as usize,
+                    Some(capabilities.cast()),
+                    std::mem::size_of::<SECURITY_CAPABILITIES>(),
+                    None,
+                    None,
+                )
+                .map_err(|e| format!("UpdateProcThreadAttribute failed: {:?}", e))?;
+            }
+            if let Some(policy) = &attributes.child_policy {
+                let policy: *const u32 = &**policy;
+                UpdateProcThreadAttribute(
+                    attributes.list(),
+                    0,
+                    PROC_THREAD_ATTRIBUTE_CHILD_PROCESS_POLICY as usize,
+                    Some(policy.cast()),
+                    std::mem::size_of::<u32>(),
+                    None,
+                    None,
+                )
+                .map_err(|e| {
+                    format!(
+                        "UpdateProcThreadAttribute (child process policy) failed: {:?}",
+                        e
+                    )
+                })?;
+            }
+            if let Some(node) = &attributes.preferred_node {
+                let node: *const u16 = &**node;
+                UpdateProcThreadAttribute(
+                    attributes.list(),
+                    0,
+                    PROC_THREAD_ATTRIBUTE_PREFERRED_NODE as usize,
+                    Some(node.cast()),
+                    std::mem::size_of::<u16>(),
+                    None,
+                    None,
+                )
+                .map_err(|e| {
+                    format!("UpdateProcThreadAttribute (preferred node) failed: {:?}", e)
+                })?;
+            }
+            if let Some(affinity) = &attributes.group_affinity {
+                let affinity: *const GROUP_AFFINITY = &**affinity;
+                UpdateProcThreadAttribute(
+                    attributes.list(),
+                    0,
+                    PROC_THREAD_ATTRIBUTE_GROUP_AFFINITY as usize,
+                    Some(affinity.cast()),
+                    std::mem::size_of::<GROUP_AFFINITY>(),
+                    None,
+                    None,
+                )
+                .map_err(|e| {
+                    format!("UpdateProcThreadAttribute (group affinity) failed: {:?}", e)
+                })?;
+            }
+        }
+        Ok(Some(attributes))
+    }
+
+    pub fn in_app_container(&self) -> bool {
+        self.app_container.is_some()
+    }
+
+    pub fn blocks_child_processes(&self) -> bool {
+        self.child_policy.is_some()
+    }
+
+    /// 组亲和性属性只作用于初始线程，进程启动后再设置进程掩码，使之后创建的线程也留在这些处理器上
+    pub fn restrict_process(&self, process: HANDLE) -> Result<(), String> {
+        let Some(affinity) = &self.group_affinity else {
+            return Ok(());
+        };
+        unsafe { SetProcessAffinityMask(process, affinity.Mask) }.map_err(|e| {
+            format!(
+                "SetProcessAffinityMask({:#x}) failed: {:?}",
+                affinity.Mask, e
+            )
+        })
+    }
+
+    /// 供 STARTUPINFOEXW 使用的属性列表
+    pub fn list(&self) -> LPPROC_THREAD_ATTRIBUTE_LIST {
+        LPPROC_THREAD_ATTRIBUTE_LIST(self.buffer.as_ptr() as *mut _)
+    }
+}
+
+impl Drop for LaunchAttributes<'_> {
+    fn drop(&mut self) {
+        if self.ready {
+            unsafe { DeleteProcThreadAttributeList(self.list()) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{group_mask, narrow_mask};
+
+    #[test]
+    fn masks_cover_the_group_and_narrow_to_the_configured_processors() {
+        assert_eq!(group_mask(4), 0b1111);
+        assert_eq!(group_mask(usize::BITS), usize::MAX);
+        assert_eq!(narrow_mask(0xff00, Some(0x0f00)), Ok(0x0f00));
+        assert_eq!(narrow_mask(0xff00, None), Ok(0xff00));
+        assert!(narrow_mask(0xff00, Some(0xff)).is_err());
+    }
+}
@@ -0,0 +1,129 @@
+use crate::backend::ProcessBackend;
+use crate::models::MonitoredProcess;
+use crate::session0::ProcessEntry;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 一次 toolhelp 快照得到的进程表，守护循环与状态查询共用，避免逐项打开进程句柄
+pub struct ProcessTable {
+    entries: HashMap<u32, ProcessEntry>,
+    /// 快照时被跟踪 PID 的创建时间，供 is_tracked_alive 识别 PID 复用；查询失败时为 None
+    created: HashMap<u32, Option<u64>>,
+    taken_at: Instant,
+    backend: Arc<dyn ProcessBackend>,
+}
+
+impl ProcessTable {
+    /// tracked 为监控项当前记录的 PID，只为其中仍在快照中的进程查询创建时间
+    pub fn capture(backend: Arc<dyn ProcessBackend>, tracked: &[u32]) -> Self {
+        let entries = backend.snapshot();
+        let created = tracked
+            .iter()
+            .filter(|pid| entries.contains_key(pid))
+            .map(|pid| (*pid, backend.creation_time(*pid)))
+            .collect();
+        Self {
+            entries,
+            created,
+            taken_at: Instant::now(),
+            backend,
+        }
+    }
+
+    pub fn entries(&self) -> &HashMap<u32, ProcessEntry> {
+        &self.entries
+    }
+
+    pub fn age(&self) -> Duration {
+        self.taken_at.elapsed()
+    }
+
+    /// 进程是否存活；快照之后才启动的进程或快照失败时单独查询
+    pub fn is_alive(&self, pid: u32, started_at: Instant) -> bool {
+        cached_liveness(
+            pid,
+            self.entries.contains_key(&pid),
+            self.entries.is_empty(),
+            started_at >= self.taken_at,
+        )
+        .unwrap_or_else(|| self.backend.is_alive(pid))
+    }
+
+    /// 监控项记录的进程是否仍在运行；PID 已被复用给其他进程（创建时间不同）时返回 false
+    pub fn is_tracked_alive(&self, process: &MonitoredProcess) -> bool {
+        process.process_id.is_some_and(|pid| {
+            self.is_alive(pid, process.startup_time)
+                && self.is_same_process(pid, process.process_created)
+        })
+    }
+
+    /// 优先与快照时记录的创建时间比较；快照之后才开始跟踪的 PID 单独查询
+    fn is_same_process(&self, pid: u32, created: Option<u64>) -> bool {
+        match (created, self.created.get(&pid)) {
+            (None, _) => true,
+            (Some(created), Some(recorded)) => *recorded == Some(created),
+            (Some(_), None) => self.backend.is_same_process(pid, created),
+        }
+    }
+}
+
+/// 根据快照判断存活状态，返回 None 表示快照无法回答
+fn cached_liveness(
+    pid: u32,
+    in_table: bool,
+    table_empty: bool,
+    started_after_snapshot: bool,
+) -> Option<bool> {
+    if pid == 0 {
+        Some(false)
+    } else if in_table {
+        Some(true)
+    } else if table_empty || started_after_snapshot {
+        None
+    } else {
+        Some(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cached_liveness, ProcessTable};
+    use crate::backend::{MockBackend, ProcessBackend};
+    use crate::models::{MonitorItem, MonitoredProcess};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn tracked_processes_are_compared_with_creation_times_from_the_capture() {
+        let backend = Arc::new(MockBackend::new());
+        let pid = backend.add_running(r"C:\app.exe", None);
+        let mut process = MonitoredProcess::from_item(MonitorItem::new(
+            r"C:\app.exe".to_string(),
+            "app".to_string(),
+        ));
+        process.process_id = Some(pid);
+        process.startup_time = Instant::now().checked_sub(Duration::from_secs(10)).unwrap();
+        process.process_created = backend.creation_time(pid);
+
+        let table = ProcessTable::capture(backend.clone(), &[pid]);
+        assert_eq!(table.created.get(&pid), Some(&process.process_created));
+        assert!(table.is_tracked_alive(&process));
+
+        process.process_created = process.process_created.map(|created| created + 1);
+        assert!(!table.is_tracked_alive(&process));
+    }
+
+    #[test]
+    fn snapshot_answers_for_processes_started_before_it() {
+        assert_eq!(cached_liveness(42, true, false, false), Some(true));
+        assert_eq!(cached_liveness(42, false, false, false), Some(false));
+        assert_eq!(cached_liveness(0, false, false, true), Some(false));
+    }
+
+    #[test]
+    fn falls_back_for_new_processes_and_failed_snapshots() {
+        assert_eq!(cached_liveness(42, false, false, true), None);
+        assert_eq!(cached_liveness(42, false, true, false), None);
+    }
+}
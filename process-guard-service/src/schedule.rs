@@ -0,0 +1,142 @@
+use crate::models::ScheduleWindow;
+use time::{OffsetDateTime, Weekday};
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// 解析 "HH:MM"，"24:00" 表示当天结束
+fn parse_time(value: &str) -> Option<u32> {
+    let (hours, minutes) = value.trim().split_once(':')?;
+    let hours: u32 = hours.parse().ok()?;
+    let minutes: u32 = minutes.parse().ok()?;
+    if minutes >= 60 || hours * 60 + minutes > MINUTES_PER_DAY {
+        return None;
+    }
+    Some(hours * 60 + minutes)
+}
+
+/// 三字母缩写或英文全称，不区分大小写
+fn parse_weekday(value: &str) -> Option<Weekday> {
+    let value = value.trim().to_ascii_lowercase();
+    let weekday = match value.as_str() {
+        "mon" | "monday" => Weekday::Monday,
+        "tue" | "tuesday" => Weekday::Tuesday,
+        "wed" | "wednesday" => Weekday::Wednesday,
+        "thu" | "thursday" => Weekday::Thursday,
+        "fri" | "friday" => Weekday::Friday,
+        "sat" | "saturday" => Weekday::Saturday,
+        "sun" | "sunday" => Weekday::Sunday,
+        _ => return None,
+    };
+    Some(weekday)
+}
+
+fn day_matches(days: &[String], weekday: Weekday) -> bool {
+    days.is_empty() || days.iter().any(|day| parse_weekday(day) == Some(weekday))
+}
+
+/// 单个时间窗口是否包含给定时刻；结束早于开始表示跨越午夜，days 按开始当天计算
+fn window_contains(window: &ScheduleWindow, weekday: Weekday, minute: u32) -> Result<bool, String> {
+    let start = parse_time(&window.start)
+        .ok_or_else(|| format!("invalid schedule start time {:?}", window.start))?;
+    let end = parse_time(&window.end)
+        .ok_or_else(|| format!("invalid schedule end time {:?}", window.end))?;
+    if let Some(day) = window.days.iter().find(|day| parse_weekday(day).is_none()) {
+        return Err(format!("invalid schedule day {:?}", day));
+    }
+
+    Ok(if start <= end {
+        day_matches(&window.days, weekday) && minute >= start && minute < end
+    } else {
+        (day_matches(&window.days, weekday) && minute >= start)
+            || (day_matches(&window.days, weekday.previous()) && minute < end)
+    })
+}
+
+/// 配置中无效的窗口，加载配置时记录一次
+pub fn invalid_windows(windows: &[ScheduleWindow]) -> Vec<String> {
+    windows
+        .iter()
+        .filter_map(|window| window_contains(window, Weekday::Monday, 0).err())
+        .collect()
+}
+
+/// 任一窗口包含该时刻即允许运行；未配置窗口时总是允许，无效窗口视为打开
+pub fn is_open_at(windows: &[ScheduleWindow], weekday: Weekday, minute: u32) -> bool {
+    if windows.is_empty() {
+        return true;
+    }
+    windows
+        .iter()
+        .any(|window| window_contains(window, weekday, minute).unwrap_or(true))
+}
+
+/// 按本地时间判断当前是否处于运行窗口内
+pub fn is_open_now(windows: &[ScheduleWindow]) -> bool {
+    if windows.is_empty() {
+        return true;
+    }
+    let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+    is_open_at(
+        windows,
+        now.weekday(),
+        now.hour() as u32 * 60 + now.minute() as u32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{invalid_windows, is_open_at, parse_weekday};
+    use crate::models::ScheduleWindow;
+    use time::Weekday;
+
+    fn window(days: &[&str], start: &str, end: &str) -> ScheduleWindow {
+        ScheduleWindow {
+            days: days.iter().map(|d| d.to_string()).collect(),
+            start: start.to_string(),
+            end: end.to_string(),
+        }
+    }
+
+    #[test]
+    fn office_hours_on_weekdays() {
+        let windows = [window(
+            &["mon", "tue", "wed", "thu", "Friday"],
+            "07:00",
+            "19:00",
+        )];
+        assert!(is_open_at(&windows, Weekday::Monday, 7 * 60));
+        assert!(!is_open_at(&windows, Weekday::Monday, 19 * 60));
+        assert!(is_open_at(&windows, Weekday::Friday, 12 * 60));
+        assert!(!is_open_at(&windows, Weekday::Saturday, 12 * 60));
+    }
+
+    #[test]
+    fn overnight_window_belongs_to_start_day() {
+        let windows = [window(&["fri"], "22:00", "06:00")];
+        assert!(is_open_at(&windows, Weekday::Friday, 23 * 60));
+        assert!(is_open_at(&windows, Weekday::Saturday, 3 * 60));
+        assert!(!is_open_at(&windows, Weekday::Friday, 3 * 60));
+        assert!(!is_open_at(&windows, Weekday::Saturday, 23 * 60));
+    }
+
+    #[test]
+    fn invalid_windows_do_not_stop_the_process() {
+        assert!(is_open_at(
+            &[window(&[], "7am", "19:00")],
+            Weekday::Monday,
+            0
+        ));
+        assert!(is_open_at(&[], Weekday::Sunday, 0));
+        assert!(is_open_at(
+            &[window(&["monkey"], "07:00", "19:00")],
+            Weekday::Monday,
+            0
+        ));
+        assert_eq!(
+            invalid_windows(&[window(&["monkey"], "07:00", "19:00")]).len(),
+            1
+        );
+        assert_eq!(parse_weekday("monkey"), None);
+        assert_eq!(parse_weekday(" Sunday"), Some(Weekday::Sunday));
+    }
+}
@@ -0,0 +1,159 @@
+use crate::models::{MonitorItem, TaskAction};
+use log::warn;
+use time::OffsetDateTime;
+
+/// 解析后的 5 段 cron 表达式：分 时 日 月 星期
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronExpr {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days_of_month: Vec<bool>,
+    months: Vec<bool>,
+    days_of_week: Vec<bool>,
+    /// 日与星期都被限定时按标准 cron 语义取并集
+    day_restricted: bool,
+    weekday_restricted: bool,
+}
+
+/// 解析单个字段，支持 *、列表、范围与步长（如 "*/15"、"1-5"、"0,30"）
+fn parse_field(field: &str, min: u32, max: u32) -> Result<Vec<bool>, String> {
+    let mut allowed = vec![false; max as usize + 1];
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u32>()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| format!("invalid step in {:?}", part))?,
+            ),
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            let start = start
+                .parse::<u32>()
+                .map_err(|_| format!("invalid value in {:?}", part))?;
+            let end = end
+                .parse::<u32>()
+                .map_err(|_| format!("invalid value in {:?}", part))?;
+            (start, end)
+        } else {
+            let value = range
+                .parse::<u32>()
+                .map_err(|_| format!("invalid value in {:?}", part))?;
+            // "5/10" 表示从 5 开始每 10 个单位
+            (value, if step > 1 { max } else { value })
+        };
+        if start < min || end > max || start > end {
+            return Err(format!("{:?} is outside {}-{}", part, min, max));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            allowed[value as usize] = true;
+        }
+    }
+    Ok(allowed)
+}
+
+impl CronExpr {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!("cron expression {:?} must have 5 fields", expr));
+        };
+
+        let mut days_of_week = parse_field(weekday, 0, 7)?;
+        // 0 与 7 都表示星期日
+        if days_of_week[7] {
+            days_of_week[0] = true;
+        }
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)?,
+            days_of_month: parse_field(day, 1, 31)?,
+            months: parse_field(month, 1, 12)?,
+            days_of_week,
+            day_restricted: day != "*",
+            weekday_restricted: weekday != "*",
+        })
+    }
+
+    /// weekday 以 0 表示星期日
+    pub fn matches(&self, minute: u32, hour: u32, day: u32, month: u32, weekday: u32) -> bool {
+        let day_ok = self.days_of_month[day as usize];
+        let weekday_ok = self.days_of_week[weekday as usize];
+        let date_ok = match (self.day_restricted, self.weekday_restricted) {
+            (true, true) => day_ok || weekday_ok,
+            _ => day_ok && weekday_ok,
+        };
+        self.minutes[minute as usize]
+            && self.hours[hour as usize]
+            && self.months[month as usize]
+            && date_ok
+    }
+
+    pub fn matches_time(&self, now: OffsetDateTime) -> bool {
+        self.matches(
+            now.minute() as u32,
+            now.hour() as u32,
+            now.day() as u32,
+            now.month() as u32,
+            now.weekday().number_days_from_sunday() as u32,
+        )
+    }
+}
+
+/// 当前分钟内应执行的任务：(监控项 ID, 动作)
+pub fn due_tasks(items: &[MonitorItem], now: OffsetDateTime) -> Vec<(String, TaskAction)> {
+    let mut due = Vec::new();
+    for item in items {
+        for task in &item.tasks {
+            match CronExpr::parse(&task.cron) {
+                Ok(expr) if expr.matches_time(now) => due.push((item.id.clone(), task.action)),
+                Ok(_) => {}
+                Err(e) => warn!("Ignoring task of {}: {}", item.name, e),
+            }
+        }
+    }
+    due
+}
+
+/// 按本地时间取整到分钟的时间戳，用于保证每分钟只执行一次
+pub fn current_minute() -> (i64, OffsetDateTime) {
+    let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+    (now.unix_timestamp().div_euclid(60), now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CronExpr;
+
+    #[test]
+    fn parses_steps_ranges_and_lists() {
+        let expr = CronExpr::parse("*/15 7-19 * * 1-5").unwrap();
+        assert!(expr.matches(30, 7, 10, 6, 1));
+        assert!(!expr.matches(31, 7, 10, 6, 1));
+        assert!(!expr.matches(0, 20, 10, 6, 1));
+        assert!(!expr.matches(0, 8, 10, 6, 0));
+
+        let expr = CronExpr::parse("0,30 3 * * 7").unwrap();
+        assert!(expr.matches(30, 3, 1, 1, 0));
+    }
+
+    #[test]
+    fn day_and_weekday_are_ored_when_both_set() {
+        let expr = CronExpr::parse("0 4 1 * 0").unwrap();
+        assert!(expr.matches(0, 4, 1, 5, 3));
+        assert!(expr.matches(0, 4, 17, 5, 0));
+        assert!(!expr.matches(0, 4, 17, 5, 3));
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        assert!(CronExpr::parse("* * * *").is_err());
+        assert!(CronExpr::parse("60 * * * *").is_err());
+        assert!(CronExpr::parse("*/0 * * * *").is_err());
+        assert!(CronExpr::parse("a * * * *").is_err());
+    }
+}
@@ -1,273 +1,70 @@
 use crate::guardian::Guardian;
-use crate::models::SERVICE_NAME;
+use crate::http_server::{HttpRoutes, StatusHttpServer};
+use crate::models::{CheckEngine, SERVICE_NAME};
+use crate::notify::Notification;
+use crate::peer::PeerMonitor;
 use crate::pipe_server::PipeServer;
-use log::{error, info, LevelFilter, Log, Metadata, Record};
-use std::env;
+use crate::startup_gate::StartupGate;
+use crate::tray::{TrayLauncher, TrayServer};
+use crate::udp_heartbeat::UdpHeartbeatServer;
+use crate::wmi_events::ProcessExitWatcher;
+use log::{error, info};
 use std::ffi::OsString;
-use std::fs::{self, File, OpenOptions};
-use std::io::Write;
-use std::path::{Path, PathBuf};
-use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use time::macros::offset;
-use time::OffsetDateTime;
 use windows_service::define_windows_service;
 use windows_service::service::{
     ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode,
-    ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+    ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType, SessionChangeReason,
 };
 use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
 use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
 
-const MAX_LOG_SIZE: u64 = 300 * 1024 * 1024; // 300MB
-const LOG_DIR_NAME: &str = "logs";
+/// 停止过程中每个检查点之间预计的最长耗时（不含进程优雅退出的等待）
+const STOP_WAIT_HINT: Duration = Duration::from_secs(5);
+/// 启动过程中每个检查点之间预计的最长耗时（不含错峰启动的等待）
+const START_WAIT_HINT: Duration = Duration::from_secs(30);
 
-pub(crate) struct StartupGate {
-    ready: Mutex<bool>,
-    condvar: Condvar,
+/// 记录各工作线程是否仍在运行，供健康检查使用
+pub(crate) struct ServiceLiveness {
+    guardian_alive: AtomicBool,
+    pipe_alive: AtomicBool,
 }
 
-impl StartupGate {
+impl ServiceLiveness {
     pub(crate) fn new() -> Self {
         Self {
-            ready: Mutex::new(false),
-            condvar: Condvar::new(),
+            guardian_alive: AtomicBool::new(true),
+            pipe_alive: AtomicBool::new(true),
         }
     }
 
-    pub(crate) fn mark_ready(&self) {
-        let mut ready = self.ready.lock().unwrap();
-        *ready = true;
-        self.condvar.notify_all();
+    pub(crate) fn guardian_alive(&self) -> bool {
+        self.guardian_alive.load(Ordering::SeqCst)
     }
 
-    pub(crate) fn wait_until_ready(&self) {
-        let mut ready = self.ready.lock().unwrap();
-        while !*ready {
-            ready = self.condvar.wait(ready).unwrap();
-        }
+    pub(crate) fn pipe_alive(&self) -> bool {
+        self.pipe_alive.load(Ordering::SeqCst)
     }
 }
 
-/// 获取日志目录路径
-fn get_log_dir() -> PathBuf {
-    let exe_path = env::current_exe().unwrap_or_else(|_| PathBuf::from("."));
-    let exe_dir = exe_path.parent().unwrap_or(std::path::Path::new("."));
-    exe_dir.join(LOG_DIR_NAME)
-}
-
-/// 根据日期获取日志文件路径
-fn get_log_file_path(date: &OffsetDateTime) -> PathBuf {
-    let log_dir = get_log_dir();
-    let date_str = date.format(time::macros::format_description!(
-        "[year]-[month]-[day]"
-    )).unwrap_or_else(|_| "unknown".to_string());
-    log_dir.join(format!("process-guard-service-{}.log", date_str))
-}
-
-/// 计算日志目录总大小
-fn get_total_log_size(log_dir: &Path) -> u64 {
-    if !log_dir.exists() {
-        return 0;
-    }
-
-    let mut total_size = 0u64;
-    if let Ok(entries) = fs::read_dir(log_dir) {
-        for entry in entries.flatten() {
-            if let Ok(metadata) = entry.metadata() {
-                if metadata.is_file() {
-                    total_size += metadata.len();
-                }
-            }
-        }
-    }
-    total_size
-}
-
-/// 清理旧的日志文件，直到总大小低于限制
-fn cleanup_old_logs(log_dir: &Path, max_size: u64) {
-    let mut total_size = get_total_log_size(log_dir);
-
-    if total_size <= max_size {
-        return;
-    }
-
-    // 收集所有日志文件及其修改时间
-    let mut log_files: Vec<(PathBuf, std::time::SystemTime)> = Vec::new();
-    if let Ok(entries) = fs::read_dir(log_dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if let Ok(metadata) = entry.metadata() {
-                if metadata.is_file() {
-                    if let Ok(modified) = metadata.modified() {
-                        log_files.push((path, modified));
-                    }
-                }
-            }
-        }
-    }
-
-    // 按修改时间排序（最老的在前）
-    log_files.sort_by(|a, b| a.1.cmp(&b.1));
-
-    // 删除最老的文件直到低于限制
-    for (path, _) in log_files {
-        if total_size <= max_size {
-            break;
-        }
+/// 线程退出（包括 panic）时将对应标志置为 false
+struct AliveGuard<'a>(&'a AtomicBool);
 
-        if let Ok(metadata) = fs::metadata(&path) {
-            let file_size = metadata.len();
-            if fs::remove_file(&path).is_ok() {
-                total_size -= file_size;
-                eprintln!("已删除旧日志文件: {:?}", path);
-            }
-        }
-    }
-}
-
-/// 自定义日志实现，支持按日期分割和容量控制
-struct RotatingLogger {
-    level: LevelFilter,
-    log_dir: PathBuf,
-    current_date: Mutex<OffsetDateTime>,
-    file: Mutex<Option<File>>,
-}
-
-impl RotatingLogger {
-    fn new(level: LevelFilter) -> Self {
-        let log_dir = get_log_dir();
-
-        // 创建日志目录
-        if let Err(e) = fs::create_dir_all(&log_dir) {
-            eprintln!("创建日志目录失败: {:?}", e);
-        }
-
-        // 清理旧日志
-        cleanup_old_logs(&log_dir, MAX_LOG_SIZE);
-
-        let now = OffsetDateTime::now_utc().to_offset(offset!(+8));
-        let log_path = get_log_file_path(&now);
-
-        // 打开或创建日志文件
-        let file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&log_path)
-            .ok();
-
-        if file.is_some() {
-            eprintln!("日志文件已打开: {:?}", log_path);
-        } else {
-            eprintln!("无法打开日志文件: {:?}", log_path);
-        }
-
-        Self {
-            level,
-            log_dir,
-            current_date: Mutex::new(now),
-            file: Mutex::new(file),
-        }
-    }
-
-    /// 检查是否需要轮转（跨天）
-    fn check_and_rotate(&self) {
-        let now = OffsetDateTime::now_utc().to_offset(offset!(+8));
-        let current_date = *self.current_date.lock().unwrap();
-
-        // 检查是否跨天
-        if now.date() != current_date.date() {
-            let new_log_path = get_log_file_path(&now);
-
-            // 尝试打开新文件
-            if let Ok(new_file) = OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(&new_log_path)
-            {
-                let mut file_guard = self.file.lock().unwrap();
-                *file_guard = Some(new_file);
-
-                let mut date_guard = self.current_date.lock().unwrap();
-                *date_guard = now;
-
-                eprintln!("日志已轮转至新文件: {:?}", new_log_path);
-            }
-        }
-
-        // 检查容量限制
-        cleanup_old_logs(&self.log_dir, MAX_LOG_SIZE);
-    }
-
-    /// 格式化日志记录
-    fn format_log(&self, record: &Record) -> String {
-        let now = OffsetDateTime::now_utc().to_offset(offset!(+8));
-        let time_str = now
-            .format(time::macros::format_description!(
-                "[year]-[month]-[day] [hour]:[minute]:[second]"
-            ))
-            .unwrap_or_else(|_| "unknown".to_string());
-
-        format!(
-            "[{}] [{}] {}\n",
-            time_str,
-            record.level(),
-            record.args()
-        )
-    }
-}
-
-impl Log for RotatingLogger {
-    fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= self.level
-    }
-
-    fn log(&self, record: &Record) {
-        if !self.enabled(record.metadata()) {
-            return;
-        }
-
-        // 检查是否需要轮转
-        self.check_and_rotate();
-
-        let log_line = self.format_log(record);
-
-        // 写入文件
-        if let Ok(mut file_guard) = self.file.lock() {
-            if let Some(ref mut file) = *file_guard {
-                let _ = file.write_all(log_line.as_bytes());
-                let _ = file.flush();
-            }
-        }
-
-        // 同时输出到控制台（用于调试）
-        eprintln!("{}", log_line.trim());
-    }
-
-    fn flush(&self) {
-        if let Ok(mut file_guard) = self.file.lock() {
-            if let Some(ref mut file) = *file_guard {
-                let _ = file.flush();
-            }
-        }
-    }
-}
-
-fn init_logger() {
-    let logger = RotatingLogger::new(LevelFilter::Debug);
-
-    if log::set_boxed_logger(Box::new(logger)).is_ok() {
-        log::set_max_level(LevelFilter::Debug);
-        info!("日志初始化完成, 日志目录: {:?}", get_log_dir());
-    } else {
-        eprintln!("日志初始化失败");
+impl Drop for AliveGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
     }
 }
 
 define_windows_service!(ffi_service_main, service_main);
 
-fn service_main(_arguments: Vec<OsString>) {
-    init_logger();
+/// 服务启动参数，如 `sc start ProcessGuardService --safe-mode`
+pub const SAFE_MODE_ARG: &str = "--safe-mode";
+
+fn service_main(arguments: Vec<OsString>) {
+    crate::logging::init(&crate::config::read_log_sinks());
     info!("========================================");
     info!("进程守护服务启动...");
     info!("========================================");
@@ -277,6 +74,7 @@ fn service_main(_arguments: Vec<OsString>) {
     let running_for_pipe = running.clone();
     let running_for_guardian = running.clone();
     let pipe_ready = Arc::new(StartupGate::new());
+    let liveness = Arc::new(ServiceLiveness::new());
 
     let event_handler = move |control_event| -> ServiceControlHandlerResult {
         match control_event {
@@ -286,6 +84,22 @@ fn service_main(_arguments: Vec<OsString>) {
                 *running = false;
                 ServiceControlHandlerResult::NoError
             }
+            ServiceControl::SessionChange(param) => {
+                let session_id = param.notification.session_id;
+                match param.reason {
+                    SessionChangeReason::SessionLock => {
+                        crate::session0::record_session_lock(session_id, Some(true))
+                    }
+                    SessionChangeReason::SessionUnlock | SessionChangeReason::SessionLogon => {
+                        crate::session0::record_session_lock(session_id, Some(false))
+                    }
+                    SessionChangeReason::SessionLogoff => {
+                        crate::session0::record_session_lock(session_id, None)
+                    }
+                    _ => {}
+                }
+                ServiceControlHandlerResult::NoError
+            }
             ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
             _ => ServiceControlHandlerResult::NotImplemented,
         }
@@ -294,25 +108,39 @@ fn service_main(_arguments: Vec<OsString>) {
     let status_handle = service_control_handler::register(SERVICE_NAME, event_handler)
         .expect("注册服务控制处理器失败");
 
-    let _ = status_handle.set_service_status(ServiceStatus {
-        service_type: ServiceType::OWN_PROCESS,
-        current_state: ServiceState::Running,
-        controls_accepted: ServiceControlAccept::STOP,
-        exit_code: ServiceExitCode::Win32(0),
-        checkpoint: 0,
-        wait_hint: Duration::default(),
-        process_id: None,
-    });
-
-    info!("服务状态已设置为运行中");
+    let report_start_pending = |checkpoint: u32, wait_hint: Duration| {
+        let _ = status_handle.set_service_status(ServiceStatus {
+            service_type: ServiceType::OWN_PROCESS,
+            current_state: ServiceState::StartPending,
+            controls_accepted: ServiceControlAccept::empty(),
+            exit_code: ServiceExitCode::Win32(0),
+            checkpoint,
+            wait_hint,
+            process_id: None,
+        });
+    };
+    // 加载配置、打开事件库可能较慢，先报告 StartPending
+    report_start_pending(1, START_WAIT_HINT);
 
     let guardian = Arc::new(Guardian::new(
         running_for_guardian,
         Some(pipe_ready.clone()),
     ));
+    if arguments.iter().any(|arg| arg == SAFE_MODE_ARG) {
+        info!("以安全模式启动, 不启动任何监控项");
+        guardian.enter_safe_mode();
+    }
     let guardian_for_pipe = guardian.clone();
+    let guardian_for_http = guardian.clone();
+    let guardian_for_shutdown = guardian.clone();
 
+    guardian.run_diagnostics();
+    let item_count = guardian.get_config().lock().unwrap().items.len();
+    guardian.notify(Notification::service_started(item_count));
+
+    let liveness_for_guardian = liveness.clone();
     let guardian_handle = std::thread::spawn(move || {
+        let _alive = AliveGuard(&liveness_for_guardian.guardian_alive);
         info!("守护线程已启动, 进入运行循环");
         guardian.run();
         info!("守护线程已退出");
@@ -324,12 +152,141 @@ fn service_main(_arguments: Vec<OsString>) {
         running_for_pipe,
         Some(pipe_ready_for_pipe),
     );
+    let liveness_for_pipe = liveness.clone();
     let pipe_handle = std::thread::spawn(move || {
+        let _alive = AliveGuard(&liveness_for_pipe.pipe_alive);
         info!("管道服务线程已启动");
         pipe_server.run();
         info!("管道服务线程已退出");
     });
 
+    let settings = guardian_for_http
+        .get_config()
+        .lock()
+        .unwrap()
+        .settings
+        .clone();
+    let mut listeners: Vec<(String, HttpRoutes)> = Vec::new();
+    if let Some(addr) = settings.metrics_listen {
+        listeners.push((
+            addr,
+            HttpRoutes {
+                metrics: true,
+                health: false,
+            },
+        ));
+    }
+    if let Some(addr) = settings.health_listen {
+        match listeners.iter_mut().find(|(existing, _)| *existing == addr) {
+            Some((_, routes)) => routes.health = true,
+            None => listeners.push((
+                addr,
+                HttpRoutes {
+                    metrics: false,
+                    health: true,
+                },
+            )),
+        }
+    }
+    let http_handles: Vec<_> = listeners
+        .into_iter()
+        .map(|(listen_addr, routes)| {
+            let server = StatusHttpServer::new(
+                guardian_for_http.clone(),
+                running.clone(),
+                liveness.clone(),
+                listen_addr,
+                routes,
+                settings.health_max_failed_items,
+            );
+            std::thread::spawn(move || {
+                info!("HTTP 状态服务线程已启动");
+                server.run();
+                info!("HTTP 状态服务线程已退出");
+            })
+        })
+        .collect();
+
+    let peer_handle = settings.peer.clone().map(|peer| {
+        let monitor = PeerMonitor::new(guardian_for_http.clone(), running.clone(), peer);
+        std::thread::spawn(move || {
+            info!("主备对端监控线程已启动");
+            monitor.run();
+            info!("主备对端监控线程已退出");
+        })
+    });
+
+    let udp_heartbeat_handle = settings.udp_heartbeat_port.map(|port| {
+        let server = UdpHeartbeatServer::new(guardian_for_http.clone(), running.clone(), port);
+        std::thread::spawn(move || {
+            info!("UDP 心跳线程已启动");
+            server.run();
+            info!("UDP 心跳线程已退出");
+        })
+    });
+
+    let exit_watcher_handle = (settings.check_engine == CheckEngine::WmiEvents).then(|| {
+        let watcher = ProcessExitWatcher::new(guardian_for_http.clone(), running.clone());
+        std::thread::spawn(move || {
+            info!("WMI 进程退出事件线程已启动");
+            watcher.run();
+            info!("WMI 进程退出事件线程已退出");
+        })
+    });
+
+    // 托盘通道线程阻塞在 ConnectNamedPipe 上，服务停止时不等待其退出
+    if let Some(tray) = &settings.tray {
+        let server = TrayServer::new(guardian_for_http.clone(), running.clone());
+        std::thread::spawn(move || {
+            info!("托盘通道线程已启动");
+            server.run();
+        });
+        if let Some(launcher) = TrayLauncher::new(running.clone(), tray) {
+            std::thread::spawn(move || launcher.run());
+        }
+    }
+
+    // 守护引擎拉起全部进程之前保持 StartPending，每处理完一个监控项递增检查点；
+    // 错峰启动时两个检查点之间还要等待 interval_ms + jitter_ms
+    let start_wait_hint = START_WAIT_HINT
+        + settings
+            .startup_stagger
+            .as_ref()
+            .map_or(Duration::ZERO, |stagger| {
+                Duration::from_millis(stagger.interval_ms + stagger.jitter_ms)
+            });
+    let mut checkpoint = 2;
+    let mut last_progress = None;
+    report_start_pending(checkpoint, start_wait_hint);
+    loop {
+        let progress = pipe_ready.progress();
+        if progress.finished || !*running.lock().unwrap() {
+            break;
+        }
+        if last_progress != Some(progress) {
+            if last_progress.is_some() {
+                checkpoint += 1;
+                report_start_pending(checkpoint, start_wait_hint);
+            }
+            if progress.total > 0 {
+                info!("正在启动受守护进程 ({}/{})", progress.done, progress.total);
+            }
+            last_progress = Some(progress);
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    let _ = status_handle.set_service_status(ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state: ServiceState::Running,
+        controls_accepted: ServiceControlAccept::STOP | ServiceControlAccept::SESSION_CHANGE,
+        exit_code: ServiceExitCode::Win32(0),
+        checkpoint: 0,
+        wait_hint: Duration::default(),
+        process_id: None,
+    });
+
+    info!("服务状态已设置为运行中");
     info!("服务正在运行并监控进程中");
 
     loop {
@@ -344,6 +301,39 @@ fn service_main(_arguments: Vec<OsString>) {
 
     info!("服务正在停止...");
 
+    let stop_processes = settings.stop_processes_on_shutdown;
+    let stop_wait_hint = if stop_processes {
+        Duration::from_millis(settings.shutdown_timeout_ms) + STOP_WAIT_HINT
+    } else {
+        STOP_WAIT_HINT
+    };
+    let report_stop_pending = |checkpoint: u32| {
+        let _ = status_handle.set_service_status(ServiceStatus {
+            service_type: ServiceType::OWN_PROCESS,
+            current_state: ServiceState::StopPending,
+            controls_accepted: ServiceControlAccept::empty(),
+            exit_code: ServiceExitCode::Win32(0),
+            checkpoint,
+            wait_hint: stop_wait_hint,
+            process_id: None,
+        });
+    };
+    report_stop_pending(1);
+
+    // 守护线程退出后再停止进程，避免进程在停止过程中被重新拉起
+    guardian_for_shutdown.wake_check_loop();
+    let _ = guardian_handle.join();
+
+    if stop_processes {
+        guardian_for_shutdown.stop_all_processes(|done, total| {
+            info!("正在停止受守护进程 ({}/{})", done, total);
+            report_stop_pending(done as u32 + 1);
+        });
+    }
+
+    guardian_for_shutdown.notify(Notification::service_stopped());
+    guardian_for_shutdown.shutdown_notifier();
+
     let _ = status_handle.set_service_status(ServiceStatus {
         service_type: ServiceType::OWN_PROCESS,
         current_state: ServiceState::Stopped,
@@ -354,8 +344,19 @@ fn service_main(_arguments: Vec<OsString>) {
         process_id: None,
     });
 
-    let _ = guardian_handle.join();
     let _ = pipe_handle.join();
+    for handle in http_handles {
+        let _ = handle.join();
+    }
+    if let Some(handle) = peer_handle {
+        let _ = handle.join();
+    }
+    if let Some(handle) = udp_heartbeat_handle {
+        let _ = handle.join();
+    }
+    if let Some(handle) = exit_watcher_handle {
+        let _ = handle.join();
+    }
 
     info!("========================================");
     info!("进程守护服务已停止");
@@ -442,7 +443,8 @@ pub fn uninstall_service() -> Result<(), String> {
     Ok(())
 }
 
-pub fn start_service() -> Result<(), String> {
+/// 启动服务；safe_mode 为 true 时以安全模式启动
+pub fn start_service(safe_mode: bool) -> Result<(), String> {
     info!("正在启动服务");
 
     let manager_access = ServiceManagerAccess::CONNECT;
@@ -460,7 +462,8 @@ pub fn start_service() -> Result<(), String> {
             format!("打开服务失败: {:?}", e)
         })?;
 
-    service.start(&[] as &[OsString]).map_err(|e| {
+    let arguments: &[&str] = if safe_mode { &[SAFE_MODE_ARG] } else { &[] };
+    service.start(arguments).map_err(|e| {
         error!("启动服务失败: {:?}", e);
         format!("启动服务失败: {:?}", e)
     })?;
@@ -520,33 +523,3 @@ pub fn is_service_running() -> bool {
     }
     false
 }
-
-#[cfg(test)]
-mod tests {
-    use super::StartupGate;
-    use std::sync::Arc;
-    use std::sync::atomic::{AtomicBool, Ordering};
-    use std::thread;
-    use std::time::Duration;
-
-    #[test]
-    fn startup_gate_blocks_until_pipe_is_ready() {
-        let gate = Arc::new(StartupGate::new());
-        let unblocked = Arc::new(AtomicBool::new(false));
-
-        let gate_for_thread = gate.clone();
-        let unblocked_for_thread = unblocked.clone();
-        let waiter = thread::spawn(move || {
-            gate_for_thread.wait_until_ready();
-            unblocked_for_thread.store(true, Ordering::SeqCst);
-        });
-
-        thread::sleep(Duration::from_millis(50));
-        assert!(!unblocked.load(Ordering::SeqCst));
-
-        gate.mark_ready();
-        waiter.join().unwrap();
-
-        assert!(unblocked.load(Ordering::SeqCst));
-    }
-}
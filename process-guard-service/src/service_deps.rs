@@ -0,0 +1,54 @@
+use std::collections::BTreeMap;
+use windows_service::service::{ServiceAccess, ServiceState};
+use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+
+/// 查询依赖服务；全部处于 Running 时返回各服务的进程 ID（共享进程或未知时为 0），否则返回原因
+pub fn check_running(names: &[String]) -> Result<BTreeMap<String, u32>, String> {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+        .map_err(|e| format!("cannot connect to service manager: {}", e))?;
+
+    let mut pids = BTreeMap::new();
+    for name in names {
+        let status = manager
+            .open_service(name, ServiceAccess::QUERY_STATUS)
+            .and_then(|service| service.query_status())
+            .map_err(|e| format!("cannot query service {}: {}", name, e))?;
+        if status.current_state != ServiceState::Running {
+            return Err(format!("service {} is {:?}", name, status.current_state));
+        }
+        pids.insert(name.clone(), status.process_id.unwrap_or(0));
+    }
+    Ok(pids)
+}
+
+/// 与启动时记录的进程 ID 相比已重启的服务
+pub fn bounced(previous: &BTreeMap<String, u32>, current: &BTreeMap<String, u32>) -> Vec<String> {
+    current
+        .iter()
+        .filter(|(name, pid)| {
+            matches!(previous.get(*name), Some(old) if *old != 0 && **pid != 0 && old != *pid)
+        })
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::bounced;
+    use std::collections::BTreeMap;
+
+    fn pids(entries: &[(&str, u32)]) -> BTreeMap<String, u32> {
+        entries
+            .iter()
+            .map(|(name, pid)| (name.to_string(), *pid))
+            .collect()
+    }
+
+    #[test]
+    fn detects_changed_service_pids() {
+        let before = pids(&[("MSSQLSERVER", 100), ("Spooler", 200), ("Dnscache", 0)]);
+        let after = pids(&[("MSSQLSERVER", 300), ("Spooler", 200), ("Dnscache", 400)]);
+        assert_eq!(bounced(&before, &after), vec!["MSSQLSERVER".to_string()]);
+        assert!(bounced(&BTreeMap::new(), &after).is_empty());
+    }
+}
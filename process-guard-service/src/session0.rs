@@ -1,18 +1,27 @@
-use log::{debug, error, info};
+use crate::models::{IoPriority, PriorityClass, ProcessContext, ProcessResources};
+use crate::proc_attributes::LaunchAttributes;
+use log::{debug, error, info, warn};
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::OsStr;
 use std::os::windows::ffi::OsStrExt;
 use std::ptr;
+use std::sync::Mutex;
 use windows::core::{PCWSTR, PWSTR};
-use windows::Win32::Foundation::{CloseHandle, HANDLE, HMODULE, MAX_PATH};
+use windows::Win32::Foundation::{
+    CloseHandle, ERROR_NO_TOKEN, FILETIME, HANDLE, HMODULE, MAX_PATH, WAIT_OBJECT_0,
+};
 use windows::Win32::Security::{
-    GetTokenInformation, TokenElevation, TokenElevationType, TokenLinkedToken,
-    TOKEN_ELEVATION, TOKEN_ELEVATION_TYPE, TOKEN_LINKED_TOKEN,
+    GetTokenInformation, SetTokenInformation, TokenElevation, TokenElevationType, TokenLinkedToken,
+    TokenSessionId, TokenUIAccess, TOKEN_ALL_ACCESS, TOKEN_ELEVATION, TOKEN_ELEVATION_TYPE,
+    TOKEN_LINKED_TOKEN,
 };
 use windows::Win32::System::Threading::{
-    CreateProcessAsUserW, GetExitCodeProcess, OpenProcess, TerminateProcess,
-    CREATE_NEW_CONSOLE, CREATE_NO_WINDOW, CREATE_UNICODE_ENVIRONMENT, NORMAL_PRIORITY_CLASS,
-    PROCESS_INFORMATION, PROCESS_QUERY_INFORMATION, PROCESS_TERMINATE,
-    STARTUPINFOW, STARTUPINFOW_FLAGS, PROCESS_VM_READ,
+    CreateProcessAsUserW, GetCurrentProcess, GetExitCodeProcess, OpenProcess, OpenProcessToken,
+    TerminateProcess, WaitForSingleObject, CREATE_NEW_CONSOLE, CREATE_NO_WINDOW,
+    CREATE_UNICODE_ENVIRONMENT, EXTENDED_STARTUPINFO_PRESENT, NORMAL_PRIORITY_CLASS,
+    PROCESS_INFORMATION, PROCESS_QUERY_INFORMATION, PROCESS_QUERY_LIMITED_INFORMATION,
+    PROCESS_SYNCHRONIZE, PROCESS_TERMINATE, PROCESS_VM_READ, STARTUPINFOEXW, STARTUPINFOW,
+    STARTUPINFOW_FLAGS,
 };
 
 const MAXIMUM_ALLOWED: u32 = 0x02000000;
@@ -95,6 +104,59 @@ impl ProcessInfo {
     }
 }
 
+impl Default for ProcessInfo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 进程启动到的桌面
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum LaunchDesktop {
+    /// WinSta0\Default
+    #[default]
+    Default,
+    /// 安全桌面 WinSta0\Winlogon，只有 SYSTEM 可以访问
+    Secure,
+    /// 已存在的自定义桌面，如 Kiosk 或 WinSta0\Kiosk
+    Named(String),
+}
+
+impl LaunchDesktop {
+    /// 解析监控项的 desktop 字段：default、secure（或 winlogon）、其他值视为桌面名
+    pub fn parse(value: Option<&str>) -> Self {
+        let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) else {
+            return LaunchDesktop::Default;
+        };
+        match value.to_ascii_lowercase().as_str() {
+            "default" | "winsta0\\default" => LaunchDesktop::Default,
+            "secure" | "winlogon" | "winsta0\\winlogon" => LaunchDesktop::Secure,
+            _ => LaunchDesktop::Named(value.to_string()),
+        }
+    }
+
+    /// STARTUPINFOW.lpDesktop 的取值，未指定窗口站时使用 WinSta0
+    pub fn lp_desktop(&self) -> String {
+        match self {
+            LaunchDesktop::Default => "winsta0\\default".to_string(),
+            LaunchDesktop::Secure => "winsta0\\Winlogon".to_string(),
+            LaunchDesktop::Named(name) if name.contains('\\') => name.clone(),
+            LaunchDesktop::Named(name) => format!("winsta0\\{}", name),
+        }
+    }
+
+    pub fn runs_as_system(&self) -> bool {
+        *self == LaunchDesktop::Secure
+    }
+}
+
+/// 启动到的桌面以及是否为令牌设置 UIAccess
+#[derive(Debug, Clone, Default)]
+pub struct DesktopOptions {
+    pub desktop: LaunchDesktop,
+    pub ui_access: bool,
+}
+
 impl Drop for ProcessInfo {
     fn drop(&mut self) {
         unsafe {
@@ -108,7 +170,8 @@ impl Drop for ProcessInfo {
     }
 }
 
-fn to_wide_string(s: &str) -> Vec<u16> {
+/// 转换为以 0 结尾的 UTF-16 字符串，供 Win32 的 PCWSTR 参数使用
+pub(crate) fn to_wide_string(s: &str) -> Vec<u16> {
     OsStr::new(s)
         .encode_wide()
         .chain(std::iter::once(0))
@@ -237,7 +300,58 @@ fn try_get_linked_token(token: HANDLE) -> Option<HANDLE> {
 
 #[cfg(test)]
 mod tests {
-    use super::{should_prefer_linked_token, TokenLaunchSource, choose_token_launch_source};
+    use super::{
+        choose_token_launch_source, command_line_args, creation_time_matches,
+        should_prefer_linked_token, split_desktop_info, LaunchDesktop, TokenLaunchSource,
+    };
+
+    #[test]
+    fn splits_window_station_and_desktop() {
+        assert_eq!(
+            split_desktop_info(r"WinSta0\Default"),
+            (Some("WinSta0".to_string()), Some("Default".to_string()))
+        );
+        assert_eq!(
+            split_desktop_info("Default"),
+            (None, Some("Default".to_string()))
+        );
+        assert_eq!(split_desktop_info(""), (None, None));
+    }
+
+    #[test]
+    fn parses_launch_desktop() {
+        assert_eq!(LaunchDesktop::parse(None).lp_desktop(), "winsta0\\default");
+        assert_eq!(LaunchDesktop::parse(Some("Secure")), LaunchDesktop::Secure);
+        assert_eq!(
+            LaunchDesktop::parse(Some("Kiosk")).lp_desktop(),
+            "winsta0\\Kiosk"
+        );
+        assert_eq!(
+            LaunchDesktop::parse(Some("WinSta0\\Kiosk")).lp_desktop(),
+            "WinSta0\\Kiosk"
+        );
+    }
+
+    #[test]
+    fn recycled_pid_does_not_match_recorded_creation_time() {
+        assert!(creation_time_matches(None, Some(5)));
+        assert!(creation_time_matches(Some(5), Some(5)));
+        assert!(!creation_time_matches(Some(5), Some(6)));
+        assert!(!creation_time_matches(Some(5), None));
+    }
+
+    #[test]
+    fn strips_executable_from_command_line() {
+        assert_eq!(
+            command_line_args(r#""C:\Program Files\Worker\worker.exe" --queue orders "#),
+            "--queue orders"
+        );
+        assert_eq!(
+            command_line_args(r"C:\Worker\worker.exe --queue=mail"),
+            "--queue=mail"
+        );
+        assert_eq!(command_line_args(r#""C:\Worker\worker.exe""#), "");
+    }
 
     #[test]
     fn prefers_linked_token_for_limited_non_elevated_admin_token() {
@@ -302,108 +416,222 @@ fn get_active_session_id() -> u32 {
     }
 }
 
+/// 启动自检：能否获取活动会话用户的令牌（需要以 LocalSystem 运行）；没有用户登录时返回 None
+pub fn probe_user_token() -> Result<Option<u32>, String> {
+    let session_id = get_active_session_id();
+    if session_id == 0xFFFFFFFF {
+        return Ok(None);
+    }
+    let mut token = HANDLE::default();
+    unsafe {
+        if WTSQueryUserToken(session_id, &mut token) == 0 {
+            let err = windows::core::Error::from_win32();
+            // 控制台会话没有用户登录
+            if err.code() == ERROR_NO_TOKEN.to_hresult() {
+                return Ok(None);
+            }
+            return Err(format!(
+                "WTSQueryUserToken 失败（会话 {}）: {}",
+                session_id, err
+            ));
+        }
+        let _ = CloseHandle(token);
+    }
+    Ok(Some(session_id))
+}
+
+/// 由服务控制处理器的会话变更通知维护的锁屏状态；未收到通知的会话在首次使用时查询
+static SESSION_LOCKS: Mutex<BTreeMap<u32, bool>> = Mutex::new(BTreeMap::new());
+
+/// 记录会话锁定/解锁通知；locked 为 None 表示会话已注销
+pub fn record_session_lock(session_id: u32, locked: Option<bool>) {
+    let mut locks = SESSION_LOCKS.lock().unwrap();
+    match locked {
+        Some(locked) => {
+            info!(
+                "会话 {} 已{}",
+                session_id,
+                if locked { "锁定" } else { "解锁" }
+            );
+            locks.insert(session_id, locked);
+        }
+        None => {
+            locks.remove(&session_id);
+        }
+    }
+}
+
+/// 查询会话当前是否处于锁屏状态；查询失败时返回 None
+fn query_session_locked(session_id: u32) -> Option<bool> {
+    use windows::Win32::System::RemoteDesktop::{
+        WTSQuerySessionInformationW, WTSSessionInfoEx, WTSINFOEXW, WTS_CURRENT_SERVER_HANDLE,
+        WTS_SESSIONSTATE_LOCK,
+    };
+    unsafe {
+        let mut buffer = PWSTR::null();
+        let mut bytes = 0u32;
+        WTSQuerySessionInformationW(
+            WTS_CURRENT_SERVER_HANDLE,
+            session_id,
+            WTSSessionInfoEx,
+            &mut buffer,
+            &mut bytes,
+        )
+        .ok()?;
+        if buffer.is_null() {
+            return None;
+        }
+        let info = &*(buffer.0 as *const WTSINFOEXW);
+        let locked = if info.Level == 1 {
+            Some(info.Data.WTSInfoExLevel1.SessionFlags as u32 == WTS_SESSIONSTATE_LOCK)
+        } else {
+            None
+        };
+        WTSFreeMemory(buffer.0 as *mut std::ffi::c_void);
+        locked
+    }
+}
+
+/// 启动交互程序的目标会话（与 start_process_in_session0 相同）处于锁屏状态时返回其会话 ID
+pub fn locked_console_session() -> Option<u32> {
+    let session_id = get_active_session_id();
+    if session_id == 0xFFFFFFFF || session_id == 0 {
+        return None;
+    }
+    let known = SESSION_LOCKS.lock().unwrap().get(&session_id).copied();
+    let locked = match known {
+        Some(locked) => locked,
+        None => {
+            let locked = query_session_locked(session_id)?;
+            SESSION_LOCKS.lock().unwrap().insert(session_id, locked);
+            locked
+        }
+    };
+    locked.then_some(session_id)
+}
+
+/// 所有已登录用户的活动会话（不含会话 0）
+pub fn interactive_sessions() -> Vec<u32> {
+    let mut ids = Vec::new();
+    unsafe {
+        let mut session_info: *mut WTS_SESSION_INFO = ptr::null_mut();
+        let mut count: u32 = 0;
+        let result = WTSEnumerateSessionsW(HANDLE::default(), 0, 1, &mut session_info, &mut count);
+        if result != 0 && !session_info.is_null() {
+            let sessions = std::slice::from_raw_parts(session_info, count as usize);
+            ids.extend(
+                sessions
+                    .iter()
+                    .filter(|s| s.state == WTS_CONNECTSTATE_CLASS::WTSActive && s.session_id != 0)
+                    .map(|s| s.session_id),
+            );
+            WTSFreeMemory(session_info as *mut std::ffi::c_void);
+        }
+    }
+    ids
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn start_process_in_session0(
     exe_path: &str,
     working_dir: Option<&str>,
     args: Option<&str>,
     minimize: bool,
     no_window: bool,
+    attributes: Option<&LaunchAttributes>,
+    desktop: &DesktopOptions,
+    std_output: Option<HANDLE>,
+) -> Result<ProcessInfo, String> {
+    let session_id = get_active_session_id();
+    if session_id == 0xFFFFFFFF {
+        return Err("获取活动会话ID失败".to_string());
+    }
+    start_process_in_session(
+        session_id,
+        exe_path,
+        working_dir,
+        args,
+        minimize,
+        no_window,
+        attributes,
+        desktop,
+        std_output,
+    )
+}
+
+/// 是否为获取用户会话令牌或以用户身份创建进程失败；守护引擎据此对反复失败的监控项退避
+pub fn is_user_session_error(message: &str) -> bool {
+    const PREFIXES: [&str; 5] = [
+        "获取活动会话ID失败",
+        "WTSQueryUserToken 失败",
+        "DuplicateTokenEx 失败",
+        "CreateEnvironmentBlock 失败",
+        "CreateProcessAsUserW 失败",
+    ];
+    PREFIXES.iter().any(|prefix| message.starts_with(prefix))
+}
+
+/// 以指定会话的登录用户身份启动进程；std_output 为可继承句柄时作为子进程的 stdout/stderr
+#[allow(clippy::too_many_arguments)]
+pub fn start_process_in_session(
+    session_id: u32,
+    exe_path: &str,
+    working_dir: Option<&str>,
+    args: Option<&str>,
+    minimize: bool,
+    no_window: bool,
+    attributes: Option<&LaunchAttributes>,
+    desktop: &DesktopOptions,
+    std_output: Option<HANDLE>,
 ) -> Result<ProcessInfo, String> {
     unsafe {
         let mut process_info = ProcessInfo::new();
-        let mut h_token = HANDLE::default();
-        let mut h_linked_token = HANDLE::default();
-        let mut h_dup_token = HANDLE::default();
         let mut p_env: *mut std::ffi::c_void = ptr::null_mut();
 
-        let session_id = get_active_session_id();
-        if session_id == 0xFFFFFFFF {
-            return Err("获取活动会话ID失败".to_string());
-        }
-
         info!("正在会话 {} 中启动进程, 路径: {}", session_id, exe_path);
 
-        let query_result = WTSQueryUserToken(session_id, &mut h_token);
-        if query_result == 0 {
-            let err = windows::core::Error::from_win32();
-            error!("WTSQueryUserToken 失败: {:?}", err);
-            return Err(format!("WTSQueryUserToken 失败: {:?}", err));
-        }
-        info!("Session {} user token diagnostics before duplication: {}", session_id, describe_token_elevation(h_token));
-
-        let (is_elevated, elevation_type_raw) = read_token_elevation_state(h_token);
-        let linked_token = if should_prefer_linked_token(is_elevated, elevation_type_raw) {
-            try_get_linked_token(h_token)
+        let h_dup_token = if desktop.desktop.runs_as_system() {
+            // 安全桌面只允许 SYSTEM 访问，以服务自身令牌在目标会话中启动
+            info!("进程将以 SYSTEM 身份在会话 {} 的安全桌面启动", session_id);
+            system_primary_token(session_id).map_err(|e| {
+                error!("{}", e);
+                e
+            })?
         } else {
-            None
+            user_primary_token(session_id)?
         };
-        let launch_source = choose_token_launch_source(
-            is_elevated,
-            elevation_type_raw,
-            linked_token.is_some(),
-        );
-        let duplicate_source_token = match launch_source {
-            TokenLaunchSource::Linked => {
-                h_linked_token = linked_token.expect("linked token should exist for linked launch source");
-                info!(
-                    "Session {} using linked elevated token for process launch: {}",
-                    session_id,
-                    describe_token_elevation(h_linked_token)
-                );
-                h_linked_token
+        if desktop.ui_access {
+            if let Err(e) = enable_ui_access(h_dup_token) {
+                let _ = CloseHandle(h_dup_token);
+                error!("{}", e);
+                return Err(e);
             }
-            TokenLaunchSource::Original => {
-                if should_prefer_linked_token(is_elevated, elevation_type_raw) {
-                    info!(
-                        "Session {} token is limited but linked token is unavailable; falling back to original token",
-                        session_id
-                    );
-                }
-                h_token
-            }
-        };
-
-        let dup_result = DuplicateTokenEx(
-            duplicate_source_token,
-            MAXIMUM_ALLOWED,
-            ptr::null_mut(),
-            SECURITY_IDENTIFICATION,
-            TOKEN_PRIMARY,
-            &mut h_dup_token,
-        );
-
-        if dup_result == 0 {
-            let err = windows::core::Error::from_win32();
-            let _ = CloseHandle(h_token);
-            if !h_linked_token.is_invalid() {
-                let _ = CloseHandle(h_linked_token);
-            }
-            error!("DuplicateTokenEx 失败: {:?}", err);
-            return Err(format!("DuplicateTokenEx 失败: {:?}", err));
         }
-        info!("Session {} duplicated primary token diagnostics: {}", session_id, describe_token_elevation(h_dup_token));
 
         let env_result = CreateEnvironmentBlock(&mut p_env, h_dup_token, false);
         if env_result == 0 {
-            let _ = CloseHandle(h_token);
-            if !h_linked_token.is_invalid() {
-                let _ = CloseHandle(h_linked_token);
-            }
             let _ = CloseHandle(h_dup_token);
             error!("CreateEnvironmentBlock 失败");
             return Err("CreateEnvironmentBlock 失败".to_string());
         }
 
-        let mut startup_info: STARTUPINFOW = std::mem::zeroed();
+        let mut startup_info_ex: STARTUPINFOEXW = std::mem::zeroed();
+        let startup_info = &mut startup_info_ex.StartupInfo;
         startup_info.cb = std::mem::size_of::<STARTUPINFOW>() as u32;
 
-        let desktop = to_wide_string("winsta0\\default");
-        startup_info.lpDesktop = PWSTR(desktop.as_ptr() as *mut u16);
+        let desktop_name = to_wide_string(&desktop.desktop.lp_desktop());
+        startup_info.lpDesktop = PWSTR(desktop_name.as_ptr() as *mut u16);
 
         if minimize {
             startup_info.dwFlags = STARTUPINFOW_FLAGS(0x00000001);
             startup_info.wShowWindow = 2;
         }
+        if let Some(output) = std_output {
+            // STARTF_USESTDHANDLES
+            startup_info.dwFlags |= STARTUPINFOW_FLAGS(0x00000100);
+            startup_info.hStdOutput = output;
+            startup_info.hStdError = output;
+        }
 
         let mut creation_flags = CREATE_UNICODE_ENVIRONMENT | NORMAL_PRIORITY_CLASS;
         if no_window {
@@ -412,6 +640,18 @@ pub fn start_process_in_session0(
             creation_flags |= CREATE_NEW_CONSOLE;
         }
 
+        if let Some(attributes) = attributes {
+            if attributes.in_app_container() {
+                info!("进程将在 AppContainer 沙箱中启动: {}", exe_path);
+            }
+            if attributes.blocks_child_processes() {
+                info!("进程将被禁止创建子进程: {}", exe_path);
+            }
+            startup_info_ex.StartupInfo.cb = std::mem::size_of::<STARTUPINFOEXW>() as u32;
+            startup_info_ex.lpAttributeList = attributes.list();
+            creation_flags |= EXTENDED_STARTUPINFO_PRESENT;
+        }
+
         let exe_wide = to_wide_string(exe_path);
         let mut cmd_line: Vec<u16> = if let Some(a) = args {
             let cmd = format!("\"{}\" {}", exe_path, a);
@@ -434,19 +674,15 @@ pub fn start_process_in_session0(
             PWSTR(cmd_line.as_mut_ptr()),
             None,
             None,
-            false,
+            std_output.is_some(),
             creation_flags,
             Some(p_env),
             cwd_ptr,
-            &mut startup_info,
+            &startup_info_ex.StartupInfo,
             &mut proc_info,
         );
 
         let _ = DestroyEnvironmentBlock(p_env);
-        let _ = CloseHandle(h_token);
-        if !h_linked_token.is_invalid() {
-            let _ = CloseHandle(h_linked_token);
-        }
         let _ = CloseHandle(h_dup_token);
 
         if create_result.is_err() {
@@ -469,6 +705,133 @@ pub fn start_process_in_session0(
     }
 }
 
+/// 会话登录用户的主令牌；受限管理员令牌优先使用关联的提升令牌
+fn user_primary_token(session_id: u32) -> Result<HANDLE, String> {
+    let mut h_token = HANDLE::default();
+    let mut h_linked_token = HANDLE::default();
+    let mut h_dup_token = HANDLE::default();
+    unsafe {
+        let query_result = WTSQueryUserToken(session_id, &mut h_token);
+        if query_result == 0 {
+            let err = windows::core::Error::from_win32();
+            error!("WTSQueryUserToken 失败: {:?}", err);
+            return Err(format!("WTSQueryUserToken 失败: {:?}", err));
+        }
+        info!(
+            "Session {} user token diagnostics before duplication: {}",
+            session_id,
+            describe_token_elevation(h_token)
+        );
+
+        let (is_elevated, elevation_type_raw) = read_token_elevation_state(h_token);
+        let linked_token = if should_prefer_linked_token(is_elevated, elevation_type_raw) {
+            try_get_linked_token(h_token)
+        } else {
+            None
+        };
+        let launch_source =
+            choose_token_launch_source(is_elevated, elevation_type_raw, linked_token.is_some());
+        let duplicate_source_token = match launch_source {
+            TokenLaunchSource::Linked => {
+                h_linked_token =
+                    linked_token.expect("linked token should exist for linked launch source");
+                info!(
+                    "Session {} using linked elevated token for process launch: {}",
+                    session_id,
+                    describe_token_elevation(h_linked_token)
+                );
+                h_linked_token
+            }
+            TokenLaunchSource::Original => {
+                if should_prefer_linked_token(is_elevated, elevation_type_raw) {
+                    info!(
+                        "Session {} token is limited but linked token is unavailable; falling back to original token",
+                        session_id
+                    );
+                }
+                h_token
+            }
+        };
+
+        let dup_result = DuplicateTokenEx(
+            duplicate_source_token,
+            MAXIMUM_ALLOWED,
+            ptr::null_mut(),
+            SECURITY_IDENTIFICATION,
+            TOKEN_PRIMARY,
+            &mut h_dup_token,
+        );
+
+        if dup_result == 0 {
+            let err = windows::core::Error::from_win32();
+            let _ = CloseHandle(h_token);
+            if !h_linked_token.is_invalid() {
+                let _ = CloseHandle(h_linked_token);
+            }
+            error!("DuplicateTokenEx 失败: {:?}", err);
+            return Err(format!("DuplicateTokenEx 失败: {:?}", err));
+        }
+        info!(
+            "Session {} duplicated primary token diagnostics: {}",
+            session_id,
+            describe_token_elevation(h_dup_token)
+        );
+
+        let _ = CloseHandle(h_token);
+        if !h_linked_token.is_invalid() {
+            let _ = CloseHandle(h_linked_token);
+        }
+        Ok(h_dup_token)
+    }
+}
+
+/// 服务自身（LocalSystem）令牌的副本，会话 ID 改为目标会话
+fn system_primary_token(session_id: u32) -> Result<HANDLE, String> {
+    unsafe {
+        let mut h_token = HANDLE::default();
+        let mut h_dup_token = HANDLE::default();
+        OpenProcessToken(GetCurrentProcess(), TOKEN_ALL_ACCESS, &mut h_token)
+            .map_err(|e| format!("OpenProcessToken 失败: {:?}", e))?;
+        let dup_result = DuplicateTokenEx(
+            h_token,
+            MAXIMUM_ALLOWED,
+            ptr::null_mut(),
+            SECURITY_IDENTIFICATION,
+            TOKEN_PRIMARY,
+            &mut h_dup_token,
+        );
+        let _ = CloseHandle(h_token);
+        if dup_result == 0 {
+            let err = windows::core::Error::from_win32();
+            return Err(format!("DuplicateTokenEx 失败: {:?}", err));
+        }
+        if let Err(e) = SetTokenInformation(
+            h_dup_token,
+            TokenSessionId,
+            &session_id as *const u32 as *const _,
+            std::mem::size_of::<u32>() as u32,
+        ) {
+            let _ = CloseHandle(h_dup_token);
+            return Err(format!("设置令牌会话 ID 失败: {:?}", e));
+        }
+        Ok(h_dup_token)
+    }
+}
+
+/// 为令牌设置 UIAccess；需要 SeTcbPrivilege，且程序须在清单中声明 uiAccess、已签名并位于受保护目录
+fn enable_ui_access(token: HANDLE) -> Result<(), String> {
+    unsafe {
+        let ui_access: u32 = 1;
+        SetTokenInformation(
+            token,
+            TokenUIAccess,
+            &ui_access as *const u32 as *const _,
+            std::mem::size_of::<u32>() as u32,
+        )
+        .map_err(|e| format!("设置 UIAccess 失败: {:?}", e))
+    }
+}
+
 pub fn check_process_alive(process_id: u32) -> bool {
     if process_id == 0 {
         return false;
@@ -492,7 +855,220 @@ pub fn check_process_alive(process_id: u32) -> bool {
     }
 }
 
+fn filetime_to_u64(ft: &FILETIME) -> u64 {
+    ((ft.dwHighDateTime as u64) << 32) | ft.dwLowDateTime as u64
+}
+
+/// FILETIME（1601 年起的 100ns 计数）转换为 Unix 毫秒时间戳
+fn filetime_to_unix_ms(ft: &FILETIME) -> Option<i64> {
+    const UNIX_EPOCH_AS_FILETIME: u64 = 116_444_736_000_000_000;
+    let raw = filetime_to_u64(ft);
+    if raw < UNIX_EPOCH_AS_FILETIME {
+        return None;
+    }
+    Some(((raw - UNIX_EPOCH_AS_FILETIME) / 10_000) as i64)
+}
+
+pub fn query_process_resources(process_id: u32) -> Option<ProcessResources> {
+    use windows::Win32::System::ProcessStatus::{
+        GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS, PROCESS_MEMORY_COUNTERS_EX,
+    };
+    use windows::Win32::System::RemoteDesktop::ProcessIdToSessionId;
+    use windows::Win32::System::Threading::{GetProcessHandleCount, GetProcessTimes};
+
+    if process_id == 0 {
+        return None;
+    }
+
+    unsafe {
+        let handle = OpenProcess(
+            PROCESS_QUERY_INFORMATION | PROCESS_VM_READ,
+            false,
+            process_id,
+        )
+        .ok()?;
+        if handle.is_invalid() {
+            return None;
+        }
+
+        let mut counters = PROCESS_MEMORY_COUNTERS_EX {
+            cb: std::mem::size_of::<PROCESS_MEMORY_COUNTERS_EX>() as u32,
+            ..Default::default()
+        };
+        let memory_result = GetProcessMemoryInfo(
+            handle,
+            (&mut counters as *mut PROCESS_MEMORY_COUNTERS_EX).cast::<PROCESS_MEMORY_COUNTERS>(),
+            std::mem::size_of::<PROCESS_MEMORY_COUNTERS_EX>() as u32,
+        );
+
+        let mut creation = FILETIME::default();
+        let mut exit = FILETIME::default();
+        let mut kernel = FILETIME::default();
+        let mut user = FILETIME::default();
+        let times_result =
+            GetProcessTimes(handle, &mut creation, &mut exit, &mut kernel, &mut user);
+
+        let mut handle_count = 0u32;
+        let handle_count_result = GetProcessHandleCount(handle, &mut handle_count);
+        let _ = CloseHandle(handle);
+
+        if memory_result.is_err() && times_result.is_err() {
+            debug!("查询进程 {} 资源占用失败", process_id);
+            return None;
+        }
+
+        let mut session_id = 0u32;
+        let session_result = ProcessIdToSessionId(process_id, &mut session_id);
+
+        Some(ProcessResources {
+            working_set_bytes: if memory_result.is_ok() {
+                counters.WorkingSetSize as u64
+            } else {
+                0
+            },
+            private_bytes: if memory_result.is_ok() {
+                counters.PrivateUsage as u64
+            } else {
+                0
+            },
+            cpu_time_100ns: if times_result.is_ok() {
+                filetime_to_u64(&kernel) + filetime_to_u64(&user)
+            } else {
+                0
+            },
+            handle_count: handle_count_result.ok().map(|_| handle_count),
+            thread_count: None,
+            start_time_unix_ms: if times_result.is_ok() {
+                filetime_to_unix_ms(&creation)
+            } else {
+                None
+            },
+            session_id: session_result.ok().map(|_| session_id),
+        })
+    }
+}
+
+/// 通过一次 Toolhelp 快照获取所有进程的线程数
+/// 进程快照中的一项
+#[derive(Debug, Clone, Default)]
+pub struct ProcessEntry {
+    pub parent_pid: u32,
+    pub thread_count: u32,
+    pub image_name: String,
+}
+
+/// 一次遍历获取所有进程的父进程、线程数与映像名
+pub fn snapshot_processes() -> HashMap<u32, ProcessEntry> {
+    use windows::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W,
+        TH32CS_SNAPPROCESS,
+    };
+
+    let mut entries = HashMap::new();
+
+    unsafe {
+        let snapshot = match CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) {
+            Ok(h) => h,
+            Err(_) => return entries,
+        };
+
+        let mut entry: PROCESSENTRY32W = std::mem::zeroed();
+        entry.dwSize = std::mem::size_of::<PROCESSENTRY32W>() as u32;
+
+        let mut result = Process32FirstW(snapshot, &mut entry);
+        while result.is_ok() {
+            let name_len = entry
+                .szExeFile
+                .iter()
+                .position(|&c| c == 0)
+                .unwrap_or(entry.szExeFile.len());
+            entries.insert(
+                entry.th32ProcessID,
+                ProcessEntry {
+                    parent_pid: entry.th32ParentProcessID,
+                    thread_count: entry.cntThreads,
+                    image_name: String::from_utf16_lossy(&entry.szExeFile[..name_len]),
+                },
+            );
+            result = Process32NextW(snapshot, &mut entry);
+        }
+
+        let _ = CloseHandle(snapshot);
+    }
+
+    entries
+}
+
+/// 在进程句柄上等待进程退出，超时返回 false；进程已不存在时返回 true。
+/// created 为记录的创建时间：按 PID 重新打开的句柄属于复用该 PID 的其他进程时，视为原进程已退出
+pub fn wait_for_process_exit(
+    process_id: u32,
+    created: Option<u64>,
+    timeout: std::time::Duration,
+) -> bool {
+    if process_id == 0 {
+        return true;
+    }
+
+    unsafe {
+        let handle = match OpenProcess(
+            PROCESS_SYNCHRONIZE | PROCESS_QUERY_LIMITED_INFORMATION,
+            false,
+            process_id,
+        ) {
+            Ok(h) => h,
+            Err(_) => return true,
+        };
+        if created.is_some() && handle_creation_time(handle) != created {
+            let _ = CloseHandle(handle);
+            return true;
+        }
+        let timeout_ms = timeout.as_millis().min(u32::MAX as u128 - 1) as u32;
+        let result = WaitForSingleObject(handle, timeout_ms);
+        let _ = CloseHandle(handle);
+        result == WAIT_OBJECT_0
+    }
+}
+
+/// 进程创建时间（FILETIME 原始值），与 PID 一起唯一标识一个进程
+pub fn process_creation_time(process_id: u32) -> Option<u64> {
+    if process_id == 0 {
+        return None;
+    }
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, process_id).ok()?;
+        let created = handle_creation_time(handle);
+        let _ = CloseHandle(handle);
+        created
+    }
+}
+
+unsafe fn handle_creation_time(handle: HANDLE) -> Option<u64> {
+    use windows::Win32::System::Threading::GetProcessTimes;
+
+    let mut creation = FILETIME::default();
+    let mut exit = FILETIME::default();
+    let mut kernel = FILETIME::default();
+    let mut user = FILETIME::default();
+    GetProcessTimes(handle, &mut creation, &mut exit, &mut kernel, &mut user).ok()?;
+    Some(filetime_to_u64(&creation))
+}
+
+/// 未记录创建时间时不做校验；记录了则必须与当前进程一致
+fn creation_time_matches(recorded: Option<u64>, current: Option<u64>) -> bool {
+    match recorded {
+        None => true,
+        Some(recorded) => current == Some(recorded),
+    }
+}
+
 pub fn kill_process(process_id: u32) -> bool {
+    kill_process_checked(process_id, None)
+}
+
+/// 终止进程；记录了创建时间时先在同一句柄上校验，PID 已被复用则不终止
+pub fn kill_process_checked(process_id: u32, created: Option<u64>) -> bool {
     if process_id == 0 {
         return true;
     }
@@ -500,7 +1076,12 @@ pub fn kill_process(process_id: u32) -> bool {
     info!("正在终止进程, PID: {}", process_id);
 
     unsafe {
-        let handle = match OpenProcess(PROCESS_TERMINATE, false, process_id) {
+        let access = if created.is_some() {
+            PROCESS_TERMINATE | PROCESS_QUERY_LIMITED_INFORMATION
+        } else {
+            PROCESS_TERMINATE
+        };
+        let handle = match OpenProcess(access, false, process_id) {
             Ok(h) => h,
             Err(_) => {
                 debug!("进程 {} 未找到或已终止", process_id);
@@ -513,13 +1094,19 @@ pub fn kill_process(process_id: u32) -> bool {
             return true;
         }
 
+        if !creation_time_matches(created, handle_creation_time(handle)) {
+            warn!("PID {} 已被其他进程复用，跳过终止", process_id);
+            let _ = CloseHandle(handle);
+            return true;
+        }
+
         let result = TerminateProcess(handle, 0);
         let _ = CloseHandle(handle);
 
         if result.is_ok() {
             info!("进程 {} 终止成功", process_id);
         }
-        
+
         result.is_ok()
     }
 }
@@ -550,12 +1137,17 @@ pub fn find_process_by_name(process_name: &str) -> Option<u32> {
 
         while result.is_ok() {
             let exe_name = String::from_utf16_lossy(
-                &entry.szExeFile
-                    [..entry.szExeFile.iter().position(|&c| c == 0).unwrap_or(entry.szExeFile.len())],
+                &entry.szExeFile[..entry
+                    .szExeFile
+                    .iter()
+                    .position(|&c| c == 0)
+                    .unwrap_or(entry.szExeFile.len())],
             );
 
             if exe_name.to_lowercase() == target_name
-                || exe_name.to_lowercase().ends_with(&format!("\\{}", target_name))
+                || exe_name
+                    .to_lowercase()
+                    .ends_with(&format!("\\{}", target_name))
             {
                 let pid = entry.th32ProcessID;
                 let _ = CloseHandle(snapshot);
@@ -630,3 +1222,523 @@ pub fn find_process_by_path(exe_path: &str) -> Option<u32> {
         None
     }
 }
+
+/// 查找所有匹配的进程：pattern 含路径分隔符时按完整路径匹配，否则按映像名匹配
+pub fn find_processes_by_image(pattern: &str) -> Vec<u32> {
+    use windows::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W,
+        TH32CS_SNAPPROCESS,
+    };
+    use windows::Win32::System::ProcessStatus::GetModuleFileNameExW;
+
+    let by_path = pattern.contains('\\') || pattern.contains('/');
+    let target = pattern.replace('/', "\\").to_lowercase();
+    let own_pid = std::process::id();
+    let mut pids = Vec::new();
+
+    unsafe {
+        let snapshot = match CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) {
+            Ok(h) => h,
+            Err(_) => return pids,
+        };
+
+        if snapshot.is_invalid() {
+            return pids;
+        }
+
+        let mut entry: PROCESSENTRY32W = std::mem::zeroed();
+        entry.dwSize = std::mem::size_of::<PROCESSENTRY32W>() as u32;
+
+        let mut result = Process32FirstW(snapshot, &mut entry);
+        while result.is_ok() {
+            let pid = entry.th32ProcessID;
+            let matched = if pid == 0 || pid == own_pid {
+                false
+            } else if by_path {
+                match OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, false, pid) {
+                    Ok(handle) if !handle.is_invalid() => {
+                        let mut buffer = [0u16; MAX_PATH as usize];
+                        let len = GetModuleFileNameExW(handle, HMODULE::default(), &mut buffer);
+                        let _ = CloseHandle(handle);
+                        len > 0
+                            && String::from_utf16_lossy(&buffer[..len as usize]).to_lowercase()
+                                == target
+                    }
+                    _ => false,
+                }
+            } else {
+                let name_len = entry
+                    .szExeFile
+                    .iter()
+                    .position(|&c| c == 0)
+                    .unwrap_or(entry.szExeFile.len());
+                String::from_utf16_lossy(&entry.szExeFile[..name_len]).to_lowercase() == target
+            };
+
+            if matched {
+                pids.push(pid);
+            }
+            result = Process32NextW(snapshot, &mut entry);
+        }
+
+        let _ = CloseHandle(snapshot);
+    }
+
+    debug!("匹配 {} 的进程: {:?}", pattern, pids);
+    pids
+}
+
+#[link(name = "ntdll")]
+extern "system" {
+    fn NtSuspendProcess(process_handle: HANDLE) -> i32;
+    fn NtResumeProcess(process_handle: HANDLE) -> i32;
+    fn NtQueryInformationProcess(
+        process_handle: HANDLE,
+        information_class: u32,
+        information: *mut std::ffi::c_void,
+        information_length: u32,
+        return_length: *mut u32,
+    ) -> i32;
+    fn NtSetInformationProcess(
+        process_handle: HANDLE,
+        information_class: u32,
+        information: *const std::ffi::c_void,
+        information_length: u32,
+    ) -> i32;
+}
+
+/// PROCESSINFOCLASS::ProcessIoPriority
+const PROCESS_IO_PRIORITY: u32 = 33;
+
+/// 设置刚启动的进程的优先级类别与 I/O 优先级；handle 需具有 PROCESS_SET_INFORMATION 权限
+pub fn set_process_priority(
+    handle: HANDLE,
+    priority_class: Option<PriorityClass>,
+    io_priority: Option<IoPriority>,
+) -> Result<(), String> {
+    use windows::Win32::System::Threading::{
+        SetPriorityClass, ABOVE_NORMAL_PRIORITY_CLASS, BELOW_NORMAL_PRIORITY_CLASS,
+        HIGH_PRIORITY_CLASS, IDLE_PRIORITY_CLASS,
+    };
+
+    if let Some(class) = priority_class {
+        let flags = match class {
+            PriorityClass::Idle => IDLE_PRIORITY_CLASS,
+            PriorityClass::BelowNormal => BELOW_NORMAL_PRIORITY_CLASS,
+            PriorityClass::Normal => NORMAL_PRIORITY_CLASS,
+            PriorityClass::AboveNormal => ABOVE_NORMAL_PRIORITY_CLASS,
+            PriorityClass::High => HIGH_PRIORITY_CLASS,
+        };
+        unsafe { SetPriorityClass(handle, flags) }
+            .map_err(|e| format!("SetPriorityClass 失败: {:?}", e))?;
+    }
+    if let Some(io) = io_priority {
+        let value: u32 = match io {
+            IoPriority::VeryLow => 0,
+            IoPriority::Low => 1,
+            IoPriority::Normal => 2,
+        };
+        let status = unsafe {
+            NtSetInformationProcess(
+                handle,
+                PROCESS_IO_PRIORITY,
+                (&value as *const u32).cast(),
+                std::mem::size_of::<u32>() as u32,
+            )
+        };
+        if status < 0 {
+            return Err(format!(
+                "设置 I/O 优先级失败, NTSTATUS=0x{:08X}",
+                status as u32
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// PROCESSINFOCLASS::ProcessCommandLineInformation（Windows 8.1 起）
+const PROCESS_COMMAND_LINE_INFORMATION: u32 = 60;
+
+#[repr(C)]
+struct UnicodeString {
+    length: u16,
+    maximum_length: u16,
+    buffer: *const u16,
+}
+
+/// 读取进程的完整命令行
+pub fn process_command_line(process_id: u32) -> Option<String> {
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_INFORMATION, false, process_id).ok()?;
+        // 以 usize 为单位分配，保证 UNICODE_STRING 对齐
+        let mut buffer = vec![0usize; 1024];
+        let mut return_length = 0u32;
+        let mut status = NtQueryInformationProcess(
+            handle,
+            PROCESS_COMMAND_LINE_INFORMATION,
+            buffer.as_mut_ptr() as *mut _,
+            (buffer.len() * std::mem::size_of::<usize>()) as u32,
+            &mut return_length,
+        );
+        // STATUS_INFO_LENGTH_MISMATCH：按返回的长度重试
+        if status as u32 == 0xC000_0004 {
+            buffer = vec![0usize; return_length as usize / std::mem::size_of::<usize>() + 1];
+            status = NtQueryInformationProcess(
+                handle,
+                PROCESS_COMMAND_LINE_INFORMATION,
+                buffer.as_mut_ptr() as *mut _,
+                (buffer.len() * std::mem::size_of::<usize>()) as u32,
+                &mut return_length,
+            );
+        }
+        let _ = CloseHandle(handle);
+        if status < 0 {
+            return None;
+        }
+
+        let header = &*(buffer.as_ptr() as *const UnicodeString);
+        if header.buffer.is_null() {
+            return Some(String::new());
+        }
+        let chars = std::slice::from_raw_parts(header.buffer, header.length as usize / 2);
+        Some(String::from_utf16_lossy(chars))
+    }
+}
+
+/// 去掉命令行中的可执行文件部分，返回参数
+pub fn command_line_args(command_line: &str) -> &str {
+    let command_line = command_line.trim_start();
+    let rest = match command_line.strip_prefix('"') {
+        Some(quoted) => quoted.find('"').map_or("", |end| &quoted[end + 1..]),
+        None => command_line
+            .find(char::is_whitespace)
+            .map_or("", |end| &command_line[end..]),
+    };
+    rest.trim()
+}
+
+/// 按路径与启动参数查找已在运行的实例，用于同一程序配置多个监控项的场景
+pub fn find_process_instance(exe_path: &str, args: Option<&str>) -> Option<u32> {
+    let expected = args.unwrap_or_default().trim();
+    find_processes_by_image(exe_path).into_iter().find(|pid| {
+        process_command_line(*pid)
+            .is_some_and(|command_line| command_line_args(&command_line) == expected)
+    })
+}
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn ReadProcessMemory(
+        process: HANDLE,
+        base_address: *const std::ffi::c_void,
+        buffer: *mut std::ffi::c_void,
+        size: usize,
+        bytes_read: *mut usize,
+    ) -> i32;
+}
+
+/// PROCESSINFOCLASS::ProcessBasicInformation
+const PROCESS_BASIC_INFORMATION_CLASS: u32 = 0;
+
+#[repr(C)]
+struct ProcessBasicInformation {
+    exit_status: i32,
+    peb_base_address: *const std::ffi::c_void,
+    affinity_mask: usize,
+    base_priority: i32,
+    unique_process_id: usize,
+    inherited_from_unique_process_id: usize,
+}
+
+/// PEB.ProcessParameters 与 RTL_USER_PROCESS_PARAMETERS 中 DesktopInfo、Environment、
+/// EnvironmentSize 的偏移
+#[cfg(target_pointer_width = "64")]
+const PEB_PROCESS_PARAMETERS_OFFSET: usize = 0x20;
+#[cfg(target_pointer_width = "64")]
+const PARAMETERS_DESKTOP_INFO_OFFSET: usize = 0xC0;
+#[cfg(target_pointer_width = "64")]
+const PARAMETERS_ENVIRONMENT_OFFSET: usize = 0x80;
+#[cfg(target_pointer_width = "64")]
+const PARAMETERS_ENVIRONMENT_SIZE_OFFSET: usize = 0x3F0;
+#[cfg(target_pointer_width = "32")]
+const PEB_PROCESS_PARAMETERS_OFFSET: usize = 0x10;
+#[cfg(target_pointer_width = "32")]
+const PARAMETERS_DESKTOP_INFO_OFFSET: usize = 0x78;
+#[cfg(target_pointer_width = "32")]
+const PARAMETERS_ENVIRONMENT_OFFSET: usize = 0x48;
+#[cfg(target_pointer_width = "32")]
+const PARAMETERS_ENVIRONMENT_SIZE_OFFSET: usize = 0x290;
+/// 读取环境块的上限，防止异常的 EnvironmentSize
+const MAX_ENVIRONMENT_BYTES: usize = 1024 * 1024;
+
+unsafe fn read_remote<T>(handle: HANDLE, address: usize) -> Option<T> {
+    let mut value = std::mem::MaybeUninit::<T>::uninit();
+    let mut read = 0usize;
+    let ok = ReadProcessMemory(
+        handle,
+        address as *const _,
+        value.as_mut_ptr() as *mut _,
+        std::mem::size_of::<T>(),
+        &mut read,
+    );
+    (ok != 0 && read == std::mem::size_of::<T>()).then(|| value.assume_init())
+}
+
+/// 目标进程 RTL_USER_PROCESS_PARAMETERS 的地址
+unsafe fn process_parameters(handle: HANDLE) -> Option<usize> {
+    let mut basic = std::mem::zeroed::<ProcessBasicInformation>();
+    let mut return_length = 0u32;
+    let status = NtQueryInformationProcess(
+        handle,
+        PROCESS_BASIC_INFORMATION_CLASS,
+        &mut basic as *mut _ as *mut _,
+        std::mem::size_of::<ProcessBasicInformation>() as u32,
+        &mut return_length,
+    );
+    if status < 0 || basic.peb_base_address.is_null() {
+        return None;
+    }
+    read_remote(
+        handle,
+        basic.peb_base_address as usize + PEB_PROCESS_PARAMETERS_OFFSET,
+    )
+}
+
+/// 读取进程的环境变量（"名称=值"，按环境块中的顺序）
+pub fn process_environment(process_id: u32) -> Option<Vec<String>> {
+    unsafe {
+        let handle = OpenProcess(
+            PROCESS_QUERY_INFORMATION | PROCESS_VM_READ,
+            false,
+            process_id,
+        )
+        .ok()?;
+        let result = (|| {
+            let parameters = process_parameters(handle)?;
+            let address: usize = read_remote(handle, parameters + PARAMETERS_ENVIRONMENT_OFFSET)?;
+            let size: usize = read_remote(handle, parameters + PARAMETERS_ENVIRONMENT_SIZE_OFFSET)?;
+            if address == 0 || size == 0 {
+                return Some(Vec::new());
+            }
+            let size = size.min(MAX_ENVIRONMENT_BYTES);
+            let mut block = vec![0u16; size / 2];
+            let mut read = 0usize;
+            let ok = ReadProcessMemory(
+                handle,
+                address as *const _,
+                block.as_mut_ptr() as *mut _,
+                block.len() * 2,
+                &mut read,
+            );
+            if ok == 0 {
+                return None;
+            }
+            block.truncate(read / 2);
+            Some(
+                block
+                    .split(|c| *c == 0)
+                    .take_while(|entry| !entry.is_empty())
+                    .map(String::from_utf16_lossy)
+                    .collect(),
+            )
+        })();
+        let _ = CloseHandle(handle);
+        result
+    }
+}
+
+/// 读取进程启动时的 DesktopInfo（如 WinSta0\Default）；为空表示继承父进程
+fn process_desktop_info(process_id: u32) -> Option<String> {
+    unsafe {
+        let handle = OpenProcess(
+            PROCESS_QUERY_INFORMATION | PROCESS_VM_READ,
+            false,
+            process_id,
+        )
+        .ok()?;
+        let result = (|| {
+            let parameters = process_parameters(handle)?;
+            let desktop: UnicodeString =
+                read_remote(handle, parameters + PARAMETERS_DESKTOP_INFO_OFFSET)?;
+            if desktop.buffer.is_null() || desktop.length == 0 {
+                return Some(String::new());
+            }
+            let mut chars = vec![0u16; desktop.length as usize / 2];
+            let mut read = 0usize;
+            let ok = ReadProcessMemory(
+                handle,
+                desktop.buffer as *const _,
+                chars.as_mut_ptr() as *mut _,
+                desktop.length as usize,
+                &mut read,
+            );
+            (ok != 0).then(|| String::from_utf16_lossy(&chars[..read / 2]))
+        })();
+        let _ = CloseHandle(handle);
+        result
+    }
+}
+
+/// 将 DesktopInfo 拆分为窗口站与桌面
+fn split_desktop_info(desktop_info: &str) -> (Option<String>, Option<String>) {
+    let desktop_info = desktop_info.trim();
+    if desktop_info.is_empty() {
+        return (None, None);
+    }
+    match desktop_info.split_once('\\') {
+        Some((station, desktop)) => (Some(station.to_string()), Some(desktop.to_string())),
+        None => (None, Some(desktop_info.to_string())),
+    }
+}
+
+/// 进程令牌对应的账户，格式为 DOMAIN\user
+fn process_user(process_id: u32) -> Option<String> {
+    use windows::Win32::Security::{
+        LookupAccountSidW, TokenUser, SID_NAME_USE, TOKEN_QUERY, TOKEN_USER,
+    };
+    use windows::Win32::System::Threading::OpenProcessToken;
+
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, process_id).ok()?;
+        let mut token = HANDLE::default();
+        let opened = OpenProcessToken(process, TOKEN_QUERY, &mut token);
+        let _ = CloseHandle(process);
+        opened.ok()?;
+
+        // 以 usize 为单位分配，保证 TOKEN_USER 对齐
+        let mut buffer = vec![0usize; 64];
+        let mut length = 0u32;
+        let queried = GetTokenInformation(
+            token,
+            TokenUser,
+            Some(buffer.as_mut_ptr() as *mut _),
+            (buffer.len() * std::mem::size_of::<usize>()) as u32,
+            &mut length,
+        );
+        if queried.is_err() {
+            let _ = CloseHandle(token);
+            return None;
+        }
+        let user = &*(buffer.as_ptr() as *const TOKEN_USER);
+
+        let mut name = [0u16; 256];
+        let mut name_len = name.len() as u32;
+        let mut domain = [0u16; 256];
+        let mut domain_len = domain.len() as u32;
+        let mut sid_use = SID_NAME_USE::default();
+        let looked_up = LookupAccountSidW(
+            PCWSTR::null(),
+            user.User.Sid,
+            PWSTR(name.as_mut_ptr()),
+            &mut name_len,
+            PWSTR(domain.as_mut_ptr()),
+            &mut domain_len,
+            &mut sid_use,
+        );
+        let _ = CloseHandle(token);
+        looked_up.ok()?;
+
+        let name = String::from_utf16_lossy(&name[..name_len as usize]);
+        let domain = String::from_utf16_lossy(&domain[..domain_len as usize]);
+        Some(if domain.is_empty() {
+            name
+        } else {
+            format!("{}\\{}", domain, name)
+        })
+    }
+}
+
+/// 查询进程的会话、用户、窗口站、启动时间与命令行；单项查询失败时该项为 None
+pub fn query_process_context(process_id: u32) -> ProcessContext {
+    use windows::Win32::System::RemoteDesktop::ProcessIdToSessionId;
+
+    let mut session_id = 0u32;
+    let session_id = unsafe { ProcessIdToSessionId(process_id, &mut session_id) }
+        .ok()
+        .map(|_| session_id);
+    let (window_station, desktop) = process_desktop_info(process_id)
+        .map(|info| split_desktop_info(&info))
+        .unwrap_or_default();
+    let start_time_unix_ms = process_creation_time(process_id).and_then(|created| {
+        filetime_to_unix_ms(&FILETIME {
+            dwLowDateTime: created as u32,
+            dwHighDateTime: (created >> 32) as u32,
+        })
+    });
+
+    ProcessContext {
+        process_id,
+        session_id,
+        user: process_user(process_id),
+        window_station,
+        desktop,
+        start_time_unix_ms,
+        command_line: process_command_line(process_id),
+    }
+}
+
+/// 挂起（suspend 为 true）或恢复进程的所有线程
+pub fn set_process_suspended(process_id: u32, suspend: bool) -> Result<(), String> {
+    use windows::Win32::System::Threading::PROCESS_SUSPEND_RESUME;
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_SUSPEND_RESUME, false, process_id)
+            .map_err(|e| format!("无法打开进程 {}: {:?}", process_id, e))?;
+        let status = if suspend {
+            NtSuspendProcess(handle)
+        } else {
+            NtResumeProcess(handle)
+        };
+        let _ = CloseHandle(handle);
+
+        if status < 0 {
+            return Err(format!(
+                "{}进程 {} 失败, NTSTATUS=0x{:08X}",
+                if suspend { "挂起" } else { "恢复" },
+                process_id,
+                status as u32
+            ));
+        }
+    }
+
+    info!(
+        "进程 {} 已{}",
+        process_id,
+        if suspend { "挂起" } else { "恢复" }
+    );
+    Ok(())
+}
+
+/// 清空进程的工作集（EmptyWorkingSet），返回收缩前后的工作集字节数
+pub fn trim_working_set(process_id: u32) -> Result<(u64, u64), String> {
+    use windows::Win32::System::ProcessStatus::{
+        EmptyWorkingSet, GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS,
+    };
+    use windows::Win32::System::Threading::PROCESS_SET_QUOTA;
+
+    unsafe fn working_set(handle: HANDLE) -> u64 {
+        let mut counters = PROCESS_MEMORY_COUNTERS {
+            cb: std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32,
+            ..Default::default()
+        };
+        match GetProcessMemoryInfo(handle, &mut counters, counters.cb) {
+            Ok(()) => counters.WorkingSetSize as u64,
+            Err(_) => 0,
+        }
+    }
+
+    unsafe {
+        let handle = OpenProcess(
+            PROCESS_QUERY_INFORMATION | PROCESS_SET_QUOTA,
+            false,
+            process_id,
+        )
+        .map_err(|e| format!("无法打开进程 {}: {:?}", process_id, e))?;
+        let before = working_set(handle);
+        let result = EmptyWorkingSet(handle);
+        let after = working_set(handle);
+        let _ = CloseHandle(handle);
+        result.map_err(|e| format!("收缩进程 {} 工作集失败: {:?}", process_id, e))?;
+        Ok((before, after))
+    }
+}
@@ -0,0 +1,135 @@
+use crate::acl::SecurityDescriptor;
+use crate::session0::to_wide_string;
+use log::debug;
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{CloseHandle, HANDLE, WAIT_OBJECT_0};
+use windows::Win32::System::Threading::{
+    CreateEventW, OpenEventW, ResetEvent, SetEvent, WaitForSingleObject, EVENT_MODIFY_STATE,
+    SYNCHRONIZATION_SYNCHRONIZE,
+};
+
+/// 停止事件的 DACL：SYSTEM 与 Administrators 完全控制，其他用户（含 AppContainer）只能等待
+const STOP_EVENT_SDDL: &str = "D:P(A;;GA;;;SY)(A;;GA;;;BA)(A;;0x100000;;;WD)(A;;0x100000;;;AC)";
+
+/// 服务运行在会话 0，未指定命名空间的事件名补上 Global\ 前缀，才能与用户会话中的程序共享
+fn qualified_event_name(name: &str) -> String {
+    if name.contains('\\') {
+        name.to_string()
+    } else {
+        format!("Global\\{}", name)
+    }
+}
+
+/// 通过命名事件发出的重启通知。事件由被守护程序创建：
+/// 服务置位 `<event>`，程序保存状态后置位 `<event>_ack` 表示可以终止
+pub struct ShutdownEvent {
+    ack: Option<HANDLE>,
+}
+
+impl ShutdownEvent {
+    /// 程序未创建事件时返回错误，调用方只需等待管道确认
+    pub fn signal(name: &str) -> Result<Self, String> {
+        let name = qualified_event_name(name);
+        let wide = to_wide_string(&name);
+        unsafe {
+            let event = OpenEventW(EVENT_MODIFY_STATE, false, PCWSTR(wide.as_ptr()))
+                .map_err(|e| format!("OpenEventW({}) failed: {:?}", name, e))?;
+            let result = SetEvent(event);
+            let _ = CloseHandle(event);
+            result.map_err(|e| format!("SetEvent({}) failed: {:?}", name, e))?;
+
+            let ack_name = format!("{}_ack", name);
+            let ack_wide = to_wide_string(&ack_name);
+            let ack = match OpenEventW(
+                SYNCHRONIZATION_SYNCHRONIZE,
+                false,
+                PCWSTR(ack_wide.as_ptr()),
+            ) {
+                Ok(ack) => Some(ack),
+                Err(e) => {
+                    debug!("No acknowledgment event {}: {:?}", ack_name, e);
+                    None
+                }
+            };
+            Ok(Self { ack })
+        }
+    }
+
+    /// 程序是否已置位确认事件
+    pub fn acknowledged(&self) -> bool {
+        self.ack
+            .map(|ack| unsafe { WaitForSingleObject(ack, 0) } == WAIT_OBJECT_0)
+            .unwrap_or(false)
+    }
+}
+
+impl Drop for ShutdownEvent {
+    fn drop(&mut self) {
+        if let Some(ack) = self.ack.take() {
+            unsafe {
+                let _ = CloseHandle(ack);
+            }
+        }
+    }
+}
+
+/// 监控项停止事件的名称；ID 中不能用于内核对象名的 '\\' 替换为 '_'
+pub fn stop_event_name(item_id: &str) -> String {
+    format!("Global\\PG_Stop_{}", item_id.replace('\\', "_"))
+}
+
+/// 服务为 stop_event 监控项创建的手动重置事件（Global\PG_Stop_<id>）。
+/// 停止或重启时置位，程序等待该事件后自行退出；启动新进程前复位
+pub struct StopEvent {
+    handle: HANDLE,
+}
+
+unsafe impl Send for StopEvent {}
+
+impl StopEvent {
+    pub fn create(item_id: &str) -> Result<Self, String> {
+        let name = stop_event_name(item_id);
+        let wide = to_wide_string(&name);
+        let security = SecurityDescriptor::from_sddl(STOP_EVENT_SDDL)?;
+        let attributes = security.attributes();
+        let handle = unsafe { CreateEventW(Some(&attributes), true, false, PCWSTR(wide.as_ptr())) }
+            .map_err(|e| format!("CreateEventW({}) failed: {:?}", name, e))?;
+        debug!("Created stop event {}", name);
+        Ok(Self { handle })
+    }
+
+    pub fn signal(&self) -> Result<(), String> {
+        unsafe { SetEvent(self.handle) }.map_err(|e| format!("SetEvent failed: {:?}", e))
+    }
+
+    pub fn reset(&self) -> Result<(), String> {
+        unsafe { ResetEvent(self.handle) }.map_err(|e| format!("ResetEvent failed: {:?}", e))
+    }
+}
+
+impl Drop for StopEvent {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseHandle(self.handle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{qualified_event_name, stop_event_name};
+
+    #[test]
+    fn unqualified_names_use_the_global_namespace() {
+        assert_eq!(
+            qualified_event_name("MyApp.Shutdown"),
+            "Global\\MyApp.Shutdown"
+        );
+        assert_eq!(qualified_event_name("Local\\MyApp"), "Local\\MyApp");
+        assert_eq!(
+            stop_event_name("order-worker"),
+            "Global\\PG_Stop_order-worker"
+        );
+        assert_eq!(stop_event_name(r"a\b"), "Global\\PG_Stop_a_b");
+    }
+}
@@ -0,0 +1,150 @@
+use crate::session0::to_wide_string;
+use windows::core::{GUID, PCWSTR};
+use windows::Win32::Foundation::{HANDLE, HWND};
+use windows::Win32::Security::Cryptography::{CertGetNameStringW, CERT_NAME_SIMPLE_DISPLAY_TYPE};
+use windows::Win32::Security::WinTrust::{
+    WTHelperGetProvCertFromChain, WTHelperGetProvSignerFromChain, WTHelperProvDataFromStateData,
+    WinVerifyTrust, WINTRUST_ACTION_GENERIC_VERIFY_V2, WINTRUST_DATA, WINTRUST_DATA_0,
+    WINTRUST_FILE_INFO, WTD_CHOICE_FILE, WTD_REVOKE_NONE, WTD_STATEACTION_CLOSE,
+    WTD_STATEACTION_VERIFY, WTD_UI_NONE,
+};
+
+const TRUST_E_NOSIGNATURE: u32 = 0x800B_0100;
+const TRUST_E_SUBJECT_FORM_UNKNOWN: u32 = 0x800B_0003;
+const TRUST_E_PROVIDER_UNKNOWN: u32 = 0x800B_0001;
+const TRUST_E_BAD_DIGEST: u32 = 0x8009_6010;
+const TRUST_E_EXPLICIT_DISTRUST: u32 = 0x800B_0111;
+const CERT_E_UNTRUSTEDROOT: u32 = 0x800B_0109;
+const CERT_E_EXPIRED: u32 = 0x800B_0101;
+const CERT_E_REVOKED: u32 = 0x800C_010C;
+const CRYPT_E_SECURITY_SETTINGS: u32 = 0x8009_2026;
+
+/// 将 WinVerifyTrust 的返回值转换为可读的原因
+fn describe_trust_error(code: i32) -> String {
+    let reason = match code as u32 {
+        TRUST_E_NOSIGNATURE | TRUST_E_SUBJECT_FORM_UNKNOWN | TRUST_E_PROVIDER_UNKNOWN => {
+            "file is not signed"
+        }
+        TRUST_E_BAD_DIGEST => "signature does not match file contents",
+        TRUST_E_EXPLICIT_DISTRUST => "signer is explicitly distrusted",
+        CERT_E_UNTRUSTEDROOT => "certificate chain ends in an untrusted root",
+        CERT_E_EXPIRED => "signing certificate has expired",
+        CERT_E_REVOKED => "signing certificate has been revoked",
+        CRYPT_E_SECURITY_SETTINGS => "signature verification blocked by local policy",
+        _ => "signature verification failed",
+    };
+    format!("{} (0x{:08X})", reason, code as u32)
+}
+
+/// 发布者比较忽略大小写与首尾空白
+fn publisher_matches(expected: &str, actual: &str) -> bool {
+    expected.trim().eq_ignore_ascii_case(actual.trim())
+}
+
+/// 从已验证的状态数据中读取第一个签名者证书的显示名称
+unsafe fn signer_display_name(state: HANDLE) -> Option<String> {
+    let provider = WTHelperProvDataFromStateData(state);
+    if provider.is_null() {
+        return None;
+    }
+    let signer = WTHelperGetProvSignerFromChain(provider, 0, false, 0);
+    if signer.is_null() {
+        return None;
+    }
+    let cert = WTHelperGetProvCertFromChain(signer, 0);
+    if cert.is_null() || (*cert).pCert.is_null() {
+        return None;
+    }
+
+    let mut buffer = [0u16; 256];
+    let len = CertGetNameStringW(
+        (*cert).pCert,
+        CERT_NAME_SIMPLE_DISPLAY_TYPE,
+        0,
+        None,
+        Some(&mut buffer),
+    ) as usize;
+    if len <= 1 {
+        return None;
+    }
+    Some(String::from_utf16_lossy(&buffer[..len - 1]))
+}
+
+/// 使用 WinVerifyTrust 校验 Authenticode 签名，成功时返回签名者名称
+pub fn verify_signature(exe_path: &str) -> Result<String, String> {
+    let path = to_wide_string(exe_path);
+    let mut file_info = WINTRUST_FILE_INFO {
+        cbStruct: std::mem::size_of::<WINTRUST_FILE_INFO>() as u32,
+        pcwszFilePath: PCWSTR(path.as_ptr()),
+        hFile: HANDLE::default(),
+        pgKnownSubject: std::ptr::null_mut(),
+    };
+    let mut data = WINTRUST_DATA {
+        cbStruct: std::mem::size_of::<WINTRUST_DATA>() as u32,
+        dwUIChoice: WTD_UI_NONE,
+        fdwRevocationChecks: WTD_REVOKE_NONE,
+        dwUnionChoice: WTD_CHOICE_FILE,
+        Anonymous: WINTRUST_DATA_0 {
+            pFile: &mut file_info,
+        },
+        dwStateAction: WTD_STATEACTION_VERIFY,
+        ..Default::default()
+    };
+    let mut action: GUID = WINTRUST_ACTION_GENERIC_VERIFY_V2;
+
+    unsafe {
+        let status = WinVerifyTrust(
+            HWND::default(),
+            &mut action,
+            (&mut data as *mut WINTRUST_DATA).cast(),
+        );
+
+        let result = if status == 0 {
+            Ok(signer_display_name(data.hWVTStateData).unwrap_or_default())
+        } else {
+            Err(describe_trust_error(status))
+        };
+
+        // 释放 WTD_STATEACTION_VERIFY 分配的状态数据
+        data.dwStateAction = WTD_STATEACTION_CLOSE;
+        WinVerifyTrust(
+            HWND::default(),
+            &mut action,
+            (&mut data as *mut WINTRUST_DATA).cast(),
+        );
+
+        result
+    }
+}
+
+/// 校验签名，并在指定了发布者时检查签名者是否一致
+pub fn check_signature(exe_path: &str, expected_publisher: Option<&str>) -> Result<(), String> {
+    let signer = verify_signature(exe_path)
+        .map_err(|e| format!("Signature check failed for {}: {}", exe_path, e))?;
+
+    match expected_publisher {
+        Some(expected) if !publisher_matches(expected, &signer) => Err(format!(
+            "Signature check failed for {}: signed by \"{}\", expected \"{}\"",
+            exe_path, signer, expected
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{describe_trust_error, publisher_matches};
+
+    #[test]
+    fn publisher_comparison_ignores_case_and_whitespace() {
+        assert!(publisher_matches("Contoso Ltd", " contoso ltd "));
+        assert!(!publisher_matches("Contoso Ltd", "Contoso Ltd."));
+    }
+
+    #[test]
+    fn unsigned_files_are_described() {
+        let message = describe_trust_error(0x800B_0100u32 as i32);
+        assert!(message.starts_with("file is not signed"));
+        assert!(message.ends_with("(0x800B0100)"));
+    }
+}
@@ -0,0 +1,85 @@
+//! 守护引擎的启动闸门，嵌入守护引擎的宿主程序可用它推迟拉起进程并跟踪启动进度
+
+use std::sync::{Condvar, Mutex};
+
+/// 启动闸门：守护引擎在 mark_ready 之前不拉起进程（服务中用于等待管道就绪），
+/// 之后通过它报告启动进度，服务据此向 SCM 报告检查点
+#[derive(Default)]
+pub struct StartupGate {
+    ready: Mutex<bool>,
+    condvar: Condvar,
+    progress: Mutex<StartupProgress>,
+}
+
+/// 守护引擎启动全部进程的进度
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StartupProgress {
+    /// 已处理（启动、接管或跳过）的监控项数
+    pub done: usize,
+    pub total: usize,
+    /// 启动阶段已结束（包括因服务停止而中止）
+    pub finished: bool,
+}
+
+impl StartupGate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mark_ready(&self) {
+        let mut ready = self.ready.lock().unwrap();
+        *ready = true;
+        self.condvar.notify_all();
+    }
+
+    pub fn wait_until_ready(&self) {
+        let mut ready = self.ready.lock().unwrap();
+        while !*ready {
+            ready = self.condvar.wait(ready).unwrap();
+        }
+    }
+
+    pub fn report_progress(&self, done: usize, total: usize) {
+        let mut progress = self.progress.lock().unwrap();
+        progress.done = done;
+        progress.total = total;
+    }
+
+    pub fn mark_started(&self) {
+        self.progress.lock().unwrap().finished = true;
+    }
+
+    pub fn progress(&self) -> StartupProgress {
+        *self.progress.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StartupGate;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn startup_gate_blocks_until_pipe_is_ready() {
+        let gate = Arc::new(StartupGate::new());
+        let unblocked = Arc::new(AtomicBool::new(false));
+
+        let gate_for_thread = gate.clone();
+        let unblocked_for_thread = unblocked.clone();
+        let waiter = thread::spawn(move || {
+            gate_for_thread.wait_until_ready();
+            unblocked_for_thread.store(true, Ordering::SeqCst);
+        });
+
+        thread::sleep(Duration::from_millis(50));
+        assert!(!unblocked.load(Ordering::SeqCst));
+
+        gate.mark_ready();
+        waiter.join().unwrap();
+
+        assert!(unblocked.load(Ordering::SeqCst));
+    }
+}
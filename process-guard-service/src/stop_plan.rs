@@ -0,0 +1,194 @@
+use crate::models::StopStep;
+use log::{debug, info, warn};
+use std::time::Duration;
+
+/// 停止序列用到的操作，由守护循环针对具体进程提供
+pub trait StopActions {
+    /// 等待进程退出，最长 timeout；返回进程是否已退出
+    fn wait_for_exit(&self, timeout: Duration) -> bool;
+    fn http(&self, method: &str, url: &str, timeout: Duration) -> Result<u16, String>;
+    fn close_window(&self) -> Result<(), String>;
+    /// 置位停止事件，未启用 stop_event 时返回 false
+    fn signal_stop_event(&self) -> bool;
+    fn kill(&self, tree: bool);
+}
+
+/// 停止序列的执行结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StopOutcome {
+    /// 执行了多少步（不含序列结束后的强制终止）
+    pub steps_run: usize,
+    /// 进程是否在任何终止步骤之前自行退出
+    pub graceful: bool,
+}
+
+/// 按顺序执行停止序列；进程退出后跳过剩余步骤，全部执行后仍在运行则强制终止
+pub fn run(name: &str, steps: &[StopStep], actions: &impl StopActions) -> StopOutcome {
+    let mut killed = false;
+    for (index, step) in steps.iter().enumerate() {
+        if actions.wait_for_exit(Duration::ZERO) {
+            info!("{} exited after {} stop step(s)", name, index);
+            return StopOutcome {
+                steps_run: index,
+                graceful: !killed,
+            };
+        }
+        debug!("Stop step {} of {}: {}", index + 1, name, step.as_str());
+        match step {
+            StopStep::Http {
+                url,
+                method,
+                timeout_ms,
+            } => match actions.http(method, url, Duration::from_millis(*timeout_ms)) {
+                Ok(status) => info!(
+                    "Stop request {} {} for {} returned HTTP {}",
+                    method, url, name, status
+                ),
+                Err(e) => warn!("Stop request {} {} for {} failed: {}", method, url, name, e),
+            },
+            StopStep::CloseWindow => {
+                if let Err(e) = actions.close_window() {
+                    warn!("Failed to close the window of {}: {}", name, e);
+                }
+            }
+            StopStep::StopEvent => {
+                if !actions.signal_stop_event() {
+                    warn!(
+                        "Stop event of {} was not signaled (stop_event disabled?)",
+                        name
+                    );
+                }
+            }
+            StopStep::Wait { ms } => {
+                actions.wait_for_exit(Duration::from_millis(*ms));
+            }
+            StopStep::Kill | StopStep::KillTree => {
+                actions.kill(matches!(step, StopStep::KillTree));
+                killed = true;
+            }
+        }
+    }
+
+    if actions.wait_for_exit(Duration::ZERO) {
+        info!("{} exited after {} stop step(s)", name, steps.len());
+    } else {
+        warn!(
+            "{} is still running after its stop sequence, terminating",
+            name
+        );
+        actions.kill(false);
+        killed = true;
+    }
+    StopOutcome {
+        steps_run: steps.len(),
+        graceful: !killed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run, StopActions, StopOutcome};
+    use crate::models::StopStep;
+    use std::cell::{Cell, RefCell};
+    use std::time::Duration;
+
+    /// 在第 exit_on_wait 次非零等待时退出的进程
+    struct FakeProcess {
+        exit_on_wait: usize,
+        waits: Cell<usize>,
+        exited: Cell<bool>,
+        calls: RefCell<Vec<String>>,
+    }
+
+    impl StopActions for FakeProcess {
+        fn wait_for_exit(&self, timeout: Duration) -> bool {
+            if !timeout.is_zero() {
+                self.waits.set(self.waits.get() + 1);
+                self.calls
+                    .borrow_mut()
+                    .push(format!("wait {}", timeout.as_millis()));
+                if self.waits.get() == self.exit_on_wait {
+                    self.exited.set(true);
+                }
+            }
+            self.exited.get()
+        }
+
+        fn http(&self, method: &str, url: &str, _timeout: Duration) -> Result<u16, String> {
+            self.calls.borrow_mut().push(format!("{} {}", method, url));
+            Err("connection refused".to_string())
+        }
+
+        fn close_window(&self) -> Result<(), String> {
+            self.calls.borrow_mut().push("close".to_string());
+            Ok(())
+        }
+
+        fn signal_stop_event(&self) -> bool {
+            false
+        }
+
+        fn kill(&self, tree: bool) {
+            self.calls
+                .borrow_mut()
+                .push(if tree { "kill_tree" } else { "kill" }.to_string());
+            self.exited.set(true);
+        }
+    }
+
+    #[test]
+    fn stops_at_exit_and_kills_when_sequence_runs_out() {
+        let steps = [
+            StopStep::Http {
+                url: "http://127.0.0.1:8080/shutdown".to_string(),
+                method: "POST".to_string(),
+                timeout_ms: 5000,
+            },
+            StopStep::CloseWindow,
+            StopStep::Wait { ms: 10_000 },
+            StopStep::KillTree,
+        ];
+        let process = |exit_on_wait| FakeProcess {
+            exit_on_wait,
+            waits: Cell::new(0),
+            exited: Cell::new(false),
+            calls: RefCell::new(Vec::new()),
+        };
+
+        let graceful = process(1);
+        let outcome = run("app", &steps, &graceful);
+        assert_eq!(
+            outcome,
+            StopOutcome {
+                steps_run: 3,
+                graceful: true
+            }
+        );
+        assert_eq!(
+            graceful.calls.into_inner(),
+            ["POST http://127.0.0.1:8080/shutdown", "close", "wait 10000"]
+        );
+
+        let stubborn = process(usize::MAX);
+        let outcome = run("app", &steps, &stubborn);
+        assert_eq!(
+            outcome,
+            StopOutcome {
+                steps_run: 4,
+                graceful: false
+            }
+        );
+        assert_eq!(stubborn.calls.into_inner().last().unwrap(), "kill_tree");
+
+        let forced = process(usize::MAX);
+        let outcome = run("app", &steps[..3], &forced);
+        assert_eq!(
+            outcome,
+            StopOutcome {
+                steps_run: 3,
+                graceful: false
+            }
+        );
+        assert_eq!(forced.calls.into_inner().last().unwrap(), "kill");
+    }
+}
@@ -0,0 +1,765 @@
+use crate::launch_info::LaunchInfo;
+use crate::models::RestartReason;
+use log::{debug, error, info};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// 计为故障的事件类型，用于 last_failure
+const FAILURE_KINDS: [EventKind; 4] = [
+    EventKind::ProcessExited,
+    EventKind::HeartbeatTimeout,
+    EventKind::HealthCheckFailed,
+    EventKind::StartFailed,
+];
+
+/// 持久化事件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Started,
+    StartFailed,
+    Restarted,
+    ProcessExited,
+    HeartbeatTimeout,
+    Stopped,
+    SecurityRejected,
+    HealthCheckFailed,
+    Blocked,
+    OrphanDetected,
+    Suspended,
+    Resumed,
+    Escalated,
+    Observed,
+    SurplusInstances,
+    Deferred,
+    MemoryTrimmed,
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::Started => "started",
+            EventKind::StartFailed => "start_failed",
+            EventKind::Restarted => "restarted",
+            EventKind::ProcessExited => "process_exited",
+            EventKind::HeartbeatTimeout => "heartbeat_timeout",
+            EventKind::Stopped => "stopped",
+            EventKind::SecurityRejected => "security_rejected",
+            EventKind::HealthCheckFailed => "health_check_failed",
+            EventKind::Blocked => "blocked",
+            EventKind::OrphanDetected => "orphan_detected",
+            EventKind::Suspended => "suspended",
+            EventKind::Resumed => "resumed",
+            EventKind::Escalated => "escalated",
+            EventKind::Observed => "observed",
+            EventKind::SurplusInstances => "surplus_instances",
+            EventKind::Deferred => "deferred",
+            EventKind::MemoryTrimmed => "memory_trimmed",
+        }
+    }
+}
+
+/// events 表中的一条记录
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StoredEvent {
+    pub item_id: String,
+    pub kind: String,
+    pub detail: String,
+    /// restarted 事件的重启原因（RestartReason 的 snake_case 名称）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub ts: i64,
+}
+
+/// resource_samples 表中的一次资源采样
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ResourceSample {
+    pub ts: i64,
+    /// 首次采样时尚无上一次 CPU 时间，为空
+    pub cpu_percent: Option<f64>,
+    pub working_set_bytes: u64,
+    pub private_bytes: u64,
+    pub handle_count: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct UptimeReport {
+    pub item_id: String,
+    pub window_days: u32,
+    pub availability_percent: f64,
+    pub restarts: u32,
+    pub heartbeat_failures: u32,
+    /// 按重启原因统计的重启次数；早期版本记录的重启没有原因，不计入
+    pub restarts_by_reason: BTreeMap<String, u32>,
+}
+
+/// launches 表中的一次启动记录
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct StoredLaunch {
+    pub ts: i64,
+    pub pid: u32,
+    #[serde(flatten)]
+    pub info: LaunchInfo,
+}
+
+/// list 中附带的监控项统计
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ItemStats {
+    /// 当前在线区间已持续的秒数，未运行时为空
+    pub current_uptime_secs: Option<u64>,
+    /// 相邻两次重启的平均间隔（秒），少于两次重启时为空
+    pub mean_time_between_restarts_secs: Option<u64>,
+    /// 最近一次故障事件（进程退出、心跳超时、健康检查失败或启动失败）
+    pub last_failure: Option<StoredEvent>,
+    pub availability_24h_percent: f64,
+}
+
+pub fn now_unix_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// 计算若干在线区间在 [window_start, window_end] 内的可用率
+///
+/// 统计起点取窗口起点与该监控项首次被记录时间（`first_tracked`）中较晚者，
+/// 避免新加入的监控项被算作长期离线；窗口内首个区间之前的离线时间仍计入分母。
+fn availability_percent(
+    segments: &[(i64, i64)],
+    first_tracked: Option<i64>,
+    window_start: i64,
+    window_end: i64,
+) -> f64 {
+    let tracked_since = first_tracked.map_or(window_start, |first| first.max(window_start));
+
+    let total = window_end - tracked_since;
+    if total <= 0 {
+        return if segments.is_empty() { 0.0 } else { 100.0 };
+    }
+
+    let covered: i64 = segments
+        .iter()
+        .map(|(start, end)| {
+            let start = (*start).max(tracked_since);
+            let end = (*end).min(window_end);
+            (end - start).max(0)
+        })
+        .sum();
+
+    (covered as f64 / total as f64 * 100.0).min(100.0)
+}
+
+/// 查询监控项最早一段在线区间的起点，不受统计窗口限制
+fn first_tracked(conn: &Connection, item_id: &str) -> rusqlite::Result<Option<i64>> {
+    conn.query_row(
+        "SELECT MIN(started_at) FROM uptime_segments WHERE item_id = ?1",
+        params![item_id],
+        |row| row.get(0),
+    )
+}
+
+/// 基于 SQLite 的事件与在线时长存储
+pub struct EventStore {
+    conn: Mutex<Connection>,
+}
+
+impl EventStore {
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        Self::init(conn)
+    }
+
+    #[cfg(test)]
+    fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> rusqlite::Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                item_id TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                detail TEXT NOT NULL DEFAULT '',
+                ts INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_events_item_ts ON events(item_id, ts);
+            CREATE TABLE IF NOT EXISTS uptime_segments (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                item_id TEXT NOT NULL,
+                started_at INTEGER NOT NULL,
+                ended_at INTEGER NOT NULL,
+                open INTEGER NOT NULL DEFAULT 1
+            );
+            CREATE INDEX IF NOT EXISTS idx_uptime_item ON uptime_segments(item_id, ended_at);
+            CREATE TABLE IF NOT EXISTS resource_samples (
+                item_id TEXT NOT NULL,
+                ts INTEGER NOT NULL,
+                cpu_percent REAL,
+                working_set_bytes INTEGER NOT NULL,
+                private_bytes INTEGER NOT NULL,
+                handle_count INTEGER
+            );
+            CREATE INDEX IF NOT EXISTS idx_samples_item_ts ON resource_samples(item_id, ts);
+            CREATE INDEX IF NOT EXISTS idx_samples_ts ON resource_samples(ts);
+            CREATE TABLE IF NOT EXISTS launches (
+                item_id TEXT NOT NULL,
+                ts INTEGER NOT NULL,
+                pid INTEGER NOT NULL,
+                exe_path TEXT NOT NULL,
+                file_version TEXT,
+                product_version TEXT,
+                sha256 TEXT,
+                command_line TEXT,
+                env_fingerprint TEXT
+            );
+            CREATE INDEX IF NOT EXISTS idx_launches_item_ts ON launches(item_id, ts);",
+        )?;
+
+        // 早期版本的 events 表没有 reason 列
+        let has_reason = conn
+            .prepare("SELECT 1 FROM pragma_table_info('events') WHERE name = 'reason'")?
+            .exists([])?;
+        if !has_reason {
+            conn.execute("ALTER TABLE events ADD COLUMN reason TEXT", [])?;
+        }
+
+        // 上次运行遗留的未关闭区间以最后一次刷新时间为准
+        let closed = conn.execute("UPDATE uptime_segments SET open = 0 WHERE open = 1", [])?;
+        if closed > 0 {
+            info!(
+                "Closed {} dangling uptime segments from previous run",
+                closed
+            );
+        }
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    pub fn record_event(&self, item_id: &str, kind: EventKind, detail: &str) {
+        let conn = self.conn.lock().unwrap();
+        if let Err(e) = conn.execute(
+            "INSERT INTO events (item_id, kind, detail, ts) VALUES (?1, ?2, ?3, ?4)",
+            params![item_id, kind.as_str(), detail, now_unix_ms()],
+        ) {
+            error!(
+                "Failed to record {} event for {}: {}",
+                kind.as_str(),
+                item_id,
+                e
+            );
+        }
+    }
+
+    /// 记录一次重启及其原因
+    pub fn record_restart(&self, item_id: &str, reason: RestartReason, detail: &str) {
+        let conn = self.conn.lock().unwrap();
+        if let Err(e) = conn.execute(
+            "INSERT INTO events (item_id, kind, detail, reason, ts) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                item_id,
+                EventKind::Restarted.as_str(),
+                detail,
+                reason.as_str(),
+                now_unix_ms()
+            ],
+        ) {
+            error!("Failed to record restart event for {}: {}", item_id, e);
+        }
+    }
+
+    /// 记录一次启动实际使用的程序与参数
+    pub fn record_launch(&self, item_id: &str, pid: u32, info: &LaunchInfo) {
+        let conn = self.conn.lock().unwrap();
+        if let Err(e) = conn.execute(
+            "INSERT INTO launches (item_id, ts, pid, exe_path, file_version, product_version, \
+             sha256, command_line, env_fingerprint) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                item_id,
+                now_unix_ms(),
+                pid,
+                info.exe_path,
+                info.file_version,
+                info.product_version,
+                info.sha256,
+                info.command_line,
+                info.env_fingerprint
+            ],
+        ) {
+            error!("Failed to record launch of {}: {}", item_id, e);
+        }
+    }
+
+    /// 某个监控项最近的若干次启动，按时间倒序
+    pub fn launches(&self, item_id: &str, limit: u32) -> rusqlite::Result<Vec<StoredLaunch>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT ts, pid, exe_path, file_version, product_version, sha256, command_line, \
+             env_fingerprint FROM launches WHERE item_id = ?1 ORDER BY ts DESC, rowid DESC LIMIT ?2",
+        )?;
+        let launches = stmt
+            .query_map(params![item_id, limit], |row| {
+                Ok(StoredLaunch {
+                    ts: row.get(0)?,
+                    pid: row.get(1)?,
+                    info: LaunchInfo {
+                        exe_path: row.get(2)?,
+                        file_version: row.get(3)?,
+                        product_version: row.get(4)?,
+                        sha256: row.get(5)?,
+                        command_line: row.get(6)?,
+                        env_fingerprint: row.get(7)?,
+                    },
+                })
+            })?
+            .collect();
+        launches
+    }
+
+    /// 开始新的在线区间（会先关闭该项尚未结束的区间）
+    pub fn begin_uptime(&self, item_id: &str) {
+        let now = now_unix_ms();
+        let conn = self.conn.lock().unwrap();
+        let result = conn
+            .execute(
+                "UPDATE uptime_segments SET ended_at = ?2, open = 0 WHERE item_id = ?1 AND open = 1",
+                params![item_id, now],
+            )
+            .and_then(|_| {
+                conn.execute(
+                    "INSERT INTO uptime_segments (item_id, started_at, ended_at, open) VALUES (?1, ?2, ?2, 1)",
+                    params![item_id, now],
+                )
+            });
+        if let Err(e) = result {
+            error!("Failed to begin uptime segment for {}: {}", item_id, e);
+        }
+    }
+
+    /// 进程仍存活时延长当前在线区间
+    pub fn extend_uptime(&self, item_id: &str) {
+        let conn = self.conn.lock().unwrap();
+        if let Err(e) = conn.execute(
+            "UPDATE uptime_segments SET ended_at = ?2 WHERE item_id = ?1 AND open = 1",
+            params![item_id, now_unix_ms()],
+        ) {
+            error!("Failed to extend uptime segment for {}: {}", item_id, e);
+        }
+    }
+
+    pub fn end_uptime(&self, item_id: &str) {
+        let conn = self.conn.lock().unwrap();
+        if let Err(e) = conn.execute(
+            "UPDATE uptime_segments SET ended_at = ?2, open = 0 WHERE item_id = ?1 AND open = 1",
+            params![item_id, now_unix_ms()],
+        ) {
+            error!("Failed to end uptime segment for {}: {}", item_id, e);
+        }
+    }
+
+    pub fn end_all_uptime(&self) {
+        let conn = self.conn.lock().unwrap();
+        if let Err(e) = conn.execute(
+            "UPDATE uptime_segments SET ended_at = ?1, open = 0 WHERE open = 1",
+            params![now_unix_ms()],
+        ) {
+            error!("Failed to close uptime segments: {}", e);
+        }
+    }
+
+    pub fn record_sample(&self, item_id: &str, sample: &ResourceSample) {
+        let conn = self.conn.lock().unwrap();
+        if let Err(e) = conn.execute(
+            "INSERT INTO resource_samples (item_id, ts, cpu_percent, working_set_bytes, private_bytes, handle_count) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                item_id,
+                sample.ts,
+                sample.cpu_percent,
+                sample.working_set_bytes as i64,
+                sample.private_bytes as i64,
+                sample.handle_count
+            ],
+        ) {
+            error!("Failed to record resource sample for {}: {}", item_id, e);
+        }
+    }
+
+    /// 删除 retention_days 天以前的资源采样
+    pub fn prune_samples(&self, retention_days: u32) {
+        let cutoff = now_unix_ms() - retention_days as i64 * DAY_MS;
+        let conn = self.conn.lock().unwrap();
+        match conn.execute(
+            "DELETE FROM resource_samples WHERE ts < ?1",
+            params![cutoff],
+        ) {
+            Ok(0) => {}
+            Ok(removed) => debug!(
+                "Pruned {} resource samples older than {} days",
+                removed, retention_days
+            ),
+            Err(e) => error!("Failed to prune resource samples: {}", e),
+        }
+    }
+
+    /// 某个监控项在 since 之后的资源采样，按时间正序，最多返回最近的 limit 条
+    pub fn resource_history(
+        &self,
+        item_id: &str,
+        since: i64,
+        limit: u32,
+    ) -> rusqlite::Result<Vec<ResourceSample>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT ts, cpu_percent, working_set_bytes, private_bytes, handle_count FROM resource_samples \
+             WHERE item_id = ?1 AND ts >= ?2 ORDER BY ts DESC LIMIT ?3",
+        )?;
+        let mut samples = stmt
+            .query_map(params![item_id, since, limit], |row| {
+                Ok(ResourceSample {
+                    ts: row.get(0)?,
+                    cpu_percent: row.get(1)?,
+                    working_set_bytes: row.get::<_, i64>(2)? as u64,
+                    private_bytes: row.get::<_, i64>(3)? as u64,
+                    handle_count: row.get(4)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        samples.reverse();
+        Ok(samples)
+    }
+
+    /// 最近的若干条事件，按时间倒序
+    pub fn recent_events(&self, limit: u32) -> rusqlite::Result<Vec<StoredEvent>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT item_id, kind, detail, reason, ts FROM events ORDER BY ts DESC, id DESC LIMIT ?1",
+        )?;
+        let events = stmt
+            .query_map(params![limit], |row| {
+                Ok(StoredEvent {
+                    item_id: row.get(0)?,
+                    kind: row.get(1)?,
+                    detail: row.get(2)?,
+                    reason: row.get(3)?,
+                    ts: row.get(4)?,
+                })
+            })?
+            .collect();
+        events
+    }
+
+    /// 某个监控项最近的若干条事件，按时间倒序
+    pub fn item_events(&self, item_id: &str, limit: u32) -> rusqlite::Result<Vec<StoredEvent>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT item_id, kind, detail, reason, ts FROM events WHERE item_id = ?1 \
+             ORDER BY ts DESC, id DESC LIMIT ?2",
+        )?;
+        let events = stmt
+            .query_map(params![item_id, limit], |row| {
+                Ok(StoredEvent {
+                    item_id: row.get(0)?,
+                    kind: row.get(1)?,
+                    detail: row.get(2)?,
+                    reason: row.get(3)?,
+                    ts: row.get(4)?,
+                })
+            })?
+            .collect();
+        events
+    }
+
+    pub fn item_stats(&self, item_id: &str) -> rusqlite::Result<ItemStats> {
+        self.item_stats_at(item_id, now_unix_ms())
+    }
+
+    fn item_stats_at(&self, item_id: &str, now: i64) -> rusqlite::Result<ItemStats> {
+        let conn = self.conn.lock().unwrap();
+        let current_uptime_secs = conn
+            .query_row(
+                "SELECT MAX(started_at) FROM uptime_segments WHERE item_id = ?1 AND open = 1",
+                params![item_id],
+                |row| row.get::<_, Option<i64>>(0),
+            )?
+            .map(|started_at| ((now - started_at).max(0) / 1000) as u64);
+
+        let (restarts, first, last) = conn.query_row(
+            "SELECT COUNT(*), MIN(ts), MAX(ts) FROM events WHERE item_id = ?1 AND kind = ?2",
+            params![item_id, EventKind::Restarted.as_str()],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, Option<i64>>(1)?,
+                    row.get::<_, Option<i64>>(2)?,
+                ))
+            },
+        )?;
+        let mean_time_between_restarts_secs = match (first, last) {
+            (Some(first), Some(last)) if restarts > 1 => {
+                Some(((last - first) / (restarts - 1) / 1000) as u64)
+            }
+            _ => None,
+        };
+
+        let last_failure = conn
+            .query_row(
+                "SELECT item_id, kind, detail, reason, ts FROM events
+                 WHERE item_id = ?1 AND kind IN (?2, ?3, ?4, ?5)
+                 ORDER BY ts DESC, id DESC LIMIT 1",
+                params![
+                    item_id,
+                    FAILURE_KINDS[0].as_str(),
+                    FAILURE_KINDS[1].as_str(),
+                    FAILURE_KINDS[2].as_str(),
+                    FAILURE_KINDS[3].as_str()
+                ],
+                |row| {
+                    Ok(StoredEvent {
+                        item_id: row.get(0)?,
+                        kind: row.get(1)?,
+                        detail: row.get(2)?,
+                        reason: row.get(3)?,
+                        ts: row.get(4)?,
+                    })
+                },
+            )
+            .optional()?;
+
+        let window_start = now - DAY_MS;
+        let segments = conn
+            .prepare(
+                "SELECT started_at, ended_at FROM uptime_segments
+                 WHERE item_id = ?1 AND ended_at >= ?2 AND started_at <= ?3",
+            )?
+            .query_map(params![item_id, window_start, now], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(ItemStats {
+            current_uptime_secs,
+            mean_time_between_restarts_secs,
+            last_failure,
+            availability_24h_percent: availability_percent(
+                &segments,
+                first_tracked(&conn, item_id)?,
+                window_start,
+                now,
+            ),
+        })
+    }
+
+    pub fn uptime_report(
+        &self,
+        item_ids: &[String],
+        days: u32,
+    ) -> rusqlite::Result<Vec<UptimeReport>> {
+        let window_end = now_unix_ms();
+        self.uptime_report_at(item_ids, days, window_end)
+    }
+
+    fn uptime_report_at(
+        &self,
+        item_ids: &[String],
+        days: u32,
+        window_end: i64,
+    ) -> rusqlite::Result<Vec<UptimeReport>> {
+        let window_start = window_end - days as i64 * DAY_MS;
+        let conn = self.conn.lock().unwrap();
+
+        let mut segment_stmt = conn.prepare(
+            "SELECT started_at, ended_at FROM uptime_segments
+             WHERE item_id = ?1 AND ended_at >= ?2 AND started_at <= ?3",
+        )?;
+        let mut count_stmt = conn.prepare(
+            "SELECT COUNT(*) FROM events WHERE item_id = ?1 AND kind = ?2 AND ts >= ?3 AND ts <= ?4",
+        )?;
+        let mut reason_stmt = conn.prepare(
+            "SELECT reason, COUNT(*) FROM events
+             WHERE item_id = ?1 AND kind = ?2 AND reason IS NOT NULL AND ts >= ?3 AND ts <= ?4
+             GROUP BY reason",
+        )?;
+
+        let mut reports = Vec::with_capacity(item_ids.len());
+        for item_id in item_ids {
+            let segments = segment_stmt
+                .query_map(params![item_id, window_start, window_end], |row| {
+                    Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+
+            let mut count = |kind: EventKind| -> rusqlite::Result<u32> {
+                count_stmt.query_row(
+                    params![item_id, kind.as_str(), window_start, window_end],
+                    |row| row.get(0),
+                )
+            };
+
+            let restarts_by_reason = reason_stmt
+                .query_map(
+                    params![
+                        item_id,
+                        EventKind::Restarted.as_str(),
+                        window_start,
+                        window_end
+                    ],
+                    |row| Ok((row.get::<_, String>(0)?, row.get::<_, u32>(1)?)),
+                )?
+                .collect::<rusqlite::Result<BTreeMap<_, _>>>()?;
+
+            reports.push(UptimeReport {
+                item_id: item_id.clone(),
+                window_days: days,
+                availability_percent: availability_percent(
+                    &segments,
+                    first_tracked(&conn, item_id)?,
+                    window_start,
+                    window_end,
+                ),
+                restarts: count(EventKind::Restarted)?,
+                heartbeat_failures: count(EventKind::HeartbeatTimeout)?,
+                restarts_by_reason,
+            });
+        }
+
+        Ok(reports)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{availability_percent, now_unix_ms, EventKind, EventStore, ResourceSample, DAY_MS};
+    use crate::launch_info::LaunchInfo;
+    use crate::models::RestartReason;
+
+    #[test]
+    fn availability_counts_only_overlap_with_window() {
+        // 跟踪 10 个单位，其中在线 5 个
+        let segments = [(0, 3), (5, 7)];
+        assert!((availability_percent(&segments, Some(0), 0, 10) - 50.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn availability_starts_counting_from_first_tracked_time() {
+        let segments = [(50, 100)];
+        assert!((availability_percent(&segments, Some(50), 0, 100) - 100.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn availability_counts_downtime_before_first_segment_in_window() {
+        let segments = [(50, 100)];
+        assert!((availability_percent(&segments, Some(-10), 0, 100) - 50.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn availability_without_segments_is_zero() {
+        assert_eq!(availability_percent(&[], None, 0, 100), 0.0);
+    }
+
+    #[test]
+    fn uptime_report_counts_restarts_in_window() {
+        let store = EventStore::open_in_memory().unwrap();
+        store.begin_uptime("a");
+        store.record_restart("a", RestartReason::ProcessExited, "process not alive");
+        store.record_restart("a", RestartReason::ManualRestart, "killed by operator");
+        store.record_event("a", EventKind::HeartbeatTimeout, "");
+        store.end_uptime("a");
+
+        let report = store
+            .uptime_report_at(&["a".to_string()], 7, now_unix_ms() + DAY_MS / 24)
+            .unwrap();
+
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].restarts, 2);
+        assert_eq!(report[0].heartbeat_failures, 1);
+        assert_eq!(report[0].restarts_by_reason["process_exited"], 1);
+        assert_eq!(report[0].restarts_by_reason["manual_restart"], 1);
+    }
+
+    #[test]
+    fn launches_are_returned_newest_first() {
+        let store = EventStore::open_in_memory().unwrap();
+        let mut info = LaunchInfo {
+            exe_path: r"C:\app\app.exe".to_string(),
+            file_version: Some("1.0.0.0".to_string()),
+            product_version: Some("1.0.0.0".to_string()),
+            sha256: Some("aa".to_string()),
+            command_line: Some(r#""C:\app\app.exe" --port 80"#.to_string()),
+            env_fingerprint: None,
+        };
+        store.record_launch("a", 10, &info);
+        info.sha256 = Some("bb".to_string());
+        store.record_launch("a", 11, &info);
+        store.record_launch("b", 12, &info);
+
+        let launches = store.launches("a", 10).unwrap();
+        assert_eq!(launches.len(), 2);
+        assert_eq!(launches[0].pid, 11);
+        assert_eq!(launches[0].info, info);
+        assert_eq!(launches[1].info.sha256.as_deref(), Some("aa"));
+    }
+
+    #[test]
+    fn item_stats_summarize_history() {
+        let store = EventStore::open_in_memory().unwrap();
+        store.begin_uptime("a");
+        store.record_event("a", EventKind::HeartbeatTimeout, "pid=1 heartbeat timeout");
+        store.record_restart("a", RestartReason::HeartbeatTimeout, "heartbeat timeout");
+        store.record_event("a", EventKind::Stopped, "user stop");
+
+        let stats = store.item_stats_at("a", now_unix_ms() + 60_000).unwrap();
+        assert!(stats.current_uptime_secs.unwrap() >= 60);
+        assert_eq!(stats.mean_time_between_restarts_secs, None);
+        assert_eq!(stats.last_failure.unwrap().kind, "heartbeat_timeout");
+        assert!((stats.availability_24h_percent - 100.0).abs() < 0.001);
+
+        let empty = store.item_stats("b").unwrap();
+        assert_eq!(empty.current_uptime_secs, None);
+        assert_eq!(empty.last_failure, None);
+    }
+
+    #[test]
+    fn recent_events_are_newest_first() {
+        let store = EventStore::open_in_memory().unwrap();
+        store.record_event("a", EventKind::Started, "pid=1");
+        store.record_event("a", EventKind::ProcessExited, "pid=1");
+        store.record_event("b", EventKind::Started, "pid=2");
+
+        let events = store.recent_events(2).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].item_id, "b");
+        assert_eq!(events[1].kind, "process_exited");
+    }
+
+    #[test]
+    fn resource_history_returns_latest_samples_in_order() {
+        let store = EventStore::open_in_memory().unwrap();
+        for ts in [1_000, 2_000, 3_000] {
+            let sample = ResourceSample {
+                ts,
+                cpu_percent: None,
+                working_set_bytes: ts as u64,
+                private_bytes: 0,
+                handle_count: None,
+            };
+            store.record_sample("a", &sample);
+        }
+
+        let samples = store.resource_history("a", 1_500, 10).unwrap();
+        assert_eq!(
+            samples.iter().map(|s| s.ts).collect::<Vec<_>>(),
+            vec![2_000, 3_000]
+        );
+        assert_eq!(store.resource_history("a", 0, 1).unwrap()[0].ts, 3_000);
+        assert!(store.resource_history("b", 0, 10).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+use crate::config::load_config;
+use crate::exe_glob;
+use crate::models::{MonitorItem, SERVICE_NAME};
+use std::path::{Path, PathBuf};
+
+/// 计划任务所在的文件夹，便于一次性查看或删除
+pub const TASK_FOLDER: &str = "ProcessGuard";
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// 任务名与文件名中只保留字母数字、'-' 与 '_'
+fn task_file_stem(item: &MonitorItem) -> String {
+    let stem: String = item
+        .name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if stem.trim_matches('_').is_empty() {
+        item.id.clone()
+    } else {
+        stem
+    }
+}
+
+/// 登录时执行的命令：守护服务未运行时才按监控项的路径、参数与工作目录启动程序
+fn fallback_command(item: &MonitorItem, exe_path: &str) -> String {
+    let working_dir = Path::new(exe_path)
+        .parent()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut start = String::from("start \"\"");
+    if item.minimize {
+        start.push_str(" /MIN");
+    }
+    if !working_dir.is_empty() {
+        start.push_str(&format!(" /D \"{}\"", working_dir));
+    }
+    start.push_str(&format!(" \"{}\"", exe_path));
+    if let Some(args) = item.args.as_deref().filter(|args| !args.trim().is_empty()) {
+        start.push(' ');
+        start.push_str(args.trim());
+    }
+    format!(
+        "/c sc query \"{}\" | find \"RUNNING\" >nul || {}",
+        SERVICE_NAME, start
+    )
+}
+
+/// 与监控项启动参数一致的登录触发计划任务定义
+fn task_xml(item: &MonitorItem, exe_path: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-16"?>
+<Task version="1.2" xmlns="http://schemas.microsoft.com/windows/2004/02/mit/task">
+  <RegistrationInfo>
+    <Description>{description}</Description>
+  </RegistrationInfo>
+  <Triggers>
+    <LogonTrigger>
+      <Enabled>true</Enabled>
+    </LogonTrigger>
+  </Triggers>
+  <Principals>
+    <Principal id="Author">
+      <LogonType>InteractiveToken</LogonType>
+      <RunLevel>HighestAvailable</RunLevel>
+    </Principal>
+  </Principals>
+  <Settings>
+    <MultipleInstancesPolicy>IgnoreNew</MultipleInstancesPolicy>
+    <DisallowStartIfOnBatteries>false</DisallowStartIfOnBatteries>
+    <StopIfGoingOnBatteries>false</StopIfGoingOnBatteries>
+    <ExecutionTimeLimit>PT0S</ExecutionTimeLimit>
+    <Enabled>{enabled}</Enabled>
+  </Settings>
+  <Actions Context="Author">
+    <Exec>
+      <Command>%SystemRoot%\System32\cmd.exe</Command>
+      <Arguments>{arguments}</Arguments>
+    </Exec>
+  </Actions>
+</Task>
+"#,
+        description = xml_escape(&format!(
+            "Fallback launch of {} when {} is not running (item {})",
+            item.name, SERVICE_NAME, item.id
+        )),
+        enabled = item.enabled,
+        arguments = xml_escape(&fallback_command(item, exe_path)),
+    )
+}
+
+/// 为配置中每个 keep 监控项生成计划任务 XML（UTF-16），返回写入的文件与说明
+pub fn export_tasks(dir: &Path) -> Result<(Vec<PathBuf>, Vec<String>), String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+    let config = load_config();
+    let mut written = Vec::new();
+    let mut notes = Vec::new();
+
+    for item in config.items.iter().filter(|item| item.mode.is_keep()) {
+        let exe_path = if exe_glob::is_pattern(&item.exe_path) {
+            match exe_glob::resolve(&item.exe_path) {
+                Ok(resolved) => {
+                    notes.push(format!(
+                        "{}: wildcard path resolved to {}; re-export after upgrades",
+                        item.name, resolved
+                    ));
+                    resolved
+                }
+                Err(e) => {
+                    notes.push(format!("{}: skipped, {}", item.name, e));
+                    continue;
+                }
+            }
+        } else {
+            item.exe_path.clone()
+        };
+        if item.ha {
+            notes.push(format!(
+                "{}: ha item; the fallback starts it on every node where the service is stopped",
+                item.name
+            ));
+        }
+
+        let path = dir.join(format!("{}.xml", task_file_stem(item)));
+        let mut bytes = vec![0xFF, 0xFE];
+        for unit in task_xml(item, &exe_path).encode_utf16() {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+        std::fs::write(&path, bytes)
+            .map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+        written.push(path);
+    }
+    Ok((written, notes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{task_file_stem, task_xml};
+    use crate::models::MonitorItem;
+
+    #[test]
+    fn task_launches_item_only_when_service_is_down() {
+        let mut item = MonitorItem::new(
+            r"C:\Apps\Agent\agent.exe".to_string(),
+            "Agent <prod>".to_string(),
+        );
+        item.args = Some("--queue a&b".to_string());
+        item.minimize = true;
+        let xml = task_xml(&item, &item.exe_path);
+        assert!(xml.contains("<LogonTrigger>"));
+        assert!(xml.contains(
+            r#"<Arguments>/c sc query &quot;ProcessGuardService&quot; | find &quot;RUNNING&quot; &gt;nul || start &quot;&quot; /MIN /D &quot;C:\Apps\Agent&quot; &quot;C:\Apps\Agent\agent.exe&quot; --queue a&amp;b</Arguments>"#
+        ));
+        assert!(xml.contains("Agent &lt;prod&gt;"));
+        assert_eq!(task_file_stem(&item), "Agent__prod_");
+    }
+}
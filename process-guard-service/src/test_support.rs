@@ -0,0 +1,222 @@
+//! 集成测试支持：用普通子进程代替会话内启动，并在独立管道名上运行完整的守护循环与管道服务。
+//!
+//! 仅在启用 `test-support` feature 时编译。
+
+use crate::backend::{ProcessBackend, SpawnRequest, Win32Backend};
+use crate::guardian::Guardian;
+use crate::models::{Config, MonitorItem, PipeRequest, PipeResponse, Settings};
+use crate::pipe_server::PipeServer;
+use crate::session0::ProcessEntry;
+use crate::startup_gate::StartupGate;
+use std::collections::HashMap;
+use std::io::Read;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+static NEXT_PIPE: AtomicU32 = AtomicU32::new(0);
+
+/// 以当前进程的子进程方式启动被守护程序（捕获输出时使用标准管道），其余操作仍使用 Win32 接口
+#[derive(Default)]
+pub struct ChildBackend {
+    children: Mutex<HashMap<u32, Child>>,
+}
+
+impl ChildBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 所有仍在运行的子进程 PID
+    pub fn running(&self) -> Vec<u32> {
+        let mut children = self.children.lock().unwrap();
+        children.retain(|_, child| matches!(child.try_wait(), Ok(None)));
+        let mut pids: Vec<u32> = children.keys().copied().collect();
+        pids.sort_unstable();
+        pids
+    }
+
+    /// 终止所有仍在运行的子进程
+    pub fn kill_all(&self) {
+        for (_, mut child) in self.children.lock().unwrap().drain() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+impl ProcessBackend for ChildBackend {
+    fn spawn(&self, request: &SpawnRequest) -> Result<u32, String> {
+        let mut command = Command::new(request.exe_path);
+        if let Some(args) = request.args {
+            command.args(args.split_whitespace());
+        }
+        if let Some(dir) = request.working_dir {
+            command.current_dir(dir);
+        }
+        if request.output.is_some() {
+            command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        }
+        let mut child = command
+            .spawn()
+            .map_err(|e| format!("Failed to spawn {}: {}", request.exe_path, e))?;
+        if let Some(buffer) = request.output {
+            let stdout = child
+                .stdout
+                .take()
+                .map(|s| Box::new(s) as Box<dyn Read + Send>);
+            let stderr = child
+                .stderr
+                .take()
+                .map(|s| Box::new(s) as Box<dyn Read + Send>);
+            for mut stream in stdout.into_iter().chain(stderr) {
+                let buffer = buffer.clone();
+                std::thread::spawn(move || {
+                    let mut chunk = [0u8; 4096];
+                    while let Ok(read) = stream.read(&mut chunk) {
+                        if read == 0 {
+                            break;
+                        }
+                        buffer.push(&chunk[..read]);
+                    }
+                });
+            }
+        }
+        let pid = child.id();
+        self.children.lock().unwrap().insert(pid, child);
+        Ok(pid)
+    }
+
+    fn is_alive(&self, pid: u32) -> bool {
+        Win32Backend.is_alive(pid)
+    }
+
+    fn kill(&self, pid: u32, created: Option<u64>) -> bool {
+        let killed = Win32Backend.kill(pid, created);
+        if let Some(mut child) = self.children.lock().unwrap().remove(&pid) {
+            let _ = child.wait();
+        }
+        killed
+    }
+
+    fn wait_for_exit(&self, pid: u32, created: Option<u64>, timeout: Duration) -> bool {
+        Win32Backend.wait_for_exit(pid, created, timeout)
+    }
+
+    fn find_by_path(&self, exe_path: &str, args: Option<&str>) -> Option<u32> {
+        Win32Backend.find_by_path(exe_path, args)
+    }
+
+    fn creation_time(&self, pid: u32) -> Option<u64> {
+        Win32Backend.creation_time(pid)
+    }
+
+    fn snapshot(&self) -> HashMap<u32, ProcessEntry> {
+        Win32Backend.snapshot()
+    }
+}
+
+/// 进程内运行的守护服务：守护循环与管道服务各占一个线程，Drop 时停止并清理子进程
+pub struct TestService {
+    pub guardian: Arc<Guardian>,
+    pub backend: Arc<ChildBackend>,
+    pipe_name: String,
+    running: Arc<Mutex<bool>>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl TestService {
+    /// 以给定监控项启动服务，管道就绪后返回
+    ///
+    /// 允许同一程序的多个实例（按启动参数区分），多个测试可以并行使用同一个辅助程序。
+    pub fn start(items: Vec<MonitorItem>) -> Self {
+        let pipe_name = format!(
+            "ProcessGuardTest_{}_{}",
+            std::process::id(),
+            NEXT_PIPE.fetch_add(1, Ordering::Relaxed)
+        );
+        let running = Arc::new(Mutex::new(true));
+        let backend = Arc::new(ChildBackend::new());
+        let config = Config {
+            items,
+            settings: Settings {
+                allow_duplicate_exe_paths: true,
+                ..Settings::default()
+            },
+            ..Config::default()
+        };
+        let guardian = Arc::new(Guardian::with_backend(
+            config,
+            running.clone(),
+            backend.clone(),
+        ));
+
+        let ready = Arc::new(StartupGate::new());
+        let server = PipeServer::new(guardian.clone(), running.clone(), Some(ready.clone()))
+            .with_pipe_name(&pipe_name);
+        let pipe_thread = std::thread::spawn(move || server.run());
+        ready.wait_until_ready();
+
+        let guardian_thread = {
+            let guardian = guardian.clone();
+            std::thread::spawn(move || guardian.run())
+        };
+
+        Self {
+            guardian,
+            backend,
+            pipe_name,
+            running,
+            threads: vec![pipe_thread, guardian_thread],
+        }
+    }
+
+    pub fn pipe_name(&self) -> &str {
+        &self.pipe_name
+    }
+
+    /// 通过管道发送请求，返回服务的原始响应
+    pub fn request(&self, request: &PipeRequest) -> Result<PipeResponse, String> {
+        crate::pipe_client::request(&self.pipe_name, request)
+    }
+
+    /// 发送原始 JSON，用于检查服务如何拒绝非法请求
+    pub fn request_raw(&self, request: &str) -> Result<PipeResponse, String> {
+        crate::pipe_client::request_raw(&self.pipe_name, request)
+    }
+
+    /// 监控项当前记录的 PID
+    pub fn process_id(&self, item_id: &str) -> Option<u32> {
+        let processes = self.guardian.get_processes();
+        let processes = processes.lock().unwrap();
+        processes
+            .get(item_id)
+            .and_then(|process| process.process_id)
+    }
+
+    /// 轮询直到条件成立或超时，返回条件是否成立
+    pub fn wait_until(&self, timeout: Duration, condition: impl Fn(&Self) -> bool) -> bool {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if condition(self) {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(200));
+        }
+        condition(self)
+    }
+}
+
+impl Drop for TestService {
+    fn drop(&mut self) {
+        *self.running.lock().unwrap() = false;
+        // 管道服务阻塞在 ConnectNamedPipe 上，发一个请求让它回到循环检查 running
+        let _ = self.request(&PipeRequest::List);
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+        self.backend.kill_all();
+    }
+}
@@ -0,0 +1,125 @@
+use crate::session0::{start_process_in_session0, DesktopOptions};
+use log::{info, warn};
+
+const TOAST_TITLE: &str = "Process Guard";
+const BALLOON_DURATION_MS: u32 = 5000;
+
+fn powershell_path() -> String {
+    let system_root = std::env::var("SystemRoot").unwrap_or_else(|_| r"C:\Windows".to_string());
+    format!(
+        r"{}\System32\WindowsPowerShell\v1.0\powershell.exe",
+        system_root
+    )
+}
+
+fn quote_powershell_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// 生成在托盘区域弹出气泡提示的 PowerShell 脚本
+fn build_balloon_script(title: &str, message: &str) -> String {
+    format!(
+        "Add-Type -AssemblyName System.Windows.Forms; Add-Type -AssemblyName System.Drawing; \
+         $n = New-Object System.Windows.Forms.NotifyIcon; \
+         $n.Icon = [System.Drawing.SystemIcons]::Information; \
+         $n.BalloonTipTitle = {}; $n.BalloonTipText = {}; \
+         $n.Visible = $true; $n.ShowBalloonTip({}); \
+         Start-Sleep -Milliseconds {}; $n.Dispose()",
+        quote_powershell_literal(title),
+        quote_powershell_literal(message),
+        BALLOON_DURATION_MS,
+        BALLOON_DURATION_MS + 1000
+    )
+}
+
+fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+
+        out.push(ALPHABET[(n >> 18) as usize & 63] as char);
+        out.push(ALPHABET[(n >> 12) as usize & 63] as char);
+        out.push(if chunk.len() > 1 {
+            ALPHABET[(n >> 6) as usize & 63] as char
+        } else {
+            '='
+        });
+        out.push(if chunk.len() > 2 {
+            ALPHABET[n as usize & 63] as char
+        } else {
+            '='
+        });
+    }
+    out
+}
+
+/// PowerShell -EncodedCommand 需要 UTF-16LE 的 Base64，避免命令行转义问题
+fn encode_powershell_command(script: &str) -> String {
+    let bytes: Vec<u8> = script
+        .encode_utf16()
+        .flat_map(|unit| unit.to_le_bytes())
+        .collect();
+    base64_encode(&bytes)
+}
+
+/// 在活动用户会话中弹出“程序已被重启”的提示
+pub fn show_restart_toast(item_name: &str) {
+    let message = format!("{} was restarted by Process Guard", item_name);
+    let args = format!(
+        "-NoProfile -NonInteractive -WindowStyle Hidden -EncodedCommand {}",
+        encode_powershell_command(&build_balloon_script(TOAST_TITLE, &message))
+    );
+
+    match start_process_in_session0(
+        &powershell_path(),
+        None,
+        Some(&args),
+        false,
+        true,
+        None,
+        &DesktopOptions::default(),
+        None,
+    ) {
+        Ok(info) => info!(
+            "Restart notification shown for {} (helper PID: {})",
+            item_name, info.process_id
+        ),
+        Err(e) => warn!(
+            "Failed to show restart notification for {}: {}",
+            item_name, e
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base64_encode, build_balloon_script, encode_powershell_command};
+
+    #[test]
+    fn base64_matches_reference_vectors() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn encoded_command_uses_utf16le() {
+        // "a" => 0x61 0x00
+        assert_eq!(encode_powershell_command("a"), "YQA=");
+    }
+
+    #[test]
+    fn balloon_script_escapes_single_quotes() {
+        let script = build_balloon_script("Process Guard", "O'Brien App was restarted");
+        assert!(script.contains("'O''Brien App was restarted'"));
+    }
+}
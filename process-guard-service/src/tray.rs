@@ -0,0 +1,391 @@
+use crate::access::{caller_role, Role};
+use crate::acl::SecurityDescriptor;
+use crate::guardian::Guardian;
+use crate::models::{ChangeType, ConfigChange, PipeAccessConfig, TrayConfig, TRAY_PIPE_NAME};
+use crate::session0::to_wide_string;
+use log::{debug, error, info, warn};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{CloseHandle, HANDLE};
+use windows::Win32::Storage::FileSystem::{ReadFile, WriteFile, FILE_FLAGS_AND_ATTRIBUTES};
+use windows::Win32::System::Pipes::{
+    ConnectNamedPipe, CreateNamedPipeW, DisconnectNamedPipe, GetNamedPipeClientSessionId,
+    PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_WAIT,
+};
+
+const BUFFER_SIZE: u32 = 16384;
+const PIPE_ACCESS_DUPLEX: u32 = 0x00000003;
+/// SYSTEM 与管理员完全控制，交互式登录用户可读写
+const TRAY_PIPE_SDDL: &str = "D:P(A;;GA;;;SY)(A;;GA;;;BA)(A;;GRGW;;;IU)";
+/// 同时服务的托盘连接上限；另留一个管道实例用于拒绝超出的连接
+const MAX_TRAY_CLIENTS: usize = 16;
+/// 订阅连接检查状态变化的间隔
+const PUSH_INTERVAL_MS: u64 = 1000;
+/// 启动器检查各会话托盘程序的间隔
+const LAUNCH_INTERVAL_SECS: u64 = 15;
+
+/// 托盘程序发来的请求：subscribe 之后服务持续推送状态，restart / pause 需要 allow_actions
+#[derive(Debug, Deserialize)]
+struct TrayRequest {
+    #[serde(rename = "type")]
+    request_type: String,
+    #[serde(default)]
+    id: Option<String>,
+}
+
+/// 托盘只需要的精简状态：id、名称、running / down / paused 与 PID
+fn tray_item_state(status: &Value) -> Value {
+    let state = if status["enabled"].as_bool() != Some(true) {
+        "paused"
+    } else if status["is_alive"].as_bool() == Some(true) {
+        "running"
+    } else {
+        "down"
+    };
+    json!({
+        "id": status["id"],
+        "name": status["name"],
+        "state": state,
+        "pid": status["process_id"],
+        "restart_count": status["restart_count"],
+    })
+}
+
+fn tray_states(guardian: &Guardian) -> Value {
+    let status = guardian.get_status();
+    let items: Vec<Value> = status["items"]
+        .as_array()
+        .map(|items| items.iter().map(tray_item_state).collect())
+        .unwrap_or_default();
+    json!({ "event": "items", "items": items })
+}
+
+/// 仅服务于本机交互式会话的托盘通道
+pub struct TrayServer {
+    guardian: Arc<Guardian>,
+    running: Arc<Mutex<bool>>,
+    active_clients: Arc<AtomicUsize>,
+}
+
+/// 占用一个托盘连接名额，连接线程结束时释放
+struct TraySlot(Arc<AtomicUsize>);
+
+impl TraySlot {
+    /// 名额已满时返回 None
+    fn acquire(active: &Arc<AtomicUsize>) -> Option<Self> {
+        active
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+                (count < MAX_TRAY_CLIENTS).then_some(count + 1)
+            })
+            .ok()
+            .map(|_| Self(active.clone()))
+    }
+}
+
+impl Drop for TraySlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// 交给客户端线程的管道句柄
+struct ClientPipe(HANDLE);
+
+unsafe impl Send for ClientPipe {}
+
+impl Drop for ClientPipe {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = DisconnectNamedPipe(self.0);
+            let _ = CloseHandle(self.0);
+        }
+    }
+}
+
+impl ClientPipe {
+    fn read_request(&self) -> Option<TrayRequest> {
+        let mut buffer = vec![0u8; BUFFER_SIZE as usize];
+        let mut read = 0u32;
+        unsafe { ReadFile(self.0, Some(&mut buffer), Some(&mut read), None) }.ok()?;
+        serde_json::from_slice(&buffer[..read as usize]).ok()
+    }
+
+    /// 写入一行 JSON；客户端断开时返回 false
+    fn write_line(&self, value: &Value) -> bool {
+        let mut line = value.to_string();
+        line.push('\n');
+        let mut written = 0u32;
+        unsafe { WriteFile(self.0, Some(line.as_bytes()), Some(&mut written), None) }.is_ok()
+    }
+}
+
+impl TrayServer {
+    pub fn new(guardian: Arc<Guardian>, running: Arc<Mutex<bool>>) -> Self {
+        Self {
+            guardian,
+            running,
+            active_clients: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn run(&self) {
+        let security = match SecurityDescriptor::from_sddl(TRAY_PIPE_SDDL) {
+            Ok(security) => security,
+            Err(e) => {
+                error!("Tray channel disabled: {}", e);
+                return;
+            }
+        };
+        let attributes = security.attributes();
+        let pipe_name = to_wide_string(&format!("\\\\.\\pipe\\{}", TRAY_PIPE_NAME));
+        info!("Tray channel listening on \\\\.\\pipe\\{}", TRAY_PIPE_NAME);
+
+        while *self.running.lock().unwrap() {
+            let handle = unsafe {
+                CreateNamedPipeW(
+                    PCWSTR(pipe_name.as_ptr()),
+                    FILE_FLAGS_AND_ATTRIBUTES(PIPE_ACCESS_DUPLEX),
+                    PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                    MAX_TRAY_CLIENTS as u32 + 1,
+                    BUFFER_SIZE,
+                    BUFFER_SIZE,
+                    0,
+                    Some(&attributes),
+                )
+            };
+            if handle.is_invalid() {
+                error!("Failed to create tray pipe");
+                std::thread::sleep(Duration::from_secs(1));
+                continue;
+            }
+            let client = ClientPipe(handle);
+            if unsafe { ConnectNamedPipe(client.0, None) }.is_err() {
+                let err = windows::core::Error::from_win32();
+                if err.code() != windows::Win32::Foundation::ERROR_PIPE_CONNECTED.into() {
+                    continue;
+                }
+            }
+
+            let mut session_id = 0u32;
+            let session = unsafe { GetNamedPipeClientSessionId(client.0, &mut session_id) };
+            if session.is_err() || session_id == 0 {
+                warn!("Rejected tray client outside an interactive session");
+                continue;
+            }
+
+            let Some(slot) = TraySlot::acquire(&self.active_clients) else {
+                warn!(
+                    "Rejected tray client from session {}: {} clients already connected",
+                    session_id, MAX_TRAY_CLIENTS
+                );
+                client.write_line(&json!({
+                    "success": false,
+                    "message": "too many tray clients",
+                }));
+                continue;
+            };
+
+            let guardian = self.guardian.clone();
+            let running = self.running.clone();
+            std::thread::spawn(move || {
+                let _slot = slot;
+                serve_client(&guardian, &running, client, session_id)
+            });
+        }
+        info!("Tray channel stopped");
+    }
+}
+
+fn serve_client(guardian: &Guardian, running: &Mutex<bool>, client: ClientPipe, session_id: u32) {
+    let Some(request) = client.read_request() else {
+        debug!(
+            "Tray client in session {} sent an invalid request",
+            session_id
+        );
+        return;
+    };
+    match request.request_type.as_str() {
+        "subscribe" => {
+            debug!("Tray client subscribed from session {}", session_id);
+            let mut last = Value::Null;
+            while *running.lock().unwrap() {
+                let states = tray_states(guardian);
+                if states != last {
+                    if !client.write_line(&states) {
+                        break;
+                    }
+                    last = states;
+                }
+                std::thread::sleep(Duration::from_millis(PUSH_INTERVAL_MS));
+            }
+            debug!("Tray client in session {} disconnected", session_id);
+        }
+        "restart" | "pause" => {
+            let result = tray_action(guardian, client.0, &request, session_id);
+            let response = match result {
+                Ok(message) => json!({ "success": true, "message": message }),
+                Err(message) => json!({ "success": false, "message": message }),
+            };
+            client.write_line(&response);
+        }
+        other => {
+            client.write_line(&json!({
+                "success": false,
+                "message": format!("unknown request type: {}", other),
+            }));
+        }
+    }
+}
+
+/// 托盘操作要求调用方至少为 operator；未配置 pipe_access 时只允许 Administrators 组成员
+fn tray_action(
+    guardian: &Guardian,
+    pipe: HANDLE,
+    request: &TrayRequest,
+    session_id: u32,
+) -> Result<String, String> {
+    let (allowed, access) = {
+        let config = guardian.get_config();
+        let config = config.lock().unwrap();
+        let allowed = config
+            .settings
+            .tray
+            .as_ref()
+            .is_some_and(|tray| tray.allow_actions);
+        (allowed, config.settings.pipe_access.clone())
+    };
+    if !allowed {
+        return Err("tray actions are disabled".to_string());
+    }
+    let access = access.unwrap_or_else(|| PipeAccessConfig {
+        admin_groups: vec!["S-1-5-32-544".to_string()],
+        ..Default::default()
+    });
+    let role = caller_role(pipe, &access).map_err(|e| {
+        warn!(
+            "Could not check tray client in session {}: {}",
+            session_id, e
+        );
+        "could not verify the caller".to_string()
+    })?;
+    if role < Some(Role::Operator) {
+        warn!(
+            "Rejected tray {} from session {}: insufficient role",
+            request.request_type, session_id
+        );
+        return Err("permission denied".to_string());
+    }
+    if guardian.safe_mode() {
+        return Err("the service is in safe mode".to_string());
+    }
+    let id = request.id.as_deref().ok_or("missing id")?;
+    let item = guardian
+        .get_config()
+        .lock()
+        .unwrap()
+        .items
+        .iter()
+        .find(|item| item.id == id)
+        .cloned()
+        .ok_or_else(|| format!("no item {}", id))?;
+
+    let change_type = if request.request_type == "restart" {
+        if !item.enabled {
+            return Err(format!("{} is paused", item.name));
+        }
+        ChangeType::Kill
+    } else {
+        ChangeType::Pause
+    };
+    info!(
+        "Tray client in session {} requested {} of {}",
+        session_id, request.request_type, item.name
+    );
+    let message = format!("{} {} requested", item.name, request.request_type);
+    guardian.add_change(ConfigChange { item, change_type });
+    Ok(message)
+}
+
+/// 在每个交互式会话中保持一个托盘程序实例
+pub struct TrayLauncher {
+    running: Arc<Mutex<bool>>,
+    exe_path: String,
+}
+
+impl TrayLauncher {
+    pub fn new(running: Arc<Mutex<bool>>, config: &TrayConfig) -> Option<Self> {
+        let exe_path = config.exe_path.clone()?;
+        Some(Self { running, exe_path })
+    }
+
+    pub fn run(&self) {
+        info!("Tray launcher started for {}", self.exe_path);
+        let mut launched: HashMap<u32, (u32, Option<u64>)> = HashMap::new();
+        while *self.running.lock().unwrap() {
+            let sessions = crate::session0::interactive_sessions();
+            launched.retain(|session, _| sessions.contains(session));
+            for session in sessions {
+                let alive = launched.get(&session).is_some_and(|(pid, created)| {
+                    crate::session0::check_process_alive(*pid)
+                        && crate::session0::process_creation_time(*pid) == *created
+                });
+                if alive {
+                    continue;
+                }
+                match crate::session0::start_process_in_session(
+                    session,
+                    &self.exe_path,
+                    None,
+                    None,
+                    false,
+                    true,
+                    None,
+                    &crate::session0::DesktopOptions::default(),
+                    None,
+                ) {
+                    Ok(info) => {
+                        let pid = info.process_id;
+                        info!("Started tray helper in session {} (PID {})", session, pid);
+                        launched
+                            .insert(session, (pid, crate::session0::process_creation_time(pid)));
+                    }
+                    Err(e) => warn!("Failed to start tray helper in session {}: {}", session, e),
+                }
+            }
+            for _ in 0..LAUNCH_INTERVAL_SECS * 10 {
+                if !*self.running.lock().unwrap() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        }
+        info!("Tray launcher stopped");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::tray_item_state;
+    use serde_json::json;
+
+    #[test]
+    fn summarizes_item_status_for_the_tray() {
+        let status = json!({
+            "id": "a", "name": "Agent", "enabled": true, "is_alive": true,
+            "process_id": 42, "restart_count": 3, "resources": {}
+        });
+        assert_eq!(
+            tray_item_state(&status),
+            json!({ "id": "a", "name": "Agent", "state": "running", "pid": 42, "restart_count": 3 })
+        );
+        let down = json!({ "id": "b", "enabled": true, "is_alive": false });
+        assert_eq!(tray_item_state(&down)["state"], "down");
+        let paused = json!({ "id": "c", "enabled": false, "is_alive": true });
+        assert_eq!(tray_item_state(&paused)["state"], "paused");
+    }
+}
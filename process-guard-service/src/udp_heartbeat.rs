@@ -0,0 +1,96 @@
+use crate::guardian::Guardian;
+use log::{debug, error, info, warn};
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// 接收超时，用于定期检查服务是否正在停止
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(1);
+/// 心跳数据报的最大长度，超出部分被截断后按格式错误处理
+const MAX_DATAGRAM: usize = 512;
+
+/// 数据报格式为 "<id>:<令牌>"，按最后一个冒号拆分，末尾的换行会被忽略
+fn parse_datagram(data: &[u8]) -> Option<(&str, &str)> {
+    let text = std::str::from_utf8(data)
+        .ok()?
+        .trim_end_matches(['\r', '\n']);
+    let (id, token) = text.rsplit_once(':')?;
+    (!id.is_empty() && !token.is_empty()).then_some((id, token))
+}
+
+/// 在本机回环地址上接收 UDP 心跳，避免大量高频心跳反复连接命名管道
+pub struct UdpHeartbeatServer {
+    guardian: Arc<Guardian>,
+    running: Arc<Mutex<bool>>,
+    port: u16,
+}
+
+impl UdpHeartbeatServer {
+    pub fn new(guardian: Arc<Guardian>, running: Arc<Mutex<bool>>, port: u16) -> Self {
+        Self {
+            guardian,
+            running,
+            port,
+        }
+    }
+
+    pub fn run(&self) {
+        let socket = match UdpSocket::bind((Ipv4Addr::LOCALHOST, self.port)) {
+            Ok(socket) => socket,
+            Err(e) => {
+                error!("Failed to bind UDP heartbeat port {}: {}", self.port, e);
+                return;
+            }
+        };
+        if let Err(e) = socket.set_read_timeout(Some(RECEIVE_TIMEOUT)) {
+            error!("Failed to set UDP heartbeat receive timeout: {}", e);
+            return;
+        }
+        info!("UDP heartbeat listener on 127.0.0.1:{}", self.port);
+
+        let mut buffer = [0u8; MAX_DATAGRAM];
+        while *self.running.lock().unwrap() {
+            match socket.recv_from(&mut buffer) {
+                Ok((len, _)) => match parse_datagram(&buffer[..len]) {
+                    Some((id, token)) => {
+                        if !self.guardian.update_heartbeat_with_token(id, token) {
+                            debug!("Rejected UDP heartbeat for {}", id);
+                        }
+                    }
+                    None => debug!("Malformed UDP heartbeat datagram ({} bytes)", len),
+                },
+                // Windows 上向已关闭的端口回送数据后可能收到 ConnectionReset，忽略即可
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::ConnectionReset
+                    ) => {}
+                Err(e) => {
+                    warn!("UDP heartbeat receive failed: {}", e);
+                    std::thread::sleep(RECEIVE_TIMEOUT);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_datagram;
+
+    #[test]
+    fn splits_id_and_token_at_the_last_colon() {
+        assert_eq!(
+            parse_datagram(b"worker-1:s3cret\n"),
+            Some(("worker-1", "s3cret"))
+        );
+        assert_eq!(
+            parse_datagram(b"ns:worker:s3cret"),
+            Some(("ns:worker", "s3cret"))
+        );
+        assert_eq!(parse_datagram(b"worker-1"), None);
+        assert_eq!(parse_datagram(b"worker-1:"), None);
+        assert_eq!(parse_datagram(&[0xff, b':', b'x']), None);
+    }
+}
@@ -0,0 +1,263 @@
+use crate::models::WindowCheckConfig;
+use crate::session0::{start_process_in_session0, DesktopOptions};
+use std::time::Duration;
+use windows::Win32::Foundation::{BOOL, HWND, LPARAM, WAIT_OBJECT_0, WPARAM};
+use windows::Win32::System::Threading::{
+    GetExitCodeProcess, TerminateProcess, WaitForSingleObject,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    EnumWindows, GetClassNameW, GetWindowTextW, GetWindowThreadProcessId, IsWindowVisible,
+    PostMessageW, SendMessageTimeoutW, SMTO_ABORTIFHUNG, WM_CLOSE, WM_NULL,
+};
+
+/// 辅助进程的退出码
+const EXIT_RESPONSIVE: u32 = 0;
+const EXIT_NOT_FOUND: u32 = 1;
+const EXIT_HUNG: u32 = 2;
+const EXIT_BAD_ARGS: u32 = 3;
+
+/// 辅助进程自身启动与退出的额外等待时间
+const HELPER_EXTRA_WAIT_MS: u64 = 5000;
+
+pub const HELPER_ARG: &str = "--check-window";
+pub const CLOSE_HELPER_ARG: &str = "--close-window";
+
+/// 十六进制编码，避免窗口标题中的空格和引号影响命令行解析
+fn hex_encode(value: &str) -> String {
+    if value.is_empty() {
+        return "-".to_string();
+    }
+    value.bytes().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hex_decode(value: &str) -> Option<String> {
+    if value == "-" {
+        return Some(String::new());
+    }
+    let bytes = value
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair).ok().filter(|p| p.len() == 2)?;
+            u8::from_str_radix(pair, 16).ok()
+        })
+        .collect::<Option<Vec<u8>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+fn build_helper_args(pid: u32, config: &WindowCheckConfig) -> String {
+    format!(
+        "{} {} {} {} {}",
+        HELPER_ARG,
+        pid,
+        hex_encode(config.class_name.as_deref().unwrap_or("")),
+        hex_encode(config.title.as_deref().unwrap_or("")),
+        config.hung_timeout_ms
+    )
+}
+
+/// 类名完全匹配（忽略大小写），标题按子串匹配（忽略大小写）；未配置的条件视为满足
+fn window_matches(class_name: &str, title: &str, want_class: &str, want_title: &str) -> bool {
+    let class_ok = want_class.is_empty() || class_name.eq_ignore_ascii_case(want_class);
+    let title_ok =
+        want_title.is_empty() || title.to_lowercase().contains(&want_title.to_lowercase());
+    class_ok && title_ok
+}
+
+struct EnumContext {
+    pid: u32,
+    class_name: String,
+    title: String,
+    found: Vec<HWND>,
+}
+
+unsafe extern "system" fn enum_window(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let context = &mut *(lparam.0 as *mut EnumContext);
+
+    let mut window_pid: u32 = 0;
+    GetWindowThreadProcessId(hwnd, Some(&mut window_pid));
+    if window_pid != context.pid || !IsWindowVisible(hwnd).as_bool() {
+        return BOOL(1);
+    }
+
+    let mut class_buffer = [0u16; 256];
+    let class_len = GetClassNameW(hwnd, &mut class_buffer).max(0) as usize;
+    let mut title_buffer = [0u16; 512];
+    let title_len = GetWindowTextW(hwnd, &mut title_buffer).max(0) as usize;
+
+    if window_matches(
+        &String::from_utf16_lossy(&class_buffer[..class_len]),
+        &String::from_utf16_lossy(&title_buffer[..title_len]),
+        &context.class_name,
+        &context.title,
+    ) {
+        context.found.push(hwnd);
+    }
+    BOOL(1)
+}
+
+/// 在用户会话中以 `--check-window` 参数运行时的入口，返回进程退出码
+pub fn run_helper(args: &[String]) -> u32 {
+    let parsed = (|| {
+        let pid = args.first()?.parse::<u32>().ok()?;
+        let class_name = hex_decode(args.get(1)?)?;
+        let title = hex_decode(args.get(2)?)?;
+        let timeout_ms = args.get(3)?.parse::<u32>().ok()?;
+        Some((pid, class_name, title, timeout_ms))
+    })();
+    let Some((pid, class_name, title, timeout_ms)) = parsed else {
+        return EXIT_BAD_ARGS;
+    };
+
+    let windows = find_windows(pid, class_name, title);
+    if windows.is_empty() {
+        return EXIT_NOT_FOUND;
+    }
+
+    // 任一匹配窗口能在超时内处理消息即认为程序响应正常
+    let responsive = windows.iter().any(|hwnd| unsafe {
+        let mut result: usize = 0;
+        SendMessageTimeoutW(
+            *hwnd,
+            WM_NULL,
+            WPARAM(0),
+            LPARAM(0),
+            SMTO_ABORTIFHUNG,
+            timeout_ms,
+            Some(&mut result),
+        )
+        .0 != 0
+    });
+    if responsive {
+        EXIT_RESPONSIVE
+    } else {
+        EXIT_HUNG
+    }
+}
+
+fn find_windows(pid: u32, class_name: String, title: String) -> Vec<HWND> {
+    let mut context = EnumContext {
+        pid,
+        class_name,
+        title,
+        found: Vec::new(),
+    };
+    unsafe {
+        let _ = EnumWindows(
+            Some(enum_window),
+            LPARAM(&mut context as *mut EnumContext as isize),
+        );
+    }
+    context.found
+}
+
+/// 以 `--close-window <pid>` 运行时的入口：向进程的所有可见顶层窗口发送 WM_CLOSE
+pub fn run_close_helper(args: &[String]) -> u32 {
+    let Some(pid) = args.first().and_then(|pid| pid.parse::<u32>().ok()) else {
+        return EXIT_BAD_ARGS;
+    };
+
+    let windows = find_windows(pid, String::new(), String::new());
+    if windows.is_empty() {
+        return EXIT_NOT_FOUND;
+    }
+    for hwnd in windows {
+        unsafe {
+            let _ = PostMessageW(hwnd, WM_CLOSE, WPARAM(0), LPARAM(0));
+        }
+    }
+    EXIT_RESPONSIVE
+}
+
+fn run_helper_in_user_session(args: &str, wait: Duration) -> Result<u32, String> {
+    let exe_path =
+        std::env::current_exe().map_err(|e| format!("cannot locate service executable: {}", e))?;
+    let exe_path = exe_path.to_string_lossy();
+
+    let helper = start_process_in_session0(
+        &exe_path,
+        None,
+        Some(args),
+        false,
+        true,
+        None,
+        &DesktopOptions::default(),
+        None,
+    )?;
+    unsafe {
+        if WaitForSingleObject(helper.process_handle, wait.as_millis() as u32) != WAIT_OBJECT_0 {
+            // 卡在挂起窗口上的辅助进程不会自行退出，每次检查都会留下一个
+            let _ = TerminateProcess(helper.process_handle, EXIT_HUNG);
+            return Err("window helper did not finish in time".to_string());
+        }
+        let mut exit_code: u32 = 0;
+        GetExitCodeProcess(helper.process_handle, &mut exit_code)
+            .map_err(|e| format!("cannot read window helper result: {:?}", e))?;
+        Ok(exit_code)
+    }
+}
+
+/// 请求用户会话中的进程关闭其窗口，用于服务停止时的优雅退出
+pub fn request_close_in_user_session(pid: u32) -> Result<(), String> {
+    let args = format!("{} {}", CLOSE_HELPER_ARG, pid);
+    match run_helper_in_user_session(&args, Duration::from_millis(HELPER_EXTRA_WAIT_MS))? {
+        EXIT_RESPONSIVE => Ok(()),
+        EXIT_NOT_FOUND => Err(format!("PID {} has no window to close", pid)),
+        other => Err(format!("close window helper failed with code {}", other)),
+    }
+}
+
+/// 服务位于会话 0，无法枚举用户桌面的窗口，因此在用户会话中启动自身作为辅助进程检查
+pub fn check_window_in_user_session(pid: u32, config: &WindowCheckConfig) -> Result<(), String> {
+    let args = build_helper_args(pid, config);
+    let wait = Duration::from_millis(config.hung_timeout_ms + HELPER_EXTRA_WAIT_MS);
+
+    match run_helper_in_user_session(&args, wait)? {
+        EXIT_RESPONSIVE => Ok(()),
+        EXIT_NOT_FOUND => Err(format!("no matching window found for PID {}", pid)),
+        EXIT_HUNG => Err(format!("window of PID {} is not responding", pid)),
+        other => Err(format!("window check helper failed with code {}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_helper_args, hex_decode, hex_encode, window_matches};
+    use crate::models::WindowCheckConfig;
+
+    #[test]
+    fn hex_round_trips_titles_with_spaces() {
+        let title = "Kiosk \"Main\" 窗口";
+        assert_eq!(hex_decode(&hex_encode(title)).unwrap(), title);
+        assert_eq!(hex_encode(""), "-");
+        assert_eq!(hex_decode("-").unwrap(), "");
+        assert!(hex_decode("abc").is_none());
+    }
+
+    #[test]
+    fn helper_args_encode_missing_filters_as_dash() {
+        let config = WindowCheckConfig {
+            class_name: Some("KioskWnd".to_string()),
+            title: None,
+            hung_timeout_ms: 5000,
+            interval_ms: 10000,
+            failure_threshold: 3,
+        };
+        assert_eq!(
+            build_helper_args(42, &config),
+            "--check-window 42 4b696f736b576e64 - 5000"
+        );
+    }
+
+    #[test]
+    fn window_filters_are_case_insensitive() {
+        assert!(window_matches(
+            "KioskWnd",
+            "Kiosk - Home",
+            "kioskwnd",
+            "home"
+        ));
+        assert!(window_matches("Any", "Anything", "", ""));
+        assert!(!window_matches("Other", "Kiosk", "KioskWnd", ""));
+    }
+}
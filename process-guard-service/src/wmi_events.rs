@@ -0,0 +1,111 @@
+use crate::guardian::Guardian;
+use log::{info, warn};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use windows::core::{w, BSTR, PCWSTR, VARIANT};
+use windows::Win32::System::Com::{
+    CoCreateInstance, CoInitializeEx, CoSetProxyBlanket, CoUninitialize, CLSCTX_INPROC_SERVER,
+    COINIT_MULTITHREADED, EOAC_NONE, RPC_C_AUTHN_LEVEL_CALL, RPC_C_IMP_LEVEL_IMPERSONATE,
+};
+use windows::Win32::System::Wmi::{
+    IWbemClassObject, IWbemLocator, WbemLocator, WBEM_FLAG_FORWARD_ONLY,
+    WBEM_FLAG_RETURN_IMMEDIATELY,
+};
+
+const QUERY: &str = "SELECT ProcessID FROM Win32_ProcessStopTrace";
+/// 每次等待事件的超时，用于定期检查服务是否正在停止
+const NEXT_TIMEOUT_MS: i32 = 1000;
+/// 订阅失败或中断后重新订阅的间隔，期间守护循环按默认周期轮询
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(60);
+/// RPC_C_AUTHN_WINNT / RPC_C_AUTHZ_NONE
+const RPC_C_AUTHN_WINNT: u32 = 10;
+const RPC_C_AUTHZ_NONE: u32 = 0;
+
+/// 订阅 WMI 进程退出事件，受守护进程退出时唤醒守护循环
+pub struct ProcessExitWatcher {
+    guardian: Arc<Guardian>,
+    running: Arc<Mutex<bool>>,
+}
+
+impl ProcessExitWatcher {
+    pub fn new(guardian: Arc<Guardian>, running: Arc<Mutex<bool>>) -> Self {
+        Self { guardian, running }
+    }
+
+    fn is_running(&self) -> bool {
+        *self.running.lock().unwrap()
+    }
+
+    pub fn run(&self) {
+        let initialized = unsafe { CoInitializeEx(None, COINIT_MULTITHREADED).is_ok() };
+        while self.is_running() {
+            if let Err(e) = self.watch() {
+                warn!("WMI process exit subscription failed: {}", e);
+            }
+            self.guardian.set_exit_events_active(false);
+            let retry_at = Instant::now() + RESUBSCRIBE_DELAY;
+            while self.is_running() && Instant::now() < retry_at {
+                std::thread::sleep(Duration::from_millis(500));
+            }
+        }
+        if initialized {
+            unsafe { CoUninitialize() };
+        }
+    }
+
+    /// 建立订阅并转发事件，直到服务停止或订阅出错
+    fn watch(&self) -> windows::core::Result<()> {
+        unsafe {
+            let locator: IWbemLocator = CoCreateInstance(&WbemLocator, None, CLSCTX_INPROC_SERVER)?;
+            let services = locator.ConnectServer(
+                &BSTR::from(r"ROOT\CIMV2"),
+                &BSTR::new(),
+                &BSTR::new(),
+                &BSTR::new(),
+                0,
+                &BSTR::new(),
+                None,
+            )?;
+            CoSetProxyBlanket(
+                &services,
+                RPC_C_AUTHN_WINNT,
+                RPC_C_AUTHZ_NONE,
+                PCWSTR::null(),
+                RPC_C_AUTHN_LEVEL_CALL,
+                RPC_C_IMP_LEVEL_IMPERSONATE,
+                None,
+                EOAC_NONE,
+            )?;
+            let events = services.ExecNotificationQuery(
+                &BSTR::from("WQL"),
+                &BSTR::from(QUERY),
+                WBEM_FLAG_RETURN_IMMEDIATELY | WBEM_FLAG_FORWARD_ONLY,
+                None,
+            )?;
+            info!("Subscribed to WMI process exit events");
+            self.guardian.set_exit_events_active(true);
+
+            while self.is_running() {
+                let mut objects = [None];
+                let mut returned = 0u32;
+                // 超时返回 WBEM_S_TIMEDOUT，属于成功码
+                events
+                    .Next(NEXT_TIMEOUT_MS, &mut objects, &mut returned)
+                    .ok()?;
+                if returned == 0 {
+                    continue;
+                }
+                if let Some(pid) = objects[0].take().as_ref().and_then(process_id) {
+                    self.guardian.on_process_exit(pid);
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+fn process_id(event: &IWbemClassObject) -> Option<u32> {
+    let mut value = VARIANT::default();
+    unsafe { event.Get(w!("ProcessID"), 0, &mut value, None, None) }.ok()?;
+    u32::try_from(&value).ok()
+}
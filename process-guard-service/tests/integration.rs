@@ -0,0 +1,134 @@
+//! 端到端测试：真实的辅助进程、守护循环与管道服务。
+//!
+//! 运行：`cargo test --features test-support --test integration`
+
+use process_guard_core::models::{MonitorItem, PipeRequest};
+use process_guard_core::test_support::TestService;
+use std::time::Duration;
+
+const HELPER: &str = env!("CARGO_BIN_EXE_pg-test-helper");
+
+/// 启动宽限期 5 秒加一个检查周期 3 秒，留足余量
+const RESTART_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 监控项 ID 附加在参数末尾，使并行测试的实例互不混淆（辅助程序忽略多余参数）
+fn helper_item(id: &str, args: &str) -> MonitorItem {
+    MonitorItem {
+        id: id.to_string(),
+        args: Some(format!("{} {}", args, id)),
+        heartbeat_required: false,
+        post_kill_delay_ms: 0,
+        ..MonitorItem::new(HELPER.to_string(), id.to_string())
+    }
+}
+
+#[test]
+fn crashing_process_is_restarted() {
+    let service = TestService::start(vec![helper_item("crasher", "crash-after 1")]);
+
+    assert!(service.wait_until(RESTART_TIMEOUT, |s| s.process_id("crasher").is_some()));
+    let first = service.process_id("crasher").unwrap();
+
+    assert!(service.wait_until(RESTART_TIMEOUT, |s| {
+        matches!(s.process_id("crasher"), Some(pid) if pid != first)
+    }));
+}
+
+#[test]
+fn output_before_a_crash_is_kept_across_restarts() {
+    let mut crasher = helper_item("noisy", "crash-after 1");
+    crasher.capture_output = true;
+    let service = TestService::start(vec![crasher]);
+
+    let request = PipeRequest::GetOutput {
+        id: "noisy".to_string(),
+    };
+    assert!(service.wait_until(RESTART_TIMEOUT, |s| {
+        s.request(&request)
+            .ok()
+            .and_then(|r| r.data)
+            .is_some_and(|data| {
+                data["output"]
+                    .as_str()
+                    .is_some_and(|output| output.matches("--- starting noisy ---").count() >= 2)
+            })
+    }));
+    let data = service.request(&request).unwrap().data.unwrap();
+    assert!(data["output"]
+        .as_str()
+        .unwrap()
+        .contains("exiting with code 3"));
+}
+
+#[test]
+fn heartbeating_process_stays_up_and_silent_one_is_restarted() {
+    let mut beating = helper_item("beating", "heartbeat");
+    beating.heartbeat_required = true;
+    beating.heartbeat_timeout_ms = 3000;
+    let mut silent = helper_item("silent", "sleep");
+    silent.heartbeat_required = true;
+    silent.heartbeat_timeout_ms = 3000;
+
+    // 心跳进程需要知道管道名，因此先启动服务再通过管道添加
+    let service = TestService::start(vec![silent]);
+    beating.args = Some(format!("heartbeat {} beating 500", service.pipe_name()));
+    let response = service
+        .request(&PipeRequest::Add { config: beating })
+        .unwrap();
+    assert!(response.success, "{:?}", response.message);
+
+    assert!(service.wait_until(RESTART_TIMEOUT, |s| {
+        s.process_id("beating").is_some() && s.process_id("silent").is_some()
+    }));
+    let beating_pid = service.process_id("beating").unwrap();
+    let silent_pid = service.process_id("silent").unwrap();
+
+    assert!(service.wait_until(RESTART_TIMEOUT, |s| {
+        matches!(s.process_id("silent"), Some(pid) if pid != silent_pid)
+    }));
+    assert_eq!(service.process_id("beating"), Some(beating_pid));
+}
+
+#[test]
+fn items_are_added_and_removed_over_the_pipe() {
+    let service = TestService::start(Vec::new());
+
+    let response = service
+        .request(&PipeRequest::Add {
+            config: helper_item("sleeper", "sleep"),
+        })
+        .unwrap();
+    assert!(response.success, "{:?}", response.message);
+    assert!(service.wait_until(RESTART_TIMEOUT, |s| s.process_id("sleeper").is_some()));
+
+    let list = service.request(&PipeRequest::List).unwrap();
+    let items = list.data.unwrap();
+    assert_eq!(items.as_array().map(Vec::len), Some(1));
+
+    let response = service
+        .request(&PipeRequest::Remove {
+            id: "sleeper".to_string(),
+        })
+        .unwrap();
+    assert!(response.success, "{:?}", response.message);
+    assert!(service.wait_until(RESTART_TIMEOUT, |s| s.backend.running().is_empty()));
+
+    let unknown = service
+        .request_raw(r#"{"type":"no_such_request"}"#)
+        .unwrap();
+    assert!(!unknown.success);
+    let missing_config = service.request_raw(r#"{"type":"add"}"#).unwrap();
+    assert!(!missing_config.success);
+}
+
+#[test]
+fn list_does_not_return_heartbeat_tokens() {
+    let mut item = helper_item("udp-beating", "sleep");
+    item.heartbeat_token = Some("s3cret".to_string());
+    let service = TestService::start(vec![item]);
+
+    let list = service.request(&PipeRequest::List).unwrap();
+    let items = list.data.unwrap();
+    assert_eq!(items[0]["id"], "udp-beating");
+    assert!(items[0].get("heartbeat_token").is_none());
+}